@@ -6,7 +6,7 @@ use cells::topological::topological_sort;
 
 pub fn refcell_benchmark(c: &mut Criterion) {
   let megatable_raw = read_to_string("./sample_tables/megatable.json").unwrap();
-  let (_, exprs) = parse_from_input(&megatable_raw).unwrap();
+  let exprs = parse_from_input(&megatable_raw).unwrap().exprs;
 
   c.bench_function("topological_sort", |b| {
     b.iter(|| topological_sort(black_box(&exprs)))
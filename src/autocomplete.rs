@@ -0,0 +1,160 @@
+//! Autocompletion of the formula being typed, e.g., `= SU` suggests `SUM(` and `SUMIF(`,
+//! and `= B` suggests the filled cells of the column `B`; inside a function call, the hint
+//! of its arguments is shown. The caret is assumed to be at the end of the input.
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+use crate::cell_id::CellId;
+use crate::func::Func;
+use crate::registry;
+
+const MAX_SUGGESTIONS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+  /// The text replacing the typed name, e.g., `SUM(`.
+  pub text: String,
+  /// Shown next to the text, e.g., the arguments of a function or the input of a cell.
+  pub hint: String,
+}
+
+fn is_name_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Returns the name typed at the end of a formula, or `None` outside formulas and strings.
+fn typed_name(input: &str) -> Option<&str> {
+  let formula = input.strip_prefix('=')?;
+  if formula.matches('"').count() % 2 == 1 {
+    return None;
+  }
+
+  let name = &formula[formula.trim_end_matches(is_name_char).len()..];
+  name
+    .starts_with(|c: char| c.is_ascii_alphabetic())
+    .then_some(name)
+}
+
+/// Returns the functions and the filled cells starting with the typed name;
+/// the cells go first once a row number is typed.
+pub fn suggest<S: Borrow<str>>(input: &str, inputs: &HashMap<CellId, S>) -> Vec<Suggestion> {
+  let Some(name) = typed_name(input) else {
+    return vec![];
+  };
+  let name = name.to_ascii_uppercase();
+
+  let mut funcs = Func::ALL
+    .iter()
+    .map(|func| func.name())
+    .chain(registry::names())
+    .filter(|func| func.starts_with(&name))
+    .filter_map(|func| Func::try_from(func).ok())
+    .collect::<Vec<_>>();
+  funcs.sort_by_key(|func| func.name());
+  let funcs = funcs.into_iter().map(|func| Suggestion {
+    text: if func.is_constant() {
+      func.name().to_string()
+    } else {
+      format!("{}(", func.name())
+    },
+    hint: format!("{}({})", func.name(), func.signature()),
+  });
+
+  // cell ids are displayed with zero-padded rows, e.g., `B01`, but typed without them
+  let mut cells = inputs
+    .iter()
+    .map(|(cell_id, input)| (cell_id, format!("{}{}", cell_id.col, cell_id.row), input))
+    .filter(|(_, id, _)| id.starts_with(&name))
+    .collect::<Vec<_>>();
+  cells.sort_by_key(|(cell_id, _, _)| **cell_id);
+  let cells = cells.into_iter().map(|(_, id, input)| Suggestion {
+    text: id,
+    hint: input.borrow().to_string(),
+  });
+
+  if name.contains(|c: char| c.is_ascii_digit()) {
+    cells.chain(funcs).take(MAX_SUGGESTIONS).collect()
+  } else {
+    funcs.chain(cells).take(MAX_SUGGESTIONS).collect()
+  }
+}
+
+/// Replaces the typed name with the suggestion.
+pub fn complete(input: &str, suggestion: &Suggestion) -> String {
+  let name = typed_name(input).unwrap_or_default();
+  format!("{}{}", &input[..input.len() - name.len()], suggestion.text)
+}
+
+/// Returns the arguments of the innermost function whose call isn't closed yet,
+/// e.g., `ROUND(number, [digits])` for `= ROUND(A1 * 2`.
+pub fn signature_hint(input: &str) -> Option<String> {
+  let formula = input.strip_prefix('=')?;
+
+  let mut calls = vec![];
+  let mut in_string = false;
+  for (i, c) in formula.char_indices() {
+    match c {
+      '"' => in_string = !in_string,
+      _ if in_string => (),
+      '(' => {
+        let before = &formula[..i];
+        calls.push(&before[before.trim_end_matches(is_name_char).len()..]);
+      }
+      ')' => {
+        calls.pop();
+      }
+      _ => (),
+    }
+  }
+
+  let func = Func::try_from(*calls.last()?).ok()?;
+  Some(format!("{}({})", func.name(), func.signature()))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn autocomplete_test() {
+    let inputs = HashMap::from([
+      (CellId::try_from("B1").unwrap(), String::from("10")),
+      (CellId::try_from("B12").unwrap(), String::from("= B1 * 2")),
+      (CellId::try_from("C1").unwrap(), String::from("x")),
+    ]);
+    let texts = |input: &str| {
+      suggest(input, &inputs)
+        .into_iter()
+        .map(|suggestion| suggestion.text)
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(texts("= 1 + su"), vec!["SUBSTITUTE(", "SUM(", "SUMIF("]);
+    assert_eq!(texts("= B"), vec!["B1", "B12"]);
+    assert_eq!(texts("= AVERAGE(B1"), vec!["B1", "B12"]);
+    assert_eq!(texts("= P")[..2], ["PERCENTILE(", "PI"]);
+    assert!(texts("sum").is_empty());
+    assert!(texts("= \"su").is_empty());
+    assert!(texts("= 12").is_empty());
+    assert_eq!(texts("= c").len(), MAX_SUGGESTIONS);
+
+    let suggestion = &suggest("= ROUND(su", &inputs)[1];
+    assert_eq!(suggestion.hint, "SUM(number1, [number2], …)");
+    assert_eq!(complete("= ROUND(su", suggestion), "= ROUND(SUM(");
+
+    assert_eq!(
+      signature_hint("= ROUND(A1 * 2"),
+      Some(String::from("ROUND(number, [digits])"))
+    );
+    assert_eq!(
+      signature_hint("= ROUND(LEN(\"(\"), "),
+      Some(String::from("ROUND(number, [digits])"))
+    );
+    assert_eq!(signature_hint("= ROUND(A1)"), None);
+    assert_eq!(signature_hint("= (A1 + 1"), None);
+
+    for func in Func::ALL {
+      assert_eq!(Func::try_from(func.name()), Ok(func));
+    }
+  }
+}
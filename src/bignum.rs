@@ -0,0 +1,147 @@
+//! Numbers outside of the range or the precision of `f64`. Arithmetic operators produce them
+//! instead of infinities or rounded integers, e.g., for `= 2^100` or `= 10^400`.
+//! Integers are exact, while other results are rounded to 100 significant digits.
+use bigdecimal::{BigDecimal, FromPrimitive, One, ToPrimitive, Zero};
+
+use crate::expr::Op;
+use crate::value::Value;
+
+// integers up to 2^53 are exactly representable by f64
+const MAX_EXACT_INT: f64 = 9_007_199_254_740_992.0;
+// significant digits of the results of division
+const DIV_PRECISION: u64 = 100;
+// significant digits shown in the scientific notation
+const DISPLAY_PRECISION: u64 = 15;
+
+/// Applies an arithmetic operator exactly, if any of the values is a big number, or if
+/// the floating point result would overflow or lose precision of integers; returns `None`
+/// if the floating point result should be used instead.
+pub fn apply(op: Op, left: &Value, right: &Value) -> Option<Value> {
+  let res = match (left, right) {
+    (Value::Big(_), Value::Num(_) | Value::Big(_)) | (Value::Num(_), Value::Big(_)) => {
+      apply_big(op, &to_big(left)?, &to_big(right)?)?
+    }
+    (Value::Num(left), Value::Num(right)) => {
+      let res = match op {
+        Op::Add => left + right,
+        Op::Sub => left - right,
+        Op::Mul => left * right,
+        Op::Pow => left.powf(*right),
+        _ => return None,
+      };
+      let overflows = !res.is_finite() && left.is_finite() && right.is_finite();
+      let is_inexact = left.fract() == 0.0 && right.fract() == 0.0 && res.abs() >= MAX_EXACT_INT;
+      if !overflows && !is_inexact {
+        return None;
+      }
+
+      apply_big(
+        op,
+        &BigDecimal::from_f64(*left)?,
+        &BigDecimal::from_f64(*right)?,
+      )?
+    }
+    _ => return None,
+  };
+
+  Some(from_big(res))
+}
+
+fn apply_big(op: Op, left: &BigDecimal, right: &BigDecimal) -> Option<BigDecimal> {
+  match op {
+    Op::Add => Some(left + right),
+    Op::Sub => Some(left - right),
+    Op::Mul => Some(left * right),
+    // division by zero is reported by the floating point arithmetic
+    Op::Div if right.is_zero() => None,
+    Op::Div => Some((left / right).with_prec(DIV_PRECISION)),
+    // only integer powers are supported
+    Op::Pow if right.is_integer() => Some(left.powi(right.to_i64()?)),
+    _ => None,
+  }
+}
+
+pub fn to_big(value: &Value) -> Option<BigDecimal> {
+  match value {
+    Value::Num(num) => BigDecimal::from_f64(*num),
+    Value::Big(big) => Some(big.clone()),
+    _ => None,
+  }
+}
+
+/// Big numbers that fit into `f64` without losing precision become regular numbers.
+fn from_big(big: BigDecimal) -> Value {
+  let max_exact_int = BigDecimal::from_f64(MAX_EXACT_INT).unwrap_or_else(BigDecimal::one);
+
+  match big.to_f64() {
+    Some(num) if big.abs() <= max_exact_int => Value::Num(num),
+    _ => Value::Big(big.normalized()),
+  }
+}
+
+/// Big numbers are converted to infinities if they don't fit into `f64`.
+pub fn to_f64(big: &BigDecimal) -> f64 {
+  match big.to_f64() {
+    Some(num) => num,
+    None if *big < BigDecimal::zero() => f64::NEG_INFINITY,
+    None => f64::INFINITY,
+  }
+}
+
+/// Formats integers with up to 21 digits as is, and other numbers in the scientific notation,
+/// e.g., `1.26765060022823E+30`.
+pub fn format(big: &BigDecimal) -> String {
+  let (_, scale) = big.as_bigint_and_exponent();
+  if big.is_integer() && big.digits() as i64 - scale <= 21 {
+    return big.with_scale(0).to_string();
+  }
+
+  let (int, scale) = big.with_prec(DISPLAY_PRECISION).as_bigint_and_exponent();
+  let sign = if int < Zero::zero() { "-" } else { "" };
+  let digits = int.magnitude().to_string();
+  let exponent = digits.len() as i64 - 1 - scale;
+
+  let (first, rest) = digits.split_at(1);
+  let rest = rest.trim_end_matches('0');
+  let point = if rest.is_empty() { "" } else { "." };
+  let exponent_sign = if exponent < 0 { "-" } else { "+" };
+  format!(
+    "{sign}{first}{point}{rest}E{exponent_sign}{}",
+    exponent.abs()
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::expr::EvalContext;
+  use crate::parser::parse;
+  use std::collections::HashMap;
+
+  #[test]
+  fn bignum_test() {
+    let ctx = HashMap::new();
+    let eval = |input: &str| parse(input).unwrap().eval(&EvalContext::new(&ctx)).unwrap();
+
+    assert_eq!(eval("= 2^10"), Value::Num(1024.0));
+    assert_eq!(eval("= 0.5 * 3"), Value::Num(1.5));
+    assert_eq!(eval("= 2^100").to_string(), "1.26765060022823E+30");
+    assert_eq!(eval("= 2^60").to_string(), "1152921504606846976");
+    assert_eq!(eval("= 2^60 + 1").to_string(), "1152921504606846977");
+    assert_eq!(eval("= 2^53 + 1").to_string(), "9007199254740993");
+    assert_eq!(eval("= 10^400 * 5").to_string(), "5E+400");
+    assert_eq!(eval("= -(10^400) / 4").to_string(), "-2.5E+399");
+    assert_eq!(eval("= 10^400 / 10^399"), Value::Num(10.0));
+    assert_eq!(eval("= 2^100 - 2^100"), Value::Num(0.0));
+    assert_eq!(eval("= 10^400 > 10^399"), Value::Num(1.0));
+    assert_eq!(eval("= 10^400 = 10^400 + 1"), Value::Num(0.0));
+    assert_eq!(eval("= 2^70 < 1"), Value::Num(0.0));
+    assert_eq!(eval("= SUM(10^400, 1)"), Value::Num(f64::INFINITY));
+    assert_eq!(eval("= 2^0.5"), Value::Num(2f64.powf(0.5)));
+
+    assert!(parse("= 10^400 / 0")
+      .unwrap()
+      .eval(&EvalContext::new(&ctx))
+      .is_err());
+  }
+}
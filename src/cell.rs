@@ -1,43 +1,205 @@
-use web_sys::{window, HtmlInputElement};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::{window, ClipboardEvent, HtmlElement, HtmlInputElement, HtmlSelectElement};
 use yew::prelude::*;
 
-use crate::{cell_id::CellId, expr::Expr};
+use crate::{
+  autocomplete::Suggestion,
+  cell_id::{CellId, Size},
+  completions::{is_completion_key, Completions},
+  error::EvalError,
+  expr::Expr,
+  highlighted::{is_highlighted, Highlighted},
+  keymap::{Action, Chord, Keymap},
+  navigation::Jump,
+  selection::Selection,
+  style::Style,
+  value::Value,
+};
 
-#[derive(PartialEq, Properties)]
+/// Returns the id of an element in a pane of the split view; the first pane keeps the plain ids.
+pub fn element_id(pane: usize, id: &str) -> String {
+  match pane {
+    0 => id.to_string(),
+    _ => format!("pane{pane}-{id}"),
+  }
+}
+
+#[derive(Properties)]
 pub struct CellProps {
+  // the pane of the split view rendering the cell
+  #[prop_or_default]
+  pub pane: usize,
   pub is_focused: bool,
   pub is_input: bool,
+  pub is_selected: bool,
+  // whether the cell matches the Ctrl+F search
+  pub is_found: bool,
+  // whether the focused cell reads from the cell, or the cell reads from it, directly or via other cells
+  pub is_precedent: bool,
+  pub is_dependent: bool,
+  // whether the cell is in a circular reference
+  pub in_cycle: bool,
+  // shows the input of the cell, e.g., its formula, instead of its value
+  pub show_formulas: bool,
+  pub style: Style,
+  // explains the validation rule broken by the value of the cell, if any
+  pub invalid: Option<String>,
+  // why the formula of the cell failed to parse, if it did
+  pub parse_error: Option<String>,
+  // outlines the cell referenced by the formula being typed, see `crate::highlighted::paint_css`
+  pub painted: Option<String>,
+  // the values allowed by the list validation rule, picked from a dropdown instead of typing
+  pub options: Option<Vec<String>>,
+  // the small square at the bottom right corner of the selection
+  pub has_fill_handle: bool,
   pub cell_id: CellId,
-  pub input: Option<String>,
-  pub expr: Option<Expr>,
-  pub computed: Option<Expr>,
+  // the size of the table, to wrap Tab and to select whole rows and columns
+  pub size: Size,
+  // the number of columns and rows spanned by merged cells
+  pub colspan: usize,
+  pub rowspan: usize,
+  // the width of the column in pixels
+  pub width: u32,
+  // the offsets of the frozen rows from the top in rem, and of the frozen columns
+  // from the left in pixels, which keep them in view
+  pub frozen_top: Option<f64>,
+  pub frozen_left: Option<u32>,
+  // shared with the model of the table
+  pub input: Option<Rc<str>>,
+  pub expr: Option<Rc<Expr>>,
+  pub computed: Option<Result<Value, EvalError>>,
   pub onfocused: Callback<CellId>,
   pub onfocusout: Callback<FocusEvent>,
   pub onbecameinput: Callback<CellId>,
   pub onlostinput: Callback<CellId>,
+  // switches the cell to input mode, keeping its input, e.g., on F2
+  pub onedit: Callback<CellId>,
+  // moves the focus from the cell, e.g., on Ctrl+Arrow
+  pub onjump: Callback<Jump>,
+  // starts a selection on mouse down, and extends it on Shift+Click or dragging into the cell
+  pub onselectionstarted: Callback<CellId>,
+  pub onselectionextended: Callback<CellId>,
+  // moves the opposite corner of the selection, e.g., on Shift+Arrow
+  pub onselectionjumped: Callback<Jump>,
+  // selects the row or the column of the cell, e.g., on Shift+Space or Ctrl+Space
+  pub onselected: Callback<Selection>,
+  // copying, cutting, and pasting the selection; the input handles them itself
+  pub oncopy: Callback<()>,
+  pub oncut: Callback<()>,
+  pub onpaste: Callback<String>,
+  // starts dragging the fill handle
+  pub onfillstarted: Callback<()>,
+  // the key bindings, and the actions handled by the table, e.g., Undo or Find;
+  // the input undoes the typing itself
+  pub keymap: Rc<Keymap>,
+  pub onaction: Callback<Action>,
+  // the completions of the typed formula with the highlighted one, and the hint of the arguments
+  // of the function being typed; the arrows, Enter, Tab, and Escape are sent to the table
+  pub suggestions: Vec<Suggestion>,
+  pub suggestion_index: usize,
+  pub hint: Option<String>,
+  pub oncompletionkey: Callback<Chord>,
+  pub oncompletionpicked: Callback<usize>,
   pub oninput: Callback<InputEvent>,
   // sets a custom string as if it was inputted into cell -
   // useful for processing of keyboard input on a focused cell, for example
   pub sendinput: Callback<String>,
 }
 
+/// Compares everything but the callbacks, so that only the changed cells are re-rendered:
+/// the table recreates the callbacks on each render, but they only capture `cell_id`.
+impl PartialEq for CellProps {
+  fn eq(&self, other: &Self) -> bool {
+    self.pane == other.pane
+      && self.cell_id == other.cell_id
+      && self.is_focused == other.is_focused
+      && self.is_input == other.is_input
+      && self.is_selected == other.is_selected
+      && self.is_found == other.is_found
+      && self.is_precedent == other.is_precedent
+      && self.is_dependent == other.is_dependent
+      && self.in_cycle == other.in_cycle
+      && self.show_formulas == other.show_formulas
+      && self.has_fill_handle == other.has_fill_handle
+      && self.size == other.size
+      && self.colspan == other.colspan
+      && self.rowspan == other.rowspan
+      && self.width == other.width
+      && self.frozen_top == other.frozen_top
+      && self.frozen_left == other.frozen_left
+      && self.style == other.style
+      && self.invalid == other.invalid
+      && self.parse_error == other.parse_error
+      && self.painted == other.painted
+      && self.options == other.options
+      && self.input == other.input
+      && match (&self.expr, &other.expr) {
+        (Some(expr), Some(other_expr)) => Rc::ptr_eq(expr, other_expr),
+        (expr, other_expr) => expr.is_none() && other_expr.is_none(),
+      }
+      && self.computed == other.computed
+      && Rc::ptr_eq(&self.keymap, &other.keymap)
+      && self.suggestions == other.suggestions
+      && self.suggestion_index == other.suggestion_index
+      && self.hint == other.hint
+  }
+}
+
 /**
 A cell that can be both selected and typed into.
 */
 #[function_component]
 pub fn Cell(props: &CellProps) -> Html {
   let input_ref = use_node_ref();
+  let select_ref = use_node_ref();
+  let is_dropdown = props.options.is_some();
+  // typed booleans are shown as checkboxes, toggled by clicking them or pressing Space
+  let checked = match (props.expr.as_deref(), is_dropdown) {
+    (Some(Expr::Bool(checked)), false) => Some(*checked),
+    _ => None,
+  };
+  let toggle = {
+    let cell_id = props.cell_id;
+    let parent_sendinput = props.sendinput.clone();
+    let parent_onlostinput = props.onlostinput.clone();
 
-  let input_value = props.input.clone().unwrap_or_default();
+    move || {
+      if let Some(checked) = checked {
+        parent_sendinput.emit(String::from(if checked { "FALSE" } else { "TRUE" }));
+        parent_onlostinput.emit(cell_id);
+      }
+    }
+  };
+
+  let input_value = props.input.as_deref().unwrap_or_default().to_string();
 
   // if `computed_value` is present, show it in the div cell, otherwise show `value`
-  let div_value = match props.computed {
-    Some(Expr::Num(n)) => n.to_string(),
-    _ => props.input.clone().unwrap_or_default(),
+  let div_value = match &props.computed {
+    Some(Ok(value)) => props.style.number_format.format(value),
+    Some(Err(err)) => err.kind.code().to_string(),
+    None => input_value.clone(),
+  };
+  // the error message is shown on hover
+  let error = match &props.computed {
+    Some(Err(err)) => Some(err.message.clone()),
+    _ => None,
+  };
+
+  // what the screen readers read for the cell, e.g., `B2 42`
+  let shown = if props.show_formulas {
+    &input_value
+  } else {
+    &div_value
+  };
+  let cell_label = if shown.is_empty() {
+    format!("{} blank", props.cell_id)
+  } else {
+    format!("{} {shown}", props.cell_id)
   };
 
   let onfocus = {
-    let cell_id = props.cell_id.clone();
+    let cell_id = props.cell_id;
     let parent_onfocus = props.onfocused.clone();
 
     Callback::from(move |_ev: FocusEvent| {
@@ -45,21 +207,67 @@ pub fn Cell(props: &CellProps) -> Html {
     })
   };
 
+  // Shift+Click extends the selection, keeping the focus
   let onclick = {
-    let cell_id = props.cell_id.clone();
+    let cell_id = props.cell_id;
     let parent_onfocus = props.onfocused.clone();
 
-    Callback::from(move |_ev: MouseEvent| {
-      parent_onfocus.emit(cell_id);
+    Callback::from(move |ev: MouseEvent| {
+      if !ev.shift_key() {
+        parent_onfocus.emit(cell_id);
+      }
+    })
+  };
+
+  let onmousedown = {
+    let cell_id = props.cell_id;
+    let parent_onselectionstarted = props.onselectionstarted.clone();
+    let parent_onselectionextended = props.onselectionextended.clone();
+
+    Callback::from(move |ev: MouseEvent| {
+      if ev.shift_key() {
+        // prevents focusing the div
+        ev.prevent_default();
+        parent_onselectionextended.emit(cell_id);
+      } else {
+        parent_onselectionstarted.emit(cell_id);
+      }
+    })
+  };
+
+  let fill_onmousedown = {
+    let parent_onfillstarted = props.onfillstarted.clone();
+
+    Callback::from(move |ev: MouseEvent| {
+      // keeps the selection and the focus
+      ev.prevent_default();
+      ev.stop_propagation();
+      parent_onfillstarted.emit(());
+    })
+  };
+
+  let onmouseenter = {
+    let cell_id = props.cell_id;
+    let parent_onselectionextended = props.onselectionextended.clone();
+
+    Callback::from(move |ev: MouseEvent| {
+      // dragging with the primary button pressed
+      if ev.buttons() & 1 != 0 {
+        parent_onselectionextended.emit(cell_id);
+      }
     })
   };
 
   let ondblclick = {
-    let cell_id = props.cell_id.clone();
+    let cell_id = props.cell_id;
     let input_ref = input_ref.clone();
     let parent_onbecameinput = props.onbecameinput.clone();
 
     Callback::from(move |_ev: MouseEvent| {
+      if is_dropdown {
+        return;
+      }
+
       parent_onbecameinput.emit(cell_id);
 
       input_ref
@@ -71,18 +279,22 @@ pub fn Cell(props: &CellProps) -> Html {
   };
 
   let div_onkeypress = {
-    let cell_id = props.cell_id.clone();
+    let cell_id = props.cell_id;
     let input_ref = input_ref.clone();
+    let select_ref = select_ref.clone();
     let parent_sendinput = props.sendinput.clone();
     let parent_onbecameinput = props.onbecameinput.clone();
+    let keymap = props.keymap.clone();
 
     Callback::from(move |ev: KeyboardEvent| {
-      if ev.key_code() != 13 {
+      if is_dropdown {
+        ev.prevent_default();
+        focus_select(&select_ref);
+      } else if keymap.action(&Chord::from_event(&ev)) != Some(Action::Commit) {
         // firefox doesn't register this keypress, but chrome does
         let should_send_input = window()
-          .map(|w| match w.navigator().user_agent() {
-            Ok(user_agent) if user_agent.to_lowercase().contains("firefox") => true,
-            _ => false,
+          .map(|w| {
+            matches!(w.navigator().user_agent(), Ok(user_agent) if user_agent.to_lowercase().contains("firefox"))
           })
           .unwrap_or_default();
 
@@ -117,61 +329,373 @@ pub fn Cell(props: &CellProps) -> Html {
     })
   };
 
-  let input_onkeypress = {
-    let cell_id = props.cell_id.clone();
+  // the last column and row spanned by the cell
+  let last_cell_id = CellId {
+    col: (props.cell_id.col as u8 + props.colspan as u8 - 1) as char,
+    row: props.cell_id.row + props.rowspan - 1,
+  };
+
+  // the bound keys are handled on `keydown`, since `keypress` isn't fired for Tab, F2,
+  // and arrows; Enter and Tab confirm the input and move to the next row or column,
+  // and the arrows, Home, and End move the caret in the input
+  let onkeydown = {
+    let cell_id = props.cell_id;
+    let size = props.size;
+    let is_input = props.is_input;
+    let keymap = props.keymap.clone();
+    let has_suggestions = is_input && !props.suggestions.is_empty();
+    let parent_onaction = props.onaction.clone();
+    let parent_oncompletionkey = props.oncompletionkey.clone();
+    let parent_onjump = props.onjump.clone();
+    let parent_onselectionjumped = props.onselectionjumped.clone();
+    let parent_onselected = props.onselected.clone();
     let parent_onlostinput = props.onlostinput.clone();
     let parent_onfocus = props.onfocused.clone();
+    let parent_onedit = props.onedit.clone();
+    let select_ref = select_ref.clone();
+    let toggle = toggle.clone();
 
     Callback::from(move |ev: KeyboardEvent| {
-      // Enter
-      if ev.key_code() == 13 {
-        let mut focused_cell_id = cell_id.clone();
-        focused_cell_id.row += 1;
-
-        parent_onlostinput.emit(cell_id);
-        parent_onfocus.emit(focused_cell_id);
+      let chord = Chord::from_event(&ev);
+      if has_suggestions && is_completion_key(&keymap, &chord) {
+        ev.prevent_default();
+        parent_oncompletionkey.emit(chord);
+        return;
+      }
+      let Some(action) = keymap.action(&chord) else {
+        return;
+      };
+      let move_focus = |focused_cell_id: Option<CellId>| {
+        if let Some(focused_cell_id) = focused_cell_id {
+          ev.prevent_default();
+          parent_onlostinput.emit(cell_id);
+          parent_onfocus.emit(focused_cell_id);
+        }
       };
+
+      match action {
+        Action::Commit if is_input => move_focus(Some(CellId {
+          row: last_cell_id.row + 1,
+          ..cell_id
+        })),
+        Action::NextCell => move_focus(Some(
+          CellId {
+            row: cell_id.row,
+            ..last_cell_id
+          }
+          .next_in_row(size),
+        )),
+        Action::PrevCell => move_focus(cell_id.prev_in_row(size)),
+        Action::Edit if is_dropdown => {
+          ev.prevent_default();
+          focus_select(&select_ref);
+        }
+        Action::Edit => {
+          ev.prevent_default();
+          parent_onedit.emit(cell_id);
+        }
+        Action::Find | Action::Palette | Action::Recalculate => {
+          ev.prevent_default();
+          parent_onaction.emit(action);
+        }
+        // prevents the paste event
+        Action::PasteSpecial | Action::Undo | Action::Redo | Action::SelectAll | Action::Clear
+          if !is_input =>
+        {
+          ev.prevent_default();
+          parent_onaction.emit(action);
+        }
+        Action::Toggle if !is_input && checked.is_some() => {
+          ev.prevent_default();
+          toggle();
+        }
+        // prevents starting the input with a space
+        Action::SelectRow if !is_input => {
+          ev.prevent_default();
+          parent_onselected.emit(Selection::row(cell_id.row, size));
+        }
+        Action::SelectColumn if !is_input => {
+          ev.prevent_default();
+          parent_onselected.emit(Selection::col(cell_id.col, size));
+        }
+        action if !is_input => {
+          if let Some(jump) = action.jump(page_rows(&ev)) {
+            ev.prevent_default();
+            if chord.shift {
+              parent_onselectionjumped.emit(jump);
+            } else {
+              parent_onjump.emit(jump);
+            }
+          }
+        }
+        _ => (),
+      }
+    })
+  };
+
+  let oncopy = {
+    let parent_oncopy = props.oncopy.clone();
+
+    Callback::from(move |ev: Event| {
+      ev.prevent_default();
+      parent_oncopy.emit(());
+    })
+  };
+
+  let oncut = {
+    let parent_oncut = props.oncut.clone();
+
+    Callback::from(move |ev: Event| {
+      ev.prevent_default();
+      parent_oncut.emit(());
+    })
+  };
+
+  let onpaste = {
+    let parent_onpaste = props.onpaste.clone();
+
+    Callback::from(move |ev: Event| {
+      let text = ev
+        .dyn_ref::<ClipboardEvent>()
+        .and_then(|ev| ev.clipboard_data())
+        .and_then(|data| data.get_data("text/plain").ok());
+
+      if let Some(text) = text {
+        ev.prevent_default();
+        parent_onpaste.emit(text);
+      }
+    })
+  };
+
+  // picking a value commits it like typing it and leaving the input
+  let select_onchange = {
+    let cell_id = props.cell_id;
+    let parent_sendinput = props.sendinput.clone();
+    let parent_onlostinput = props.onlostinput.clone();
+
+    Callback::from(move |ev: Event| {
+      let select: HtmlSelectElement = ev.target_unchecked_into();
+      parent_sendinput.emit(select.value());
+      parent_onlostinput.emit(cell_id);
+    })
+  };
+
+  let select_onfocus = {
+    let cell_id = props.cell_id;
+    let parent_onfocus = props.onfocused.clone();
+
+    Callback::from(move |_ev: FocusEvent| {
+      parent_onfocus.emit(cell_id);
     })
   };
 
+  // the current input is listed too, even if it's not allowed, to show it
+  let options = props.options.as_ref().map(|options| {
+    let mut options = options.clone();
+    if !input_value.is_empty()
+      && !options
+        .iter()
+        .any(|option| option.eq_ignore_ascii_case(&input_value))
+    {
+      options.push(input_value.clone());
+    }
+    options
+  });
+
+  let height = 2.125 * props.rowspan as f64;
+  // formulas are shown colored over the transparent text of the input while typing
+  let is_highlighted = props.is_input && is_highlighted(&input_value);
+
   // note that the div gets a tabindex to allow focus & keyboard events;
   // `input_ref` is used to focus the input
   html! {
-    <td>
-      <div class="flex">
+    <td
+      role="gridcell"
+      aria-selected={ props.is_selected.to_string() }
+      colspan={ props.colspan.to_string() }
+      rowspan={ props.rowspan.to_string() }
+      class={ match (props.frozen_top, props.frozen_left) {
+        (Some(_), Some(_)) => "sticky z-[25]",
+        (Some(_), None) | (None, Some(_)) => "sticky z-20",
+        (None, None) => "",
+      }}
+      style={ format!(
+        "{}{}",
+        props.frozen_top.map(|top| format!("top: {top}rem;")).unwrap_or_default(),
+        props.frozen_left.map(|left| format!("inset-inline-start: {left}px;")).unwrap_or_default(),
+      )}
+    >
+      <div class="relative flex">
         <input
           ref={ input_ref }
-          id={ props.cell_id.to_string() }
+          id={ element_id(props.pane, &props.cell_id.to_string()) }
           type="text"
+          // the input is reached through its cell rather than by tabbing
+          tabindex={ if props.is_input { "0" } else { "-1" } }
+          aria-label={ format!("{} input", props.cell_id) }
           class={classes!(vec![
-            "px-2 py-0.5 w-[16rem] h-[2.125rem] outline-none text-right snap-start",
+            "px-2 py-0.5 outline-none [text-align:end] snap-start",
             "border-collapse border-[1px] border-indigo-900 bg-indigo-800 font-mono",
-            if props.is_input { "z-10" } else { "z-0 select-none" }
+            if props.is_input { "z-10" } else { "z-0 select-none" },
+            if is_highlighted { "text-transparent caret-white" } else { "" }
           ])}
-          value={ input_value }
+          style={ format!("width: {}px; height: {height}rem;", props.width) }
+          value={ input_value.clone() }
           {onfocus}
           oninput={ props.oninput.clone() }
-          onkeypress={ input_onkeypress }
+          onkeydown={ onkeydown.clone() }
           onfocusout={ input_onfocusout }
         />
+        if is_highlighted {
+          <Highlighted
+            input={ input_value.clone() }
+            class={ classes!(vec!["z-20 justify-end px-2 py-0.5 border-[1px] border-transparent"]) }
+            style={ format!("width: {}px; height: {height}rem;", props.width) }
+          />
+        }
+        if props.is_input {
+          <Completions
+            suggestions={ props.suggestions.clone() }
+            index={ props.suggestion_index }
+            hint={ props.hint.clone() }
+            onpick={ props.oncompletionpicked.clone() }
+          />
+        }
 
         <div
-          id={ format!("div_{}", props.cell_id.to_string()) }
+          id={ element_id(props.pane, &format!("div_{}", props.cell_id)) }
           tabindex="0"
+          aria-label={ cell_label.clone() }
           class={classes!(vec![
-            "flex px-2 py-0.5 w-[16rem] -ml-[16rem] h-[2.125rem] outline-none",
+            "relative flex px-2 py-0.5 outline-none",
             "border-[1px] border-indigo-900 ",
             if props.is_input { "z-0" } else { "z-10" },
-            if props.is_focused { "bg-indigo-700" } else { "bg-indigo-800" },
+            if props.is_precedent { "outline-dashed outline-2 -outline-offset-2 outline-sky-400" } else { "" },
+            if props.is_dependent { "outline-dashed outline-2 -outline-offset-2 outline-emerald-400" } else { "" },
+            if props.in_cycle { "outline outline-2 -outline-offset-2 outline-red-500" } else { "" },
+            match (props.is_focused, props.is_selected, props.is_found) {
+              (true, _, _) => "bg-indigo-700",
+              (false, true, _) => "bg-indigo-600",
+              (false, false, true) => "bg-amber-800",
+              (false, false, false) => "bg-indigo-800",
+            },
           ])}
+          style={ format!(
+            "width: {0}px; margin-inline-start: -{0}px; height: {height}rem;{1}{2}",
+            props.width,
+            // the focused, selected, and found cells are highlighted instead
+            match &props.style.background {
+              Some(background) if !(props.is_focused || props.is_selected || props.is_found) =>
+                format!("background-color: {background};"),
+              _ => String::new(),
+            },
+            props.painted.clone().unwrap_or_default(),
+          )}
+          title={ props.invalid.clone() }
           {onclick}
+          {onmousedown}
+          {onmouseenter}
+          {oncopy}
+          {oncut}
+          {onpaste}
           {ondblclick}
           onkeypress={ div_onkeypress }
+          {onkeydown}
           onfocusout={ div_onfocusout }
         >
-          <span class="grow text-right select-none font-mono">{ div_value }</span>
+          if props.show_formulas {
+            <span class="grow truncate whitespace-pre [text-align:start] select-none font-mono text-neutral-300">
+              { input_value.clone() }
+            </span>
+          } else if let Some(options) = options {
+            <select
+              ref={ select_ref }
+              class="grow [text-align:end] font-mono outline-none cursor-pointer bg-transparent"
+              style={ props.style.to_css() }
+              onchange={ select_onchange }
+              onfocus={ select_onfocus }
+              // the select picks the options by the typed letters itself
+              onkeypress={ |ev: KeyboardEvent| ev.stop_propagation() }
+            >
+              <option class="bg-indigo-800" value="" selected={ input_value.is_empty() }></option>
+              {
+                options.into_iter().map(|option| html! {
+                  <option
+                    class="bg-indigo-800"
+                    selected={ option.eq_ignore_ascii_case(&input_value) }
+                    value={ option.clone() }
+                  >
+                    { option }
+                  </option>
+                }).collect::<Html>()
+              }
+            </select>
+          } else if let Some(checked) = checked {
+            <span class="grow flex items-center justify-end">
+              <input
+                type="checkbox"
+                class="w-4 h-4 cursor-pointer accent-violet-500"
+                { checked }
+                onclick={ Callback::from(move |_ev: MouseEvent| toggle()) }
+              />
+            </span>
+          } else {
+            <span
+              class={classes!(vec![
+                "grow [text-align:end] select-none font-mono",
+                if error.is_some() { "text-red-300" } else { "" },
+              ])}
+              title={ error.clone() }
+              style={ props.style.to_css() }
+            >
+              { div_value }
+            </span>
+          }
+          if let Some(message) = props.parse_error.as_ref().or(error.as_ref()) {
+            <div
+              class="absolute top-0 left-0 z-10 border-t-8 border-r-8 border-t-red-600 border-r-transparent"
+              title={ format!("{}: {message}", props.cell_id) }
+            />
+          }
+          if props.invalid.is_some() {
+            <div class="absolute top-0 right-0 border-t-8 border-l-8 border-t-red-500 border-l-transparent" />
+          }
+          if props.has_fill_handle && !props.is_input {
+            <div
+              class="absolute -bottom-1 [inset-inline-end:-0.25rem] w-2 h-2 z-20 bg-neutral-200 cursor-crosshair"
+              onmousedown={ fill_onmousedown }
+            />
+          }
         </div>
       </div>
     </td>
   }
 }
+
+fn focus_select(select_ref: &NodeRef) {
+  if let Some(select) = select_ref.cast::<HtmlSelectElement>() {
+    let _ = select.focus();
+  }
+}
+
+/// Returns the number of rows fitting into the window, given the cell div that got the event.
+fn page_rows(ev: &KeyboardEvent) -> usize {
+  let cell_height = ev
+    .target()
+    .and_then(|target| target.dyn_into::<HtmlElement>().ok())
+    .map(|div| div.offset_height())
+    .unwrap_or_default();
+  let window_height = window()
+    .and_then(|window| window.inner_height().ok())
+    .and_then(|height| height.as_f64())
+    .unwrap_or_default();
+
+  if cell_height > 0 {
+    // minus the header row
+    (window_height as usize / cell_height as usize)
+      .saturating_sub(1)
+      .max(1)
+  } else {
+    1
+  }
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display};
+use std::{cmp::Ordering, collections::HashMap, fmt::Display};
 
 use crate::expr::Expr;
 
@@ -14,12 +14,113 @@ pub struct CellId {
   pub row: usize,
 }
 
+/// The columns are `A..=Z`, so the table cannot grow wider than that.
+pub const MAX_COLS: usize = 26;
+
+/// The number of rows and columns shown in the table; it grows when the cells past its edge
+/// are focused or filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Size {
+  pub rows: usize,
+  pub cols: usize,
+}
+
+impl Default for Size {
+  fn default() -> Self {
+    Size { rows: 50, cols: 10 }
+  }
+}
+
+impl Size {
+  pub fn last_col(&self) -> char {
+    (b'A' + self.cols.clamp(1, MAX_COLS) as u8 - 1) as char
+  }
+
+  /// Returns the columns of the table, starting with `A`.
+  pub fn cols(&self) -> impl Iterator<Item = char> {
+    'A'..=self.last_col()
+  }
+
+  pub fn contains(&self, cell_id: CellId) -> bool {
+    cell_id.col <= self.last_col() && cell_id.row <= self.rows
+  }
+
+  /// Returns the size grown to contain the cell.
+  pub fn grown_to(&self, cell_id: CellId) -> Size {
+    Size {
+      rows: self.rows.max(cell_id.row),
+      cols: self.cols.max((cell_id.col as u8 - b'A') as usize + 1),
+    }
+  }
+}
+
 impl Display for CellId {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "{}{:02}", self.col, self.row)
   }
 }
 
+/// Cells are ordered row by row, like `CellId::range` returns them.
+impl Ord for CellId {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.row, self.col).cmp(&(other.row, other.col))
+  }
+}
+
+impl PartialOrd for CellId {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl CellId {
+  /// Returns all `CellId`s in the rectangle spanned by `from` and `to` (inclusive),
+  /// row by row. The corners can be given in any order.
+  pub fn range(from: CellId, to: CellId) -> impl Iterator<Item = CellId> {
+    let (min_col, max_col) = (from.col.min(to.col), from.col.max(to.col));
+    let (min_row, max_row) = (from.row.min(to.row), from.row.max(to.row));
+
+    (min_row..=max_row).flat_map(move |row| (min_col..=max_col).map(move |col| CellId { col, row }))
+  }
+
+  /// Whether the cell can be in the table, which grows to contain it.
+  pub fn is_valid(&self) -> bool {
+    self.col.is_ascii_uppercase() && self.row >= 1
+  }
+
+  /// Returns the cell to the right, wrapping to the first column of the next row
+  /// after the last column of the table.
+  pub fn next_in_row(&self, size: Size) -> CellId {
+    if self.col >= size.last_col() {
+      CellId {
+        col: 'A',
+        row: self.row + 1,
+      }
+    } else {
+      CellId {
+        col: (self.col as u8 + 1) as char,
+        row: self.row,
+      }
+    }
+  }
+
+  /// Returns the cell to the left, wrapping to the last column of the previous row,
+  /// or `None` in the first cell of the table.
+  pub fn prev_in_row(&self, size: Size) -> Option<CellId> {
+    match self.col {
+      'A' if self.row <= 1 => None,
+      'A' => Some(CellId {
+        col: size.last_col(),
+        row: self.row - 1,
+      }),
+      col => Some(CellId {
+        col: (col as u8 - 1) as char,
+        row: self.row,
+      }),
+    }
+  }
+}
+
 impl TryFrom<&str> for CellId {
   type Error = &'static str;
 
@@ -70,4 +171,70 @@ mod tests {
       Err("malformed cell id: missing or non-existent row (should be a positive integer)")
     );
   }
+
+  #[test]
+  fn range_test() {
+    let a1 = CellId { col: 'A', row: 1 };
+    let b2 = CellId { col: 'B', row: 2 };
+    let expected = vec![
+      a1,
+      CellId { col: 'B', row: 1 },
+      CellId { col: 'A', row: 2 },
+      b2,
+    ];
+
+    assert_eq!(CellId::range(a1, b2).collect::<Vec<_>>(), expected);
+    assert_eq!(CellId::range(b2, a1).collect::<Vec<_>>(), expected);
+
+    let mut sorted = expected.clone();
+    sorted.reverse();
+    sorted.sort();
+    assert_eq!(sorted, expected);
+    assert_eq!(CellId::range(a1, a1).collect::<Vec<_>>(), vec![a1]);
+  }
+
+  #[test]
+  fn next_and_prev_in_row_test() {
+    let size = Size::default();
+    let a1 = CellId { col: 'A', row: 1 };
+    let b1 = CellId { col: 'B', row: 1 };
+    let j1 = CellId { col: 'J', row: 1 };
+    let a2 = CellId { col: 'A', row: 2 };
+    let j50 = CellId { col: 'J', row: 50 };
+    let z50 = CellId { col: 'Z', row: 50 };
+
+    assert_eq!(a1.next_in_row(size), b1);
+    assert_eq!(b1.prev_in_row(size), Some(a1));
+    assert_eq!(j1.next_in_row(size), a2);
+    assert_eq!(a2.prev_in_row(size), Some(j1));
+    assert_eq!(a1.prev_in_row(size), None);
+    assert_eq!(j50.next_in_row(size), CellId { col: 'A', row: 51 });
+
+    assert!(z50.is_valid());
+    assert!(!CellId { col: 'A', row: 0 }.is_valid());
+    assert!(CellId { col: 'A', row: 51 }.is_valid());
+  }
+
+  #[test]
+  fn size_test() {
+    let size = Size { rows: 10, cols: 3 };
+    assert_eq!(size.last_col(), 'C');
+    assert_eq!(size.cols().collect::<String>(), "ABC");
+    assert!(size.contains(CellId { col: 'C', row: 10 }));
+    assert!(!size.contains(CellId { col: 'D', row: 1 }));
+    assert!(!size.contains(CellId { col: 'A', row: 11 }));
+    assert_eq!(
+      size.grown_to(CellId { col: 'E', row: 2 }),
+      Size { rows: 10, cols: 5 }
+    );
+    assert_eq!(
+      size.grown_to(CellId { col: 'A', row: 60 }),
+      Size { rows: 60, cols: 3 }
+    );
+
+    // the columns grow like the rows, up to `Z`
+    let size = Size::default().grown_to(CellId { col: 'Z', row: 1 });
+    assert_eq!(size.cols, MAX_COLS);
+    assert_eq!(size.last_col(), 'Z');
+  }
 }
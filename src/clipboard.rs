@@ -0,0 +1,263 @@
+//! Copying and pasting ranges of cells. The system clipboard gets the displayed values
+//! as tab-separated text, which other spreadsheets understand, while the inputs are kept
+//! in `Clipboard` to paste the formulas within the table. Text copied from other spreadsheets
+//! is pasted as tab- or comma-separated values, see `parse_delimited`.
+use crate::cell_id::CellId;
+use crate::selection::Selection;
+
+/// What to paste from `Clipboard`, chosen in the Paste Special dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasteMode {
+  /// The inputs, including formulas, as Ctrl+V does.
+  #[default]
+  Formulas,
+  /// The displayed values instead of the formulas.
+  Values,
+  /// The inputs with the rows and the columns swapped.
+  Transpose,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clipboard {
+  pub source: Selection,
+  /// Inputs of the copied cells, row by row.
+  pub inputs: Vec<Vec<String>>,
+  /// Displayed values of the copied cells, row by row.
+  pub values: Vec<Vec<String>>,
+  /// The text written to the system clipboard.
+  pub text: String,
+  /// Whether the source cells are cleared on paste.
+  pub is_cut: bool,
+}
+
+impl Clipboard {
+  /// Copies the selected cells, given their inputs and displayed values.
+  pub fn new(
+    source: Selection,
+    is_cut: bool,
+    input: impl Fn(CellId) -> String,
+    display: impl Fn(CellId) -> String,
+  ) -> Self {
+    let (top_left, bottom_right) = source.bounds();
+    let rows = |cell_text: &dyn Fn(CellId) -> String| {
+      (top_left.row..=bottom_right.row)
+        .map(|row| {
+          (top_left.col..=bottom_right.col)
+            .map(|col| cell_text(CellId { col, row }))
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+    };
+
+    let values = rows(&display);
+    Clipboard {
+      source,
+      inputs: rows(&input),
+      text: to_tsv(&values),
+      values,
+      is_cut,
+    }
+  }
+
+  /// Whether the text read from the system clipboard was copied from the table,
+  /// ignoring the line endings changed by the operating system.
+  pub fn matches(&self, text: &str) -> bool {
+    self.text == text.replace("\r\n", "\n").trim_end_matches('\n')
+  }
+
+  /// Returns the inputs to set to paste the cells with the top left corner at `to`,
+  /// including the cleared source cells of a cut. Cells after the column `Z` are skipped.
+  pub fn paste(&self, to: CellId, mode: PasteMode) -> Vec<(CellId, String)> {
+    let pasted = match mode {
+      PasteMode::Formulas => paste_rows(&self.inputs, to),
+      PasteMode::Values => paste_rows(&self.values, to),
+      PasteMode::Transpose => paste_rows(&transpose(&self.inputs), to),
+    };
+
+    let cleared = self
+      .source
+      .cells()
+      .filter(|cell_id| self.is_cut && !pasted.iter().any(|(pasted_id, _)| pasted_id == cell_id))
+      .map(|cell_id| (cell_id, String::new()));
+
+    cleared.chain(pasted.iter().cloned()).collect()
+  }
+}
+
+fn transpose(rows: &[Vec<String>]) -> Vec<Vec<String>> {
+  let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+  (0..width)
+    .map(|col| {
+      rows
+        .iter()
+        .map(|row| row.get(col).cloned().unwrap_or_default())
+        .collect()
+    })
+    .collect()
+}
+
+/// Returns the inputs to set to paste the rows with the top left corner at `to`,
+/// skipping the cells after the column `Z`.
+pub fn paste_rows(rows: &[Vec<String>], to: CellId) -> Vec<(CellId, String)> {
+  rows
+    .iter()
+    .enumerate()
+    .flat_map(|(row_offset, row)| {
+      row.iter().enumerate().map(move |(col_offset, input)| {
+        // columns after `Z` are outside of the table
+        let cell_id = CellId {
+          col: (to.col as u8 + col_offset.min(26) as u8) as char,
+          row: to.row + row_offset,
+        };
+        (cell_id, input.clone())
+      })
+    })
+    .filter(|(cell_id, _)| cell_id.is_valid())
+    .collect()
+}
+
+/// Splits the text copied from other spreadsheets into rows of cells: by tabs, if there are any,
+/// or by commas, if there are several lines, or returns the text as a single cell otherwise.
+/// Cells can be quoted with `"` to include separators, newlines, and `""` for quotes.
+pub fn parse_delimited(text: &str) -> Vec<Vec<String>> {
+  let text = text.replace("\r\n", "\n");
+  let text = text.strip_suffix('\n').unwrap_or(&text);
+  let separator = if text.contains('\t') {
+    '\t'
+  } else if text.contains('\n') {
+    ','
+  } else {
+    return vec![vec![text.to_string()]];
+  };
+
+  let mut rows = vec![];
+  let mut row = vec![];
+  let mut cell = String::new();
+  let mut is_quoted = false;
+  let mut chars = text.chars().peekable();
+  while let Some(ch) = chars.next() {
+    match ch {
+      '"' if is_quoted && chars.peek() == Some(&'"') => {
+        chars.next();
+        cell.push('"');
+      }
+      '"' if is_quoted => is_quoted = false,
+      '"' if cell.is_empty() => is_quoted = true,
+      ch if ch == separator && !is_quoted => row.push(std::mem::take(&mut cell)),
+      '\n' if !is_quoted => {
+        row.push(std::mem::take(&mut cell));
+        rows.push(std::mem::take(&mut row));
+      }
+      ch => cell.push(ch),
+    }
+  }
+  row.push(cell);
+  rows.push(row);
+
+  rows
+}
+
+/// Joins the cells with tabs, and the rows with newlines.
+pub fn to_tsv(rows: &[Vec<String>]) -> String {
+  rows
+    .iter()
+    .map(|row| row.join("\t"))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn clipboard_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let source = Selection {
+      anchor: cell_id("A1"),
+      extent: cell_id("B2"),
+    };
+    let input = |cell_id: CellId| format!("= {cell_id}");
+    let display = |cell_id: CellId| cell_id.to_string();
+
+    let copied = Clipboard::new(source, false, input, display);
+    assert_eq!(copied.text, "A01\tB01\nA02\tB02");
+    assert!(copied.matches("A01\tB01\r\nA02\tB02\r\n"));
+    assert!(!copied.matches("A01"));
+    assert_eq!(
+      copied.paste(cell_id("Y50"), PasteMode::Formulas),
+      vec![
+        (cell_id("Y50"), String::from("= A01")),
+        (cell_id("Z50"), String::from("= B01")),
+        (cell_id("Y51"), String::from("= A02")),
+        (cell_id("Z51"), String::from("= B02")),
+      ]
+    );
+
+    let cut = Clipboard::new(source, true, input, display);
+    assert_eq!(
+      cut.paste(cell_id("B2"), PasteMode::Formulas),
+      vec![
+        (cell_id("A1"), String::new()),
+        (cell_id("B1"), String::new()),
+        (cell_id("A2"), String::new()),
+        (cell_id("B2"), String::from("= A01")),
+        (cell_id("C2"), String::from("= B01")),
+        (cell_id("B3"), String::from("= A02")),
+        (cell_id("C3"), String::from("= B02")),
+      ]
+    );
+
+    assert_eq!(
+      copied.paste(cell_id("C1"), PasteMode::Values),
+      vec![
+        (cell_id("C1"), String::from("A01")),
+        (cell_id("D1"), String::from("B01")),
+        (cell_id("C2"), String::from("A02")),
+        (cell_id("D2"), String::from("B02")),
+      ]
+    );
+    assert_eq!(
+      copied.paste(cell_id("C1"), PasteMode::Transpose),
+      vec![
+        (cell_id("C1"), String::from("= A01")),
+        (cell_id("D1"), String::from("= A02")),
+        (cell_id("C2"), String::from("= B01")),
+        (cell_id("D2"), String::from("= B02")),
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_delimited_test() {
+    let rows = |rows: &[&[&str]]| {
+      rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      parse_delimited("1\t2\r\n3\t\r\n"),
+      rows(&[&["1", "2"], &["3", ""]])
+    );
+    assert_eq!(
+      parse_delimited("a,b\n1,2"),
+      rows(&[&["a", "b"], &["1", "2"]])
+    );
+    assert_eq!(parse_delimited("Hello, world"), rows(&[&["Hello, world"]]));
+    assert_eq!(
+      parse_delimited("\"a\tb\"\t\"say \"\"hi\"\"\"\n\"multi\nline\"\t= 1"),
+      rows(&[&["a\tb", "say \"hi\""], &["multi\nline", "= 1"]])
+    );
+
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    assert_eq!(
+      paste_rows(&rows(&[&["1", "2"], &["3"]]), cell_id("Z49")),
+      vec![
+        (cell_id("Z49"), String::from("1")),
+        (cell_id("Z50"), String::from("3")),
+      ]
+    );
+  }
+}
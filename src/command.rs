@@ -0,0 +1,949 @@
+//! Changes of the table, such as typing, pasting, or switching the settings, as commands,
+//! which can be applied and inverted to undo them, see `crate::history`.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::cell_id::{CellId, MAX_COLS};
+use crate::expr::{NegPrecedence, NumberMode};
+use crate::parser::map_refs;
+use crate::selection::Selection;
+use crate::style::Style;
+use crate::validation::Rule;
+use crate::value::Value;
+
+/// The state changed by the commands; the `Table` recomputes the cells after applying them.
+pub trait CommandTarget {
+  /// Returns the cells with inputs.
+  fn cells(&self) -> Vec<CellId>;
+  /// Returns the input of the cell, or an empty string for empty cells.
+  fn input(&self, cell_id: CellId) -> String;
+  /// Returns the computed value of the cell, or `None` if it failed, e.g., to sort by it.
+  fn value(&self, cell_id: CellId) -> Option<Value>;
+  /// Sets the input of the cell; an empty string clears it.
+  fn set_input(&mut self, cell_id: CellId, input: String);
+  fn neg_precedence(&self) -> NegPrecedence;
+  fn set_neg_precedence(&mut self, neg_precedence: NegPrecedence);
+  fn number_mode(&self) -> NumberMode;
+  fn set_number_mode(&mut self, number_mode: NumberMode);
+  /// Returns the merged cells.
+  fn merges(&self) -> Vec<Selection>;
+  /// Merges the cells of the selection into its top left cell, or splits them.
+  fn set_merged(&mut self, selection: Selection, is_merged: bool);
+  /// Returns the style of the cell, or the default one for unstyled cells.
+  fn style(&self, cell_id: CellId) -> Style;
+  fn set_style(&mut self, cell_id: CellId, style: Style);
+  /// Returns the cells with styles.
+  fn styled_cells(&self) -> Vec<CellId>;
+  /// Returns the validation rules of the cells; the later rules take precedence.
+  fn validations(&self) -> Vec<(Selection, Rule)>;
+  fn set_validations(&mut self, validations: Vec<(Selection, Rule)>);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+  /// Sets the inputs of the cells in order, e.g., when typing, pasting, or filling.
+  SetInputs(Vec<(CellId, String)>),
+  /// Sets the styles of the cells, e.g., making the selected cells bold.
+  SetStyles(Vec<(CellId, Style)>),
+  SetNegPrecedence(NegPrecedence),
+  SetNumberMode(NumberMode),
+  /// Replaces the validation rules, e.g., to shift them with the cells.
+  SetValidations(Vec<(Selection, Rule)>),
+  /// Inserts an empty row before the row, shifting the rows below it down.
+  InsertRow(usize),
+  /// Deletes the row, shifting the rows below it up.
+  DeleteRow(usize),
+  /// Inserts an empty column before the column, shifting the columns after it right.
+  InsertCol(char),
+  /// Deletes the column, shifting the columns after it left.
+  DeleteCol(char),
+  /// Merges the cells into the top left one, e.g., for headings, clearing the others.
+  Merge(Selection),
+  /// Splits the merged cells, and sets the inputs of the cells merged away, e.g., to undo merging.
+  Unmerge(Selection, Vec<(CellId, String)>),
+  /// Replaces the merged cells, keeping the inputs, e.g., to shift them with the cells.
+  SetMerges(Vec<Selection>),
+  /// Sorts the rows from the first to the last one by the values in the column, moving
+  /// entire rows; numbers go before text, and failed and empty cells go last.
+  SortRows {
+    col: char,
+    rows: (usize, usize),
+    descending: bool,
+  },
+  /// Applies the commands in order, e.g., moving the inputs of the cells and their styles.
+  Batch(Vec<Command>),
+}
+
+impl Command {
+  pub fn apply(&self, target: &mut impl CommandTarget) {
+    match self {
+      Command::SetInputs(inputs) => {
+        for (cell_id, input) in inputs {
+          target.set_input(*cell_id, input.clone());
+        }
+      }
+      Command::SetStyles(styles) => {
+        for (cell_id, style) in styles {
+          target.set_style(*cell_id, style.clone());
+        }
+      }
+      Command::SetNegPrecedence(neg_precedence) => target.set_neg_precedence(*neg_precedence),
+      Command::SetNumberMode(number_mode) => target.set_number_mode(*number_mode),
+      Command::SetValidations(validations) => target.set_validations(validations.clone()),
+      Command::Merge(selection) => {
+        Command::SetInputs(
+          merged_away(*selection)
+            .map(|cell_id| (cell_id, String::new()))
+            .collect(),
+        )
+        .apply(target);
+        target.set_merged(*selection, true);
+      }
+      Command::Unmerge(selection, inputs) => {
+        target.set_merged(*selection, false);
+        Command::SetInputs(inputs.clone()).apply(target);
+      }
+      Command::SetMerges(merges) => {
+        let before = target.merges();
+        for merged in before.iter().filter(|merged| !merges.contains(merged)) {
+          target.set_merged(*merged, false);
+        }
+        for merged in merges.iter().filter(|merged| !before.contains(merged)) {
+          target.set_merged(*merged, true);
+        }
+      }
+      Command::Batch(commands) => {
+        for command in commands {
+          command.apply(target);
+        }
+      }
+      _ => self.moved(target).apply(target),
+    }
+  }
+
+  /// Returns the command reverting this one, given the state before applying it.
+  pub fn invert(&self, target: &impl CommandTarget) -> Command {
+    match self {
+      Command::SetInputs(inputs) => {
+        // a cell can be set several times, e.g., cleared and then pasted into
+        let mut set = HashMap::new();
+        let mut inverse = inputs
+          .iter()
+          .map(|(cell_id, input)| {
+            let before = set
+              .insert(*cell_id, input.clone())
+              .unwrap_or_else(|| target.input(*cell_id));
+            (*cell_id, before)
+          })
+          .collect::<Vec<_>>();
+        inverse.reverse();
+        Command::SetInputs(inverse)
+      }
+      Command::SetStyles(styles) => Command::SetStyles(
+        styles
+          .iter()
+          .map(|(cell_id, _)| (*cell_id, target.style(*cell_id)))
+          .collect(),
+      ),
+      Command::SetNegPrecedence(_) => Command::SetNegPrecedence(target.neg_precedence()),
+      Command::SetNumberMode(_) => Command::SetNumberMode(target.number_mode()),
+      Command::SetValidations(_) => Command::SetValidations(target.validations()),
+      Command::Merge(selection) => Command::Unmerge(
+        *selection,
+        merged_away(*selection)
+          .map(|cell_id| (cell_id, target.input(cell_id)))
+          .filter(|(_, input)| !input.is_empty())
+          .collect(),
+      ),
+      Command::Unmerge(selection, _) => Command::Merge(*selection),
+      Command::SetMerges(_) => Command::SetMerges(target.merges()),
+      // the commands of a batch change different things, so each of them is inverted
+      // given the state before the batch
+      Command::Batch(commands) => Command::Batch(
+        commands
+          .iter()
+          .rev()
+          .map(|command| command.invert(target))
+          .collect(),
+      ),
+      _ => self.moved(target).invert(target),
+    }
+  }
+
+  /// Returns why the command can't be applied, e.g., inserting a column would shift
+  /// the cells of the last column out of the table.
+  pub fn check(&self, target: &impl CommandTarget) -> Result<(), String> {
+    let last_col = (b'A' + MAX_COLS as u8 - 1) as char;
+    match *self {
+      Command::InsertCol(inserted)
+        if inserted <= last_col
+          && target
+            .cells()
+            .into_iter()
+            .chain(target.styled_cells())
+            .any(|cell_id| cell_id.col == last_col) =>
+      {
+        Err(format!(
+          "cannot insert a column, as column {last_col} isn't empty"
+        ))
+      }
+      _ => Ok(()),
+    }
+  }
+
+  /// Returns the new position of the cell after inserting or deleting a row or a column,
+  /// or `None` if the cell is deleted or shifted past the column `Z`.
+  fn move_cell(&self, cell_id: CellId) -> Option<CellId> {
+    let CellId { col, row } = cell_id;
+    let shift_col = |offset: i8| (col as u8).wrapping_add_signed(offset) as char;
+    let moved = match *self {
+      Command::InsertRow(inserted) if row >= inserted => CellId { row: row + 1, col },
+      Command::DeleteRow(deleted) if row == deleted => return None,
+      Command::DeleteRow(deleted) if row > deleted => CellId { row: row - 1, col },
+      Command::InsertCol(inserted) if col >= inserted => CellId {
+        col: shift_col(1),
+        row,
+      },
+      Command::DeleteCol(deleted) if col == deleted => return None,
+      Command::DeleteCol(deleted) if col > deleted => CellId {
+        col: shift_col(-1),
+        row,
+      },
+      _ => cell_id,
+    };
+    Some(moved).filter(CellId::is_valid)
+  }
+
+  /// Returns the new bounds of the cells after inserting or deleting a row or a column, or
+  /// sorting the rows, or `None` if all of them are deleted, or their rows are sorted apart.
+  fn move_range(
+    &self,
+    selection: Selection,
+    sorted_rows: &HashMap<usize, usize>,
+  ) -> Option<Selection> {
+    let last_col = b'A' as usize + MAX_COLS - 1;
+    let (top_left, bottom_right) = selection.bounds();
+    let rows = (top_left.row, bottom_right.row);
+    let cols = (top_left.col as usize, bottom_right.col as usize);
+    // the inserted row or column widens the range if it's inside, and the deleted one narrows it
+    let insert = |(first, last): (usize, usize), inserted: usize| {
+      (
+        first + usize::from(first >= inserted),
+        last + usize::from(last >= inserted),
+      )
+    };
+    let delete = |(first, last): (usize, usize), deleted: usize| {
+      (first != deleted || last != deleted).then(|| {
+        (
+          first - usize::from(first > deleted),
+          last - usize::from(last >= deleted),
+        )
+      })
+    };
+
+    let (rows, cols) = match *self {
+      Command::InsertRow(inserted) => (insert(rows, inserted), cols),
+      Command::DeleteRow(deleted) => (delete(rows, deleted)?, cols),
+      Command::InsertCol(inserted) => {
+        let (first, last) = insert(cols, inserted as usize);
+        (rows, (first, last.min(last_col)))
+      }
+      Command::DeleteCol(deleted) => (rows, delete(cols, deleted as usize)?),
+      Command::SortRows { .. } => {
+        let moved = (rows.0..=rows.1).map(|row| sorted_rows.get(&row).copied().unwrap_or(row));
+        let (first, last) = (moved.clone().min()?, moved.max()?);
+        // the sorted rows are moved to different rows, so they stay together only if they fit
+        (last - first == rows.1 - rows.0).then_some(((first, last), cols))?
+      }
+      _ => (rows, cols),
+    };
+
+    let cell_id = |row: usize, col: usize| CellId {
+      col: col as u8 as char,
+      row,
+    };
+    (cols.0 <= cols.1).then(|| Selection {
+      anchor: cell_id(rows.0, cols.0),
+      extent: cell_id(rows.1, cols.1),
+    })
+  }
+
+  /// Returns the new rows of the sorted rows.
+  fn sorted_rows(&self, target: &impl CommandTarget) -> HashMap<usize, usize> {
+    let Command::SortRows {
+      col,
+      rows: (first, last),
+      descending,
+    } = *self
+    else {
+      return HashMap::new();
+    };
+
+    let mut rows = (first..=last)
+      .map(|row| (row, target.value(CellId { col, row })))
+      .collect::<Vec<_>>();
+    // failed and empty cells go last in both orders
+    let rank = |value: &Option<Value>| match value {
+      Some(value) if !value.is_blank() => 0,
+      None => 1,
+      Some(_) => 2,
+    };
+    rows.sort_by(|(_, a), (_, b)| {
+      rank(a).cmp(&rank(b)).then_with(|| match (a, b) {
+        (Some(a), Some(b)) => {
+          let ordering = a.compare(b).unwrap_or(Ordering::Equal);
+          if descending {
+            ordering.reverse()
+          } else {
+            ordering
+          }
+        }
+        _ => Ordering::Equal,
+      })
+    });
+
+    (first..=last)
+      .zip(rows)
+      .map(|(to, (from, _))| (from, to))
+      .collect()
+  }
+
+  /// Returns the commands moving the cells with their styles, number formats, validation rules,
+  /// and merges after inserting or deleting a row or a column, or sorting the rows, with
+  /// the references in the formulas rewritten; references to the deleted cells become `#REF!`.
+  /// The merges sorted apart are split, as their top left cells no longer stay on top.
+  fn moved(&self, target: &impl CommandTarget) -> Command {
+    let sorted_rows = self.sorted_rows(target);
+    let move_cell = |cell_id: CellId| match sorted_rows.get(&cell_id.row) {
+      Some(row) => Some(CellId {
+        row: *row,
+        ..cell_id
+      }),
+      None => self.move_cell(cell_id),
+    };
+
+    Command::Batch(vec![
+      Command::SetInputs(move_values(
+        target.cells(),
+        move_cell,
+        |cell_id| map_refs(&target.input(cell_id), move_cell),
+        |cell_id| target.input(cell_id),
+      )),
+      Command::SetStyles(move_values(
+        target.styled_cells(),
+        move_cell,
+        |cell_id| target.style(cell_id),
+        |cell_id| target.style(cell_id),
+      )),
+      Command::SetValidations(
+        target
+          .validations()
+          .into_iter()
+          .filter_map(|(selection, rule)| Some((self.move_range(selection, &sorted_rows)?, rule)))
+          .collect(),
+      ),
+      Command::SetMerges(
+        target
+          .merges()
+          .into_iter()
+          .filter_map(|merged| {
+            let moved = self.move_range(merged, &sorted_rows)?;
+            let ((top_left, _), (moved_top_left, _)) = (merged.bounds(), moved.bounds());
+            // the top left cell keeps the input, unless it's deleted
+            let is_top_left = move_cell(top_left).map_or(true, |cell_id| cell_id == moved_top_left);
+            (is_top_left && !moved.is_single_cell()).then_some(moved)
+          })
+          .collect(),
+      ),
+    ])
+  }
+}
+
+/// Returns the values to set to move the cells, e.g., their inputs or styles; the cells left
+/// behind are cleared before the moved ones are set, as they can overlap.
+fn move_values<T: Default + PartialEq>(
+  mut cells: Vec<CellId>,
+  move_cell: impl Fn(CellId) -> Option<CellId>,
+  moved_value: impl Fn(CellId) -> T,
+  value: impl Fn(CellId) -> T,
+) -> Vec<(CellId, T)> {
+  cells.sort();
+
+  let mut moved = HashMap::new();
+  for &cell_id in &cells {
+    if let Some(to) = move_cell(cell_id) {
+      moved.insert(to, moved_value(cell_id));
+    }
+  }
+
+  let cleared = cells
+    .iter()
+    .filter(|cell_id| !moved.contains_key(cell_id))
+    .map(|cell_id| (*cell_id, T::default()))
+    .collect::<Vec<_>>();
+  let mut set = moved
+    .into_iter()
+    .filter(|(cell_id, moved)| value(*cell_id) != *moved)
+    .collect::<Vec<_>>();
+  set.sort_by_key(|(cell_id, _)| *cell_id);
+
+  cleared.into_iter().chain(set).collect()
+}
+
+/// Returns the cells of the selection, except its top left cell, which stays after merging.
+fn merged_away(selection: Selection) -> impl Iterator<Item = CellId> {
+  let (top_left, _) = selection.bounds();
+  selection
+    .cells()
+    .filter(move |cell_id| *cell_id != top_left)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::format::NumberFormat;
+
+  #[derive(Default)]
+  struct Target {
+    inputs: HashMap<CellId, String>,
+    neg_precedence: NegPrecedence,
+    number_mode: NumberMode,
+    merges: Vec<Selection>,
+    styles: HashMap<CellId, Style>,
+    validations: Vec<(Selection, Rule)>,
+  }
+
+  impl CommandTarget for Target {
+    fn cells(&self) -> Vec<CellId> {
+      self.inputs.keys().copied().collect()
+    }
+
+    fn input(&self, cell_id: CellId) -> String {
+      self.inputs.get(&cell_id).cloned().unwrap_or_default()
+    }
+
+    fn value(&self, cell_id: CellId) -> Option<Value> {
+      match self.inputs.get(&cell_id) {
+        Some(input) if input.starts_with('=') => None,
+        Some(input) => Some(
+          input
+            .parse()
+            .map_or_else(|_| Value::Str(input.clone()), Value::Num),
+        ),
+        None => Some(Value::Empty),
+      }
+    }
+
+    fn set_input(&mut self, cell_id: CellId, input: String) {
+      if input.is_empty() {
+        self.inputs.remove(&cell_id);
+      } else {
+        self.inputs.insert(cell_id, input);
+      }
+    }
+
+    fn neg_precedence(&self) -> NegPrecedence {
+      self.neg_precedence
+    }
+
+    fn set_neg_precedence(&mut self, neg_precedence: NegPrecedence) {
+      self.neg_precedence = neg_precedence;
+    }
+
+    fn number_mode(&self) -> NumberMode {
+      self.number_mode
+    }
+
+    fn set_number_mode(&mut self, number_mode: NumberMode) {
+      self.number_mode = number_mode;
+    }
+
+    fn merges(&self) -> Vec<Selection> {
+      self.merges.clone()
+    }
+
+    fn set_merged(&mut self, selection: Selection, is_merged: bool) {
+      self.merges.retain(|merged| *merged != selection);
+      if is_merged {
+        self.merges.push(selection);
+      }
+    }
+
+    fn style(&self, cell_id: CellId) -> Style {
+      self.styles.get(&cell_id).cloned().unwrap_or_default()
+    }
+
+    fn set_style(&mut self, cell_id: CellId, style: Style) {
+      if style.is_default() {
+        self.styles.remove(&cell_id);
+      } else {
+        self.styles.insert(cell_id, style);
+      }
+    }
+
+    fn styled_cells(&self) -> Vec<CellId> {
+      self.styles.keys().copied().collect()
+    }
+
+    fn validations(&self) -> Vec<(Selection, Rule)> {
+      self.validations.clone()
+    }
+
+    fn set_validations(&mut self, validations: Vec<(Selection, Rule)>) {
+      self.validations = validations;
+    }
+  }
+
+  #[test]
+  fn command_test() {
+    let a1 = CellId { col: 'A', row: 1 };
+    let b1 = CellId { col: 'B', row: 1 };
+    let mut target = Target::default();
+    target.set_input(a1, String::from("1"));
+
+    let command = Command::SetInputs(vec![
+      (a1, String::new()),
+      (b1, String::from("2")),
+      (a1, String::from("3")),
+    ]);
+    let inverse = command.invert(&target);
+    assert_eq!(
+      inverse,
+      Command::SetInputs(vec![
+        (a1, String::new()),
+        (b1, String::new()),
+        (a1, String::from("1")),
+      ])
+    );
+
+    command.apply(&mut target);
+    assert_eq!(target.input(a1), "3");
+    assert_eq!(target.input(b1), "2");
+    inverse.apply(&mut target);
+    assert_eq!(target.input(a1), "1");
+    assert_eq!(target.input(b1), "");
+
+    let command = Command::SetNumberMode(NumberMode::Decimal);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.number_mode(), NumberMode::Decimal);
+    inverse.apply(&mut target);
+    assert_eq!(target.number_mode(), NumberMode::Float);
+  }
+
+  #[test]
+  fn rows_and_cols_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    for (id, input) in [("A1", "1"), ("A2", "= A1 * 2"), ("B3", "= SUM(A1:A2) + B1")] {
+      target.set_input(cell_id(id), input.to_string());
+    }
+    let inputs = |target: &Target| {
+      let mut inputs = target
+        .inputs
+        .iter()
+        .map(|(cell_id, input)| (cell_id.to_string(), input.clone()))
+        .collect::<Vec<_>>();
+      inputs.sort();
+      inputs
+    };
+    let before = inputs(&target);
+
+    let command = Command::InsertRow(2);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(
+      inputs(&target),
+      vec![
+        (String::from("A01"), String::from("1")),
+        (String::from("A03"), String::from("= A01 * 2")),
+        (String::from("B04"), String::from("= SUM(A01:A03) + B01")),
+      ]
+    );
+    inverse.apply(&mut target);
+    assert_eq!(inputs(&target), before);
+
+    let command = Command::DeleteCol('A');
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(
+      inputs(&target),
+      vec![(String::from("A03"), String::from("= SUM(#REF!) + A01"))]
+    );
+    inverse.apply(&mut target);
+    assert_eq!(inputs(&target), before);
+
+    Command::DeleteRow(1).apply(&mut target);
+    assert_eq!(
+      inputs(&target),
+      vec![
+        (String::from("A01"), String::from("= #REF! * 2")),
+        (String::from("B02"), String::from("= SUM(#REF!) + #REF!")),
+      ]
+    );
+
+    // the last column would be shifted out of the table
+    assert_eq!(Command::InsertCol('B').check(&target), Ok(()));
+    target.set_input(cell_id("Z1"), String::from("1"));
+    assert!(Command::InsertCol('B').check(&target).is_err());
+    assert_eq!(Command::InsertRow(1).check(&target), Ok(()));
+  }
+
+  #[test]
+  fn merge_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    target.set_input(cell_id("A1"), String::from("Title"));
+    target.set_input(cell_id("B1"), String::from("1"));
+    let selection = Selection {
+      anchor: cell_id("A1"),
+      extent: cell_id("C1"),
+    };
+
+    let command = Command::Merge(selection);
+    let inverse = command.invert(&target);
+    assert_eq!(
+      inverse,
+      Command::Unmerge(selection, vec![(cell_id("B1"), String::from("1"))])
+    );
+
+    command.apply(&mut target);
+    assert_eq!(target.merges, vec![selection]);
+    assert_eq!(target.input(cell_id("A1")), "Title");
+    assert_eq!(target.input(cell_id("B1")), "");
+
+    inverse.apply(&mut target);
+    assert_eq!(target.merges, vec![]);
+    assert_eq!(target.input(cell_id("B1")), "1");
+    assert_eq!(inverse.invert(&target), command);
+  }
+
+  #[test]
+  fn moved_merges_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let range = |range: &str| {
+      let (anchor, extent) = range.split_once(':').unwrap();
+      Selection {
+        anchor: cell_id(anchor),
+        extent: cell_id(extent),
+      }
+    };
+    let merged = |target: &Target| {
+      let mut merged = target
+        .merges()
+        .into_iter()
+        .map(|selection| {
+          let (top_left, bottom_right) = selection.bounds();
+          format!("{top_left}:{bottom_right}")
+        })
+        .collect::<Vec<_>>();
+      merged.sort();
+      merged
+    };
+    let mut target = Target::default();
+    target.set_input(cell_id("A2"), String::from("Title"));
+    Command::Merge(range("A2:B3")).apply(&mut target);
+
+    // inserting a row above the merged cells shifts them with the input
+    let command = Command::InsertRow(1);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(merged(&target), ["A03:B04"]);
+    assert_eq!(target.input(cell_id("A3")), "Title");
+    inverse.apply(&mut target);
+    assert_eq!(merged(&target), ["A02:B03"]);
+    assert_eq!(target.input(cell_id("A2")), "Title");
+
+    // inserting a row inside the merged cells widens them, and deleting one narrows them
+    let command = Command::InsertRow(3);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(merged(&target), ["A02:B04"]);
+    inverse.apply(&mut target);
+    Command::DeleteCol('B').apply(&mut target);
+    assert_eq!(merged(&target), ["A02:A03"]);
+    Command::DeleteRow(3).apply(&mut target);
+    assert_eq!(merged(&target), Vec::<String>::new());
+    assert_eq!(target.input(cell_id("A2")), "Title");
+  }
+
+  #[test]
+  fn sort_merges_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let range = |range: &str| {
+      let (anchor, extent) = range.split_once(':').unwrap();
+      Selection {
+        anchor: cell_id(anchor),
+        extent: cell_id(extent),
+      }
+    };
+    let mut target = Target::default();
+    for (id, input) in [
+      ("A2", "c"),
+      ("B2", "Third"),
+      ("A3", "a"),
+      ("A4", "b"),
+      ("D2", "Left"),
+    ] {
+      target.set_input(cell_id(id), input.to_string());
+    }
+    Command::Merge(range("B2:C2")).apply(&mut target);
+    Command::Merge(range("D2:D3")).apply(&mut target);
+
+    // the merged cells in a row are sorted with it, and the ones across the rows are split
+    let command = Command::SortRows {
+      col: 'A',
+      rows: (2, 4),
+      descending: false,
+    };
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.merges(), vec![range("B4:C4")]);
+    assert_eq!(target.input(cell_id("B4")), "Third");
+    assert_eq!(target.input(cell_id("D4")), "Left");
+
+    inverse.apply(&mut target);
+    assert_eq!(target.merges().len(), 2);
+    assert!(target.merges().contains(&range("B2:C2")));
+    assert!(target.merges().contains(&range("D2:D3")));
+    assert_eq!(target.input(cell_id("B2")), "Third");
+    assert_eq!(target.input(cell_id("D2")), "Left");
+  }
+
+  #[test]
+  fn set_styles_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    let bold = Style {
+      bold: true,
+      ..Style::default()
+    };
+    target.set_style(cell_id("A1"), bold.clone());
+
+    let command = Command::SetStyles(vec![
+      (cell_id("A1"), Style::default()),
+      (cell_id("B1"), bold.clone()),
+    ]);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.style(cell_id("A1")), Style::default());
+    assert_eq!(target.style(cell_id("B1")), bold);
+
+    inverse.apply(&mut target);
+    assert_eq!(target.style(cell_id("A1")), bold);
+    assert_eq!(target.style(cell_id("B1")), Style::default());
+  }
+
+  #[test]
+  fn moved_styles_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    let bold = Style {
+      bold: true,
+      ..Style::default()
+    };
+    target.set_input(cell_id("A1"), String::from("b"));
+    target.set_input(cell_id("A2"), String::from("a"));
+    target.set_style(cell_id("A1"), bold.clone());
+    target.set_style(cell_id("B1"), bold.clone());
+
+    let command = Command::InsertRow(1);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.styled_cells().len(), 2);
+    assert_eq!(target.style(cell_id("A2")), bold);
+    assert_eq!(target.style(cell_id("B2")), bold);
+    inverse.apply(&mut target);
+    assert_eq!(target.styled_cells().len(), 2);
+    assert_eq!(target.style(cell_id("A1")), bold);
+    assert_eq!(target.style(cell_id("B1")), bold);
+
+    let command = Command::DeleteCol('A');
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.styled_cells(), vec![cell_id("A1")]);
+    inverse.apply(&mut target);
+    assert_eq!(target.style(cell_id("A1")), bold);
+
+    // the styles are sorted with the rows
+    let command = Command::SortRows {
+      col: 'A',
+      rows: (1, 2),
+      descending: false,
+    };
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.input(cell_id("A2")), "b");
+    assert_eq!(target.style(cell_id("A1")), Style::default());
+    assert_eq!(target.style(cell_id("A2")), bold);
+    assert_eq!(target.style(cell_id("B2")), bold);
+    inverse.apply(&mut target);
+    assert_eq!(target.style(cell_id("A1")), bold);
+    assert_eq!(target.style(cell_id("A2")), Style::default());
+
+    // the styles of the last column would be shifted out of the table
+    target.set_style(cell_id("Z1"), bold);
+    assert!(Command::InsertCol('A').check(&target).is_err());
+  }
+
+  #[test]
+  fn moved_number_formats_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    let percent = Style {
+      number_format: NumberFormat {
+        decimals: Some(1),
+        percent: true,
+        ..NumberFormat::default()
+      },
+      ..Style::default()
+    };
+    target.set_input(cell_id("B2"), String::from("0.5"));
+    target.set_style(cell_id("B2"), percent.clone());
+
+    let command = Command::DeleteRow(1);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(
+      target.style(cell_id("B1")).number_format,
+      percent.number_format
+    );
+    assert_eq!(target.style(cell_id("B2")), Style::default());
+    inverse.apply(&mut target);
+    assert_eq!(target.style(cell_id("B2")), percent);
+
+    let command = Command::InsertCol('A');
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(target.input(cell_id("C2")), "0.5");
+    assert_eq!(target.style(cell_id("C2")), percent);
+    inverse.apply(&mut target);
+    assert_eq!(target.styled_cells(), vec![cell_id("B2")]);
+  }
+
+  #[test]
+  fn moved_validations_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let range = |range: &str| {
+      let (anchor, extent) = range.split_once(':').unwrap();
+      Selection {
+        anchor: cell_id(anchor),
+        extent: cell_id(extent),
+      }
+    };
+    let rule = Rule::List(vec![String::from("yes"), String::from("no")]);
+    let mut target = Target::default();
+    for (id, input) in [("A2", "c"), ("A3", "a"), ("A4", "b")] {
+      target.set_input(cell_id(id), input.to_string());
+    }
+    target.set_validations(
+      ["A2:A4", "B2:B3", "B3:B4", "Z1:Z1"]
+        .map(|validated| (range(validated), rule.clone()))
+        .to_vec(),
+    );
+    let validated = |target: &Target| {
+      target
+        .validations()
+        .into_iter()
+        .map(|(selection, _)| {
+          let (top_left, bottom_right) = selection.bounds();
+          format!("{top_left}:{bottom_right}")
+        })
+        .collect::<Vec<_>>()
+    };
+    let before = validated(&target);
+
+    let command = Command::InsertRow(3);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(
+      validated(&target),
+      ["A02:A05", "B02:B04", "B04:B05", "Z01:Z01"]
+    );
+    inverse.apply(&mut target);
+    assert_eq!(validated(&target), before);
+
+    let command = Command::DeleteRow(2);
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(
+      validated(&target),
+      ["A02:A03", "B02:B02", "B02:B03", "Z01:Z01"]
+    );
+    inverse.apply(&mut target);
+    assert_eq!(validated(&target), before);
+
+    let command = Command::DeleteCol('A');
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(validated(&target), ["A02:A03", "A03:A04", "Y01:Y01"]);
+    inverse.apply(&mut target);
+    assert_eq!(validated(&target), before);
+
+    // the rules shifted past the last column are dropped
+    Command::InsertCol('Z').apply(&mut target);
+    assert_eq!(validated(&target), ["A02:A04", "B02:B03", "B03:B04"]);
+    target.set_validations(vec![
+      (range("A2:A4"), rule.clone()),
+      (range("B2:B3"), rule.clone()),
+      (range("B3:B4"), rule),
+    ]);
+
+    // the rows A3, A4, and A2 go first, so B2 and B3 are sorted apart
+    Command::SortRows {
+      col: 'A',
+      rows: (2, 4),
+      descending: false,
+    }
+    .apply(&mut target);
+    assert_eq!(validated(&target), ["A02:A04", "B02:B03"]);
+  }
+
+  #[test]
+  fn sort_rows_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let mut target = Target::default();
+    for (id, input) in [
+      ("A1", "Name"),
+      ("A2", "b"),
+      ("B2", "10"),
+      ("A3", "a"),
+      ("B3", "9"),
+      ("B4", "= A1"),
+      ("A5", "c"),
+      ("B5", "abc"),
+      ("C5", "= A5"),
+    ] {
+      target.set_input(cell_id(id), input.to_string());
+    }
+    let col = |target: &Target, col: char| {
+      (1..=5)
+        .map(|row| target.input(CellId { col, row }))
+        .collect::<Vec<_>>()
+    };
+
+    let before = col(&target, 'B');
+
+    let command = Command::SortRows {
+      col: 'B',
+      rows: (2, 5),
+      descending: false,
+    };
+    let inverse = command.invert(&target);
+    command.apply(&mut target);
+    assert_eq!(col(&target, 'A'), vec!["Name", "a", "b", "c", ""]);
+    assert_eq!(col(&target, 'B'), vec!["", "9", "10", "abc", "= A01"]);
+    assert_eq!(col(&target, 'C'), vec!["", "", "", "= A04", ""]);
+    inverse.apply(&mut target);
+    assert_eq!(col(&target, 'B'), before);
+
+    Command::SortRows {
+      col: 'A',
+      rows: (2, 5),
+      descending: true,
+    }
+    .apply(&mut target);
+    assert_eq!(col(&target, 'A'), vec!["Name", "c", "b", "a", ""]);
+    assert_eq!(col(&target, 'C'), vec!["", "= A02", "", "", ""]);
+  }
+}
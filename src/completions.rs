@@ -0,0 +1,61 @@
+use yew::prelude::*;
+
+use crate::autocomplete::Suggestion;
+use crate::keymap::{Action, Chord, Keymap};
+
+#[derive(PartialEq, Properties)]
+pub struct CompletionsProps {
+  pub suggestions: Vec<Suggestion>,
+  // the highlighted suggestion, picked with Enter or Tab
+  pub index: usize,
+  // the arguments of the function whose call is typed
+  pub hint: Option<String>,
+  pub onpick: Callback<usize>,
+}
+
+/// Whether the key moves through the suggestions or picks one, instead of its usual action.
+pub fn is_completion_key(keymap: &Keymap, chord: &Chord) -> bool {
+  matches!(chord.key.as_str(), "ArrowUp" | "ArrowDown")
+    || matches!(
+      keymap.action(chord),
+      Some(Action::Commit | Action::NextCell | Action::Close)
+    )
+}
+
+/// The dropdown of the suggested completions under the input of a formula.
+#[function_component]
+pub fn Completions(props: &CompletionsProps) -> Html {
+  if props.suggestions.is_empty() && props.hint.is_none() {
+    return html! {};
+  }
+
+  let view_suggestion = |(index, suggestion): (usize, &Suggestion)| {
+    let parent_onpick = props.onpick.clone();
+
+    html! {
+      <div
+        class={classes!(vec![
+          "flex justify-between gap-4 px-2 py-0.5 cursor-pointer hover:bg-indigo-700",
+          if index == props.index { "bg-indigo-600" } else { "" }
+        ])}
+        onmousedown={ Callback::from(move |ev: MouseEvent| {
+          // keeps the focus in the input
+          ev.prevent_default();
+          parent_onpick.emit(index);
+        })}
+      >
+        <span>{ suggestion.text.clone() }</span>
+        <span class="truncate text-neutral-300">{ suggestion.hint.clone() }</span>
+      </div>
+    }
+  };
+
+  html! {
+    <div class="absolute top-full left-0 z-[60] w-[24rem] flex flex-col py-1 font-mono text-base text-left bg-indigo-800 border-[1px] border-indigo-900 shadow-lg">
+      if let Some(hint) = &props.hint {
+        <div class="px-2 py-0.5 italic text-neutral-300">{ hint }</div>
+      }
+      { props.suggestions.iter().enumerate().map(view_suggestion).collect::<Html>() }
+    </div>
+  }
+}
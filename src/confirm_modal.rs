@@ -0,0 +1,47 @@
+use yew::prelude::*;
+
+use crate::btn::*;
+use crate::modal::*;
+
+#[derive(PartialEq, Properties)]
+pub struct ConfirmModalProps {
+  pub title: String,
+  pub message: String,
+  // the title of the button confirming the action
+  pub confirm: String,
+  pub onconfirm: Callback<()>,
+  pub is_visible: bool,
+  pub onclose: Callback<()>,
+}
+
+/// Asks to confirm an action which can't be undone, e.g., clearing or deleting a sheet.
+#[function_component]
+pub fn ConfirmModal(props: &ConfirmModalProps) -> Html {
+  let onconfirm = {
+    let parent_onconfirm = props.onconfirm.clone();
+    let parent_onclose = props.onclose.clone();
+
+    Callback::from(move |_ev: MouseEvent| {
+      parent_onclose.emit(());
+      parent_onconfirm.emit(());
+    })
+  };
+
+  let oncancel = {
+    let parent_onclose = props.onclose.clone();
+
+    Callback::from(move |_ev: MouseEvent| {
+      parent_onclose.emit(());
+    })
+  };
+
+  html! {
+    <Modal title={ props.title.clone() } is_visible={props.is_visible} onclose={props.onclose.clone()}>
+      <p class="pb-4 text-base">{ props.message.clone() }</p>
+      <div class="flex gap-4">
+        <Btn title={ props.confirm.clone() } color={ BtnColors::Purple } onclick={onconfirm} />
+        <Btn title="Cancel" color={ BtnColors::Violet } onclick={oncancel} />
+      </div>
+    </Modal>
+  }
+}
@@ -0,0 +1,203 @@
+//! Dates are represented as serial numbers like in other spreadsheets: the integer part
+//! is the number of days since 1899-12-30, and the fractional part is the time of the day.
+//! For example, `45292.5` is `2024-01-01 12:00`.
+use regex::Regex;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+// days between 0000-03-01 and 1899-12-30
+const EPOCH_OFFSET: i64 = 693_899;
+
+lazy_static! {
+  static ref DATE_RE: Regex = Regex::new(
+    r"^(?P<y>\d{4})-(?P<m>\d{1,2})-(?P<d>\d{1,2})(?:[T ](?P<hh>\d{1,2}):(?P<mm>\d{2})(?::(?P<ss>\d{2}))?)?$"
+  )
+  .unwrap();
+}
+
+/// Returns the serial number of a date; months and days outside of the usual ranges
+/// roll over, e.g., month 13 of 2023 is January 2024.
+pub fn serial_from_ymd(year: i64, month: i64, day: i64) -> f64 {
+  let year = year + (month - 1).div_euclid(12);
+  let month = (month - 1).rem_euclid(12) + 1;
+
+  (days_from_civil(year, month, 1) + day - 1) as f64
+}
+
+/// Returns year, month, and day of the serial number's date.
+pub fn ymd_from_serial(serial: f64) -> (i64, i64, i64) {
+  civil_from_days(serial.floor() as i64)
+}
+
+/// Returns the day of the week of the serial number's date, from 0 (Sunday) to 6 (Saturday).
+pub fn weekday(serial: f64) -> i64 {
+  // serial number 1 is a Sunday
+  (serial.floor() as i64 - 1).rem_euclid(7)
+}
+
+/// Returns year, month, day, hours, minutes, and seconds of the serial number,
+/// rounding it to the nearest second.
+pub fn datetime_from_serial(serial: f64) -> ((i64, i64, i64), (i64, i64, i64)) {
+  let mut days = serial.floor();
+  let mut seconds = ((serial - days) * SECONDS_PER_DAY).round() as i64;
+  if seconds == SECONDS_PER_DAY as i64 {
+    days += 1.0;
+    seconds = 0;
+  }
+
+  (
+    ymd_from_serial(days),
+    (seconds / 3600, seconds % 3600 / 60, seconds % 60),
+  )
+}
+
+/// Adds `months` to the serial number's date, keeping the day of the month if possible,
+/// and using the last day of the month otherwise, e.g., January 31 + 1 month is February 29.
+pub fn add_months(serial: f64, months: i64) -> f64 {
+  let (year, month, day) = ymd_from_serial(serial);
+  let last_day = ymd_from_serial(serial_from_ymd(year, month + months + 1, 0)).2;
+
+  serial_from_ymd(year, month + months, day.min(last_day)) + (serial - serial.floor())
+}
+
+/// Parses ISO-like dates, such as `2024-03-05`, `2024-03-05 14:30`, or `2024-03-05T14:30:15`.
+pub fn parse(input: &str) -> Option<f64> {
+  let captures = DATE_RE.captures(input.trim())?;
+  let part = |name: &str| {
+    captures
+      .name(name)
+      .map_or(Ok(0), |part| part.as_str().parse::<i64>())
+  };
+
+  let (year, month, day) = (part("y").ok()?, part("m").ok()?, part("d").ok()?);
+  let (hours, minutes, seconds) = (part("hh").ok()?, part("mm").ok()?, part("ss").ok()?);
+  let days_in_month = ymd_from_serial(serial_from_ymd(year, month + 1, 0)).2;
+  if !(1..=12).contains(&month)
+    || !(1..=days_in_month).contains(&day)
+    || hours > 23
+    || minutes > 59
+    || seconds > 59
+  {
+    return None;
+  }
+
+  let time = (hours * 3600 + minutes * 60 + seconds) as f64 / SECONDS_PER_DAY;
+  Some(serial_from_ymd(year, month, day) + time)
+}
+
+/// Formats the serial number as `2024-03-05`, adding the time if it's not midnight.
+pub fn format(serial: f64) -> String {
+  let ((year, month, day), (hours, minutes, seconds)) = datetime_from_serial(serial);
+
+  match (hours, minutes, seconds) {
+    (0, 0, 0) => format!("{year:04}-{month:02}-{day:02}"),
+    (_, _, 0) => format!("{year:04}-{month:02}-{day:02} {hours:02}:{minutes:02}"),
+    _ => format!("{year:04}-{month:02}-{day:02} {hours:02}:{minutes:02}:{seconds:02}"),
+  }
+}
+
+/// Returns the current date and time as a serial number in the local time zone of the browser
+/// (or UTC, when not running in the browser).
+pub fn now() -> f64 {
+  #[cfg(target_arch = "wasm32")]
+  let (millis, offset_minutes) = {
+    let date = js_sys::Date::new_0();
+    (date.get_time(), date.get_timezone_offset())
+  };
+
+  #[cfg(not(target_arch = "wasm32"))]
+  let (millis, offset_minutes) = (
+    std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0.0, |duration| duration.as_millis() as f64),
+    0.0,
+  );
+
+  let unix_epoch = serial_from_ymd(1970, 1, 1);
+  unix_epoch + (millis / 1000.0 - offset_minutes * 60.0) / SECONDS_PER_DAY
+}
+
+// See http://howardhinnant.github.io/date_algorithms.html for the explanation
+// of the following two algorithms.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+  era * 146_097 + day_of_era - EPOCH_OFFSET
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + EPOCH_OFFSET;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days - era * 146_097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn serial_test() {
+    assert_eq!(serial_from_ymd(1899, 12, 30), 0.0);
+    assert_eq!(serial_from_ymd(1900, 3, 1), 61.0);
+    assert_eq!(serial_from_ymd(2024, 1, 1), 45292.0);
+    assert_eq!(serial_from_ymd(2023, 13, 1), 45292.0);
+    assert_eq!(serial_from_ymd(2024, 3, 0), serial_from_ymd(2024, 2, 29));
+    assert_eq!(serial_from_ymd(2024, 0, 31), serial_from_ymd(2023, 12, 31));
+
+    assert_eq!(ymd_from_serial(45292.75), (2024, 1, 1));
+    assert_eq!(ymd_from_serial(-1.0), (1899, 12, 29));
+    assert_eq!(
+      add_months(serial_from_ymd(2024, 1, 31), 1),
+      serial_from_ymd(2024, 2, 29)
+    );
+    assert_eq!(
+      add_months(serial_from_ymd(2024, 3, 15), -15),
+      serial_from_ymd(2022, 12, 15)
+    );
+    assert_eq!(add_months(45292.5, 12), serial_from_ymd(2025, 1, 1) + 0.5);
+
+    for serial in (-1000..100_000).step_by(7) {
+      let (year, month, day) = ymd_from_serial(serial as f64);
+      assert_eq!(serial_from_ymd(year, month, day), serial as f64);
+    }
+  }
+
+  #[test]
+  fn parse_and_format_test() {
+    assert_eq!(parse("2024-01-01"), Some(45292.0));
+    assert_eq!(parse(" 2024-1-1 12:00"), Some(45292.5));
+    assert_eq!(parse("2024-01-01T06:00:00"), Some(45292.25));
+    assert_eq!(parse("2024-02-29"), Some(45351.0));
+    assert_eq!(parse("2023-02-29"), None);
+    assert_eq!(parse("2024-13-01"), None);
+    assert_eq!(parse("2024-01-01 24:00"), None);
+    assert_eq!(parse("01/01/2024"), None);
+    assert_eq!(parse("2024"), None);
+
+    assert_eq!(format(45292.0), "2024-01-01");
+    assert_eq!(format(45292.5), "2024-01-01 12:00");
+    assert_eq!(
+      format(45292.0 + 3725.0 / SECONDS_PER_DAY),
+      "2024-01-01 01:02:05"
+    );
+    assert_eq!(format(45292.9999999), "2024-01-02");
+
+    assert!(now() > serial_from_ymd(2023, 1, 1));
+  }
+}
@@ -0,0 +1,151 @@
+//! The focus and the typing state of the table: a cell is focused by clicking it or moving
+//! to it with the keyboard, and typed into in place or in the big input above the table.
+use crate::cell_id::CellId;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditState {
+  /// No cell is focused, e.g., the focus moved to the toolbar; `last` is the cell
+  /// focused before, which the toolbar and the menus still apply to.
+  Idle { last: Option<CellId> },
+  /// The cell is focused, but not typed into.
+  Focused(CellId),
+  /// The cell is typed into; `draft` is the text typed so far, which is set as the input
+  /// of the cell on each keystroke.
+  Editing {
+    cell_id: CellId,
+    via_big_input: bool,
+    draft: String,
+  },
+}
+
+impl Default for EditState {
+  fn default() -> Self {
+    EditState::Idle { last: None }
+  }
+}
+
+impl EditState {
+  /// Returns the focused cell, which is the typed into cell while editing.
+  pub fn focused_cell(&self) -> Option<CellId> {
+    match self {
+      EditState::Idle { .. } => None,
+      EditState::Focused(cell_id) | EditState::Editing { cell_id, .. } => Some(*cell_id),
+    }
+  }
+
+  pub fn input_cell(&self) -> Option<CellId> {
+    match self {
+      EditState::Editing { cell_id, .. } => Some(*cell_id),
+      _ => None,
+    }
+  }
+
+  /// Returns the focused cell, or the cell focused last if the focus moved out of the table.
+  pub fn current_cell(&self) -> Option<CellId> {
+    match self {
+      EditState::Idle { last } => *last,
+      _ => self.focused_cell(),
+    }
+  }
+
+  /// Whether the cell is typed into in place, rather than in the big input.
+  pub fn is_typed_in(&self, cell_id: CellId) -> bool {
+    matches!(self, EditState::Editing { cell_id: editing, via_big_input: false, .. } if *editing == cell_id)
+  }
+
+  /// Focuses the cell; focusing the typed into cell continues typing in place.
+  pub fn focus(&mut self, cell_id: CellId) {
+    match self {
+      EditState::Editing {
+        cell_id: editing,
+        via_big_input,
+        ..
+      } if *editing == cell_id => *via_big_input = false,
+      _ => *self = EditState::Focused(cell_id),
+    }
+  }
+
+  /// The focus left the cell; returns `false` if it wasn't focused. The typed into cell
+  /// stays typed into, e.g., while a reference is clicked or the big input is focused.
+  pub fn blur(&mut self, cell_id: CellId) -> bool {
+    match self {
+      EditState::Focused(focused) if *focused == cell_id => {
+        *self = EditState::Idle {
+          last: Some(cell_id),
+        };
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// The big input got the focus: it shows the typed into cell, or the cell focused last.
+  pub fn focus_big_input(&mut self) {
+    match self {
+      EditState::Editing { via_big_input, .. } => *via_big_input = true,
+      EditState::Idle {
+        last: Some(cell_id),
+      } => *self = EditState::Focused(*cell_id),
+      _ => (),
+    }
+  }
+
+  /// Starts or continues typing the `draft` into the cell.
+  pub fn edit(&mut self, cell_id: CellId, draft: String, via_big_input: bool) {
+    *self = EditState::Editing {
+      cell_id,
+      via_big_input,
+      draft,
+    };
+  }
+
+  /// Replaces the typed text, e.g., when the typing is undone; does nothing unless editing.
+  pub fn set_draft(&mut self, text: String) {
+    if let EditState::Editing { draft, .. } = self {
+      *draft = text;
+    }
+  }
+
+  /// Stops typing, keeping the cell focused.
+  pub fn commit(&mut self) {
+    if let EditState::Editing { cell_id, .. } = self {
+      *self = EditState::Focused(*cell_id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn edit_state_test() {
+    let a1 = CellId::try_from("A1").unwrap();
+    let b2 = CellId::try_from("B2").unwrap();
+
+    let mut state = EditState::default();
+    state.focus(a1);
+    assert_eq!(state, EditState::Focused(a1));
+    assert!(!state.blur(b2));
+    assert!(state.blur(a1));
+    assert_eq!(state.focused_cell(), None);
+    assert_eq!(state.current_cell(), Some(a1));
+
+    state.focus_big_input();
+    state.edit(a1, String::from("= B"), true);
+    assert_eq!(state.input_cell(), Some(a1));
+    assert!(!state.is_typed_in(a1));
+    // clicking a reference keeps typing, and moves it into the cell
+    assert!(!state.blur(a1));
+    state.focus(a1);
+    assert!(state.is_typed_in(a1));
+
+    state.set_draft(String::from("= B2"));
+    state.commit();
+    assert_eq!(state, EditState::Focused(a1));
+
+    state.edit(a1, String::from("x"), false);
+    state.focus(b2);
+    assert_eq!(state, EditState::Focused(b2));
+  }
+}
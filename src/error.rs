@@ -0,0 +1,66 @@
+//! Errors of evaluating cells, which are shown in the cells instead of their values,
+//! using the error codes of other spreadsheets.
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+  /// A reference to a failed cell, or to a deleted one.
+  Ref,
+  DivZero,
+  /// Any other error, e.g., an argument of a wrong type.
+  Value,
+  /// A cell referencing itself, directly or via other cells.
+  Cycle,
+  /// An array that cannot spill into the neighboring cells.
+  Spill,
+  /// A cell exceeding the evaluation limits, see `crate::limits`.
+  Limit,
+}
+
+impl ErrorKind {
+  pub fn code(&self) -> &'static str {
+    match self {
+      ErrorKind::Ref => "#REF!",
+      ErrorKind::DivZero => "#DIV/0!",
+      ErrorKind::Value => "#VALUE!",
+      ErrorKind::Cycle => "#CYCLE!",
+      ErrorKind::Spill => "#SPILL!",
+      ErrorKind::Limit => "#LIMIT!",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalError {
+  pub kind: ErrorKind,
+  pub message: String,
+}
+
+impl EvalError {
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+    EvalError {
+      kind,
+      message: message.into(),
+    }
+  }
+}
+
+impl Display for EvalError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.message)
+  }
+}
+
+impl Error for EvalError {}
+
+/// Errors that are not `EvalError`s are `#VALUE!` errors.
+impl From<Box<dyn Error>> for EvalError {
+  fn from(err: Box<dyn Error>) -> Self {
+    match err.downcast::<EvalError>() {
+      Ok(err) => *err,
+      Err(err) => EvalError::new(ErrorKind::Value, err.to_string()),
+    }
+  }
+}
@@ -0,0 +1,70 @@
+use yew::prelude::*;
+
+use crate::cell_id::CellId;
+use crate::modal::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellProblem {
+  pub cell_id: CellId,
+  pub input: String,
+  // the parse error, or the message of the evaluation error
+  pub message: String,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct ErrorsModalProps {
+  pub problems: Vec<CellProblem>,
+  pub is_visible: bool,
+  pub onclose: Callback<()>,
+  // jumps to the cell
+  pub onselect: Callback<CellId>,
+}
+
+/// Lists the cells failing to parse or to evaluate; clicking one, or pressing Enter on it,
+/// selects it.
+#[function_component]
+pub fn ErrorsModal(props: &ErrorsModalProps) -> Html {
+  let view_problem = |problem: &CellProblem| {
+    let cell_id = problem.cell_id;
+    let parent_onselect = props.onselect.clone();
+    // the row is picked with the keyboard too
+    let onkeydown = {
+      let parent_onselect = parent_onselect.clone();
+      Callback::from(move |ev: KeyboardEvent| {
+        if matches!(ev.key().as_str(), "Enter" | " ") {
+          ev.prevent_default();
+          parent_onselect.emit(cell_id);
+        }
+      })
+    };
+
+    html! {
+      <tr
+        class="cursor-pointer hover:bg-violet-800 focus:outline-none focus:bg-violet-700"
+        tabindex="0"
+        onclick={ Callback::from(move |_ev: MouseEvent| parent_onselect.emit(cell_id)) }
+        {onkeydown}
+      >
+        <td class="pr-4 align-top font-mono">{ cell_id.to_string() }</td>
+        <td class="pr-4 align-top max-w-[10rem] truncate font-mono" title={ problem.input.clone() }>
+          { problem.input.clone() }
+        </td>
+        <td class="align-top text-red-300">{ problem.message.clone() }</td>
+      </tr>
+    }
+  };
+
+  html! {
+    <Modal title="Errors" is_visible={props.is_visible} onclose={props.onclose.clone()}>
+      if props.problems.is_empty() {
+        <p class="text-base">{ "No cell fails to parse or to evaluate." }</p>
+      } else {
+        <div class="max-h-[60vh] overflow-y-auto">
+          <table class="text-base">
+            { props.problems.iter().map(view_problem).collect::<Html>() }
+          </table>
+        </div>
+      }
+    </Modal>
+  }
+}
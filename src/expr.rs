@@ -1,9 +1,18 @@
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 
+use crate::bignum;
 use crate::cell_id::CellId;
-use crate::topological::topological_sort;
+use crate::error::{ErrorKind, EvalError};
+use crate::func::Func;
+use crate::limits::{Budget, Limits};
+use crate::topological::{format_cycle, State};
+use crate::trace::{self, CellTrace, EvalTrace};
+use crate::value::Value;
 use Op::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -14,20 +23,157 @@ pub enum Op {
   Mul,
   Div,
   Pow,
+  Equal,
+  NotEqual,
+  Less,
+  LessOrEq,
+  Greater,
+  GreaterOrEq,
+}
+
+/// Controls how unary minus binds relative to `^`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NegPrecedence {
+  /// `-2^2` is `-(2^2) = -4`, as in mathematical notation.
+  #[default]
+  StrictMath,
+  /// `-2^2` is `(-2)^2 = 4`, as in Excel and Google Sheets.
+  Spreadsheet,
+}
+
+/// Controls the numeric type used by arithmetic operators and `SUM`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberMode {
+  /// Binary floating point numbers, so `0.1 + 0.2` is `0.30000000000000004`.
+  #[default]
+  Float,
+  /// Exact decimal numbers, so `0.1 + 0.2` is `0.3`; the results are stored
+  /// as the closest floating point numbers.
+  Decimal,
+}
+
+/// The computed values of the cells, and the settings expressions are evaluated with.
+#[derive(Debug, Clone, Copy)]
+pub struct EvalContext<'a> {
+  pub values: &'a HashMap<CellId, Value>,
+  pub number_mode: NumberMode,
+}
+
+impl<'a> EvalContext<'a> {
+  /// Returns the context with the default settings.
+  pub fn new(values: &'a HashMap<CellId, Value>) -> Self {
+    EvalContext {
+      values,
+      number_mode: NumberMode::default(),
+    }
+  }
 }
 
 impl Op {
   pub fn precedence(&self) -> u8 {
+    self.precedence_with(NegPrecedence::default())
+  }
+
+  pub fn precedence_with(&self, neg_precedence: NegPrecedence) -> u8 {
     match &self {
+      Equal | NotEqual | Less | LessOrEq | Greater | GreaterOrEq => 0,
       Add | Sub => 1,
       Mul | Div => 2,
-      Neg | Pow => 3,
+      Pow => 3,
+      Neg => match neg_precedence {
+        NegPrecedence::StrictMath => 3,
+        NegPrecedence::Spreadsheet => 4,
+      },
     }
   }
 
   pub fn is_left_associative(&self) -> bool {
     !(*self == Neg || *self == Pow)
   }
+
+  /// Applies a comparison operator to the values; returns false for other operators.
+  pub fn compare(&self, left: &Value, right: &Value) -> bool {
+    match left.compare(right) {
+      Some(ordering) => match self {
+        Equal => ordering.is_eq(),
+        NotEqual => ordering.is_ne(),
+        Less => ordering.is_lt(),
+        LessOrEq => ordering.is_le(),
+        Greater => ordering.is_gt(),
+        GreaterOrEq => ordering.is_ge(),
+        _ => false,
+      },
+      // NaN is not equal to anything
+      None => *self == NotEqual,
+    }
+  }
+
+  /// Applies a binary operator to the values.
+  fn apply_to_values(
+    &self,
+    left: &Value,
+    right: &Value,
+    number_mode: NumberMode,
+  ) -> Result<Value, Box<dyn Error>> {
+    if matches!(
+      self,
+      Equal | NotEqual | Less | LessOrEq | Greater | GreaterOrEq
+    ) {
+      return Ok(Value::Num(from_bool(self.compare(left, right))));
+    }
+    if let Some(value) = bignum::apply(*self, left, right) {
+      return Ok(value);
+    }
+
+    let (left_num, right_num) = (left.as_num()?, right.as_num()?);
+    match (self, left, right) {
+      // adding or subtracting days keeps dates, while the difference of dates is in days
+      (Add, Value::Date(_), Value::Num(_)) | (Add, Value::Num(_), Value::Date(_)) => {
+        Ok(Value::Date(left_num + right_num))
+      }
+      (Sub, Value::Date(_), Value::Num(_)) => Ok(Value::Date(left_num - right_num)),
+      _ => Ok(Value::Num(self.apply(left_num, right_num, number_mode)?)),
+    }
+  }
+
+  /// Applies an arithmetic binary operator to the numbers.
+  fn apply(&self, left: f64, right: f64, number_mode: NumberMode) -> Result<f64, Box<dyn Error>> {
+    if *self == Div && right == 0.0 {
+      return Err(EvalError::new(ErrorKind::DivZero, "division by zero").into());
+    }
+    if number_mode == NumberMode::Decimal {
+      // numbers outside of the decimal range fall back to floating point arithmetic
+      if let Some(res) = self.apply_decimal(left, right) {
+        return Ok(res);
+      }
+    }
+
+    match self {
+      Add => Ok(left + right),
+      Sub => Ok(left - right),
+      Mul => Ok(left * right),
+      Div => Ok(left / right),
+      Pow => Ok(left.powf(right)),
+      _ => panic!(
+        "programming error: this cannot be reached, since Neg and comparisons should be handled before"
+      ),
+    }
+  }
+
+  /// Applies an arithmetic binary operator to the numbers converted to decimals;
+  /// returns `None` if they don't fit into decimals.
+  fn apply_decimal(&self, left: f64, right: f64) -> Option<f64> {
+    let (left, right) = (Decimal::from_f64(left)?, Decimal::from_f64(right)?);
+
+    match self {
+      Add => left.checked_add(right),
+      Sub => left.checked_sub(right),
+      Mul => left.checked_mul(right),
+      Div => left.checked_div(right),
+      _ => None,
+    }?
+    .to_f64()
+  }
 }
 
 impl TryFrom<&str> for Op {
@@ -40,23 +186,41 @@ impl TryFrom<&str> for Op {
       "*" => Ok(Mul),
       "/" => Ok(Div),
       "^" => Ok(Pow),
+      "=" => Ok(Equal),
+      "<>" => Ok(NotEqual),
+      "<" => Ok(Less),
+      "<=" => Ok(LessOrEq),
+      ">" => Ok(Greater),
+      ">=" => Ok(GreaterOrEq),
       _ => Err(format!("`{value}` is not a valid operator.")),
     }
   }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub enum Expr {
   Str(String),
   Num(f64),
+  Date(f64),
+  Bool(bool),
+  /// A cleared cell.
+  #[default]
+  Empty,
   CellRef(CellId),
-  Apply { op: Op, args: Vec<Expr> },
-}
-
-impl Default for Expr {
-  fn default() -> Self {
-    Expr::Str(String::new())
-  }
+  /// A reference to a deleted cell, written as `#REF!`.
+  RefError,
+  Range {
+    from: CellId,
+    to: CellId,
+  },
+  Apply {
+    op: Op,
+    args: Vec<Expr>,
+  },
+  Call {
+    func: Func,
+    args: Vec<Expr>,
+  },
 }
 
 impl Expr {
@@ -68,9 +232,15 @@ impl Expr {
     let mut stack = vec![self];
     while let Some(expr) = stack.pop() {
       match expr {
-        Expr::Str(_) | Expr::Num(_) => (),
-        Expr::CellRef(cell_id) => deps.push(cell_id.clone()),
-        Expr::Apply { args, .. } => {
+        Expr::Str(_)
+        | Expr::Num(_)
+        | Expr::Date(_)
+        | Expr::Bool(_)
+        | Expr::Empty
+        | Expr::RefError => (),
+        Expr::CellRef(cell_id) => deps.push(*cell_id),
+        Expr::Range { from, to } => deps.extend(CellId::range(*from, *to)),
+        Expr::Apply { args, .. } | Expr::Call { args, .. } => {
           for arg in args {
             stack.push(arg);
           }
@@ -81,33 +251,114 @@ impl Expr {
     deps
   }
 
-  pub fn eval(&self, ctx: &HashMap<CellId, f64>) -> Result<f64, Box<dyn Error>> {
+  /// Returns the number of cells referenced by the expression, counting each cell of
+  /// the ranges, without listing them like `get_deps`.
+  pub fn cells_referenced(&self) -> usize {
+    match self {
+      Expr::Str(_)
+      | Expr::Num(_)
+      | Expr::Date(_)
+      | Expr::Bool(_)
+      | Expr::Empty
+      | Expr::RefError => 0,
+      Expr::CellRef(_) => 1,
+      Expr::Range { from, to } => {
+        let rows = from.row.abs_diff(to.row) + 1;
+        let cols = (from.col as u32).abs_diff(to.col as u32) as usize + 1;
+        rows.saturating_mul(cols)
+      }
+      Expr::Apply { args, .. } | Expr::Call { args, .. } => args
+        .iter()
+        .fold(0, |count, arg| count.saturating_add(arg.cells_referenced())),
+    }
+  }
+
+  /// Returns the depth of the expression tree, e.g., 1 for `A1`, and 2 for `A1 + 1`.
+  pub fn depth(&self) -> usize {
+    let mut max_depth = 0;
+
+    let mut stack = vec![(self, 1)];
+    while let Some((expr, depth)) = stack.pop() {
+      max_depth = max_depth.max(depth);
+      if let Expr::Apply { args, .. } | Expr::Call { args, .. } = expr {
+        stack.extend(args.iter().map(|arg| (arg, depth + 1)));
+      }
+    }
+
+    max_depth
+  }
+
+  /// Returns true if the expression calls a volatile function, such as `RAND()`.
+  pub fn is_volatile(&self) -> bool {
+    self.calls(Func::is_volatile)
+  }
+
+  /// Returns true if the expression may evaluate to an array, e.g., if it calls `SEQUENCE`,
+  /// or applies an operator to a range.
+  pub fn may_return_array(&self) -> bool {
     match self {
-      Expr::Num(num) => Ok(*num),
-      Expr::CellRef(cell_id) => ctx.get(cell_id).map(|v| *v).ok_or_else(|| {
-        format!("cannot resolve reference to {cell_id:?}")
-          .as_str()
-          .into()
+      Expr::Str(_)
+      | Expr::Num(_)
+      | Expr::Date(_)
+      | Expr::Bool(_)
+      | Expr::Empty
+      | Expr::CellRef(_)
+      | Expr::RefError => false,
+      Expr::Range { .. } => true,
+      Expr::Apply { args, .. } => args.iter().any(Expr::may_return_array),
+      Expr::Call { func, args } => {
+        self.calls(Func::returns_array)
+          || (func.may_return_arg() && args.iter().any(Expr::may_return_array))
+      }
+    }
+  }
+
+  /// Returns true if the expression calls a function matching the predicate.
+  fn calls(&self, predicate: impl Fn(&Func) -> bool) -> bool {
+    let mut stack = vec![self];
+    while let Some(expr) = stack.pop() {
+      match expr {
+        Expr::Call { func, .. } if predicate(func) => return true,
+        Expr::Apply { args, .. } | Expr::Call { args, .. } => stack.extend(args),
+        _ => (),
+      }
+    }
+
+    false
+  }
+
+  pub fn eval(&self, ctx: &EvalContext) -> Result<Value, Box<dyn Error>> {
+    match self {
+      Expr::Num(num) => Ok(Value::Num(*num)),
+      Expr::Date(serial) => Ok(Value::Date(*serial)),
+      Expr::Bool(value) => Ok(Value::Bool(*value)),
+      Expr::Str(s) => Ok(Value::Str(s.clone())),
+      Expr::Empty => Ok(Value::Empty),
+      Expr::RefError => Err(EvalError::new(ErrorKind::Ref, "reference to a deleted cell").into()),
+      Expr::CellRef(cell_id) => ctx.values.get(cell_id).cloned().ok_or_else(|| {
+        EvalError::new(
+          ErrorKind::Ref,
+          format!("cannot resolve reference to {cell_id:?}"),
+        )
+        .into()
       }),
       Expr::Apply { op, args } => match op {
-        Op::Neg => args[0].eval(ctx).map(|v| -v),
+        Op::Neg => map(args[0].eval(ctx)?, &|value| match value {
+          Value::Big(big) => Ok(Value::Big(-big)),
+          value => Ok(Value::Num(-value.as_num()?)),
+        }),
         _ => {
-          let args = args
+          let mut args = args
             .iter()
             .map(|arg| arg.eval(ctx))
             .collect::<Result<Vec<_>, _>>()?;
 
           if args.len() == 2 {
-            match op {
-              Add => Ok(args[0] + args[1]),
-              Sub => Ok(args[0] - args[1]),
-              Mul => Ok(args[0] * args[1]),
-              Div => Ok(args[0] / args[1]),
-              Pow => Ok(args[0].powf(args[1])),
-              _ => panic!(
-                "programming error: this cannot be reached, since Neg should be handled before"
-              ),
-            }
+            let right = args.pop().unwrap();
+            let left = args.pop().unwrap();
+            broadcast(left, right, &|left, right| {
+              op.apply_to_values(left, right, ctx.number_mode)
+            })
           } else {
             Err(
               format!("binary operation {op:?} got incorrect number of arguments: {args:?}")
@@ -117,53 +368,615 @@ impl Expr {
           }
         }
       },
-      Expr::Str(_) => Err("cannot evaluate strings".into()),
+      // aggregate functions skip empty cells, while operators treat them as zeros
+      Expr::Range { from, to } => Ok(Value::Array(
+        (from.row.min(to.row)..=from.row.max(to.row))
+          .map(|row| {
+            (from.col.min(to.col)..=from.col.max(to.col))
+              .map(|col| {
+                ctx
+                  .values
+                  .get(&CellId { col, row })
+                  .cloned()
+                  .unwrap_or(Value::Empty)
+              })
+              .collect()
+          })
+          .collect(),
+      )),
+      Expr::Call { func, args } => func.eval(args, ctx),
     }
   }
 }
 
-/// Evaluates a parsed cell_id -> expr map, returning a map cell_id -> expr,
-/// in which expressions will be replaced by their computed values where possible
-pub fn eval(exprs: &HashMap<CellId, Expr>) -> Result<HashMap<CellId, Expr>, Box<dyn Error>> {
-  let mut values = HashMap::new();
-  let mut computed = HashMap::new();
+/// Applies `f` to the value, or to each value of an array.
+fn map(
+  value: Value,
+  f: &impl Fn(&Value) -> Result<Value, Box<dyn Error>>,
+) -> Result<Value, Box<dyn Error>> {
+  match value {
+    Value::Array(rows) => Ok(Value::Array(
+      rows
+        .iter()
+        .map(|row| row.iter().map(f).collect::<Result<Vec<_>, _>>())
+        .collect::<Result<Vec<_>, _>>()?,
+    )),
+    value => f(&value),
+  }
+}
+
+/// Applies `f` to the values, or to each pair of the corresponding values of arrays
+/// of the same size; a single value is paired with each value of an array.
+fn broadcast(
+  left: Value,
+  right: Value,
+  f: &impl Fn(&Value, &Value) -> Result<Value, Box<dyn Error>>,
+) -> Result<Value, Box<dyn Error>> {
+  match (left, right) {
+    (Value::Array(left), Value::Array(right)) => {
+      if left.len() != right.len() || left.first().map(Vec::len) != right.first().map(Vec::len) {
+        return Err("cannot combine arrays of different sizes".into());
+      }
 
-  for cell_id in topological_sort(exprs)? {
-    if let Some(expr) = exprs.get(&cell_id) {
-      match expr {
-        Expr::Str(_) => {
-          computed.insert(cell_id, expr.clone());
-        }
-        Expr::Num(n) => {
-          values.insert(cell_id, *n);
-          computed.insert(cell_id, expr.clone());
+      Ok(Value::Array(
+        left
+          .iter()
+          .zip(&right)
+          .map(|(left, right)| {
+            left
+              .iter()
+              .zip(right)
+              .map(|(left, right)| f(left, right))
+              .collect::<Result<Vec<_>, _>>()
+          })
+          .collect::<Result<Vec<_>, _>>()?,
+      ))
+    }
+    (left @ Value::Array(_), right) => map(left, &|left| f(left, &right)),
+    (left, right) => map(right, &|right| f(&left, right)),
+  }
+}
+
+/// Sums the numbers, see `NumberMode`.
+pub fn sum(nums: &[f64], number_mode: NumberMode) -> f64 {
+  match number_mode {
+    NumberMode::Float => nums.iter().sum(),
+    NumberMode::Decimal => nums.iter().fold(0.0, |acc, num| {
+      Add.apply_decimal(acc, *num).unwrap_or(acc + num)
+    }),
+  }
+}
+
+/// Booleans are represented as `1` (true) and `0` (false), like in other spreadsheets.
+pub fn from_bool(value: bool) -> f64 {
+  if value {
+    1.0
+  } else {
+    0.0
+  }
+}
+
+/// Any non-zero number is considered true, like in other spreadsheets.
+pub fn to_bool(value: f64) -> bool {
+  value != 0.0
+}
+
+/// Evaluates a parsed cell_id -> expr map, returning a map cell_id -> computed value,
+/// or the error of computing the cell.
+pub fn eval<E: Borrow<Expr>>(
+  exprs: &HashMap<CellId, E>,
+) -> HashMap<CellId, Result<Value, EvalError>> {
+  eval_changed(
+    exprs,
+    &HashMap::new(),
+    &HashSet::new(),
+    EvalOptions::default(),
+  )
+}
+
+// each pass can discover new spilled arrays, which may change the order of evaluation
+const MAX_SPILL_PASSES: usize = 8;
+
+/// Options of `eval_changed`; the defaults are used by `eval`.
+#[derive(Default)]
+pub struct EvalOptions<'a> {
+  /// The dependency graph kept between edits, see `State::from_references`, which is used
+  /// instead of building it from the expressions. With the graph, only the changed cells,
+  /// the volatile cells, the arrays, and the cells depending on them are evaluated, in
+  /// the order kept by the graph. The graph keeps the cells filled by spilled arrays too.
+  pub graph: Option<&'a mut State<CellId>>,
+  /// Resolves the references to the cells missing from the expressions, e.g., to the values
+  /// supplied by a host application. Since the resolved values may change between
+  /// evaluations, all cells are evaluated, and the cells depending on the resolved values
+  /// are never cached. The references to the cells left unresolved see empty values.
+  pub resolve: Option<&'a dyn Fn(CellId) -> Option<Value>>,
+  /// The cells exceeding the limits get `#LIMIT!` errors.
+  pub limits: Limits,
+  /// The numeric type of the arithmetic operators and `SUM`.
+  pub number_mode: NumberMode,
+  /// Records how long each cell took to evaluate, how many cells it referenced,
+  /// and whether its value changed.
+  pub trace: Option<&'a mut EvalTrace>,
+}
+
+/// The formulas of the cells and their values computed before, e.g., kept in the records
+/// of the table, so that they aren't collected into maps on each evaluation.
+pub trait CellStore {
+  fn expr(&self, cell_id: CellId) -> Option<&Expr>;
+
+  /// Returns the value computed by the previous evaluation.
+  fn cached(&self, cell_id: CellId) -> Option<&Result<Value, EvalError>>;
+
+  /// Returns the cells with formulas, to build the dependency graph, unless it's kept.
+  fn formula_cells(&self) -> Vec<CellId>;
+}
+
+struct Maps<'a, E> {
+  exprs: &'a HashMap<CellId, E>,
+  cached: &'a HashMap<CellId, Result<Value, EvalError>>,
+}
+
+impl<E: Borrow<Expr>> CellStore for Maps<'_, E> {
+  fn expr(&self, cell_id: CellId) -> Option<&Expr> {
+    self.exprs.get(&cell_id).map(Borrow::borrow)
+  }
+
+  fn cached(&self, cell_id: CellId) -> Option<&Result<Value, EvalError>> {
+    self.cached.get(&cell_id)
+  }
+
+  fn formula_cells(&self) -> Vec<CellId> {
+    self.exprs.keys().copied().collect()
+  }
+}
+
+/// The cells referencing failed cells fail with the same error, unless they handle it,
+/// e.g., with `IFERROR`.
+///
+/// Like `eval`, but reuses the `cached` computed values of the cells that are not volatile,
+/// and don't depend on the `changed` cells or volatile cells. With a kept graph, the cells
+/// without cached values are evaluated as if they changed.
+///
+/// Array results spill into the cells below and to the right of the cell, which fails if
+/// any of those cells are not empty. Since it's not known which cells are filled by arrays
+/// before evaluation, the cells are re-evaluated until the spilled cells stop changing,
+/// ordering cells referencing the spilled cells after the cells containing the arrays.
+pub fn eval_changed<E: Borrow<Expr>>(
+  exprs: &HashMap<CellId, E>,
+  cached: &HashMap<CellId, Result<Value, EvalError>>,
+  changed: &HashSet<CellId>,
+  options: EvalOptions,
+) -> HashMap<CellId, Result<Value, EvalError>> {
+  let cells = Maps { exprs, cached };
+  if options.graph.is_none() || options.resolve.is_some() {
+    return eval_cells(&cells, changed, options)
+      .into_iter()
+      .filter_map(|(cell_id, value)| Some((cell_id, value?)))
+      .collect();
+  }
+
+  let mut changed = changed.clone();
+  changed.extend(exprs.keys().filter(|cell_id| !cached.contains_key(cell_id)));
+  let mut computed = cached.clone();
+  for (cell_id, value) in eval_cells(&cells, &changed, options) {
+    match value {
+      Some(value) => computed.insert(cell_id, value),
+      None => computed.remove(&cell_id),
+    };
+  }
+  computed
+}
+
+/// Like `eval_changed`, but only returns the values of the evaluated cells, and `None` for
+/// the cells left empty, e.g., no longer filled by spilled arrays. With a kept graph and
+/// without a resolver, only the cells depending on the changes are evaluated, and the other
+/// cells keep their cached values; otherwise, all cells are evaluated.
+pub fn eval_cells(
+  cells: &impl CellStore,
+  changed: &HashSet<CellId>,
+  mut options: EvalOptions,
+) -> HashMap<CellId, Option<Result<Value, EvalError>>> {
+  let start = trace::now();
+  let is_full = options.graph.is_none() || options.resolve.is_some();
+  let mut built;
+  let graph = match options.graph.take() {
+    Some(graph) => graph,
+    None => {
+      let exprs = cells
+        .formula_cells()
+        .into_iter()
+        .filter_map(|cell_id| Some((cell_id, cells.expr(cell_id)?)))
+        .collect::<HashMap<_, _>>();
+      built = State::from_references(&exprs, &options.limits);
+      &mut built
+    }
+  };
+
+  let evaluator = Evaluator {
+    cells,
+    changed,
+    spilled: graph.spilled().clone(),
+    volatile: graph.volatile().clone(),
+    resolve: options.resolve.unwrap_or(&|_| None),
+    limits: &options.limits,
+    number_mode: options.number_mode,
+    is_traced: options.trace.is_some(),
+  };
+  let mut budget = Budget::new(&options.limits);
+  let mut pass = Pass::default();
+  let mut is_settled = false;
+
+  for _ in 0..MAX_SPILL_PASSES {
+    let (sorted, unresolved) = if is_full {
+      let (sorted, unresolved) = graph.sorted();
+      (sorted.to_vec(), unresolved)
+    } else {
+      // the arrays are never cached, and the cells filled by them since the previous
+      // evaluation changed too
+      let seeds = changed
+        .iter()
+        .chain(graph.volatile())
+        .chain(graph.arrays())
+        .copied()
+        .chain(moved_targets(&evaluator.spilled, graph.spilled()));
+      let cone = graph.with_dependents(seeds.collect::<Vec<_>>());
+      graph.sort(cone)
+    };
+    let cycles = if unresolved.is_empty() {
+      vec![]
+    } else {
+      graph.cycles()
+    };
+
+    pass = evaluator.pass(&sorted, &unresolved, &cycles, &mut budget);
+    // only the last pass is traced, since the previous ones are discarded
+    if let Some(trace) = options.trace.as_deref_mut() {
+      trace.passes += 1;
+      trace.cells = std::mem::take(&mut pass.trace);
+    }
+
+    let moved = moved_targets(graph.spilled(), &pass.spilled);
+    for &target in &moved {
+      let source = pass.spilled.get(&target).copied();
+      graph.set_spilled(target, source, evaluator.deps(target));
+    }
+    if moved.is_empty() {
+      is_settled = true;
+      break;
+    }
+  }
+
+  if !is_settled {
+    for (target, cell_id) in std::mem::take(&mut pass.spilled) {
+      graph.set_spilled(target, None, evaluator.deps(target));
+      pass.computed.remove(&target);
+      pass.cleared.insert(target);
+      pass.computed.insert(
+        cell_id,
+        Err(EvalError::new(
+          ErrorKind::Spill,
+          "spilled arrays keep changing, check formulas referencing the spilled cells",
+        )),
+      );
+    }
+  }
+
+  let mut updates = pass
+    .cleared
+    .into_iter()
+    .map(|cell_id| (cell_id, None))
+    .collect::<HashMap<_, _>>();
+  updates.extend(
+    pass
+      .computed
+      .into_iter()
+      .map(|(cell_id, value)| (cell_id, Some(value))),
+  );
+
+  if let Some(trace) = options.trace {
+    trace.millis = trace::now() - start;
+  }
+  updates
+}
+
+/// Returns the cells filled by different arrays, or filled only in one of the maps.
+fn moved_targets(
+  before: &HashMap<CellId, CellId>,
+  after: &HashMap<CellId, CellId>,
+) -> HashSet<CellId> {
+  before
+    .keys()
+    .chain(after.keys())
+    .filter(|target| before.get(target) != after.get(target))
+    .copied()
+    .collect()
+}
+
+/// The inputs of an evaluation shared by its passes.
+struct Evaluator<'a, C> {
+  cells: &'a C,
+  changed: &'a HashSet<CellId>,
+  // the cells filled by spilled arrays in the previous evaluation
+  spilled: HashMap<CellId, CellId>,
+  volatile: HashSet<CellId>,
+  resolve: &'a dyn Fn(CellId) -> Option<Value>,
+  limits: &'a Limits,
+  number_mode: NumberMode,
+  is_traced: bool,
+}
+
+#[derive(Default)]
+struct Pass {
+  computed: HashMap<CellId, Result<Value, EvalError>>,
+  // cells left empty, e.g., no longer filled by spilled arrays
+  cleared: HashSet<CellId>,
+  // maps cells filled by spilled arrays to the cells containing the arrays
+  spilled: HashMap<CellId, CellId>,
+  trace: HashMap<CellId, CellTrace>,
+}
+
+impl<C: CellStore> Evaluator<'_, C> {
+  /// Evaluates the `sorted` cells, in which cells referencing the cells filled by spilled
+  /// arrays go after the arrays, once the spilled cells are known.
+  fn pass(
+    &self,
+    sorted: &[CellId],
+    unresolved: &[CellId],
+    cycles: &[Vec<CellId>],
+    budget: &mut Budget,
+  ) -> Pass {
+    let mut values = HashMap::new();
+    // the cells depending on volatile cells are marked dirty while evaluating
+    let mut dirty = self.changed.clone();
+    dirty.extend(&self.volatile);
+    let mut pass = Pass::default();
+
+    for cycle in cycles {
+      for cell_id in cycle {
+        if self.cells.expr(*cell_id).is_some() {
+          let err = EvalError::new(
+            ErrorKind::Cycle,
+            format!("circular reference {}", format_cycle(cycle)),
+          );
+          pass.computed.insert(*cell_id, Err(err));
         }
-        Expr::CellRef(another_cell_id) => {
-          if let Some(another_value) = values.get(another_cell_id) {
-            values.insert(cell_id, *another_value);
-          }
+      }
+    }
+    for &cell_id in unresolved {
+      if self.cells.expr(cell_id).is_some() {
+        pass.computed.entry(cell_id).or_insert_with(|| {
+          Err(EvalError::new(
+            ErrorKind::Cycle,
+            format!("cell {cell_id} depends on a circular reference"),
+          ))
+        });
+      }
+    }
 
-          if let Some(another_computed) = computed.get(another_cell_id) {
-            computed.insert(cell_id, another_computed.clone());
-          } else {
-            return Err(
-              format!("reference to an empty cell {another_cell_id} in cell {cell_id}").into(),
-            );
-          }
+    for &cell_id in sorted {
+      let Some(expr) = self.cells.expr(cell_id) else {
+        // the cleared cells, and the cells no longer filled by spilled arrays, are empty again
+        if !pass.spilled.contains_key(&cell_id)
+          && (self.changed.contains(&cell_id) || self.spilled.contains_key(&cell_id))
+        {
+          values.insert(cell_id, (self.resolve)(cell_id).unwrap_or(Value::Empty));
+          dirty.insert(cell_id);
+          pass.cleared.insert(cell_id);
         }
-        Expr::Apply { .. } => {
-          let value = expr.eval(&values)?;
+        continue;
+      };
+      if is_blank(expr) && pass.spilled.contains_key(&cell_id) {
+        continue;
+      }
+      let start = self.is_traced.then(trace::now);
+
+      let deps = self.limits.deps(expr);
+      for &dep in &deps {
+        self.read(dep, &pass, &mut values, &mut dirty);
+      }
+
+      // arrays are never cached, since only their top left values are stored in `computed`,
+      // and neither are errors
+      let is_dirty = dirty.contains(&cell_id)
+        || expr.may_return_array()
+        || deps.iter().any(|dep| dirty.contains(dep));
+      let cached_value = match self.cells.cached(cell_id) {
+        Some(Ok(value)) if !is_dirty && !matches!(value, Value::Array(_)) => Some(value.clone()),
+        _ => None,
+      };
+      let is_cached = cached_value.is_some();
+      if !is_cached {
+        dirty.insert(cell_id);
+      }
+
+      let value = match (cached_value, expr) {
+        (Some(value), _) => Ok(value),
+        (None, Expr::CellRef(another_cell_id)) => match values.get(another_cell_id) {
+          Some(another_value) => Ok(Value::clone(another_value)),
+          None => Err(
+            EvalError::new(
+              ErrorKind::Ref,
+              format!("cannot resolve reference to {another_cell_id} in cell {cell_id}"),
+            )
+            .into(),
+          ),
+        },
+        (None, _) => match budget.spend(expr) {
+          Ok(()) => expr.eval(&EvalContext {
+            values: &values,
+            number_mode: self.number_mode,
+          }),
+          Err(err) => Err(err.into()),
+        },
+      };
+
+      let res = value.and_then(|value| match value {
+        Value::Array(rows) => spill(
+          cell_id,
+          rows,
+          self.cells,
+          &mut pass,
+          &mut values,
+          &mut dirty,
+        ),
+        value => {
+          pass.computed.insert(cell_id, Ok(value.clone()));
           values.insert(cell_id, value);
-          computed.insert(cell_id, Expr::Num(value));
+          Ok(())
+        }
+      });
+      if let Err(err) = res {
+        // errors of the referenced cells are propagated
+        let err = deps
+          .iter()
+          .find_map(|dep| self.error_of(*dep, &pass))
+          .unwrap_or_else(|| EvalError::from(err));
+        pass.computed.insert(cell_id, Err(err));
+      }
+
+      if let Some(start) = start {
+        let cell_trace = CellTrace {
+          millis: trace::now() - start,
+          deps_read: expr.cells_referenced(),
+          is_cached,
+          is_changed: self.cells.cached(cell_id) != pass.computed.get(&cell_id),
+        };
+        pass.trace.insert(cell_id, cell_trace);
+      }
+    }
+
+    pass
+  }
+
+  /// Reads the value of a referenced cell not evaluated in this pass: the cached value
+  /// of a formula or of a cell filled by an array before, or the resolved value;
+  /// the references to the other cells see empty values.
+  fn read(
+    &self,
+    cell_id: CellId,
+    pass: &Pass,
+    values: &mut HashMap<CellId, Value>,
+    dirty: &mut HashSet<CellId>,
+  ) {
+    if values.contains_key(&cell_id) || pass.computed.contains_key(&cell_id) {
+      return;
+    }
+
+    if self.cells.expr(cell_id).is_some() || self.spilled.contains_key(&cell_id) {
+      if let Some(Ok(value)) = self.cells.cached(cell_id) {
+        values.insert(cell_id, value.clone());
+      }
+      return;
+    }
+    match (self.resolve)(cell_id) {
+      Some(value) => {
+        values.insert(cell_id, value);
+        dirty.insert(cell_id);
+      }
+      // the empty values only differ from the cached ones, if the cells were filled
+      // by spilled arrays before
+      None => {
+        values.insert(cell_id, Value::Empty);
+        if self.cells.cached(cell_id).is_some() {
+          dirty.insert(cell_id);
         }
       }
     }
   }
 
-  Ok(computed)
+  /// Returns the error of computing the cell in this pass, or before.
+  fn error_of(&self, cell_id: CellId, pass: &Pass) -> Option<EvalError> {
+    let value = match pass.computed.get(&cell_id) {
+      Some(value) => Some(value),
+      None => self.cells.expr(cell_id).and(self.cells.cached(cell_id)),
+    };
+    match value {
+      Some(Err(err)) => Some(err.clone()),
+      _ => None,
+    }
+  }
+
+  /// Returns the cells the formula of the cell depends on, without the spilled arrays.
+  fn deps(&self, cell_id: CellId) -> Vec<CellId> {
+    self
+      .cells
+      .expr(cell_id)
+      .map_or_else(Vec::new, |expr| self.limits.deps(expr))
+  }
+}
+
+/// Writes the values of the array into the cell and the cells below and to the right of it.
+fn spill(
+  cell_id: CellId,
+  rows: Vec<Vec<Value>>,
+  cells: &impl CellStore,
+  pass: &mut Pass,
+  values: &mut HashMap<CellId, Value>,
+  dirty: &mut HashSet<CellId>,
+) -> Result<(), Box<dyn Error>> {
+  let width = rows.first().map_or(0, Vec::len);
+  let targets = (0..rows.len())
+    .flat_map(|row_offset| {
+      (0..width).map(move |col_offset| {
+        char::from_u32(cell_id.col as u32 + col_offset as u32)
+          .filter(char::is_ascii_uppercase)
+          .map(|col| CellId {
+            col,
+            row: cell_id.row + row_offset,
+          })
+      })
+    })
+    .collect::<Option<Vec<_>>>()
+    .ok_or_else(|| {
+      EvalError::new(
+        ErrorKind::Spill,
+        format!("array in cell {cell_id} doesn't fit into the table"),
+      )
+    })?;
+
+  for target in &targets {
+    if let Some(another_cell_id) = pass.spilled.get(target) {
+      return Err(
+        EvalError::new(
+          ErrorKind::Spill,
+          format!(
+            "array in cell {cell_id} cannot spill into {target} filled by the array in {another_cell_id}"
+          ),
+        )
+        .into(),
+      );
+    }
+    if *target != cell_id && cells.expr(*target).is_some_and(|expr| !is_blank(expr)) {
+      return Err(
+        EvalError::new(
+          ErrorKind::Spill,
+          format!("array in cell {cell_id} cannot spill into non-empty cell {target}"),
+        )
+        .into(),
+      );
+    }
+  }
+
+  for (target, value) in targets.into_iter().zip(rows.into_iter().flatten()) {
+    if target != cell_id {
+      pass.spilled.insert(target, cell_id);
+      pass.cleared.remove(&target);
+      dirty.insert(target);
+    }
+
+    pass.computed.insert(target, Ok(value.clone()));
+    values.insert(target, value);
+  }
+
+  Ok(())
+}
+
+fn is_blank(expr: &Expr) -> bool {
+  matches!(expr, Expr::Empty)
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod test {
   use super::*;
   use crate::parser::parse;
@@ -172,12 +985,559 @@ mod test {
   fn expr_eval_test() {
     let expr = parse("= A1 - (A2 - A3 ^ B1 / 2.5) + C1").unwrap();
     let ctx = HashMap::from_iter(vec![
-      (CellId { col: 'A', row: 1 }, 12.0),
-      (CellId { col: 'A', row: 2 }, 500.5),
-      (CellId { col: 'A', row: 3 }, -3.1415),
-      (CellId { col: 'B', row: 1 }, 2.0),
-      (CellId { col: 'C', row: 1 }, 0.2187456),
+      (CellId { col: 'A', row: 1 }, Value::Num(12.0)),
+      (CellId { col: 'A', row: 2 }, Value::Num(500.5)),
+      (CellId { col: 'A', row: 3 }, Value::Num(-3.1415)),
+      (CellId { col: 'B', row: 1 }, Value::Num(2.0)),
+      (CellId { col: 'C', row: 1 }, Value::Num(0.2187456)),
     ]);
-    assert_eq!(expr.eval(&ctx).unwrap(), -484.33364550000005);
+    assert_eq!(
+      expr.eval(&EvalContext::new(&ctx)).unwrap(),
+      Value::Num(-484.33364550000005)
+    );
+  }
+
+  #[test]
+  fn eval_test() {
+    let exprs = HashMap::from_iter(vec![
+      (CellId { col: 'A', row: 1 }, parse("10").unwrap()),
+      (CellId { col: 'A', row: 2 }, parse("oranges").unwrap()),
+      (CellId { col: 'A', row: 3 }, parse("= A1 * 2").unwrap()),
+      (CellId { col: 'B', row: 1 }, parse("= SUM(A1:A5)").unwrap()),
+      (
+        CellId { col: 'B', row: 2 },
+        parse("= COUNT(A1:A5) + B1").unwrap(),
+      ),
+      // text cells can be referenced, too
+      (CellId { col: 'C', row: 1 }, parse("= A2").unwrap()),
+      (
+        CellId { col: 'C', row: 2 },
+        parse("= CONCAT(A2, \" x \", A1:A2)").unwrap(),
+      ),
+    ]);
+
+    let computed = eval(&exprs);
+    assert_eq!(computed[&CellId { col: 'B', row: 1 }], Ok(Value::Num(30.0)));
+    assert_eq!(computed[&CellId { col: 'B', row: 2 }], Ok(Value::Num(32.0)));
+    assert_eq!(
+      computed[&CellId { col: 'C', row: 1 }],
+      Ok(Value::Str("oranges".to_string()))
+    );
+    assert_eq!(
+      computed[&CellId { col: 'C', row: 2 }],
+      Ok(Value::Str("oranges x 10oranges".to_string()))
+    );
+  }
+
+  #[test]
+  fn eval_changed_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("1").unwrap()),
+      (cell_id("A2"), parse("= A1 * 2").unwrap()),
+      (cell_id("A3"), parse("= RAND()").unwrap()),
+      (cell_id("A4"), parse("= A3 + 10").unwrap()),
+    ]);
+    // A2 and A4 are stale on purpose to check which cached values are reused
+    let cached = HashMap::from_iter(vec![
+      (cell_id("A1"), Ok(Value::Num(1.0))),
+      (cell_id("A2"), Ok(Value::Num(100.0))),
+      (cell_id("A3"), Ok(Value::Num(5.0))),
+      (cell_id("A4"), Ok(Value::Num(15.0))),
+    ]);
+
+    let computed = eval_changed(&exprs, &cached, &HashSet::new(), EvalOptions::default());
+    assert_eq!(computed[&cell_id("A2")], Ok(Value::Num(100.0)));
+    assert!(matches!(computed[&cell_id("A3")], Ok(Value::Num(n)) if n < 1.0));
+    assert!(matches!(computed[&cell_id("A4")], Ok(Value::Num(n)) if n < 11.0));
+
+    let computed = eval_changed(
+      &exprs,
+      &cached,
+      &HashSet::from([cell_id("A1")]),
+      EvalOptions::default(),
+    );
+    assert_eq!(computed[&cell_id("A2")], Ok(Value::Num(2.0)));
+
+    let computed = eval(&exprs);
+    assert_eq!(computed[&cell_id("A2")], Ok(Value::Num(2.0)));
+  }
+
+  #[test]
+  fn eval_changed_graph_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("= B1 + 1").unwrap()),
+      (cell_id("A2"), parse("= SEQUENCE(2)").unwrap()),
+      (cell_id("B2"), parse("= A3 * 10").unwrap()),
+    ]);
+    let mut graph = State::from_references(&exprs, &Limits::default());
+
+    let computed = eval_changed(
+      &exprs,
+      &HashMap::new(),
+      &HashSet::new(),
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(1.0)));
+    assert_eq!(computed[&cell_id("B2")], Ok(Value::Num(20.0)));
+
+    // B1 was empty when the graph was built
+    exprs.insert(cell_id("B1"), parse("= C1 * 2").unwrap());
+    exprs.insert(cell_id("C1"), parse("3").unwrap());
+    for cell in ["B1", "C1"] {
+      graph.set_dependencies(cell_id(cell), exprs[&cell_id(cell)].get_deps());
+    }
+    let changed = HashSet::from([cell_id("B1"), cell_id("C1")]);
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &changed,
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(7.0)));
+    assert_eq!(computed, eval(&exprs));
+
+    // cleared cells are removed, and the references to them see empty values
+    exprs.remove(&cell_id("C1"));
+    graph.set_dependencies(cell_id("C1"), []);
+    let changed = HashSet::from([cell_id("C1")]);
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &changed,
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(1.0)));
+    assert!(!computed.contains_key(&cell_id("C1")));
+
+    // the cells no longer filled by a spilled array are removed too
+    exprs.insert(cell_id("A2"), parse("= SEQUENCE(1)").unwrap());
+    graph.set_dependencies(cell_id("A2"), []);
+    let changed = HashSet::from([cell_id("A2")]);
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &changed,
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert!(!computed.contains_key(&cell_id("A3")));
+    assert_eq!(computed[&cell_id("B2")], Ok(Value::Num(0.0)));
+    assert_eq!(computed, eval(&exprs));
+  }
+
+  #[test]
+  fn empty_references_cached_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("1").unwrap()),
+      (cell_id("B1"), parse("= SUM(A1:A100)").unwrap()),
+      (cell_id("B2"), parse("= B1 + A50").unwrap()),
+      (cell_id("C1"), parse("5").unwrap()),
+      (cell_id("D1"), parse("= SEQUENCE(2)").unwrap()),
+      (cell_id("E1"), parse("= D2 * 10").unwrap()),
+    ]);
+    let mut graph = State::from_references(&exprs, &Limits::default());
+    let computed = eval(&exprs);
+    assert_eq!(computed[&cell_id("B2")], Ok(Value::Num(1.0)));
+
+    // the cells reading only empty cells besides the unchanged ones aren't evaluated
+    exprs.insert(cell_id("C1"), parse("6").unwrap());
+    let mut trace = EvalTrace::default();
+    let options = EvalOptions {
+      graph: Some(&mut graph),
+      trace: Some(&mut trace),
+      ..EvalOptions::default()
+    };
+    let changed = HashSet::from([cell_id("C1")]);
+    let computed = eval_changed(&exprs, &computed, &changed, options);
+    assert!(!trace.cells.contains_key(&cell_id("B1")));
+    assert!(!trace.cells.contains_key(&cell_id("B2")));
+    assert!(!trace.cells[&cell_id("C1")].is_cached);
+
+    // the cells that are no longer filled by a spilled array are empty again
+    exprs.insert(cell_id("D1"), parse("= SEQUENCE(1)").unwrap());
+    let changed = HashSet::from([cell_id("D1")]);
+    let computed = eval_changed(&exprs, &computed, &changed, EvalOptions::default());
+    assert_eq!(computed[&cell_id("E1")], Ok(Value::Num(0.0)));
+  }
+
+  #[test]
+  fn spill_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("= SEQUENCE(2, 3)").unwrap()),
+      (cell_id("B2"), parse("").unwrap()),
+      (cell_id("D1"), parse("= SEQUENCE(2, 1, 10)").unwrap()),
+    ]);
+
+    let computed = eval(&exprs);
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(1.0)));
+    assert_eq!(computed[&cell_id("C1")], Ok(Value::Num(3.0)));
+    assert_eq!(computed[&cell_id("B2")], Ok(Value::Num(5.0)));
+    assert_eq!(computed[&cell_id("D2")], Ok(Value::Num(11.0)));
+
+    exprs.insert(cell_id("C2"), parse("foo").unwrap());
+    let computed = eval(&exprs);
+    match &computed[&cell_id("A1")] {
+      Err(err) => {
+        assert_eq!(err.kind, ErrorKind::Spill);
+        assert!(err.message.contains("non-empty cell C02"), "{err}");
+      }
+      value => panic!("expected a spill error, got {value:?}"),
+    }
+    assert_eq!(computed[&cell_id("D2")], Ok(Value::Num(11.0)));
+
+    exprs.remove(&cell_id("C2"));
+    exprs.insert(cell_id("B1"), parse("= SEQUENCE(1, 2)").unwrap());
+    assert_eq!(error_kind(&eval(&exprs), "A1"), Some(ErrorKind::Spill));
+
+    let exprs = HashMap::from_iter(vec![(cell_id("Y1"), parse("= SEQUENCE(1, 3)").unwrap())]);
+    assert_eq!(error_kind(&eval(&exprs), "Y1"), Some(ErrorKind::Spill));
+  }
+
+  #[test]
+  fn array_formulas_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("1").unwrap()),
+      (cell_id("A2"), parse("2").unwrap()),
+      (cell_id("A3"), parse("3").unwrap()),
+      (cell_id("B1"), parse("= A1:A3 * 10 + A1").unwrap()),
+      (cell_id("C1"), parse("= -SEQUENCE(1, 2)").unwrap()),
+      // reference the spilled cells, so they must be evaluated after B1 and C1
+      (cell_id("E1"), parse("= B3 + D1").unwrap()),
+      (cell_id("E2"), parse("= SUM(B1:B3)").unwrap()),
+      (cell_id("E3"), parse("= IF(E1 > 0, B2:B3, 0)").unwrap()),
+    ]);
+
+    let computed = eval(&exprs);
+    assert_eq!(computed[&cell_id("B2")], Ok(Value::Num(21.0)));
+    assert_eq!(computed[&cell_id("B3")], Ok(Value::Num(31.0)));
+    assert_eq!(computed[&cell_id("D1")], Ok(Value::Num(-2.0)));
+    assert_eq!(computed[&cell_id("E1")], Ok(Value::Num(29.0)));
+    assert_eq!(computed[&cell_id("E2")], Ok(Value::Num(63.0)));
+    assert_eq!(computed[&cell_id("E4")], Ok(Value::Num(31.0)));
+
+    let exprs = HashMap::from_iter(vec![(cell_id("A1"), parse("= SEQUENCE(A2)").unwrap())]);
+    assert_eq!(error_kind(&eval(&exprs), "A1"), Some(ErrorKind::Value));
+  }
+
+  #[test]
+  fn error_values_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("0").unwrap()),
+      (cell_id("A2"), parse("= 1 / A1").unwrap()),
+      (cell_id("A3"), parse("= A2 + 1").unwrap()),
+      (cell_id("A4"), parse("= IFERROR(A3, -1)").unwrap()),
+      (cell_id("B1"), parse("= #REF! * 2").unwrap()),
+      (cell_id("B2"), parse("= UPPER(B1)").unwrap()),
+      (cell_id("B3"), parse("= \"a\" * 2").unwrap()),
+      (cell_id("C1"), parse("= C2 + 1").unwrap()),
+      (cell_id("C2"), parse("= C1 + 1").unwrap()),
+      (cell_id("C3"), parse("= C2 * 2").unwrap()),
+      (cell_id("D1"), parse("= A1 + 5").unwrap()),
+    ]);
+
+    let computed = eval(&exprs);
+    assert_eq!(error_kind(&computed, "A2"), Some(ErrorKind::DivZero));
+    assert_eq!(error_kind(&computed, "A3"), Some(ErrorKind::DivZero));
+    assert_eq!(computed[&cell_id("A4")], Ok(Value::Num(-1.0)));
+    assert_eq!(error_kind(&computed, "B1"), Some(ErrorKind::Ref));
+    assert_eq!(error_kind(&computed, "B2"), Some(ErrorKind::Ref));
+    assert_eq!(error_kind(&computed, "B3"), Some(ErrorKind::Value));
+    assert_eq!(error_kind(&computed, "C1"), Some(ErrorKind::Cycle));
+    assert_eq!(error_kind(&computed, "C2"), Some(ErrorKind::Cycle));
+    assert_eq!(error_kind(&computed, "C3"), Some(ErrorKind::Cycle));
+    let message = |s: &str| computed[&cell_id(s)].as_ref().unwrap_err().message.clone();
+    assert_eq!(message("C2"), "circular reference C01 → C02 → C01");
+    assert_eq!(message("C3"), "cell C03 depends on a circular reference");
+    assert_eq!(computed[&cell_id("D1")], Ok(Value::Num(5.0)));
+
+    // references to empty cells are not errors
+    let exprs = HashMap::from_iter(vec![(cell_id("A1"), parse("= Z99 * 2").unwrap())]);
+    assert_eq!(eval(&exprs)[&cell_id("A1")], Ok(Value::Num(0.0)));
+  }
+
+  #[test]
+  fn self_reference_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("2").unwrap()),
+      (cell_id("A2"), parse("= A1 * 3").unwrap()),
+      (cell_id("B1"), parse("= SUM(A1:A3)").unwrap()),
+    ]);
+    let mut graph = State::from_references(&exprs, &Limits::default());
+    let computed = eval_changed(
+      &exprs,
+      &HashMap::new(),
+      &HashSet::new(),
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("B1")], Ok(Value::Num(8.0)));
+
+    // an accidental self-reference only fails the cell and its dependents
+    exprs.insert(cell_id("A3"), parse("= SUM(A1:A3)").unwrap());
+    graph.set_dependencies(cell_id("A3"), exprs[&cell_id("A3")].get_deps());
+    let changed = HashSet::from([cell_id("A3")]);
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &changed,
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(
+      computed[&cell_id("A3")].as_ref().unwrap_err().message,
+      "circular reference A03 → A03"
+    );
+    assert_eq!(error_kind(&computed, "B1"), Some(ErrorKind::Cycle));
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(2.0)));
+    assert_eq!(computed[&cell_id("A2")], Ok(Value::Num(6.0)));
+
+    exprs.insert(cell_id("A3"), parse("= SUM(A1:A2)").unwrap());
+    graph.set_dependencies(cell_id("A3"), exprs[&cell_id("A3")].get_deps());
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &changed,
+      EvalOptions {
+        graph: Some(&mut graph),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("B1")], Ok(Value::Num(16.0)));
+  }
+
+  #[test]
+  fn resolver_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("= Z1 * 2").unwrap()),
+      (cell_id("A2"), parse("= SUM(Z1:Z3) + A1").unwrap()),
+      (cell_id("A3"), parse("= CONCAT(\"price: \", Z2)").unwrap()),
+      (cell_id("A4"), parse("= Y1").unwrap()),
+    ]);
+    let resolve = |cell_id: CellId| match cell_id.to_string().as_str() {
+      "Z01" => Some(Value::Num(10.0)),
+      "Z02" => Some(Value::Str("high".to_string())),
+      "Z03" => Some(Value::Num(5.0)),
+      _ => None,
+    };
+
+    let computed = eval_changed(
+      &exprs,
+      &HashMap::new(),
+      &HashSet::new(),
+      EvalOptions {
+        resolve: Some(&resolve),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(20.0)));
+    assert_eq!(computed[&cell_id("A2")], Ok(Value::Num(35.0)));
+    assert_eq!(
+      computed[&cell_id("A3")],
+      Ok(Value::Str("price: high".to_string()))
+    );
+    assert_eq!(computed[&cell_id("A4")], Ok(Value::Empty));
+    assert!(!computed.contains_key(&cell_id("Z1")));
+
+    // cells depending on the resolved cells are not cached
+    let resolve =
+      |cell_id: CellId| (cell_id == CellId::try_from("Z1").unwrap()).then_some(Value::Num(1.0));
+    let computed = eval_changed(
+      &exprs,
+      &computed,
+      &HashSet::new(),
+      EvalOptions {
+        resolve: Some(&resolve),
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(2.0)));
+  }
+
+  #[test]
+  fn eval_changed_traced_test() {
+    fn eval_changed_traced(
+      exprs: &HashMap<CellId, Expr>,
+      cached: &HashMap<CellId, Result<Value, EvalError>>,
+      changed: &HashSet<CellId>,
+    ) -> (HashMap<CellId, Result<Value, EvalError>>, EvalTrace) {
+      let mut trace = EvalTrace::default();
+      let options = EvalOptions {
+        trace: Some(&mut trace),
+        ..EvalOptions::default()
+      };
+      (eval_changed(exprs, cached, changed, options), trace)
+    }
+
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("1").unwrap()),
+      (cell_id("A2"), parse("= A1 * 2").unwrap()),
+      (cell_id("A3"), parse("= SUM(A1:A2, B1:B2)").unwrap()),
+      (cell_id("B3"), parse("= SEQUENCE(2)").unwrap()),
+    ]);
+
+    let (computed, trace) = eval_changed_traced(&exprs, &HashMap::new(), &HashSet::new());
+    assert_eq!(computed[&cell_id("A3")], Ok(Value::Num(3.0)));
+    assert_eq!(trace.passes, 2);
+    assert_eq!(trace.cells.len(), 4);
+    assert_eq!(trace.cells[&cell_id("A3")].deps_read, 4);
+    assert_eq!(trace.changed().count(), 4);
+    assert_eq!(trace.slowest(2).len(), 2);
+    assert!(trace
+      .slowest(4)
+      .windows(2)
+      .all(|w| w[0].1.millis >= w[1].1.millis));
+
+    exprs.insert(cell_id("A1"), parse("5").unwrap());
+    let changed = HashSet::from([cell_id("A1")]);
+    exprs.insert(cell_id("C1"), parse("= 7").unwrap());
+    let (_, trace) = eval_changed_traced(&exprs, &computed, &changed);
+    assert!(!trace.cells[&cell_id("C1")].is_cached);
+    assert!(!trace.cells[&cell_id("A2")].is_cached);
+    assert!(trace.cells[&cell_id("A2")].is_changed);
+
+    let (_, trace) = eval_changed_traced(&exprs, &eval(&exprs), &HashSet::new());
+    assert!(trace.cells[&cell_id("A2")].is_cached);
+    assert_eq!(trace.changed().count(), 0);
+  }
+
+  #[test]
+  fn limits_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("1").unwrap()),
+      (cell_id("A2"), parse("= ((A1 + 1) * 2) - 3").unwrap()),
+      (cell_id("A3"), parse("= SUM(B1:C50)").unwrap()),
+      (cell_id("A4"), parse("= A2 + 1").unwrap()),
+    ]);
+    assert_eq!(exprs[&cell_id("A2")].depth(), 4);
+
+    let limits = Limits {
+      max_depth: 3,
+      max_cells_touched: 50,
+      ..Limits::default()
+    };
+    let computed = eval_changed(
+      &exprs,
+      &HashMap::new(),
+      &HashSet::new(),
+      EvalOptions {
+        limits,
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(computed[&cell_id("A1")], Ok(Value::Num(1.0)));
+    assert_eq!(error_kind(&computed, "A2"), Some(ErrorKind::Limit));
+    assert_eq!(error_kind(&computed, "A3"), Some(ErrorKind::Limit));
+    assert_eq!(error_kind(&computed, "A4"), Some(ErrorKind::Limit));
+
+    let limits = Limits {
+      max_millis: -1.0,
+      ..Limits::default()
+    };
+    let computed = eval_changed(
+      &exprs,
+      &HashMap::new(),
+      &HashSet::new(),
+      EvalOptions {
+        limits,
+        ..EvalOptions::default()
+      },
+    );
+    assert_eq!(error_kind(&computed, "A2"), Some(ErrorKind::Limit));
+
+    let computed = eval(&exprs);
+    assert_eq!(computed[&cell_id("A4")], Ok(Value::Num(2.0)));
+
+    // huge ranges fail without listing their cells
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("= SUM(B1:B50000000)").unwrap()),
+      (cell_id("A2"), parse("= A1 + 1").unwrap()),
+      (cell_id("B1"), parse("1").unwrap()),
+    ]);
+    let computed = eval(&exprs);
+    assert_eq!(error_kind(&computed, "A1"), Some(ErrorKind::Limit));
+    assert_eq!(error_kind(&computed, "A2"), Some(ErrorKind::Limit));
+  }
+
+  #[test]
+  fn number_mode_test() {
+    let cell_id = |s: &str| CellId::try_from(s).unwrap();
+    let exprs = HashMap::from_iter(vec![
+      (cell_id("A1"), parse("0.1").unwrap()),
+      (cell_id("A2"), parse("0.2").unwrap()),
+      (cell_id("A3"), parse("= A1 + A2").unwrap()),
+      (cell_id("A4"), parse("= SUM(A1:A2, 0.3) * 3").unwrap()),
+      (cell_id("A5"), parse("= 1e300 * 1e10").unwrap()),
+      (cell_id("A6"), parse("= 1 / 3").unwrap()),
+    ]);
+
+    let computed = eval(&exprs);
+    assert_eq!(
+      computed[&cell_id("A3")],
+      Ok(Value::Num(0.30000000000000004))
+    );
+
+    let options = EvalOptions {
+      number_mode: NumberMode::Decimal,
+      ..EvalOptions::default()
+    };
+    let computed = eval_changed(&exprs, &HashMap::new(), &HashSet::new(), options);
+    assert_eq!(computed[&cell_id("A3")], Ok(Value::Num(0.3)));
+    assert_eq!(computed[&cell_id("A4")], Ok(Value::Num(1.8)));
+    assert_eq!(
+      computed[&cell_id("A5")].as_ref().unwrap().to_string(),
+      "1E+310"
+    );
+    assert!(matches!(computed[&cell_id("A6")], Ok(Value::Num(n)) if (n - 1.0 / 3.0).abs() < 1e-15));
+
+    // the mode is only used by the evaluation it's passed to
+    let computed = eval(&exprs);
+    assert_eq!(
+      computed[&cell_id("A3")],
+      Ok(Value::Num(0.30000000000000004))
+    );
+  }
+
+  fn error_kind(
+    computed: &HashMap<CellId, Result<Value, EvalError>>,
+    cell_id: &str,
+  ) -> Option<ErrorKind> {
+    match computed.get(&CellId::try_from(cell_id).unwrap()) {
+      Some(Err(err)) => Some(err.kind),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn budget_sample_test() {
+    let raw = std::fs::read_to_string("./sample_tables/budget.json").unwrap();
+    let exprs = crate::table::parse_from_input(&raw).unwrap().exprs;
+
+    let computed = eval(&exprs);
+    assert!(matches!(computed[&CellId::try_from("D10").unwrap()], Ok(Value::Num(n)) if n > 0.0));
   }
 }
@@ -0,0 +1,331 @@
+//! Filling cells by dragging the fill handle of the selection: numbers, dates, weekdays,
+//! and months continue their series, formulas are copied with their references shifted,
+//! and other inputs are repeated.
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+use crate::cell_id::CellId;
+use crate::date;
+use crate::navigation::Direction;
+use crate::parser::map_refs;
+use crate::selection::Selection;
+
+const NAMES: [&[&str]; 4] = [
+  &[
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+  ],
+  &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+  &[
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+  ],
+  &[
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+  ],
+];
+
+/// Returns the direction of filling the `source` selection up to the cell `to`,
+/// and the filled cells, or `None` if `to` is inside of the selection.
+pub fn fill_range(source: Selection, to: CellId) -> Option<(Direction, Selection)> {
+  let (top_left, bottom_right) = source.bounds();
+  let shift_col = |col: char, offset: i8| (col as u8).wrapping_add_signed(offset) as char;
+
+  // the corners of the filled cells as `(col, row)`
+  let (direction, from, to) = if to.row > bottom_right.row {
+    let from = (top_left.col, bottom_right.row + 1);
+    (Direction::Down, from, (bottom_right.col, to.row))
+  } else if to.row < top_left.row {
+    let from = (top_left.col, to.row);
+    (Direction::Up, from, (bottom_right.col, top_left.row - 1))
+  } else if to.col > bottom_right.col {
+    let from = (shift_col(bottom_right.col, 1), top_left.row);
+    (Direction::Right, from, (to.col, bottom_right.row))
+  } else if to.col < top_left.col {
+    let from = (to.col, top_left.row);
+    (
+      Direction::Left,
+      from,
+      (shift_col(top_left.col, -1), bottom_right.row),
+    )
+  } else {
+    return None;
+  };
+
+  let cell_id = |(col, row)| CellId { col, row };
+  Some((
+    direction,
+    Selection {
+      anchor: cell_id(from),
+      extent: cell_id(to),
+    },
+  ))
+}
+
+/// Returns the inputs of the cells filled by dragging the fill handle of the `source` selection
+/// to the cell `to`. Each row or column of the selection is continued separately.
+pub fn fill<S: Borrow<str>>(
+  inputs: &HashMap<CellId, S>,
+  source: Selection,
+  to: CellId,
+) -> Vec<(CellId, String)> {
+  let Some((direction, filled)) = fill_range(source, to) else {
+    return vec![];
+  };
+
+  let (top_left, bottom_right) = source.bounds();
+  let (filled_top_left, filled_bottom_right) = filled.bounds();
+  let cols = top_left.col..=bottom_right.col;
+  let rows = top_left.row..=bottom_right.row;
+  let (lines, count) = match direction {
+    Direction::Up | Direction::Down => (
+      cols
+        .map(|col| {
+          rows
+            .clone()
+            .map(|row| CellId { col, row })
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>(),
+      filled_bottom_right.row - filled_top_left.row + 1,
+    ),
+    Direction::Left | Direction::Right => (
+      rows
+        .map(|row| {
+          cols
+            .clone()
+            .map(|col| CellId { col, row })
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>(),
+      (filled_bottom_right.col as u8 - filled_top_left.col as u8 + 1) as usize,
+    ),
+  };
+
+  let mut res = vec![];
+  for mut line in lines {
+    // the cells of the line in the order of filling
+    if matches!(direction, Direction::Up | Direction::Left) {
+      line.reverse();
+    }
+
+    let source = line
+      .iter()
+      .map(|cell_id| {
+        inputs
+          .get(cell_id)
+          .map_or_else(String::new, |input| input.borrow().to_string())
+      })
+      .collect::<Vec<_>>();
+    let mut cell_id = line[line.len() - 1];
+    for input in fill_line(&source, count, direction) {
+      match direction.step(cell_id) {
+        Some(next) => cell_id = next,
+        None => break,
+      }
+      res.push((cell_id, input));
+    }
+  }
+
+  res
+}
+
+/// Returns `count` inputs continuing the `source` inputs in the `direction`.
+pub fn fill_line(source: &[String], count: usize, direction: Direction) -> Vec<String> {
+  if let Some(series) = numbers(source)
+    .or_else(|| dates(source))
+    .or_else(|| names(source))
+  {
+    return (1..=count).map(series).collect();
+  }
+
+  // formulas are shifted by the distance to the copied cell
+  (0..count)
+    .map(|i| {
+      let distance = source.len() + i - i % source.len();
+      map_refs(&source[i % source.len()], |cell_id| {
+        (0..distance).try_fold(cell_id, |cell_id, _| direction.step(cell_id))
+      })
+    })
+    .collect()
+}
+
+/// Returns the common difference of the values, if they form an arithmetic sequence.
+fn step(values: &[f64]) -> Option<f64> {
+  let step = values[values.len() - 1] - values[values.len() - 2];
+  values
+    .windows(2)
+    .all(|pair| (pair[1] - pair[0] - step).abs() < 1e-9)
+    .then_some(step)
+}
+
+/// Two or more numbers with the same difference; a single number is repeated instead.
+fn numbers(source: &[String]) -> Option<Box<dyn Fn(usize) -> String>> {
+  let nums = source
+    .iter()
+    .map(|input| input.trim().parse::<f64>().ok())
+    .collect::<Option<Vec<_>>>()?;
+  if nums.len() < 2 {
+    return None;
+  }
+
+  let (last, step) = (nums[nums.len() - 1], step(&nums)?);
+  // rounds off the floating point errors, e.g., of 0.1 + 0.2
+  Some(Box::new(move |i| {
+    (((last + step * i as f64) * 1e9).round() / 1e9).to_string()
+  }))
+}
+
+/// Dates with the same difference, or a single date followed by the next days.
+fn dates(source: &[String]) -> Option<Box<dyn Fn(usize) -> String>> {
+  let serials = source
+    .iter()
+    .map(|input| date::parse(input))
+    .collect::<Option<Vec<_>>>()?;
+
+  let last = serials[serials.len() - 1];
+  let step = if serials.len() < 2 {
+    1.0
+  } else {
+    step(&serials)?
+  };
+  Some(Box::new(move |i| date::format(last + step * i as f64)))
+}
+
+/// Weekday or month names, full or abbreviated, in any case.
+fn names(source: &[String]) -> Option<Box<dyn Fn(usize) -> String>> {
+  NAMES.iter().find_map(|names| {
+    let indices = source
+      .iter()
+      .map(|input| {
+        names
+          .iter()
+          .position(|name| name.eq_ignore_ascii_case(input.trim()))
+      })
+      .collect::<Option<Vec<_>>>()?;
+
+    let len = names.len();
+    let last = indices[indices.len() - 1];
+    let step = if indices.len() < 2 {
+      1
+    } else {
+      let step = (last + len - indices[indices.len() - 2]) % len;
+      indices
+        .windows(2)
+        .all(|pair| (pair[1] + len - pair[0]) % len == step)
+        .then_some(step)?
+    };
+
+    let last_input = source[source.len() - 1].trim().to_string();
+    let is_upper = last_input.chars().all(|ch| ch.is_ascii_uppercase());
+    let is_lower = last_input.chars().all(|ch| ch.is_ascii_lowercase());
+    let series: Box<dyn Fn(usize) -> String> = Box::new(move |i| {
+      let name = names[(last + step * i) % len];
+      match (is_upper, is_lower) {
+        (true, _) => name.to_uppercase(),
+        (_, true) => name.to_lowercase(),
+        _ => name.to_string(),
+      }
+    });
+    Some(series)
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn strings(inputs: &[&str]) -> Vec<String> {
+    inputs.iter().map(|input| input.to_string()).collect()
+  }
+
+  #[test]
+  fn fill_line_test() {
+    let fill_down = |source: &[&str], count| fill_line(&strings(source), count, Direction::Down);
+
+    assert_eq!(fill_down(&["1", "2", "3"], 2), strings(&["4", "5"]));
+    assert_eq!(fill_down(&["0.1", "0.2"], 2), strings(&["0.3", "0.4"]));
+    assert_eq!(fill_down(&["10", "8"], 2), strings(&["6", "4"]));
+    assert_eq!(fill_down(&["5"], 2), strings(&["5", "5"]));
+    assert_eq!(fill_down(&["1", "2", "4"], 3), strings(&["1", "2", "4"]));
+    assert_eq!(
+      fill_down(&["2024-02-28"], 2),
+      strings(&["2024-02-29", "2024-03-01"])
+    );
+    assert_eq!(
+      fill_down(&["2024-01-01", "2024-01-08"], 1),
+      strings(&["2024-01-15"])
+    );
+    assert_eq!(fill_down(&["Sat"], 3), strings(&["Sun", "Mon", "Tue"]));
+    assert_eq!(
+      fill_down(&["MONDAY", "WEDNESDAY"], 2),
+      strings(&["FRIDAY", "SUNDAY"])
+    );
+    assert_eq!(fill_down(&["nov"], 2), strings(&["dec", "jan"]));
+    assert_eq!(fill_down(&["a", "b"], 3), strings(&["a", "b", "a"]));
+    assert_eq!(
+      fill_down(&["= A1 * 2", "= A2 + 1"], 3),
+      strings(&["= A03 * 2", "= A04 + 1", "= A05 * 2"])
+    );
+    assert_eq!(
+      fill_line(&strings(&["= B1"]), 2, Direction::Left),
+      strings(&["= A01", "= #REF!"])
+    );
+  }
+
+  #[test]
+  fn fill_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let inputs = [("A1", "1"), ("A2", "2"), ("B1", "Jan"), ("B2", "= A2")]
+      .into_iter()
+      .map(|(id, input)| (cell_id(id), input.to_string()))
+      .collect::<HashMap<_, _>>();
+    let source = Selection {
+      anchor: cell_id("A1"),
+      extent: cell_id("B2"),
+    };
+
+    assert_eq!(
+      fill(&inputs, source, cell_id("A3")),
+      vec![
+        (cell_id("A3"), String::from("3")),
+        (cell_id("B3"), String::from("Jan")),
+      ]
+    );
+    assert_eq!(
+      fill(&inputs, source, cell_id("D2")),
+      vec![
+        (cell_id("C1"), String::from("1")),
+        (cell_id("D1"), String::from("Jan")),
+        (cell_id("C2"), String::from("2")),
+        (cell_id("D2"), String::from("= C02")),
+      ]
+    );
+    assert_eq!(fill(&inputs, source, cell_id("B1")), vec![]);
+    assert_eq!(
+      fill_range(source, cell_id("A5")),
+      Some((
+        Direction::Down,
+        Selection {
+          anchor: cell_id("A3"),
+          extent: cell_id("B5"),
+        }
+      ))
+    );
+  }
+}
@@ -0,0 +1,73 @@
+//! Filters hiding the rows of the table by the values in their columns, typed into
+//! the filter editor of a column header, e.g., `> 10`. The hidden rows keep their data.
+use crate::expr::Op;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+  /// Compares the value to the operand like formulas do, e.g., `> 10`, or `= done`
+  /// matching `Done` too.
+  Compare(Op, Value),
+  /// The displayed value contains the text, case-insensitively.
+  Contains(String),
+}
+
+impl Filter {
+  /// Parses a comparison with `=`, `<>`, `<`, `<=`, `>`, or `>=`, or the text to search for;
+  /// returns `None` for an empty text.
+  pub fn parse(text: &str) -> Option<Filter> {
+    let text = text.trim();
+    if text.is_empty() {
+      return None;
+    }
+
+    // the longer operators are tried first, as `<` is a prefix of `<=` and `<>`
+    for op in ["<>", "<=", ">=", "=", "<", ">"] {
+      if let Some(operand) = text.strip_prefix(op) {
+        let operand = operand.trim();
+        let value = operand
+          .parse()
+          .map_or_else(|_| Value::Str(operand.to_string()), Value::Num);
+        return Some(Filter::Compare(Op::try_from(op).ok()?, value));
+      }
+    }
+
+    Some(Filter::Contains(text.to_lowercase()))
+  }
+
+  pub fn matches(&self, value: &Value) -> bool {
+    match self {
+      // unlike formulas, text is neither less nor greater than numbers
+      Filter::Compare(op @ (Op::Equal | Op::NotEqual), operand) => op.compare(value, operand),
+      Filter::Compare(op, operand) => {
+        value.is_text() == operand.is_text() && op.compare(value, operand)
+      }
+      Filter::Contains(text) => value.to_string().to_lowercase().contains(text),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn filter_test() {
+    let matches = |filter: &str, value: Value| Filter::parse(filter).unwrap().matches(&value);
+
+    assert_eq!(Filter::parse("  "), None);
+    assert_eq!(
+      Filter::parse(">= 10"),
+      Some(Filter::Compare(Op::GreaterOrEq, Value::Num(10.0)))
+    );
+    assert!(matches("> 10", Value::Num(11.0)));
+    assert!(!matches("> 10", Value::Num(10.0)));
+    assert!(!matches("> 10", Value::Str(String::from("abc"))));
+    assert!(matches("<> 3", Value::Num(4.0)));
+    assert!(matches("= done", Value::Str(String::from("Done"))));
+    assert!(!matches("= done", Value::Str(String::from("undone"))));
+    assert!(matches("Done", Value::Str(String::from("undone"))));
+    assert!(matches("12", Value::Num(3.125)));
+    assert!(!matches("x", Value::Empty));
+  }
+}
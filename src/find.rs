@@ -0,0 +1,99 @@
+//! Finding cells by the text of their inputs or displayed values with Ctrl+F,
+//! and replacing the text in the inputs, including formulas.
+use crate::cell_id::CellId;
+
+/// Returns the cells whose input or displayed value contains the query, case-insensitively,
+/// row by row.
+pub fn find(cells: impl Iterator<Item = (CellId, String, String)>, query: &str) -> Vec<CellId> {
+  let query = query.to_lowercase();
+  if query.is_empty() {
+    return vec![];
+  }
+
+  let mut found = cells
+    .filter(|(_, input, value)| {
+      input.to_lowercase().contains(&query) || value.to_lowercase().contains(&query)
+    })
+    .map(|(cell_id, _, _)| cell_id)
+    .collect::<Vec<_>>();
+  found.sort();
+  found
+}
+
+/// Replaces the query in the input case-insensitively. In formulas, the query must not be
+/// a part of a longer name or reference, so replacing `B2` with `C2` keeps `AB2` and `B20`.
+pub fn replace(input: &str, query: &str, replacement: &str) -> String {
+  if query.is_empty() {
+    return input.to_string();
+  }
+
+  let is_formula = input.trim_start().starts_with('=');
+  let is_name_char = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+  // lowercasing can change the byte lengths of some characters, so the chars are compared
+  let chars = input.chars().collect::<Vec<_>>();
+  let query = query.chars().collect::<Vec<_>>();
+
+  let mut res = String::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let is_match = i + query.len() <= chars.len()
+      && chars[i..i + query.len()]
+        .iter()
+        .zip(&query)
+        .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()));
+    let joins_prev = is_name_char(query.first().copied())
+      && is_name_char(i.checked_sub(1).map(|prev| chars[prev]));
+    let joins_next =
+      is_name_char(query.last().copied()) && is_name_char(chars.get(i + query.len()).copied());
+    let is_whole = !is_formula || !(joins_prev || joins_next);
+
+    if is_match && is_whole {
+      res.push_str(replacement);
+      i += query.len();
+    } else {
+      res.push(chars[i]);
+      i += 1;
+    }
+  }
+
+  res
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn find_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let cells = [
+      ("B1", "= A1 * 2", "42"),
+      ("A2", "Total", "Total"),
+      ("A1", "21", "21"),
+    ]
+    .map(|(id, input, value)| (cell_id(id), input.to_string(), value.to_string()));
+
+    assert_eq!(
+      find(cells.clone().into_iter(), "2"),
+      vec![cell_id("A1"), cell_id("B1")]
+    );
+    assert_eq!(find(cells.clone().into_iter(), "tot"), vec![cell_id("A2")]);
+    assert_eq!(find(cells.clone().into_iter(), "a1"), vec![cell_id("B1")]);
+    assert_eq!(find(cells.into_iter(), ""), vec![]);
+  }
+
+  #[test]
+  fn replace_test() {
+    assert_eq!(
+      replace("= B2 + AB2 * B20 + b2", "B2", "C2"),
+      "= C2 + AB2 * B20 + C2"
+    );
+    assert_eq!(replace("= SUM(B2:B5)", "b2", "C2"), "= SUM(C2:B5)");
+    assert_eq!(replace("= A1 + 1", "+ 1", "- 2"), "= A1 - 2");
+    assert_eq!(
+      replace("Cat concatenation", "cat", "dog"),
+      "dog condogenation"
+    );
+    assert_eq!(replace("abc", "", "x"), "abc");
+  }
+}
@@ -0,0 +1,322 @@
+//! Spreadsheet-style formatting of numbers with patterns, such as `#,##0.00`, `0.0%`,
+//! or `yyyy-mm-dd`, used by the `TEXT` function and the number formats of the cells.
+//!
+//! Number patterns use `0` for required digits, `#` for optional digits, `.` for the decimal
+//! point, `,` for thousands separators, and `%` to show percents. Date patterns use `y`, `m`,
+//! `d`, `h`, and `s` for the parts of the date and time; `m` means minutes after `h`
+//! or before `s`. Any other characters, and characters in double quotes, are kept as is.
+use serde::{Deserialize, Serialize};
+
+use crate::date;
+use crate::value::Value;
+
+const MONTHS: [&str; 12] = [
+  "January",
+  "February",
+  "March",
+  "April",
+  "May",
+  "June",
+  "July",
+  "August",
+  "September",
+  "October",
+  "November",
+  "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+  "Sunday",
+  "Monday",
+  "Tuesday",
+  "Wednesday",
+  "Thursday",
+  "Friday",
+  "Saturday",
+];
+
+/// A character of a pattern, and whether it's quoted, i.e., should be kept as is.
+type PatternChar = (char, bool);
+
+/// The number format of a cell, set from the toolbar; only the displayed value is formatted,
+/// while formulas referencing the cell see the exact number.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+  /// The number of decimal places, or `None` to show as many as needed.
+  pub decimals: Option<usize>,
+  pub percent: bool,
+  pub currency: bool,
+  pub thousands: bool,
+}
+
+impl NumberFormat {
+  /// Returns the pattern of the format, or `None` for the general format.
+  pub fn pattern(&self) -> Option<String> {
+    if *self == NumberFormat::default() {
+      return None;
+    }
+
+    let int = if self.thousands { "#,##0" } else { "0" };
+    let frac = match self.decimals {
+      Some(0) => String::new(),
+      Some(decimals) => format!(".{}", "0".repeat(decimals)),
+      None if self.currency => String::from(".00"),
+      None => String::from(".##########"),
+    };
+    let currency = if self.currency { "$" } else { "" };
+    let percent = if self.percent { "%" } else { "" };
+    Some(format!("{currency}{int}{frac}{percent}"))
+  }
+
+  /// Formats numbers with the pattern, and shows other values as is.
+  pub fn format(&self, value: &Value) -> String {
+    match (value, self.pattern()) {
+      (Value::Num(_) | Value::Big(_), Some(pattern)) => match value.as_num() {
+        Ok(num) => apply(num, &pattern),
+        Err(_) => value.to_string(),
+      },
+      _ => value.to_string(),
+    }
+  }
+}
+
+/// Formats the number according to the pattern; patterns with date parts, but without digit
+/// placeholders, format the number as a date serial number.
+pub fn apply(num: f64, pattern: &str) -> String {
+  if pattern.eq_ignore_ascii_case("general") {
+    return num.to_string();
+  }
+
+  let mut chars = vec![];
+  let mut is_quoted = false;
+  for c in pattern.chars() {
+    if c == '"' {
+      is_quoted = !is_quoted;
+    } else {
+      chars.push((c, is_quoted));
+    }
+  }
+
+  let has = |expected: &str| {
+    chars
+      .iter()
+      .any(|(c, is_quoted)| !is_quoted && expected.contains(c.to_ascii_lowercase()))
+  };
+  if has("ymdhs") && !has("0#") {
+    format_date(num, &chars)
+  } else {
+    format_number(num, &chars)
+  }
+}
+
+fn format_number(num: f64, chars: &[PatternChar]) -> String {
+  let is_placeholder = |(c, is_quoted): &PatternChar| !is_quoted && "0#.,".contains(*c);
+  let (start, end) = match (
+    chars.iter().position(is_placeholder),
+    chars.iter().rposition(is_placeholder),
+  ) {
+    (Some(start), Some(end)) => (start, end + 1),
+    _ => (chars.len(), chars.len()),
+  };
+  let to_string = |chars: &[PatternChar]| chars.iter().map(|(c, _)| c).collect::<String>();
+  let (prefix, section, suffix) = (
+    to_string(&chars[..start]),
+    to_string(&chars[start..end]),
+    to_string(&chars[end..]),
+  );
+
+  let is_percent = chars.iter().any(|(c, is_quoted)| *c == '%' && !is_quoted);
+  let num = if is_percent { num * 100.0 } else { num };
+
+  let (int_pattern, frac_pattern) = section.split_once('.').unwrap_or((&section, ""));
+  let count = |pattern: &str, placeholders: &str| {
+    pattern
+      .chars()
+      .filter(|c| placeholders.contains(*c))
+      .count()
+  };
+  let decimals = count(frac_pattern, "0#");
+  let min_decimals = count(frac_pattern, "0");
+  let min_int_digits = count(int_pattern, "0");
+
+  let rounded = format!("{:.decimals$}", num.abs());
+  let (int, frac) = rounded.split_once('.').unwrap_or((&rounded, ""));
+
+  let mut frac = frac.trim_end_matches('0').to_string();
+  while frac.len() < min_decimals {
+    frac.push('0');
+  }
+
+  let int = int.trim_start_matches('0');
+  let int = format!(
+    "{}{int}",
+    "0".repeat(min_int_digits.saturating_sub(int.len()))
+  );
+  let int = if int_pattern.contains(',') {
+    group_thousands(&int)
+  } else {
+    int
+  };
+
+  let is_negative = num < 0.0 && rounded.chars().any(|c| ('1'..='9').contains(&c));
+  let sign = if is_negative { "-" } else { "" };
+  let point = if frac.is_empty() { "" } else { "." };
+  format!("{sign}{prefix}{int}{point}{frac}{suffix}")
+}
+
+fn group_thousands(int: &str) -> String {
+  let mut res = String::new();
+
+  for (idx, c) in int.chars().enumerate() {
+    if idx > 0 && (int.len() - idx) % 3 == 0 {
+      res.push(',');
+    }
+    res.push(c);
+  }
+
+  res
+}
+
+fn format_date(serial: f64, chars: &[PatternChar]) -> String {
+  // runs of the same date part letters, such as ('y', 4) for `yyyy`, or literal characters
+  let mut parts: Vec<(char, usize)> = vec![];
+  for (c, is_quoted) in chars {
+    let part = c.to_ascii_lowercase();
+
+    match parts.last_mut() {
+      Some((last, len)) if !is_quoted && *len > 0 && *last == part => *len += 1,
+      _ if !is_quoted && "ymdhs".contains(part) => parts.push((part, 1)),
+      _ => parts.push((*c, 0)),
+    }
+  }
+
+  let ((year, month, day), (hours, minutes, seconds)) = date::datetime_from_serial(serial);
+  let mut res = String::new();
+  for (idx, (part, len)) in parts.iter().enumerate() {
+    let is_date_part = |(part, len): &&(char, usize)| *len > 0 && "ymdhs".contains(*part);
+    let is_minutes = *part == 'm'
+      && (parts[..idx]
+        .iter()
+        .rfind(is_date_part)
+        .map(|(part, _)| *part)
+        == Some('h')
+        || parts[idx + 1..]
+          .iter()
+          .find(is_date_part)
+          .map(|(part, _)| *part)
+          == Some('s'));
+    let padded = |num: i64| {
+      if *len >= 2 {
+        format!("{num:02}")
+      } else {
+        num.to_string()
+      }
+    };
+
+    match (part, len) {
+      (_, 0) => res.push(*part),
+      ('y', 1..=2) => res.push_str(&format!("{:02}", year.rem_euclid(100))),
+      ('y', _) => res.push_str(&format!("{year:04}")),
+      ('m', _) if is_minutes => res.push_str(&padded(minutes)),
+      ('m', 1..=2) => res.push_str(&padded(month)),
+      ('m', 3) => res.push_str(&MONTHS[month as usize - 1][..3]),
+      ('m', _) => res.push_str(MONTHS[month as usize - 1]),
+      ('d', 1..=2) => res.push_str(&padded(day)),
+      ('d', 3) => res.push_str(&WEEKDAYS[date::weekday(serial) as usize][..3]),
+      ('d', _) => res.push_str(WEEKDAYS[date::weekday(serial) as usize]),
+      ('h', _) => res.push_str(&padded(hours)),
+      _ => res.push_str(&padded(seconds)),
+    }
+  }
+
+  res
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn number_format_test() {
+    assert_eq!(apply(1234.567, "0"), "1235");
+    assert_eq!(apply(1234.567, "0.00"), "1234.57");
+    assert_eq!(apply(1234.5, "#,##0.00"), "1,234.50");
+    assert_eq!(apply(1234567.0, "#,##0"), "1,234,567");
+    assert_eq!(apply(-1234.5, "$#,##0.00"), "-$1,234.50");
+    assert_eq!(apply(0.256, "0%"), "26%");
+    assert_eq!(apply(0.256, "0.0%"), "25.6%");
+    assert_eq!(apply(2.5, "0.0#"), "2.5");
+    assert_eq!(apply(2.0, "#.##"), "2");
+    assert_eq!(apply(0.5, "#.00"), ".50");
+    assert_eq!(apply(7.0, "000"), "007");
+    assert_eq!(apply(-0.001, "0.00"), "0.00");
+    assert_eq!(apply(42.0, "0 \"days\""), "42 days");
+    assert_eq!(apply(42.0, "0 USD"), "42 USD");
+    assert_eq!(apply(1.5, "General"), "1.5");
+  }
+
+  #[test]
+  fn cell_number_format_test() {
+    let format = |format: NumberFormat, num: f64| format.format(&Value::Num(num));
+
+    assert_eq!(format(NumberFormat::default(), 1234.5), "1234.5");
+    assert_eq!(
+      format(
+        NumberFormat {
+          decimals: Some(1),
+          ..NumberFormat::default()
+        },
+        2.26
+      ),
+      "2.3"
+    );
+    assert_eq!(
+      format(
+        NumberFormat {
+          percent: true,
+          ..NumberFormat::default()
+        },
+        0.125
+      ),
+      "12.5%"
+    );
+    assert_eq!(
+      format(
+        NumberFormat {
+          currency: true,
+          thousands: true,
+          ..NumberFormat::default()
+        },
+        -1234.5
+      ),
+      "-$1,234.50"
+    );
+    assert_eq!(
+      NumberFormat {
+        thousands: true,
+        ..NumberFormat::default()
+      }
+      .format(&Value::Str(String::from("1234"))),
+      "1234"
+    );
+  }
+
+  #[test]
+  fn date_format_test() {
+    // Friday, 2024-03-01 14:05:09
+    let serial = date::serial_from_ymd(2024, 3, 1) + (14.0 * 3600.0 + 5.0 * 60.0 + 9.0) / 86400.0;
+
+    assert_eq!(apply(serial, "yyyy-mm-dd"), "2024-03-01");
+    assert_eq!(apply(serial, "dd/mm/yy"), "01/03/24");
+    assert_eq!(apply(serial, "d.m.yyyy"), "1.3.2024");
+    assert_eq!(apply(serial, "mmm d, yyyy"), "Mar 1, 2024");
+    assert_eq!(apply(serial, "dddd, mmmm d"), "Friday, March 1");
+    assert_eq!(apply(serial, "ddd"), "Fri");
+    assert_eq!(apply(serial, "hh:mm"), "14:05");
+    assert_eq!(apply(serial, "h:mm:ss"), "14:05:09");
+    assert_eq!(apply(serial, "mm:ss"), "05:09");
+    assert_eq!(apply(serial, "YYYY-MM-DD HH:MM"), "2024-03-01 14:05");
+    assert_eq!(apply(serial, "\"Day\" d"), "Day 1");
+  }
+}
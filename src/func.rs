@@ -0,0 +1,2508 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::cell_id::CellId;
+use crate::date;
+use crate::expr::{self, from_bool, to_bool, EvalContext, Expr, Op};
+use crate::format;
+use crate::registry::{self, FuncName};
+use crate::units;
+use crate::value::Value;
+use Func::*;
+
+/// Built-in formula functions, such as `SUM` in `= SUM(A1:A5, 3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Func {
+  Sum,
+  Average,
+  Min,
+  Max,
+  Count,
+  If,
+  IfError,
+  IsError,
+  IsBlank,
+  CountIf,
+  SumIf,
+  AverageIf,
+  Concat,
+  Concatenate,
+  Len,
+  Upper,
+  Lower,
+  Trim,
+  Left,
+  Right,
+  Mid,
+  Abs,
+  Sqrt,
+  Round,
+  Floor,
+  Ceiling,
+  Log,
+  Ln,
+  Exp,
+  Sin,
+  Cos,
+  Tan,
+  RoundUp,
+  RoundDown,
+  Trunc,
+  MRound,
+  Median,
+  Mode,
+  StDev,
+  StDevP,
+  Var,
+  VarP,
+  Percentile,
+  Pmt,
+  Fv,
+  Pv,
+  Npv,
+  Irr,
+  Today,
+  Now,
+  Date,
+  Year,
+  Month,
+  Day,
+  Weekday,
+  EoMonth,
+  DateDif,
+  Rand,
+  RandBetween,
+  VLookup,
+  HLookup,
+  XLookup,
+  Mod,
+  Gcd,
+  Lcm,
+  CountA,
+  CountBlank,
+  Text,
+  RegexMatch,
+  RegexExtract,
+  Find,
+  Substitute,
+  Split,
+  Sequence,
+  Ifs,
+  Switch,
+  Xor,
+  And,
+  Or,
+  Not,
+  Convert,
+  Pi,
+  E,
+  /// A custom function registered with `crate::registry::register_function`.
+  Custom(FuncName),
+}
+
+impl Func {
+  /// All the built-in functions, e.g., to autocomplete their names.
+  pub const ALL: [Func; 83] = [
+    Sum,
+    Average,
+    Min,
+    Max,
+    Count,
+    If,
+    IfError,
+    IsError,
+    IsBlank,
+    CountIf,
+    SumIf,
+    AverageIf,
+    Concat,
+    Concatenate,
+    Len,
+    Upper,
+    Lower,
+    Trim,
+    Left,
+    Right,
+    Mid,
+    Abs,
+    Sqrt,
+    Round,
+    Floor,
+    Ceiling,
+    Log,
+    Ln,
+    Exp,
+    Sin,
+    Cos,
+    Tan,
+    RoundUp,
+    RoundDown,
+    Trunc,
+    MRound,
+    Median,
+    Mode,
+    StDev,
+    StDevP,
+    Var,
+    VarP,
+    Percentile,
+    Pmt,
+    Fv,
+    Pv,
+    Npv,
+    Irr,
+    Today,
+    Now,
+    Date,
+    Year,
+    Month,
+    Day,
+    Weekday,
+    EoMonth,
+    DateDif,
+    Rand,
+    RandBetween,
+    VLookup,
+    HLookup,
+    XLookup,
+    Mod,
+    Gcd,
+    Lcm,
+    CountA,
+    CountBlank,
+    Text,
+    RegexMatch,
+    RegexExtract,
+    Find,
+    Substitute,
+    Split,
+    Sequence,
+    Ifs,
+    Switch,
+    Xor,
+    And,
+    Or,
+    Not,
+    Convert,
+    Pi,
+    E,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Sum => "SUM",
+      Average => "AVERAGE",
+      Min => "MIN",
+      Max => "MAX",
+      Count => "COUNT",
+      If => "IF",
+      IfError => "IFERROR",
+      IsError => "ISERROR",
+      IsBlank => "ISBLANK",
+      CountIf => "COUNTIF",
+      SumIf => "SUMIF",
+      AverageIf => "AVERAGEIF",
+      Concat => "CONCAT",
+      Concatenate => "CONCATENATE",
+      Len => "LEN",
+      Upper => "UPPER",
+      Lower => "LOWER",
+      Trim => "TRIM",
+      Left => "LEFT",
+      Right => "RIGHT",
+      Mid => "MID",
+      Abs => "ABS",
+      Sqrt => "SQRT",
+      Round => "ROUND",
+      Floor => "FLOOR",
+      Ceiling => "CEILING",
+      Log => "LOG",
+      Ln => "LN",
+      Exp => "EXP",
+      Sin => "SIN",
+      Cos => "COS",
+      Tan => "TAN",
+      RoundUp => "ROUNDUP",
+      RoundDown => "ROUNDDOWN",
+      Trunc => "TRUNC",
+      MRound => "MROUND",
+      Median => "MEDIAN",
+      Mode => "MODE",
+      StDev => "STDEV",
+      StDevP => "STDEVP",
+      Var => "VAR",
+      VarP => "VARP",
+      Percentile => "PERCENTILE",
+      Pmt => "PMT",
+      Fv => "FV",
+      Pv => "PV",
+      Npv => "NPV",
+      Irr => "IRR",
+      Today => "TODAY",
+      Now => "NOW",
+      Date => "DATE",
+      Year => "YEAR",
+      Month => "MONTH",
+      Day => "DAY",
+      Weekday => "WEEKDAY",
+      EoMonth => "EOMONTH",
+      DateDif => "DATEDIF",
+      Rand => "RAND",
+      RandBetween => "RANDBETWEEN",
+      VLookup => "VLOOKUP",
+      HLookup => "HLOOKUP",
+      XLookup => "XLOOKUP",
+      Mod => "MOD",
+      Gcd => "GCD",
+      Lcm => "LCM",
+      CountA => "COUNTA",
+      CountBlank => "COUNTBLANK",
+      Text => "TEXT",
+      RegexMatch => "REGEXMATCH",
+      RegexExtract => "REGEXEXTRACT",
+      Find => "FIND",
+      Substitute => "SUBSTITUTE",
+      Split => "SPLIT",
+      Sequence => "SEQUENCE",
+      Ifs => "IFS",
+      Switch => "SWITCH",
+      Xor => "XOR",
+      And => "AND",
+      Or => "OR",
+      Not => "NOT",
+      Convert => "CONVERT",
+      Pi => "PI",
+      E => "E",
+      Custom(name) => name.as_str(),
+    }
+  }
+
+  /// Returns the arguments of the function, e.g., `text, [count]`, with the optional ones
+  /// in brackets.
+  pub fn signature(&self) -> String {
+    let signature = match self {
+      Sum | Gcd | Lcm => "number1, [number2], …",
+      Average | Min | Max | Count | Median | Mode | StDev | StDevP | Var | VarP | CountA => {
+        "value1, [value2], …"
+      }
+      If => "condition, value_if_true, [value_if_false]",
+      IfError => "value, value_if_error",
+      IsError | IsBlank => "value",
+      CountIf => "range, criterion",
+      SumIf => "range, criterion, [sum_range]",
+      AverageIf => "range, criterion, [average_range]",
+      Concat | Concatenate => "text1, [text2], …",
+      Len | Upper | Lower | Trim => "text",
+      Left | Right => "text, [count]",
+      Mid => "text, start, count",
+      Abs | Sqrt | Ln | Exp | Sin | Cos | Tan => "number",
+      Round | RoundUp | RoundDown | Trunc => "number, [digits]",
+      Floor | Ceiling => "number, [significance]",
+      Log => "number, [base]",
+      MRound => "number, multiple",
+      Percentile => "range, k",
+      Pmt => "rate, periods, present_value, [future_value], [type]",
+      Fv => "rate, periods, payment, [present_value], [type]",
+      Pv => "rate, periods, payment, [future_value], [type]",
+      Npv => "rate, value1, [value2], …",
+      Irr => "values, [guess]",
+      Today | Now | Rand | Pi | E => "",
+      Date => "year, month, day",
+      Year | Month | Day => "date",
+      Weekday => "date, [type]",
+      EoMonth => "start_date, months",
+      DateDif => "start_date, end_date, unit",
+      RandBetween => "low, high",
+      VLookup | HLookup => "key, range, index, [is_sorted]",
+      XLookup => "key, lookup_range, result_range, [if_not_found], [match_mode], [search_mode]",
+      Mod => "number, divisor",
+      CountBlank => "range",
+      Text => "value, format",
+      RegexMatch | RegexExtract => "text, regex",
+      Find => "needle, text, [start]",
+      Substitute => "text, old, new, [instance]",
+      Split => "text, delimiter, [n]",
+      Sequence => "rows, [cols], [start], [step]",
+      Ifs => "condition1, value1, …",
+      Switch => "value, case1, result1, …, [default]",
+      Xor | And | Or => "condition1, [condition2], …",
+      Not => "condition",
+      Convert => "number, from_unit, to_unit",
+      Custom(name) => {
+        let Some(func) = registry::get(name.as_str()) else {
+          return String::new();
+        };
+        let (min, max) = (*func.arity.start(), *func.arity.end());
+        let mut args = (1..=min).map(|n| format!("value{n}")).collect::<Vec<_>>();
+        if max == usize::MAX {
+          args.push(format!("[value{}]", min + 1));
+          args.push(String::from("…"));
+        } else {
+          args.extend((min + 1..=max).map(|n| format!("[value{n}]")));
+        }
+        return args.join(", ");
+      }
+    };
+    signature.to_string()
+  }
+
+  /// Volatile functions return a different value each time they are evaluated,
+  /// so cells using them are recomputed on every change.
+  pub fn is_volatile(&self) -> bool {
+    matches!(self, Rand | RandBetween | Today | Now)
+  }
+
+  /// Constants can be used without parentheses, e.g., `= 2 * PI * A1`.
+  pub fn is_constant(&self) -> bool {
+    matches!(self, Pi | E)
+  }
+
+  /// Returns true for functions returning arrays, which spill into the neighboring cells.
+  pub fn returns_array(&self) -> bool {
+    matches!(self, Sequence)
+  }
+
+  /// Returns true for functions returning one of their arguments as is, such as `IF`,
+  /// which return arrays if the arguments are arrays.
+  pub fn may_return_arg(&self) -> bool {
+    matches!(self, If | IfError | Ifs | Switch | XLookup)
+  }
+
+  /// Evaluates the function with unevaluated `args`, so that each function can decide
+  /// which arguments are evaluated and how.
+  pub fn eval(&self, args: &[Expr], ctx: &EvalContext) -> Result<Value, Box<dyn Error>> {
+    match self {
+      Sum => Ok(Value::Num(expr::sum(&numbers(args, ctx)?, ctx.number_mode))),
+      Average => average(self, &numbers(args, ctx)?),
+      // as in other spreadsheets, MIN and MAX of no numeric values are 0
+      Min => Ok(Value::Num(
+        numbers(args, ctx)?
+          .into_iter()
+          .reduce(f64::min)
+          .unwrap_or(0.0),
+      )),
+      Max => Ok(Value::Num(
+        numbers(args, ctx)?
+          .into_iter()
+          .reduce(f64::max)
+          .unwrap_or(0.0),
+      )),
+      Count => Ok(Value::Num(numbers(args, ctx)?.len() as f64)),
+      // counts non-blank values, including text
+      CountA => Ok(Value::Num(
+        values(args, ctx)?
+          .iter()
+          .filter(|value| !value.is_blank())
+          .count() as f64,
+      )),
+      // counts empty cells and cells with empty text
+      CountBlank => {
+        self.check_args_count(args, 1, 1)?;
+        let (from, to) = self.range_arg(&args[0])?;
+
+        Ok(Value::Num(
+          CellId::range(from, to)
+            .filter(|cell_id| ctx.values.get(cell_id).map_or(true, Value::is_blank))
+            .count() as f64,
+        ))
+      }
+      // only the taken branch is evaluated; a missing else branch evaluates to false
+      If => {
+        self.check_args_count(args, 2, 3)?;
+
+        if to_bool(args[0].eval(ctx)?.as_num()?) {
+          args[1].eval(ctx)
+        } else {
+          args
+            .get(2)
+            .map_or(Ok(Value::Num(from_bool(false))), |arg| arg.eval(ctx))
+        }
+      }
+      // IFS(condition1, value1, [condition2, value2], ...) returns the value of the first
+      // true condition; conditions after it and other values are not evaluated
+      Ifs => {
+        if args.is_empty() || args.len() % 2 != 0 {
+          return Err("IFS expects pairs of conditions and values".into());
+        }
+
+        for pair in args.chunks(2) {
+          if to_bool(pair[0].eval(ctx)?.as_num()?) {
+            return pair[1].eval(ctx);
+          }
+        }
+        Err("IFS got no true conditions".into())
+      }
+      // SWITCH(expr, case1, value1, [case2, value2], ..., [default]) returns the value
+      // of the first case equal to `expr`, or `default`
+      Switch => {
+        if args.len() < 3 {
+          return Err(format!("SWITCH expects at least 3 arguments, got {}", args.len()).into());
+        }
+
+        let value = args[0].eval(ctx)?;
+        let mut cases = args[1..].chunks_exact(2);
+        for case in cases.by_ref() {
+          if Op::Equal.compare(&value, &case[0].eval(ctx)?) {
+            return case[1].eval(ctx);
+          }
+        }
+
+        match cases.remainder() {
+          [default] => default.eval(ctx),
+          _ => Err(format!("SWITCH got no case matching `{value}`").into()),
+        }
+      }
+      // true if an odd number of arguments are true
+      Xor => {
+        let nums = numbers(args, ctx)?;
+        if nums.is_empty() {
+          return Err("XOR expects at least 1 value".into());
+        }
+
+        let true_count = nums.into_iter().filter(|num| to_bool(*num)).count();
+        Ok(Value::Num(from_bool(true_count % 2 == 1)))
+      }
+      // the arguments are evaluated in order until one decides the result, so that
+      // `AND(A1 <> 0, 1 / A1 > 2)` doesn't fail
+      And | Or => {
+        let short_circuit = *self == Or;
+        let mut has_values = false;
+        for arg in args {
+          for num in numbers(std::slice::from_ref(arg), ctx)? {
+            has_values = true;
+            if to_bool(num) == short_circuit {
+              return Ok(Value::Num(from_bool(short_circuit)));
+            }
+          }
+        }
+
+        if !has_values {
+          return Err(format!("{} expects at least 1 value", self.name()).into());
+        }
+        Ok(Value::Num(from_bool(!short_circuit)))
+      }
+      Not => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Num(from_bool(!to_bool(
+          args[0].eval(ctx)?.as_num()?,
+        ))))
+      }
+      // the fallback is only evaluated if the first argument fails
+      IfError => {
+        self.check_args_count(args, 2, 2)?;
+        args[0].eval(ctx).or_else(|_err| args[1].eval(ctx))
+      }
+      IsError => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Num(from_bool(args[0].eval(ctx).is_err())))
+      }
+      // unlike COUNTBLANK, doesn't consider empty text blank, like other spreadsheets
+      IsBlank => {
+        self.check_args_count(args, 1, 1)?;
+        let is_blank = match &args[0] {
+          Expr::CellRef(cell_id) => ctx
+            .values
+            .get(cell_id)
+            .map_or(true, |value| *value == Value::Empty),
+          arg => arg.eval(ctx)? == Value::Empty,
+        };
+        Ok(Value::Num(from_bool(is_blank)))
+      }
+      CountIf => {
+        self.check_args_count(args, 2, 2)?;
+        Ok(Value::Num(self.matching(args, ctx)?.len() as f64))
+      }
+      SumIf => {
+        self.check_args_count(args, 2, 3)?;
+        Ok(Value::Num(expr::sum(
+          &self.matching(args, ctx)?,
+          ctx.number_mode,
+        )))
+      }
+      AverageIf => {
+        self.check_args_count(args, 2, 3)?;
+        average(self, &self.matching(args, ctx)?)
+      }
+      Concat | Concatenate => Ok(Value::Str(
+        values(args, ctx)?
+          .iter()
+          .map(|value| value.to_string())
+          .collect(),
+      )),
+      Len => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Num(text(&args[0], ctx)?.chars().count() as f64))
+      }
+      Upper => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Str(text(&args[0], ctx)?.to_uppercase()))
+      }
+      Lower => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Str(text(&args[0], ctx)?.to_lowercase()))
+      }
+      // like in other spreadsheets, also collapses repeated spaces between words
+      Trim => {
+        self.check_args_count(args, 1, 1)?;
+        Ok(Value::Str(
+          text(&args[0], ctx)?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        ))
+      }
+      Left | Right => {
+        self.check_args_count(args, 1, 2)?;
+        let s = text(&args[0], ctx)?;
+        let count = match args.get(1) {
+          Some(arg) => self.count(arg, ctx)?,
+          None => 1,
+        };
+
+        let res = if *self == Left {
+          s.chars().take(count).collect()
+        } else {
+          let len = s.chars().count();
+          s.chars().skip(len.saturating_sub(count)).collect()
+        };
+        Ok(Value::Str(res))
+      }
+      // formats a number with a pattern, such as "#,##0.00" or "yyyy-mm-dd", see `crate::format`;
+      // text is returned as is
+      Text => {
+        self.check_args_count(args, 2, 2)?;
+        let pattern = text(&args[1], ctx)?;
+
+        match args[0].eval(ctx)? {
+          Value::Str(s) => Ok(Value::Str(s)),
+          value => Ok(Value::Str(format::apply(value.as_num()?, &pattern))),
+        }
+      }
+      // the 1-based position of the first occurrence of `needle`, starting from `start`;
+      // case-sensitive, like in other spreadsheets
+      Find => {
+        self.check_args_count(args, 2, 3)?;
+        let needle = text(&args[0], ctx)?;
+        let s = text(&args[1], ctx)?;
+        let start = match args.get(2) {
+          Some(arg) => self.count(arg, ctx)?,
+          None => 1,
+        };
+        if start == 0 || start > s.chars().count() + 1 {
+          return Err(format!("FIND got start {start} outside of the text").into());
+        }
+
+        let offset = s
+          .char_indices()
+          .nth(start - 1)
+          .map_or(s.len(), |(idx, _)| idx);
+        match s[offset..].find(&needle) {
+          Some(idx) => Ok(Value::Num(
+            (start + s[offset..][..idx].chars().count()) as f64,
+          )),
+          None => Err(format!("FIND cannot find `{needle}` in `{s}`").into()),
+        }
+      }
+      // replaces all occurrences of `old` with `new`, or only the `instance`-th one
+      Substitute => {
+        self.check_args_count(args, 3, 4)?;
+        let s = text(&args[0], ctx)?;
+        let old = text(&args[1], ctx)?;
+        let new = text(&args[2], ctx)?;
+        let instance = match args.get(3) {
+          Some(arg) => Some(self.count(arg, ctx)?),
+          None => None,
+        };
+
+        match instance {
+          _ if old.is_empty() => Ok(Value::Str(s)),
+          None => Ok(Value::Str(s.replace(&old, &new))),
+          Some(0) => Err("SUBSTITUTE expects instance to be at least 1".into()),
+          Some(instance) => match s.match_indices(&old).nth(instance - 1) {
+            Some((idx, _)) => Ok(Value::Str(format!(
+              "{}{new}{}",
+              &s[..idx],
+              &s[idx + old.len()..]
+            ))),
+            None => Ok(Value::Str(s)),
+          },
+        }
+      }
+      // returns the `n`-th (1 by default) non-empty piece of the text split by `delimiter`
+      Split => {
+        self.check_args_count(args, 2, 3)?;
+        let s = text(&args[0], ctx)?;
+        let delimiter = text(&args[1], ctx)?;
+        let n = match args.get(2) {
+          Some(arg) => self.count(arg, ctx)?,
+          None => 1,
+        };
+        if delimiter.is_empty() {
+          return Err("SPLIT expects a non-empty delimiter".into());
+        }
+
+        let mut pieces = s.split(&delimiter).filter(|piece| !piece.is_empty());
+        match n.checked_sub(1).and_then(|idx| pieces.nth(idx)) {
+          Some(piece) => Ok(Value::Str(piece.to_string())),
+          None => Err(format!("SPLIT got no piece number {n} of `{s}`").into()),
+        }
+      }
+      RegexMatch => {
+        self.check_args_count(args, 2, 2)?;
+        let s = text(&args[0], ctx)?;
+        let re = regex(&text(&args[1], ctx)?)?;
+
+        Ok(Value::Num(from_bool(re.is_match(&s))))
+      }
+      // returns the first capture group, or the whole match if the pattern has no groups
+      RegexExtract => {
+        self.check_args_count(args, 2, 2)?;
+        let s = text(&args[0], ctx)?;
+        let re = regex(&text(&args[1], ctx)?)?;
+
+        match re.captures(&s) {
+          Some(captures) => Ok(Value::Str(
+            captures
+              .get(1)
+              .or_else(|| captures.get(0))
+              .map_or("", |m| m.as_str())
+              .to_string(),
+          )),
+          None => Err(format!("REGEXEXTRACT found no match for `{}`", re.as_str()).into()),
+        }
+      }
+      // `start` is 1-based
+      Mid => {
+        self.check_args_count(args, 3, 3)?;
+        let s = text(&args[0], ctx)?;
+        let start = self.count(&args[1], ctx)?;
+        let count = self.count(&args[2], ctx)?;
+
+        if start == 0 {
+          return Err("MID expects start to be at least 1".into());
+        }
+        Ok(Value::Str(s.chars().skip(start - 1).take(count).collect()))
+      }
+      Custom(name) => {
+        let func = registry::get(name.as_str()).ok_or_else(|| {
+          format!(
+            "custom function {} is not registered anymore",
+            name.as_str()
+          )
+        })?;
+        self.check_args_count(args, *func.arity.start(), *func.arity.end())?;
+
+        let values = args
+          .iter()
+          .map(|arg| arg.eval(ctx))
+          .collect::<Result<Vec<_>, _>>()?;
+        (func.f)(&values)
+      }
+      Pi => {
+        self.check_args_count(args, 0, 0)?;
+        Ok(Value::Num(std::f64::consts::PI))
+      }
+      E => {
+        self.check_args_count(args, 0, 0)?;
+        Ok(Value::Num(std::f64::consts::E))
+      }
+      Abs => self.math(args, ctx, |x, _| x.abs()),
+      Sqrt => self.math(args, ctx, |x, _| x.sqrt()),
+      Exp => self.math(args, ctx, |x, _| x.exp()),
+      Ln => self.math(args, ctx, |x, _| x.ln()),
+      Sin => self.math(args, ctx, |x, _| x.sin()),
+      Cos => self.math(args, ctx, |x, _| x.cos()),
+      Tan => self.math(args, ctx, |x, _| x.tan()),
+      Log => self.math_with_default(args, ctx, 10.0, |x, base| x.log(base)),
+      // negative `digits` round to tens, hundreds, etc.
+      // ROUND rounds half away from zero, ROUNDUP - away from zero, ROUNDDOWN & TRUNC - towards zero
+      Round => self.math_with_default(args, ctx, 0.0, |x, digits| round(x, digits, f64::round)),
+      RoundUp => self.math_with_default(args, ctx, 0.0, |x, digits| {
+        round(x, digits, |x| x.abs().ceil().copysign(x))
+      }),
+      RoundDown | Trunc => {
+        self.math_with_default(args, ctx, 0.0, |x, digits| round(x, digits, f64::trunc))
+      }
+      // rounds half away from zero to a multiple of `multiple`, which should have the same sign
+      MRound => {
+        self.check_args_count(args, 2, 2)?;
+        self.math_with_default(args, ctx, f64::NAN, |x, multiple| {
+          if multiple == 0.0 {
+            0.0
+          } else if x != 0.0 && x.signum() != multiple.signum() {
+            f64::NAN
+          } else {
+            round(x / multiple, 0.0, f64::round) * multiple
+          }
+        })
+      }
+      Median => {
+        let nums = sorted(numbers(args, ctx)?);
+        percentile(self, &nums, 0.5)
+      }
+      // the most frequent value; the first one to occur wins ties
+      Mode => {
+        let nums = numbers(args, ctx)?;
+        let mut best: Option<(f64, usize)> = None;
+
+        for num in &nums {
+          let count = nums.iter().filter(|other| *other == num).count();
+          if count > 1 && best.map_or(true, |(_, best_count)| count > best_count) {
+            best = Some((*num, count));
+          }
+        }
+
+        best
+          .map(|(num, _)| Value::Num(num))
+          .ok_or_else(|| "MODE got no repeating values".into())
+      }
+      // sample (STDEV & VAR) or population (STDEVP & VARP) statistics
+      StDev | StDevP | Var | VarP => {
+        let nums = numbers(args, ctx)?;
+        let degrees_of_freedom = match self {
+          StDev | Var => nums.len().saturating_sub(1),
+          _ => nums.len(),
+        };
+
+        if degrees_of_freedom == 0 {
+          return Err(format!("{} got too few numeric values", self.name()).into());
+        }
+
+        let mean = nums.iter().sum::<f64>() / nums.len() as f64;
+        let variance =
+          nums.iter().map(|num| (num - mean).powi(2)).sum::<f64>() / degrees_of_freedom as f64;
+
+        match self {
+          StDev | StDevP => Ok(Value::Num(variance.sqrt())),
+          _ => Ok(Value::Num(variance)),
+        }
+      }
+      // `k` is between 0 and 1, inclusive
+      Percentile => {
+        self.check_args_count(args, 2, 2)?;
+        let nums = sorted(numbers(&args[..1], ctx)?);
+        let k = args[1].eval(ctx)?.as_num()?;
+
+        percentile(self, &nums, k)
+      }
+      // cash flows follow the usual sign convention: money paid out is negative;
+      // `type` is 1 if payments are due at the beginning of a period, and 0 if at the end
+      Pmt => {
+        let [rate, nper, pv, fv, type_] =
+          self.nums(args, ctx, [None, None, None, Some(0.0), Some(0.0)])?;
+
+        if rate == 0.0 {
+          finite(self, -(pv + fv) / nper)
+        } else {
+          let growth = (1.0 + rate).powf(nper);
+          finite(
+            self,
+            -rate * (pv * growth + fv) / ((1.0 + rate * type_) * (growth - 1.0)),
+          )
+        }
+      }
+      Fv => {
+        let [rate, nper, pmt, pv, type_] =
+          self.nums(args, ctx, [None, None, None, Some(0.0), Some(0.0)])?;
+
+        if rate == 0.0 {
+          finite(self, -(pv + pmt * nper))
+        } else {
+          let growth = (1.0 + rate).powf(nper);
+          finite(
+            self,
+            -(pv * growth + pmt * (1.0 + rate * type_) * (growth - 1.0) / rate),
+          )
+        }
+      }
+      Pv => {
+        let [rate, nper, pmt, fv, type_] =
+          self.nums(args, ctx, [None, None, None, Some(0.0), Some(0.0)])?;
+
+        if rate == 0.0 {
+          finite(self, -(fv + pmt * nper))
+        } else {
+          let growth = (1.0 + rate).powf(nper);
+          finite(
+            self,
+            -(fv + pmt * (1.0 + rate * type_) * (growth - 1.0) / rate) / growth,
+          )
+        }
+      }
+      // the first value is discounted by one period
+      Npv => {
+        if args.len() < 2 {
+          return Err("NPV expects a rate and at least 1 value".into());
+        }
+
+        let rate = args[0].eval(ctx)?.as_num()?;
+        let values = numbers(&args[1..], ctx)?;
+        finite(self, net_present_value(rate, &values) / (1.0 + rate))
+      }
+      // the first value is not discounted, like in other spreadsheets
+      Irr => {
+        self.check_args_count(args, 1, 2)?;
+        let values = numbers(&args[..1], ctx)?;
+        let guess = match args.get(1) {
+          Some(arg) => arg.eval(ctx)?.as_num()?,
+          None => 0.1,
+        };
+
+        internal_rate_of_return(&values, guess)
+          .map(Value::Num)
+          .ok_or_else(|| "IRR cannot find a rate making the net present value zero".into())
+      }
+      Today => {
+        self.check_args_count(args, 0, 0)?;
+        Ok(Value::Date(date::now().floor()))
+      }
+      Now => {
+        self.check_args_count(args, 0, 0)?;
+        Ok(Value::Date(date::now()))
+      }
+      Date => {
+        let [year, month, day] = self.nums(args, ctx, [None, None, None])?;
+        Ok(Value::Date(date::serial_from_ymd(
+          year.trunc() as i64,
+          month.trunc() as i64,
+          day.trunc() as i64,
+        )))
+      }
+      Year | Month | Day => {
+        let [serial] = self.nums(args, ctx, [None])?;
+        let (year, month, day) = date::ymd_from_serial(serial);
+        let part = match self {
+          Year => year,
+          Month => month,
+          _ => day,
+        };
+        Ok(Value::Num(part as f64))
+      }
+      // `type` 1 numbers days from Sunday = 1, 2 - from Monday = 1, and 3 - from Monday = 0
+      Weekday => {
+        let [serial, type_] = self.nums(args, ctx, [None, Some(1.0)])?;
+        let from_sunday = date::weekday(serial);
+        let from_monday = (from_sunday - 1).rem_euclid(7);
+
+        match type_.trunc() as i64 {
+          1 => Ok(Value::Num((from_sunday + 1) as f64)),
+          2 => Ok(Value::Num((from_monday + 1) as f64)),
+          3 => Ok(Value::Num(from_monday as f64)),
+          _ => Err(format!("WEEKDAY expects type to be 1, 2, or 3, got {type_}").into()),
+        }
+      }
+      // the last day of the month `months` months before or after the start date
+      EoMonth => {
+        let [serial, months] = self.nums(args, ctx, [None, None])?;
+        let (year, month, _) = date::ymd_from_serial(serial);
+        Ok(Value::Date(date::serial_from_ymd(
+          year,
+          month + months.trunc() as i64 + 1,
+          0,
+        )))
+      }
+      // the number of complete years ("Y"), months ("M"), or days ("D") between the dates;
+      // "YM" ignores years, "MD" ignores months and years, and "YD" ignores years of the dates
+      DateDif => {
+        self.check_args_count(args, 3, 3)?;
+        let [start, end] = self.nums(&args[..2], ctx, [None, None])?;
+        let (start, end) = (start.floor(), end.floor());
+        let unit = text(&args[2], ctx)?.to_uppercase();
+
+        if start > end {
+          return Err("DATEDIF expects the start date to be before the end date".into());
+        }
+
+        let ((start_year, start_month, start_day), (end_year, end_month, end_day)) =
+          (date::ymd_from_serial(start), date::ymd_from_serial(end));
+        let months =
+          (end_year - start_year) * 12 + (end_month - start_month) - i64::from(end_day < start_day);
+
+        match unit.as_str() {
+          "Y" => Ok(Value::Num((months / 12) as f64)),
+          "M" => Ok(Value::Num(months as f64)),
+          "D" => Ok(Value::Num(end - start)),
+          "YM" => Ok(Value::Num((months % 12) as f64)),
+          "MD" => Ok(Value::Num(end - date::add_months(start, months))),
+          "YD" => Ok(Value::Num(end - date::add_months(start, months / 12 * 12))),
+          _ => Err(format!("DATEDIF got an unknown unit `{unit}`").into()),
+        }
+      }
+      Rand => {
+        self.check_args_count(args, 0, 0)?;
+        Ok(Value::Num(random()))
+      }
+      // a random integer between `low` and `high`, inclusive
+      RandBetween => {
+        let [low, high] = self.nums(args, ctx, [None, None])?;
+        let (low, high) = (low.ceil(), high.floor());
+
+        if low > high {
+          return Err("RANDBETWEEN expects low to be less than or equal to high".into());
+        }
+        Ok(Value::Num(low + (random() * (high - low + 1.0)).floor()))
+      }
+      // searches the first column (VLOOKUP) or row (HLOOKUP) of the range for the key,
+      // and returns the value from the `index`-th column or row of the matching row or column;
+      // if `is_sorted` is true (the default), matches the largest value less than or equal to the key
+      VLookup | HLookup => {
+        self.check_args_count(args, 3, 4)?;
+        let key = args[0].eval(ctx)?;
+        let (from, to) = self.range_arg(&args[1])?;
+        let index = self.count(&args[2], ctx)?;
+        let is_sorted = match args.get(3) {
+          Some(arg) => to_bool(arg.eval(ctx)?.as_num()?),
+          None => true,
+        };
+
+        let size = if *self == VLookup {
+          to.col as usize - from.col as usize + 1
+        } else {
+          to.row - from.row + 1
+        };
+        if index == 0 || index > size {
+          return Err(
+            format!(
+              "{} expects index between 1 and {size}, got {index}",
+              self.name()
+            )
+            .into(),
+          );
+        }
+
+        let keys_to = if *self == VLookup {
+          CellId {
+            col: from.col,
+            row: to.row,
+          }
+        } else {
+          CellId {
+            col: to.col,
+            row: from.row,
+          }
+        };
+        let keys = CellId::range(from, keys_to).collect::<Vec<_>>();
+        let match_mode = if is_sorted {
+          LookupMatch::NextSmaller
+        } else {
+          LookupMatch::Exact
+        };
+
+        match lookup(&key, &keys, ctx, match_mode, false) {
+          Some(idx) => {
+            let CellId { col, row } = keys[idx];
+            let cell_id = if *self == VLookup {
+              CellId {
+                col: (col as u8 + index as u8 - 1) as char,
+                row,
+              }
+            } else {
+              CellId {
+                col,
+                row: row + index - 1,
+              }
+            };
+            Ok(ctx.values.get(&cell_id).cloned().unwrap_or(Value::Num(0.0)))
+          }
+          None => Err(format!("{} cannot find `{key}`", self.name()).into()),
+        }
+      }
+      // XLOOKUP(key, lookup_range, return_range, [if_not_found], [match_mode], [search_mode]):
+      // `match_mode` 0 is an exact match, -1 and 1 also match the next smaller or larger value,
+      // and `search_mode` -1 searches from the last cell instead of the first one
+      XLookup => {
+        self.check_args_count(args, 3, 6)?;
+        let key = args[0].eval(ctx)?;
+        let keys = self.vector_arg(&args[1])?;
+        let results = self.vector_arg(&args[2])?;
+        if keys.len() != results.len() {
+          return Err("XLOOKUP expects lookup and return ranges of the same size".into());
+        }
+
+        let match_mode = match args.get(4).map(|arg| arg.eval(ctx)?.as_num()).transpose()? {
+          None | Some(0.0) => LookupMatch::Exact,
+          Some(-1.0) => LookupMatch::NextSmaller,
+          Some(1.0) => LookupMatch::NextLarger,
+          Some(other) => {
+            return Err(format!("XLOOKUP got an unsupported match mode {other}").into())
+          }
+        };
+        let reverse = match args.get(5).map(|arg| arg.eval(ctx)?.as_num()).transpose()? {
+          None | Some(1.0) => false,
+          Some(-1.0) => true,
+          Some(other) => {
+            return Err(format!("XLOOKUP got an unsupported search mode {other}").into())
+          }
+        };
+
+        match lookup(&key, &keys, ctx, match_mode, reverse) {
+          Some(idx) => Ok(
+            ctx
+              .values
+              .get(&results[idx])
+              .cloned()
+              .unwrap_or(Value::Num(0.0)),
+          ),
+          // the fallback is only evaluated if nothing was found
+          None => match args.get(3) {
+            Some(arg) => arg.eval(ctx),
+            None => Err(format!("XLOOKUP cannot find `{key}`").into()),
+          },
+        }
+      }
+      // the result has the same sign as the divisor, like in other spreadsheets
+      Mod => {
+        self.check_args_count(args, 2, 2)?;
+        self.math_with_default(args, ctx, f64::NAN, |x, divisor| {
+          if divisor == 0.0 {
+            f64::NAN
+          } else {
+            x - divisor * (x / divisor).floor()
+          }
+        })
+      }
+      // arguments are truncated to integers, and cannot be negative
+      Gcd | Lcm => {
+        let nums = numbers(args, ctx)?;
+        if nums.is_empty() {
+          return Err(format!("{} expects at least 1 number", self.name()).into());
+        }
+        if let Some(num) = nums.iter().find(|num| **num < 0.0) {
+          return Err(format!("{} expects non-negative numbers, got {num}", self.name()).into());
+        }
+
+        let res = nums.into_iter().map(f64::trunc).reduce(|acc, num| {
+          if *self == Gcd {
+            gcd(acc, num)
+          } else if acc == 0.0 || num == 0.0 {
+            0.0
+          } else {
+            acc / gcd(acc, num) * num
+          }
+        });
+        finite(self, res.unwrap_or_default())
+      }
+      // SEQUENCE(rows, [cols], [start], [step]) fills an array with numbers row by row
+      Sequence => {
+        let [rows, cols, start, step] =
+          self.nums(args, ctx, [None, Some(1.0), Some(1.0), Some(1.0)])?;
+        let (rows, cols) = (rows.trunc(), cols.trunc());
+        if rows < 1.0 || cols < 1.0 {
+          return Err("SEQUENCE expects at least 1 row and 1 column".into());
+        }
+        if rows * cols > MAX_ARRAY_SIZE as f64 {
+          return Err(format!("SEQUENCE is limited to {MAX_ARRAY_SIZE} values").into());
+        }
+
+        let (rows, cols) = (rows as usize, cols as usize);
+        Ok(Value::Array(
+          (0..rows)
+            .map(|row| {
+              (0..cols)
+                .map(|col| Value::Num(start + step * (row * cols + col) as f64))
+                .collect()
+            })
+            .collect(),
+        ))
+      }
+      // CONVERT(value, from_unit, to_unit), see `crate::units` for the supported units
+      Convert => {
+        self.check_args_count(args, 3, 3)?;
+        let value = args[0].eval(ctx)?.as_num()?;
+        let (from, to) = (text(&args[1], ctx)?, text(&args[2], ctx)?);
+
+        Ok(Value::Num(units::convert(value, &from, &to)?))
+      }
+      // rounds to a multiple of `significance`
+      Floor | Ceiling => self.math_with_default(args, ctx, 1.0, |x, significance| {
+        if significance == 0.0 {
+          0.0
+        } else if x > 0.0 && significance < 0.0 {
+          f64::NAN
+        } else if *self == Floor {
+          (x / significance).floor() * significance
+        } else {
+          (x / significance).ceil() * significance
+        }
+      }),
+    }
+  }
+
+  fn check_args_count(&self, args: &[Expr], min: usize, max: usize) -> Result<(), Box<dyn Error>> {
+    if args.len() < min || args.len() > max {
+      let expected = if min == max {
+        min.to_string()
+      } else {
+        format!("{min} to {max}")
+      };
+
+      Err(
+        format!(
+          "{} expects {expected} arguments, got {}",
+          self.name(),
+          args.len()
+        )
+        .into(),
+      )
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Evaluates numeric arguments, with `defaults` for the optional ones;
+  /// all required arguments should go before the optional ones.
+  fn nums<const N: usize>(
+    &self,
+    args: &[Expr],
+    ctx: &EvalContext,
+    defaults: [Option<f64>; N],
+  ) -> Result<[f64; N], Box<dyn Error>> {
+    let required = defaults.iter().filter(|default| default.is_none()).count();
+    self.check_args_count(args, required, N)?;
+
+    let mut nums = [0.0; N];
+    for (idx, default) in defaults.into_iter().enumerate() {
+      nums[idx] = match (args.get(idx), default) {
+        (Some(arg), _) => arg.eval(ctx)?.as_num()?,
+        (None, Some(default)) => default,
+        (None, None) => unreachable!("the number of arguments was checked above"),
+      };
+    }
+
+    Ok(nums)
+  }
+
+  /// Evaluates a numeric function of a single argument.
+  fn math(
+    &self,
+    args: &[Expr],
+    ctx: &EvalContext,
+    f: impl Fn(f64, f64) -> f64,
+  ) -> Result<Value, Box<dyn Error>> {
+    self.check_args_count(args, 1, 1)?;
+    self.math_with_default(args, ctx, f64::NAN, f)
+  }
+
+  /// Evaluates a numeric function of an argument and an optional 2nd argument,
+  /// which is `default` if omitted. Fails for results that are not finite numbers,
+  /// such as the square root of a negative number.
+  fn math_with_default(
+    &self,
+    args: &[Expr],
+    ctx: &EvalContext,
+    default: f64,
+    f: impl Fn(f64, f64) -> f64,
+  ) -> Result<Value, Box<dyn Error>> {
+    self.check_args_count(args, 1, 2)?;
+    let x = args[0].eval(ctx)?.as_num()?;
+    let y = match args.get(1) {
+      Some(arg) => arg.eval(ctx)?.as_num()?,
+      None => default,
+    };
+
+    let res = f(x, y);
+    if res.is_finite() {
+      Ok(Value::Num(res))
+    } else {
+      Err(format!("{} is not defined for the given arguments", self.name()).into())
+    }
+  }
+
+  /// Evaluates an argument specifying a number of characters or a position,
+  /// truncating it to an integer.
+  fn count(&self, arg: &Expr, ctx: &EvalContext) -> Result<usize, Box<dyn Error>> {
+    let num = arg.eval(ctx)?.as_num()?;
+
+    if num < 0.0 {
+      Err(format!("{} expects a non-negative number, got {num}", self.name()).into())
+    } else {
+      Ok(num.trunc() as usize)
+    }
+  }
+
+  /// Returns the top left and the bottom right corners of a range argument.
+  fn range_arg(&self, arg: &Expr) -> Result<(CellId, CellId), Box<dyn Error>> {
+    match arg {
+      Expr::CellRef(cell_id) => Ok((*cell_id, *cell_id)),
+      Expr::Range { from, to } => Ok((
+        CellId {
+          col: from.col.min(to.col),
+          row: from.row.min(to.row),
+        },
+        CellId {
+          col: from.col.max(to.col),
+          row: from.row.max(to.row),
+        },
+      )),
+      _ => Err(format!("{} expects a range, got {arg:?}", self.name()).into()),
+    }
+  }
+
+  /// Returns the cells of a range argument spanning a single row or column.
+  fn vector_arg(&self, arg: &Expr) -> Result<Vec<CellId>, Box<dyn Error>> {
+    let (from, to) = self.range_arg(arg)?;
+
+    if from.col != to.col && from.row != to.row {
+      Err(
+        format!(
+          "{} expects a single row or column, got {from}:{to}",
+          self.name()
+        )
+        .into(),
+      )
+    } else {
+      Ok(CellId::range(from, to).collect())
+    }
+  }
+
+  /// For `COUNTIF(range, criteria)` and `SUMIF(range, criteria, [sum_range])`-like
+  /// functions, returns numeric values of the cells of `sum_range` corresponding
+  /// to the cells of `range` matching the `criteria`. `sum_range` defaults to `range`.
+  ///
+  /// `COUNTIF` gets `1` for each matching cell, since it counts text cells too.
+  fn matching(&self, args: &[Expr], ctx: &EvalContext) -> Result<Vec<f64>, Box<dyn Error>> {
+    let (from, to) = self.range_arg(&args[0])?;
+    let criteria = Criteria::from(args[1].eval(ctx)?);
+    let sum_from = match args.get(2) {
+      Some(arg) => {
+        let (sum_from, sum_to) = self.range_arg(arg)?;
+
+        if (
+          sum_to.col as u8 - sum_from.col as u8,
+          sum_to.row - sum_from.row,
+        ) != (to.col as u8 - from.col as u8, to.row - from.row)
+        {
+          return Err(format!("{} expects ranges of the same size", self.name()).into());
+        }
+        sum_from
+      }
+      None => from,
+    };
+
+    let mut nums = vec![];
+    for cell_id in CellId::range(from, to) {
+      if let Some(value) = ctx.values.get(&cell_id) {
+        if criteria.matches(value) {
+          let sum_cell_id = CellId {
+            col: (sum_from.col as u8 + (cell_id.col as u8 - from.col as u8)) as char,
+            row: sum_from.row + (cell_id.row - from.row),
+          };
+
+          if *self == CountIf {
+            nums.push(1.0);
+          } else if let Some(value @ (Value::Num(_) | Value::Date(_) | Value::Bool(_))) =
+            ctx.values.get(&sum_cell_id)
+          {
+            nums.push(value.as_num()?);
+          }
+        }
+      }
+    }
+
+    Ok(nums)
+  }
+}
+
+impl TryFrom<&str> for Func {
+  type Error = String;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value.to_ascii_uppercase().as_str() {
+      "SUM" => Ok(Sum),
+      "AVERAGE" => Ok(Average),
+      "MIN" => Ok(Min),
+      "MAX" => Ok(Max),
+      "COUNT" => Ok(Count),
+      "IF" => Ok(If),
+      "IFERROR" => Ok(IfError),
+      "ISERROR" => Ok(IsError),
+      "ISBLANK" => Ok(IsBlank),
+      "COUNTIF" => Ok(CountIf),
+      "SUMIF" => Ok(SumIf),
+      "AVERAGEIF" => Ok(AverageIf),
+      "CONCAT" => Ok(Concat),
+      "CONCATENATE" => Ok(Concatenate),
+      "LEN" => Ok(Len),
+      "UPPER" => Ok(Upper),
+      "LOWER" => Ok(Lower),
+      "TRIM" => Ok(Trim),
+      "LEFT" => Ok(Left),
+      "RIGHT" => Ok(Right),
+      "MID" => Ok(Mid),
+      "ABS" => Ok(Abs),
+      "SQRT" => Ok(Sqrt),
+      "ROUND" => Ok(Round),
+      "FLOOR" => Ok(Floor),
+      "CEILING" => Ok(Ceiling),
+      "LOG" => Ok(Log),
+      "LN" => Ok(Ln),
+      "EXP" => Ok(Exp),
+      "SIN" => Ok(Sin),
+      "COS" => Ok(Cos),
+      "TAN" => Ok(Tan),
+      "ROUNDUP" => Ok(RoundUp),
+      "ROUNDDOWN" => Ok(RoundDown),
+      "TRUNC" => Ok(Trunc),
+      "MROUND" => Ok(MRound),
+      "MEDIAN" => Ok(Median),
+      "MODE" => Ok(Mode),
+      "STDEV" => Ok(StDev),
+      "STDEVP" => Ok(StDevP),
+      "VAR" => Ok(Var),
+      "VARP" => Ok(VarP),
+      "PERCENTILE" => Ok(Percentile),
+      "PMT" => Ok(Pmt),
+      "FV" => Ok(Fv),
+      "PV" => Ok(Pv),
+      "NPV" => Ok(Npv),
+      "IRR" => Ok(Irr),
+      "TODAY" => Ok(Today),
+      "NOW" => Ok(Now),
+      "DATE" => Ok(Date),
+      "YEAR" => Ok(Year),
+      "MONTH" => Ok(Month),
+      "DAY" => Ok(Day),
+      "WEEKDAY" => Ok(Weekday),
+      "EOMONTH" => Ok(EoMonth),
+      "DATEDIF" => Ok(DateDif),
+      "RAND" => Ok(Rand),
+      "RANDBETWEEN" => Ok(RandBetween),
+      "VLOOKUP" => Ok(VLookup),
+      "HLOOKUP" => Ok(HLookup),
+      "XLOOKUP" => Ok(XLookup),
+      "MOD" => Ok(Mod),
+      "GCD" => Ok(Gcd),
+      "LCM" => Ok(Lcm),
+      "COUNTA" => Ok(CountA),
+      "COUNTBLANK" => Ok(CountBlank),
+      "TEXT" => Ok(Text),
+      "REGEXMATCH" => Ok(RegexMatch),
+      "REGEXEXTRACT" => Ok(RegexExtract),
+      "FIND" => Ok(Find),
+      "SUBSTITUTE" => Ok(Substitute),
+      "SPLIT" => Ok(Split),
+      "SEQUENCE" => Ok(Sequence),
+      "IFS" => Ok(Ifs),
+      "SWITCH" => Ok(Switch),
+      "XOR" => Ok(Xor),
+      "AND" => Ok(And),
+      "OR" => Ok(Or),
+      "NOT" => Ok(Not),
+      "CONVERT" => Ok(Convert),
+      "PI" => Ok(Pi),
+      "E" => Ok(E),
+      _ => registry::get(value)
+        .map(|func| Custom(func.name))
+        .ok_or_else(|| format!("`{value}` is not a known function.")),
+    }
+  }
+}
+
+/// Returns a uniformly distributed random number between 0 (inclusive) and 1 (exclusive).
+#[cfg(target_arch = "wasm32")]
+fn random() -> f64 {
+  js_sys::Math::random()
+}
+
+/// Returns a uniformly distributed random number between 0 (inclusive) and 1 (exclusive),
+/// using a xorshift generator seeded with the current time outside of the browser.
+#[cfg(not(target_arch = "wasm32"))]
+fn random() -> f64 {
+  use std::cell::Cell;
+  use std::time::{SystemTime, UNIX_EPOCH};
+
+  thread_local! {
+    static STATE: Cell<u64> = Cell::new(
+      SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64)
+        | 1,
+    );
+  }
+
+  STATE.with(|state| {
+    let mut x = state.get();
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    state.set(x);
+
+    // the top 53 bits fit into the mantissa exactly
+    (x >> 11) as f64 / (1u64 << 53) as f64
+  })
+}
+
+/// Collects numeric values of aggregate function arguments.
+///
+/// Like in other spreadsheets, empty and text cells are skipped when referenced
+/// directly or via a range, while all other arguments are evaluated as usual.
+fn numbers(args: &[Expr], ctx: &EvalContext) -> Result<Vec<f64>, Box<dyn Error>> {
+  let mut nums = vec![];
+
+  for arg in args {
+    let cell_ids = match arg {
+      Expr::CellRef(cell_id) => CellId::range(*cell_id, *cell_id),
+      Expr::Range { from, to } => CellId::range(*from, *to),
+      _ => {
+        match arg.eval(ctx)? {
+          // like ranges, arrays skip text
+          Value::Array(rows) => {
+            nums.extend(rows.iter().flatten().filter_map(|value| match value {
+              Value::Num(_) | Value::Date(_) | Value::Bool(_) => value.as_num().ok(),
+              _ => None,
+            }))
+          }
+          value => nums.push(value.as_num()?),
+        }
+        continue;
+      }
+    };
+
+    // checked checkboxes count as `1`
+    for cell_id in cell_ids {
+      if let Some(value @ (Value::Num(_) | Value::Date(_) | Value::Bool(_))) =
+        ctx.values.get(&cell_id)
+      {
+        nums.push(value.as_num()?);
+      }
+    }
+  }
+
+  Ok(nums)
+}
+
+/// Rounds `x` to `digits` after the decimal point with the `rounding` function.
+///
+/// The scaled value is snapped to an integer if it's within a floating point error of it,
+/// so that, e.g., rounding `3.2` up to 1 digit gives `3.2` and not `3.3`.
+fn round(x: f64, digits: f64, rounding: impl Fn(f64) -> f64) -> f64 {
+  let multiplier = 10f64.powi(digits.trunc() as i32);
+  let scaled = x * multiplier;
+
+  if (scaled - scaled.round()).abs() <= 1e-9 * scaled.abs().max(1.0) {
+    scaled.round() / multiplier
+  } else {
+    rounding(scaled) / multiplier
+  }
+}
+
+/// Collects values of the arguments, expanding ranges and arrays, and skipping empty cells.
+fn values(args: &[Expr], ctx: &EvalContext) -> Result<Vec<Value>, Box<dyn Error>> {
+  let mut values = vec![];
+
+  for arg in args {
+    match arg {
+      Expr::CellRef(cell_id) => values.extend(ctx.values.get(cell_id).cloned()),
+      Expr::Range { from, to } => values
+        .extend(CellId::range(*from, *to).filter_map(|cell_id| ctx.values.get(&cell_id).cloned())),
+      _ => match arg.eval(ctx)? {
+        Value::Array(rows) => values.extend(rows.into_iter().flatten()),
+        value => values.push(value),
+      },
+    }
+  }
+
+  Ok(values)
+}
+
+const REGEX_CACHE_CAPACITY: usize = 256;
+const MAX_ARRAY_SIZE: usize = 100_000;
+
+lazy_static! {
+  // compiled patterns of regex functions, so that they aren't recompiled on every recompute
+  static ref REGEX_CACHE: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+/// Compiles the pattern, or returns the cached compiled version of it.
+pub(crate) fn regex(pattern: &str) -> Result<Regex, Box<dyn Error>> {
+  let mut cache = REGEX_CACHE.lock().unwrap_or_else(|err| err.into_inner());
+
+  if let Some(re) = cache.get(pattern) {
+    return Ok(re.clone());
+  }
+
+  let re = Regex::new(pattern).map_err(|err| format!("invalid regular expression: {err}"))?;
+  if cache.len() >= REGEX_CACHE_CAPACITY {
+    cache.clear();
+  }
+  cache.insert(pattern.to_string(), re.clone());
+  Ok(re)
+}
+
+/// Evaluates an argument as text; numbers are converted to text.
+fn text(arg: &Expr, ctx: &EvalContext) -> Result<String, Box<dyn Error>> {
+  Ok(arg.eval(ctx)?.to_string())
+}
+
+fn finite(func: &Func, num: f64) -> Result<Value, Box<dyn Error>> {
+  if num.is_finite() {
+    Ok(Value::Num(num))
+  } else {
+    Err(format!("{} is not defined for the given arguments", func.name()).into())
+  }
+}
+
+/// Net present value of the cash flows, the first of which happens now.
+fn net_present_value(rate: f64, values: &[f64]) -> f64 {
+  values
+    .iter()
+    .enumerate()
+    .map(|(period, value)| value / (1.0 + rate).powi(period as i32))
+    .sum()
+}
+
+/// Finds a rate making the net present value of the cash flows zero
+/// with Newton's method, starting with the `guess`.
+fn internal_rate_of_return(values: &[f64], guess: f64) -> Option<f64> {
+  const MAX_ITERATIONS: usize = 100;
+  const PRECISION: f64 = 1e-10;
+
+  let has_inflows = values.iter().any(|value| *value > 0.0);
+  let has_outflows = values.iter().any(|value| *value < 0.0);
+  if !has_inflows || !has_outflows {
+    return None;
+  }
+
+  let mut rate = guess;
+  for _ in 0..MAX_ITERATIONS {
+    let npv = net_present_value(rate, values);
+    let derivative: f64 = values
+      .iter()
+      .enumerate()
+      .map(|(period, value)| -(period as f64) * value / (1.0 + rate).powi(period as i32 + 1))
+      .sum();
+
+    if derivative == 0.0 || !derivative.is_finite() {
+      return None;
+    }
+
+    let next_rate = rate - npv / derivative;
+    // the rate cannot go below -100%
+    let next_rate = if next_rate <= -1.0 {
+      (rate - 1.0) / 2.0
+    } else {
+      next_rate
+    };
+
+    if (next_rate - rate).abs() < PRECISION {
+      return Some(next_rate);
+    }
+    rate = next_rate;
+  }
+
+  None
+}
+
+/// The greatest common divisor of non-negative integers, by Euclid's algorithm.
+fn gcd(mut a: f64, mut b: f64) -> f64 {
+  while b != 0.0 {
+    (a, b) = (b, a % b);
+  }
+  a
+}
+
+fn sorted(mut nums: Vec<f64>) -> Vec<f64> {
+  nums.sort_by(f64::total_cmp);
+  nums
+}
+
+/// Returns the `k`-th percentile of the sorted numbers, linearly interpolating
+/// between the closest ranks like `PERCENTILE.INC` in other spreadsheets.
+fn percentile(func: &Func, sorted: &[f64], k: f64) -> Result<Value, Box<dyn Error>> {
+  if sorted.is_empty() {
+    return Err(format!("{} got no numeric values", func.name()).into());
+  }
+  if !(0.0..=1.0).contains(&k) {
+    return Err(format!("{} expects k between 0 and 1, got {k}", func.name()).into());
+  }
+
+  let rank = k * (sorted.len() - 1) as f64;
+  let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+  Ok(Value::Num(
+    sorted[lower] + (sorted[upper] - sorted[lower]) * rank.fract(),
+  ))
+}
+
+fn average(func: &Func, nums: &[f64]) -> Result<Value, Box<dyn Error>> {
+  if nums.is_empty() {
+    Err(format!("{} got no numeric values", func.name()).into())
+  } else {
+    Ok(Value::Num(nums.iter().sum::<f64>() / nums.len() as f64))
+  }
+}
+
+/// How lookup functions match the key against the searched values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LookupMatch {
+  Exact,
+  /// The exact match, or the largest value less than the key.
+  NextSmaller,
+  /// The exact match, or the smallest value greater than the key.
+  NextLarger,
+}
+
+/// Returns the position of the cell among `cell_ids` matching the `key`; empty cells
+/// and values of a different type (text vs numbers) never match. If several cells match
+/// equally well, the first one is returned, searching from the end if `reverse` is true.
+fn lookup(
+  key: &Value,
+  cell_ids: &[CellId],
+  ctx: &EvalContext,
+  match_mode: LookupMatch,
+  reverse: bool,
+) -> Option<usize> {
+  let positions: Box<dyn Iterator<Item = usize>> = if reverse {
+    Box::new((0..cell_ids.len()).rev())
+  } else {
+    Box::new(0..cell_ids.len())
+  };
+
+  let mut best: Option<(usize, &Value)> = None;
+  for idx in positions {
+    let value = match ctx.values.get(&cell_ids[idx]) {
+      Some(value) if value.is_text() == key.is_text() => value,
+      _ => continue,
+    };
+
+    let is_better = |expected: Ordering| {
+      best.map_or(true, |(_, best_value)| {
+        value.compare(best_value) == Some(expected)
+      })
+    };
+    match (value.compare(key), match_mode) {
+      (Some(Ordering::Equal), _) => return Some(idx),
+      (Some(Ordering::Less), LookupMatch::NextSmaller) if is_better(Ordering::Greater) => {
+        best = Some((idx, value))
+      }
+      (Some(Ordering::Greater), LookupMatch::NextLarger) if is_better(Ordering::Less) => {
+        best = Some((idx, value))
+      }
+      _ => (),
+    }
+  }
+
+  best.map(|(idx, _)| idx)
+}
+
+/// Excel-style criteria, such as `">5"`, `"<>Foo"`, or `"Foo"`, used by `COUNTIF`-like functions.
+#[derive(Debug, PartialEq)]
+struct Criteria {
+  op: Op,
+  value: Value,
+}
+
+impl From<Value> for Criteria {
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Num(_)
+      | Value::Date(_)
+      | Value::Bool(_)
+      | Value::Array(_)
+      | Value::Big(_)
+      | Value::Empty => Criteria {
+        op: Op::Equal,
+        value,
+      },
+      Value::Str(s) => {
+        let prefixes = [
+          ("<=", Op::LessOrEq),
+          (">=", Op::GreaterOrEq),
+          ("<>", Op::NotEqual),
+          ("<", Op::Less),
+          (">", Op::Greater),
+          ("=", Op::Equal),
+        ];
+        let (op, operand) = prefixes
+          .into_iter()
+          .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|operand| (op, operand)))
+          .unwrap_or((Op::Equal, &s));
+
+        let value = match operand.trim().parse::<f64>() {
+          Ok(num) => Value::Num(num),
+          Err(_) if operand.trim().eq_ignore_ascii_case("true") => Value::Bool(true),
+          Err(_) if operand.trim().eq_ignore_ascii_case("false") => Value::Bool(false),
+          Err(_) => Value::Str(operand.to_string()),
+        };
+
+        Criteria { op, value }
+      }
+    }
+  }
+}
+
+impl Criteria {
+  fn matches(&self, value: &Value) -> bool {
+    if value.is_text() == self.value.is_text() {
+      self.op.compare(value, &self.value)
+    } else {
+      // a number never matches a text criteria and vice versa, unless the criteria excludes a value
+      self.op == Op::NotEqual
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::parser::parse;
+
+  fn eval_with(input: &str, ctx: &[(&str, Value)]) -> Result<Value, Box<dyn Error>> {
+    let ctx = ctx
+      .iter()
+      .map(|(cell_id, value)| (CellId::try_from(*cell_id).unwrap(), value.clone()))
+      .collect();
+
+    parse(input).unwrap().eval(&EvalContext::new(&ctx))
+  }
+
+  #[test]
+  fn aggregates_test() {
+    // A3 is empty, B1 contains text
+    let ctx = vec![
+      ("A1", Value::Num(4.0)),
+      ("A2", Value::Num(-2.0)),
+      ("A4", Value::Num(7.0)),
+      ("B1", Value::Str("apples".to_string())),
+      ("B2", Value::Num(3.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx);
+
+    assert_eq!(eval("= SUM(A1:B4)").unwrap(), Value::Num(12.0));
+    assert_eq!(eval("= sum(A1, A3, B1, 10)").unwrap(), Value::Num(14.0));
+    assert_eq!(eval("= SUM(A1:A4) * 2 + 1").unwrap(), Value::Num(19.0));
+    assert_eq!(eval("= AVERAGE(A1:A4)").unwrap(), Value::Num(3.0));
+    assert_eq!(eval("= MIN(A1:B4, 5)").unwrap(), Value::Num(-2.0));
+    assert_eq!(eval("= MAX(A4:A1, -B2)").unwrap(), Value::Num(7.0));
+    assert_eq!(eval("= COUNT(A1:B4)").unwrap(), Value::Num(4.0));
+    assert_eq!(eval("= COUNT(B1, A3)").unwrap(), Value::Num(0.0));
+    assert_eq!(eval("= MAX(B1)").unwrap(), Value::Num(0.0));
+    assert_eq!(
+      eval("= SUM(COUNT(A1:A2), MAX(A1, B2))").unwrap(),
+      Value::Num(6.0)
+    );
+
+    assert!(eval("= AVERAGE(A3, B1)").is_err());
+    assert!(eval("= A1:A4 + B1").is_err());
+  }
+
+  #[test]
+  fn counta_and_countblank_test() {
+    // A2 is empty, A3 was cleared, A6 contains empty text
+    let ctx = vec![
+      ("A1", Value::Num(0.0)),
+      ("A3", Value::Empty),
+      ("A6", Value::Str(String::new())),
+      ("A4", Value::Str("apples".to_string())),
+      ("A5", Value::Date(45292.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval("= COUNTA(A1:A5)"), Value::Num(3.0));
+    assert_eq!(eval("= COUNTA(A1, A2, 5, \"text\")"), Value::Num(3.0));
+    assert_eq!(eval("= COUNTBLANK(A1:A5)"), Value::Num(2.0));
+    assert_eq!(eval("= COUNTBLANK(A5:A1)"), Value::Num(2.0));
+    assert_eq!(eval("= COUNTBLANK(A1)"), Value::Num(0.0));
+    assert_eq!(eval("= COUNTBLANK(B1:B10)"), Value::Num(10.0));
+    assert_eq!(eval("= COUNTBLANK(A1:A6)"), Value::Num(3.0));
+
+    assert_eq!(eval("= ISBLANK(A2)"), Value::Num(1.0));
+    assert_eq!(eval("= ISBLANK(A3)"), Value::Num(1.0));
+    assert_eq!(eval("= ISBLANK(A1)"), Value::Num(0.0));
+    assert_eq!(eval("= ISBLANK(A6)"), Value::Num(0.0));
+    // aggregates skip empty cells, while operators treat them as zeros
+    assert_eq!(eval("= AVERAGE(A1:A3, 3)"), Value::Num(1.5));
+    assert_eq!(eval("= SUM(A1:A3 + 1)"), Value::Num(3.0));
+    assert_eq!(eval("= A3 + 1"), Value::Num(1.0));
+    assert_eq!(eval("= A3 = 0"), Value::Num(1.0));
+    assert_eq!(eval("= A3 = A6"), Value::Num(1.0));
+
+    assert!(eval_with("= COUNTBLANK(A1:A5, B1)", &ctx).is_err());
+    assert!(eval_with("= COUNTBLANK(5)", &ctx).is_err());
+  }
+
+  #[test]
+  fn if_test() {
+    let ctx = vec![("A1", Value::Num(4.0)), ("A2", Value::Num(0.0))];
+    let eval = |input: &str| eval_with(input, &ctx);
+
+    assert_eq!(eval("= IF(A1 > 3, 10, 20)").unwrap(), Value::Num(10.0));
+    assert_eq!(eval("= IF(A1 <= 3, 10, 20)").unwrap(), Value::Num(20.0));
+    assert_eq!(
+      eval("= IF(A1, 1, 2) + IF(A2, 1, 2)").unwrap(),
+      Value::Num(3.0)
+    );
+    assert_eq!(eval("= IF(A1 <> 4, 1)").unwrap(), Value::Num(0.0));
+    assert_eq!(eval("= IF(A1 = 2 * 2, A1 - 1)").unwrap(), Value::Num(3.0));
+
+    // the branch that is not taken is not evaluated, so the missing reference is not an error
+    assert_eq!(eval("= IF(A2 >= 0, 1, B99)").unwrap(), Value::Num(1.0));
+    assert_eq!(eval("= IF(A2 < 0, B99, 2)").unwrap(), Value::Num(2.0));
+    assert!(eval("= IF(A2 < 0, 1, B99)").is_err());
+
+    assert!(eval("= IF(A1)").is_err());
+    assert!(eval("= IF(A1, 1, 2, 3)").is_err());
+  }
+
+  #[test]
+  fn ifs_switch_xor_test() {
+    let ctx = vec![
+      ("A1", Value::Num(75.0)),
+      ("A2", Value::Str("b".to_string())),
+      ("A3", Value::Num(0.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let str = |s: &str| Value::Str(s.to_string());
+
+    assert_eq!(
+      eval(r#"= IFS(A1 >= 90, "A", A1 >= 70, "C", A1 >= 50, "D")"#),
+      str("C")
+    );
+    // neither the conditions after the first true one, nor other values are evaluated
+    assert_eq!(eval(r#"= IFS(1, "first", 1 / 0, "second")"#), str("first"));
+    assert_eq!(eval(r#"= IFS(0, 1 / 0, 1, "ok")"#), str("ok"));
+
+    assert_eq!(eval(r#"= SWITCH(A2, "a", 1, "B", 2, 3)"#), Value::Num(2.0));
+    assert_eq!(eval(r#"= SWITCH(A2, "x", 1, "y", 2, 3)"#), Value::Num(3.0));
+    assert_eq!(eval(r#"= SWITCH(A3, 0, "zero", 1 / 0)"#), str("zero"));
+
+    assert_eq!(eval("= XOR(1, 0)"), Value::Num(1.0));
+    assert_eq!(eval("= XOR(1, A1 > 50)"), Value::Num(0.0));
+    assert_eq!(eval("= XOR(A1:A3)"), Value::Num(1.0));
+
+    assert!(eval_with("= IFS(0, 1)", &ctx).is_err());
+    assert!(eval_with("= IFS(1, 2, 3)", &ctx).is_err());
+    assert!(eval_with(r#"= SWITCH(A2, "x", 1)"#, &ctx).is_err());
+    assert!(eval_with("= SWITCH(A2, 1)", &ctx).is_err());
+    assert!(eval_with("= XOR(A2)", &ctx).is_err());
+  }
+
+  #[test]
+  fn and_or_not_test() {
+    let ctx = vec![("A1", Value::Num(1.0)), ("A2", Value::Num(0.0))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval("= AND(1, A1, 2 > 1)"), Value::Num(1.0));
+    assert_eq!(eval("= AND(A1:A2)"), Value::Num(0.0));
+    assert_eq!(eval("= OR(A2, 0)"), Value::Num(0.0));
+    assert_eq!(eval("= OR(A1:A2)"), Value::Num(1.0));
+    assert_eq!(eval("= NOT(A2)"), Value::Num(1.0));
+    assert_eq!(eval("= NOT(AND(A1, A2))"), Value::Num(1.0));
+    // the arguments after the one deciding the result are not evaluated
+    assert_eq!(eval("= AND(A2 <> 0, 1 / A2 > 2)"), Value::Num(0.0));
+    assert_eq!(eval("= OR(A1, 1 / 0)"), Value::Num(1.0));
+
+    assert!(eval_with("= AND(A1, 1 / 0)", &ctx).is_err());
+    assert!(eval_with("= OR()", &ctx).is_err());
+    assert!(eval_with("= NOT(A1, A2)", &ctx).is_err());
+  }
+
+  #[test]
+  fn iferror_test() {
+    let ctx = vec![("A1", Value::Num(4.0)), ("A2", Value::Num(0.0))];
+    let eval = |input: &str| eval_with(input, &ctx);
+
+    assert_eq!(eval("= IFERROR(A1 / A2, -1)").unwrap(), Value::Num(-1.0));
+    assert_eq!(eval("= IFERROR(A2 / A1, -1)").unwrap(), Value::Num(0.0));
+    assert_eq!(eval("= IFERROR(B99 * 2, A1) + 1").unwrap(), Value::Num(5.0));
+    assert!(eval("= IFERROR(B99, 1 / A2)").is_err());
+
+    assert_eq!(eval("= ISERROR(A1 / A2)").unwrap(), Value::Num(1.0));
+    assert_eq!(eval("= ISERROR(A1)").unwrap(), Value::Num(0.0));
+    assert_eq!(eval("= ISERROR(B99)").unwrap(), Value::Num(1.0));
+    assert_eq!(
+      eval("= IF(ISERROR(B99), 10, B99)").unwrap(),
+      Value::Num(10.0)
+    );
+
+    assert!(eval("= IFERROR(A1)").is_err());
+    assert!(eval("= ISERROR(A1, A2)").is_err());
+  }
+
+  #[test]
+  fn conditional_aggregates_test() {
+    // A3 is empty
+    let ctx = vec![
+      ("A1", Value::Num(4.0)),
+      ("A2", Value::Num(8.0)),
+      ("A4", Value::Str("Foo".to_string())),
+      ("A5", Value::Num(5.0)),
+      ("B1", Value::Num(1.0)),
+      ("B2", Value::Num(2.0)),
+      ("B3", Value::Num(3.0)),
+      ("B4", Value::Num(4.0)),
+      ("B5", Value::Str("Bar".to_string())),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx);
+
+    assert_eq!(eval(r#"= COUNTIF(A1:A5, ">5")"#).unwrap(), Value::Num(1.0));
+    assert_eq!(eval(r#"= COUNTIF(A1:A5, ">=5")"#).unwrap(), Value::Num(2.0));
+    assert_eq!(eval(r#"= COUNTIF(A1:A5, "foo")"#).unwrap(), Value::Num(1.0));
+    assert_eq!(
+      eval(r#"= COUNTIF(A1:A5, "<>Foo")"#).unwrap(),
+      Value::Num(3.0)
+    );
+    assert_eq!(eval("= COUNTIF(A1:A5, 4)").unwrap(), Value::Num(1.0));
+    assert_eq!(eval(r#"= COUNTIF(A1:A5, "=8")"#).unwrap(), Value::Num(1.0));
+    assert_eq!(eval("= COUNTIF(A1:B5, B4)").unwrap(), Value::Num(2.0));
+
+    assert_eq!(eval(r#"= SUMIF(A1:A5, "<6")"#).unwrap(), Value::Num(9.0));
+    assert_eq!(
+      eval(r#"= SUMIF(A1:A5, "<6", B1:B5)"#).unwrap(),
+      Value::Num(1.0)
+    );
+    assert_eq!(
+      eval(r#"= SUMIF(A1:A5, "Foo", B1:B5)"#).unwrap(),
+      Value::Num(4.0)
+    );
+    assert_eq!(
+      eval(r#"= AVERAGEIF(A1:A5, ">4")"#).unwrap(),
+      Value::Num(6.5)
+    );
+    assert_eq!(
+      eval(r#"= AVERAGEIF(A1:A5, ">0", B1:B5)"#).unwrap(),
+      Value::Num(1.5)
+    );
+
+    assert!(eval(r#"= AVERAGEIF(A1:A5, ">100")"#).is_err());
+    assert!(eval(r#"= SUMIF(A1:A5, ">1", B1:B4)"#).is_err());
+    assert!(eval(r#"= COUNTIF(1, ">1")"#).is_err());
+    assert!(eval("= COUNTIF(A1:A5)").is_err());
+  }
+
+  #[test]
+  fn booleans_test() {
+    assert_eq!(parse("TRUE"), Ok(Expr::Bool(true)));
+    assert_eq!(parse(" false "), Ok(Expr::Bool(false)));
+    assert_eq!(Value::Bool(true).to_string(), "TRUE");
+
+    let ctx = vec![
+      ("A1", Value::Bool(true)),
+      ("A2", Value::Bool(false)),
+      ("A3", Value::Bool(true)),
+      ("B1", Value::Num(2.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx);
+
+    assert_eq!(eval("= SUM(A1:A3)").unwrap(), Value::Num(2.0));
+    assert_eq!(eval("= A1 + B1").unwrap(), Value::Num(3.0));
+    assert_eq!(eval("= IF(A2, 1, 2)").unwrap(), Value::Num(2.0));
+    assert_eq!(
+      eval(r#"= COUNTIF(A1:A3, "TRUE")"#).unwrap(),
+      Value::Num(2.0)
+    );
+    assert_eq!(
+      eval(r#"= COUNTIF(A1:A3, "false")"#).unwrap(),
+      Value::Num(1.0)
+    );
+  }
+
+  #[test]
+  fn text_test() {
+    let ctx = vec![
+      ("A1", Value::Str("  Hello,   World ".to_string())),
+      ("A2", Value::Num(42.5)),
+      ("B1", Value::Str("Ünïcode".to_string())),
+      ("B2", Value::Str("!".to_string())),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx);
+    let s = |s: &str| Value::Str(s.to_string());
+
+    assert_eq!(
+      eval(r#"= CONCAT("x = ", A2, B1:B3)"#).unwrap(),
+      s("x = 42.5Ünïcode!")
+    );
+    assert_eq!(
+      eval(r#"= CONCATENATE(TRIM(A1), B2)"#).unwrap(),
+      s("Hello, World!")
+    );
+    assert_eq!(eval("= CONCAT()").unwrap(), s(""));
+
+    assert_eq!(eval("= LEN(B1)").unwrap(), Value::Num(7.0));
+    assert_eq!(eval("= LEN(A2) * 2").unwrap(), Value::Num(8.0));
+    assert_eq!(eval("= UPPER(B1)").unwrap(), s("ÜNÏCODE"));
+    assert_eq!(eval("= LOWER(B1)").unwrap(), s("ünïcode"));
+
+    assert_eq!(eval("= LEFT(B1)").unwrap(), s("Ü"));
+    assert_eq!(eval("= LEFT(B1, 3)").unwrap(), s("Ünï"));
+    assert_eq!(eval("= LEFT(B1, 30)").unwrap(), s("Ünïcode"));
+    assert_eq!(eval("= RIGHT(B1, 2.9)").unwrap(), s("de"));
+    assert_eq!(eval("= RIGHT(A2)").unwrap(), s("5"));
+    assert_eq!(eval("= MID(B1, 2, 3)").unwrap(), s("nïc"));
+    assert_eq!(eval("= MID(B1, 6, 10)").unwrap(), s("de"));
+    assert_eq!(eval("= MID(B1, 10, 10)").unwrap(), s(""));
+
+    assert!(eval("= LEFT(B1, -1)").is_err());
+    assert!(eval("= MID(B1, 0, 1)").is_err());
+    assert!(eval("= MID(B1, 1)").is_err());
+    assert!(eval("= LEN(B1) + B1").is_err());
+  }
+
+  #[test]
+  fn search_and_replace_test() {
+    let ctx = vec![
+      ("A1", Value::Str("banana bread".to_string())),
+      ("A2", Value::Str("Smith, John, Jr.".to_string())),
+      ("A3", Value::Str("héllo wörld".to_string())),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let str = |s: &str| Value::Str(s.to_string());
+
+    assert_eq!(eval(r#"= FIND("an", A1)"#), Value::Num(2.0));
+    assert_eq!(eval(r#"= FIND("an", A1, 3)"#), Value::Num(4.0));
+    assert_eq!(eval(r#"= FIND("w", A3)"#), Value::Num(7.0));
+    assert_eq!(eval(r#"= FIND("", A1)"#), Value::Num(1.0));
+
+    assert_eq!(eval(r#"= SUBSTITUTE(A1, "a", "o")"#), str("bonono breod"));
+    assert_eq!(
+      eval(r#"= SUBSTITUTE(A1, "a", "o", 2)"#),
+      str("banona bread")
+    );
+    assert_eq!(
+      eval(r#"= SUBSTITUTE(A1, "a", "o", 9)"#),
+      str("banana bread")
+    );
+    assert_eq!(eval(r#"= SUBSTITUTE(A3, "ö", "o")"#), str("héllo world"));
+
+    assert_eq!(eval(r#"= SPLIT(A2, ", ")"#), str("Smith"));
+    assert_eq!(eval(r#"= SPLIT(A2, ", ", 3)"#), str("Jr."));
+    assert_eq!(eval(r#"= SPLIT("a,,b", ",", 2)"#), str("b"));
+
+    assert!(eval_with(r#"= FIND("An", A1)"#, &ctx).is_err());
+    assert!(eval_with(r#"= FIND("a", A1, 0)"#, &ctx).is_err());
+    assert!(eval_with(r#"= SUBSTITUTE(A1, "a", "o", 0)"#, &ctx).is_err());
+    assert!(eval_with(r#"= SPLIT(A2, ", ", 4)"#, &ctx).is_err());
+    assert!(eval_with(r#"= SPLIT(A2, "")"#, &ctx).is_err());
+  }
+
+  #[test]
+  fn regex_test() {
+    let ctx = vec![
+      ("A1", Value::Str("Order #1234 shipped".to_string())),
+      ("A2", Value::Num(2024.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval(r##"= REGEXMATCH(A1, "#\d+")"##), Value::Num(1.0));
+    assert_eq!(eval(r#"= REGEXMATCH(A1, "^shipped")"#), Value::Num(0.0));
+    assert_eq!(eval(r#"= REGEXMATCH(A2, "^20")"#), Value::Num(1.0));
+    assert_eq!(
+      eval(r##"= REGEXEXTRACT(A1, "#(\d+)")"##),
+      Value::Str("1234".to_string())
+    );
+    assert_eq!(
+      eval(r#"= REGEXEXTRACT(A1, "[a-z]+$")"#),
+      Value::Str("shipped".to_string())
+    );
+    assert_eq!(
+      eval(r#"= REGEXEXTRACT(A1, "(?i)ORDER")"#),
+      Value::Str("Order".to_string())
+    );
+    assert!(REGEX_CACHE.lock().unwrap().contains_key(r"#(\d+)"));
+
+    assert!(eval_with(r#"= REGEXEXTRACT(A1, "\d{5}")"#, &ctx).is_err());
+    assert!(eval_with(r#"= REGEXMATCH(A1, "(")"#, &ctx).is_err());
+    assert!(eval_with(r#"= REGEXMATCH(A1)"#, &ctx).is_err());
+  }
+
+  #[test]
+  fn text_format_test() {
+    let ctx = vec![
+      ("A1", Value::Num(1234.5)),
+      ("A2", Value::Date(date::serial_from_ymd(2024, 3, 1))),
+      ("A3", Value::Str("n/a".to_string())),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let str = |s: &str| Value::Str(s.to_string());
+
+    assert_eq!(eval(r##"= TEXT(A1, "#,##0.00")"##), str("1,234.50"));
+    assert_eq!(eval(r#"= TEXT(0.125, "0.0%")"#), str("12.5%"));
+    assert_eq!(eval(r#"= TEXT(A2, "mmm d, yyyy")"#), str("Mar 1, 2024"));
+    assert_eq!(eval(r#"= TEXT(A3, "0.00")"#), str("n/a"));
+    assert_eq!(
+      eval(r#"= CONCAT("Total: ", TEXT(A1 * 2, "$0"), " by ", TEXT(A2, "dd/mm"))"#),
+      str("Total: $2469 by 01/03")
+    );
+
+    assert!(eval_with("= TEXT(A1)", &ctx).is_err());
+  }
+
+  #[test]
+  fn math_test() {
+    let ctx = vec![("A1", Value::Num(-2.5)), ("A2", Value::Num(1234.5678))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap().as_num().unwrap();
+    let assert_close = |input: &str, expected: f64| {
+      let actual = eval(input);
+      assert!(
+        (actual - expected).abs() < 1e-9,
+        "{input} = {actual}, expected {expected}"
+      );
+    };
+
+    assert_eq!(eval("= ABS(A1)"), 2.5);
+    assert_eq!(eval("= SQRT(16)"), 4.0);
+    assert_close("= EXP(1)", std::f64::consts::E);
+    assert_close("= LN(EXP(2))", 2.0);
+    assert_close("= LOG(1000)", 3.0);
+    assert_close("= LOG(8, 2)", 3.0);
+    assert_close("= SIN(0) + COS(0)", 1.0);
+    assert_close("= TAN(1)", 1f64.tan());
+
+    assert_eq!(eval("= ROUND(A1)"), -3.0);
+    assert_eq!(eval("= ROUND(A2, 2)"), 1234.57);
+    assert_eq!(eval("= ROUND(A2, -2)"), 1200.0);
+    assert_eq!(eval("= FLOOR(A2)"), 1234.0);
+    assert_eq!(eval("= FLOOR(A2, 5)"), 1230.0);
+    assert_eq!(eval("= FLOOR(A1)"), -3.0);
+    assert_eq!(eval("= CEILING(A2, 0.5)"), 1235.0);
+    assert_eq!(eval("= CEILING(A1, 2)"), -2.0);
+    assert_eq!(eval("= CEILING(A1, -2)"), -4.0);
+    assert_eq!(eval("= CEILING(A1, 0)"), 0.0);
+
+    assert!(eval_with("= SQRT(A1)", &ctx).is_err());
+    assert!(eval_with("= LN(0)", &ctx).is_err());
+    assert!(eval_with("= FLOOR(A2, -1)", &ctx).is_err());
+    assert!(eval_with("= ABS(1, 2)", &ctx).is_err());
+    assert!(eval_with("= ABS()", &ctx).is_err());
+  }
+
+  #[test]
+  fn integer_math_test() {
+    let ctx = vec![
+      ("A1", Value::Num(12.0)),
+      ("A2", Value::Num(18.0)),
+      ("A3", Value::Num(8.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval("= MOD(10, 3)"), Value::Num(1.0));
+    assert_eq!(eval("= MOD(-10, 3)"), Value::Num(2.0));
+    assert_eq!(eval("= MOD(10, -3)"), Value::Num(-2.0));
+    assert_eq!(eval("= MOD(5.5, 2)"), Value::Num(1.5));
+
+    assert_eq!(eval("= GCD(A1:A3)"), Value::Num(2.0));
+    assert_eq!(eval("= GCD(A1, A2)"), Value::Num(6.0));
+    assert_eq!(eval("= GCD(12.9, 18.2)"), Value::Num(6.0));
+    assert_eq!(eval("= GCD(0, 5)"), Value::Num(5.0));
+    assert_eq!(eval("= LCM(A1:A3)"), Value::Num(72.0));
+    assert_eq!(eval("= LCM(4, 6.7)"), Value::Num(12.0));
+    assert_eq!(eval("= LCM(4, 0)"), Value::Num(0.0));
+
+    assert!(eval_with("= MOD(1, 0)", &ctx).is_err());
+    assert!(eval_with("= MOD(1)", &ctx).is_err());
+    assert!(eval_with("= GCD(-4, 6)", &ctx).is_err());
+    assert!(eval_with("= LCM()", &ctx).is_err());
+  }
+
+  #[test]
+  fn convert_test() {
+    let ctx = vec![
+      ("A1", Value::Num(10.0)),
+      ("A2", Value::Str("km".to_string())),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval(r#"= CONVERT(A1, A2, "m")"#), Value::Num(10000.0));
+    assert_eq!(eval(r#"= CONVERT(A1, "C", "K")"#), Value::Num(283.15));
+
+    assert!(eval_with(r#"= CONVERT(A1, "km", "kg")"#, &ctx).is_err());
+    assert!(eval_with(r#"= CONVERT(A2, "km", "m")"#, &ctx).is_err());
+    assert!(eval_with(r#"= CONVERT(A1, "km")"#, &ctx).is_err());
+  }
+
+  #[test]
+  fn constants_test() {
+    let ctx = vec![("A1", Value::Num(2.0))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+
+    assert_eq!(eval("= PI()"), Value::Num(std::f64::consts::PI));
+    assert_eq!(eval("= e()"), Value::Num(std::f64::consts::E));
+    assert_eq!(
+      eval("= PI * A1 ^ 2"),
+      Value::Num(std::f64::consts::PI * 4.0)
+    );
+    assert_eq!(eval("= ROUND(SIN(PI / 2), 5)"), Value::Num(1.0));
+    assert_eq!(eval("= LN(E)"), Value::Num(1.0));
+    assert_eq!(eval("= -PI"), Value::Num(-std::f64::consts::PI));
+
+    assert!(eval_with("= PI(1)", &ctx).is_err());
+    assert!(parse("= SUM").is_err());
+    assert!(parse("= PI A1").is_err());
+  }
+
+  #[test]
+  fn rounding_test() {
+    let ctx = vec![("A1", Value::Num(-2.5)), ("A2", Value::Num(1234.5678))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap().as_num().unwrap();
+
+    assert_eq!(eval("= ROUNDUP(3.2, 1)"), 3.2);
+    assert_eq!(eval("= ROUNDUP(3.21, 1)"), 3.3);
+    assert_eq!(eval("= ROUNDUP(A1)"), -3.0);
+    assert_eq!(eval("= ROUNDUP(A2, -2)"), 1300.0);
+    assert_eq!(eval("= ROUNDDOWN(A2, 2)"), 1234.56);
+    assert_eq!(eval("= ROUNDDOWN(A1)"), -2.0);
+    assert_eq!(eval("= ROUNDDOWN(A2, -3)"), 1000.0);
+    assert_eq!(eval("= TRUNC(A2)"), 1234.0);
+    assert_eq!(eval("= TRUNC(-A2, 1)"), -1234.5);
+
+    assert_eq!(eval("= MROUND(10, 3)"), 9.0);
+    assert_eq!(eval("= MROUND(A1, -2)"), -2.0);
+    assert_eq!(eval("= MROUND(-3, -2)"), -4.0);
+    assert_eq!(eval("= MROUND(1.3, 0.2)"), 1.4000000000000001);
+    assert_eq!(eval("= MROUND(A2, 0)"), 0.0);
+    assert_eq!(eval("= MROUND(0, -5)"), 0.0);
+
+    assert!(eval_with("= MROUND(A1, 2)", &ctx).is_err());
+    assert!(eval_with("= MROUND(A1)", &ctx).is_err());
+  }
+
+  #[test]
+  fn statistics_test() {
+    let ctx = vec![
+      ("A1", Value::Num(2.0)),
+      ("A2", Value::Num(4.0)),
+      ("A3", Value::Num(4.0)),
+      ("A4", Value::Num(4.0)),
+      ("A5", Value::Num(5.0)),
+      ("A6", Value::Num(5.0)),
+      ("A7", Value::Num(7.0)),
+      ("A8", Value::Num(9.0)),
+      ("B1", Value::Str("n/a".to_string())),
+      ("B2", Value::Num(3.0)),
+      ("B3", Value::Num(1.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap().as_num().unwrap();
+
+    assert_eq!(eval("= MEDIAN(A1:A8)"), 4.5);
+    assert_eq!(eval("= MEDIAN(A1:A7)"), 4.0);
+    assert_eq!(eval("= MEDIAN(B1:B3, 2)"), 2.0);
+    assert_eq!(eval("= MODE(A1:A8)"), 4.0);
+    assert_eq!(eval("= MODE(A5:A8, A2:A3)"), 5.0);
+
+    assert_eq!(eval("= STDEVP(A1:A8)"), 2.0);
+    assert_eq!(eval("= VARP(A1:A8)"), 4.0);
+    assert_eq!(eval("= VAR(A1:A8)"), 32.0 / 7.0);
+    assert_eq!(eval("= STDEV(A1:A8)"), (32.0f64 / 7.0).sqrt());
+    assert_eq!(eval("= STDEVP(B2)"), 0.0);
+
+    assert_eq!(eval("= PERCENTILE(A1:A8, 0)"), 2.0);
+    assert_eq!(eval("= PERCENTILE(A1:A8, 1)"), 9.0);
+    assert_eq!(eval("= PERCENTILE(A1:A8, 0.5)"), 4.5);
+    assert_eq!(eval("= PERCENTILE(A1:A8, 0.25)"), 4.0);
+    assert_eq!(eval("= PERCENTILE(A6:A8, 0.75)"), 8.0);
+
+    assert!(eval_with("= MODE(A7:A8)", &ctx).is_err());
+    assert!(eval_with("= STDEV(B2)", &ctx).is_err());
+    assert!(eval_with("= VARP(B1)", &ctx).is_err());
+    assert!(eval_with("= PERCENTILE(A1:A8, 1.5)", &ctx).is_err());
+    assert!(eval_with("= PERCENTILE(B1, 0.5)", &ctx).is_err());
+    assert!(eval_with("= MEDIAN()", &ctx).is_err());
+  }
+
+  #[test]
+  fn financial_test() {
+    let ctx = vec![
+      ("A1", Value::Num(-10000.0)),
+      ("A2", Value::Num(3000.0)),
+      ("A3", Value::Num(4200.0)),
+      ("A4", Value::Num(6800.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap().as_num().unwrap();
+    let assert_close = |input: &str, expected: f64| {
+      let actual = eval(input);
+      assert!(
+        (actual - expected).abs() < 1e-6,
+        "{input} = {actual}, expected {expected}"
+      );
+    };
+
+    // values are checked against other spreadsheets
+    assert_close("= PMT(0.08 / 12, 10, 10000)", -1037.0320893591);
+    assert_close("= PMT(0.08 / 12, 10, 10000, 0, 1)", -1030.1643271779);
+    assert_close("= PMT(0, 10, 10000)", -1000.0);
+    assert_close("= FV(0.06 / 12, 10, -200, -500, 1)", 2581.4033740601);
+    assert_close("= FV(0, 10, -200)", 2000.0);
+    assert_close("= PV(0.08 / 12, 12 * 20, 500)", -59777.1458511878);
+    assert_close("= PV(0, 10, 100, 50)", -1050.0);
+    assert_close("= NPV(0.1, A1, A2:A4)", 1188.4434123352);
+    assert_close("= NPV(0.1, A1:A4) * 1.1 + 0", 1307.2877535687);
+    assert_close("= IRR(A1:A4)", 0.1634056006);
+    assert_close("= IRR(A1:A4, 0.5)", 0.1634056006);
+    assert_close("= NPV(IRR(A1:A4), A2:A4) + A1", 0.0);
+
+    assert!(eval_with("= PMT(0.1, 10)", &ctx).is_err());
+    assert!(eval_with("= PMT(0.1, 0, 100)", &ctx).is_err());
+    assert!(eval_with("= NPV(0.1)", &ctx).is_err());
+    assert!(eval_with("= IRR(A2:A4)", &ctx).is_err());
+  }
+
+  #[test]
+  fn dates_test() {
+    let ctx = vec![
+      ("A1", Value::Date(date::serial_from_ymd(2024, 2, 27))),
+      ("A2", Value::Num(3.0)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let ymd = |year, month, day| Value::Date(date::serial_from_ymd(year, month, day));
+
+    assert_eq!(eval("= DATE(2024, 3, 1)"), ymd(2024, 3, 1));
+    assert_eq!(eval("= DATE(2024, 14, 1)"), ymd(2025, 2, 1));
+    assert_eq!(eval("= A1 + A2"), ymd(2024, 3, 1));
+    assert_eq!(eval("= 2 + A1"), ymd(2024, 2, 29));
+    assert_eq!(eval("= A1 - 27"), ymd(2024, 1, 31));
+    assert_eq!(eval("= DATE(2024, 3, 1) - A1"), Value::Num(3.0));
+    assert_eq!(eval("= A1 > DATE(2024, 1, 1)"), Value::Num(1.0));
+    assert_eq!(eval("= MAX(A1, A1 + 1) - A1"), Value::Num(1.0));
+    assert_eq!(
+      eval(r#"= CONCAT("Due: ", A1 + 7)"#),
+      Value::Str("Due: 2024-03-05".to_string())
+    );
+
+    let today = eval("= TODAY()").as_num().unwrap();
+    let now = eval("= NOW()").as_num().unwrap();
+    assert_eq!(today, today.floor());
+    assert!(now >= today && now < today + 1.0);
+
+    assert!(eval_with("= TODAY(1)", &ctx).is_err());
+    assert!(eval_with("= DATE(2024, 1)", &ctx).is_err());
+  }
+
+  #[test]
+  fn lookup_test() {
+    let ctx = vec![
+      ("A1", Value::Num(10.0)),
+      ("A2", Value::Num(20.0)),
+      ("A3", Value::Num(30.0)),
+      ("A4", Value::Num(20.0)),
+      ("B1", Value::Str("ten".to_string())),
+      ("B2", Value::Str("twenty".to_string())),
+      ("B3", Value::Str("thirty".to_string())),
+      ("B4", Value::Str("twenty again".to_string())),
+      ("C1", Value::Str("Apples".to_string())),
+      ("D1", Value::Str("Pears".to_string())),
+      ("C2", Value::Num(1.5)),
+      ("D2", Value::Num(2.5)),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let str = |s: &str| Value::Str(s.to_string());
+
+    assert_eq!(eval("= VLOOKUP(20, A1:B4, 2, 0)"), str("twenty"));
+    assert_eq!(eval("= VLOOKUP(25, A1:B3, 2)"), str("twenty"));
+    assert_eq!(eval("= VLOOKUP(99, A1:B3, 1, 1)"), Value::Num(30.0));
+    assert_eq!(eval("= VLOOKUP(30, B3:A1, 2, 0)"), str("thirty"));
+    assert_eq!(eval(r#"= HLOOKUP("pears", C1:D2, 2, 0)"#), Value::Num(2.5));
+    assert_eq!(eval(r#"= HLOOKUP("Oranges", C1:D2, 2)"#), Value::Num(1.5));
+
+    assert_eq!(eval("= XLOOKUP(20, A1:A4, B1:B4)"), str("twenty"));
+    assert_eq!(
+      eval("= XLOOKUP(20, A1:A4, B1:B4, 0, 0, -1)"),
+      str("twenty again")
+    );
+    assert_eq!(eval(r#"= XLOOKUP(25, A1:A4, B1:B4, "none")"#), str("none"));
+    assert_eq!(
+      eval(r#"= XLOOKUP(25, A1:A4, B1:B4, "none", -1)"#),
+      str("twenty")
+    );
+    assert_eq!(
+      eval(r#"= XLOOKUP(25, A1:A4, B1:B4, "none", 1)"#),
+      str("thirty")
+    );
+    assert_eq!(eval(r#"= XLOOKUP("pears", C1:D1, C2:D2)"#), Value::Num(2.5));
+    assert_eq!(eval("= XLOOKUP(20, A1:A4, B1:B4, 1 / 0)"), str("twenty"));
+
+    assert!(eval_with("= VLOOKUP(5, A1:B3, 2)", &ctx).is_err());
+    assert!(eval_with("= VLOOKUP(25, A1:B3, 2, 0)", &ctx).is_err());
+    assert!(eval_with("= VLOOKUP(20, A1:B3, 3, 0)", &ctx).is_err());
+    assert!(eval_with("= XLOOKUP(25, A1:A4, B1:B4)", &ctx).is_err());
+    assert!(eval_with("= XLOOKUP(20, A1:B4, B1:B4)", &ctx).is_err());
+    assert!(eval_with("= XLOOKUP(20, A1:A4, B1:B3)", &ctx).is_err());
+    assert!(eval_with("= XLOOKUP(20, A1:A4, B1:B4, 0, 2)", &ctx).is_err());
+  }
+
+  #[test]
+  fn sequence_test() {
+    let ctx = vec![("A1", Value::Num(2.0))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let nums = |rows: &[&[f64]]| {
+      Value::Array(
+        rows
+          .iter()
+          .map(|row| row.iter().map(|num| Value::Num(*num)).collect())
+          .collect(),
+      )
+    };
+
+    assert_eq!(eval("= SEQUENCE(3)"), nums(&[&[1.0], &[2.0], &[3.0]]));
+    assert_eq!(
+      eval("= SEQUENCE(A1, 3, 0, 5)"),
+      nums(&[&[0.0, 5.0, 10.0], &[15.0, 20.0, 25.0]])
+    );
+    assert_eq!(eval("= SEQUENCE(1, 2, 10, -1)").to_string(), "{10, 9}");
+
+    assert!(eval_with("= SEQUENCE(0)", &ctx).is_err());
+    assert!(eval_with("= SEQUENCE(1000, 1000)", &ctx).is_err());
+    assert_eq!(eval("= SEQUENCE(2) * 2 + 1"), nums(&[&[3.0], &[5.0]]));
+    assert_eq!(eval("= SUM(SEQUENCE(3, 3))"), Value::Num(45.0));
+    assert!(eval_with("= SEQUENCE(2) + SEQUENCE(3)", &ctx).is_err());
+  }
+
+  #[test]
+  fn random_test() {
+    let ctx = vec![("A1", Value::Num(2.5))];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap().as_num().unwrap();
+
+    for _ in 0..100 {
+      let rand = eval("= RAND()");
+      assert!((0.0..1.0).contains(&rand));
+
+      let rand_between = eval("= RANDBETWEEN(A1, 5)");
+      assert!([3.0, 4.0, 5.0].contains(&rand_between));
+    }
+    assert_eq!(eval("= RANDBETWEEN(7, 7)"), 7.0);
+
+    assert!(eval_with("= RAND(1)", &ctx).is_err());
+    assert!(eval_with("= RANDBETWEEN(A1, 2.9)", &ctx).is_err());
+    assert!(Func::Rand.is_volatile() && Func::Now.is_volatile() && !Func::Sum.is_volatile());
+  }
+
+  #[test]
+  fn date_functions_test() {
+    let ctx = vec![
+      ("A1", Value::Date(date::serial_from_ymd(2024, 1, 31))),
+      ("A2", Value::Date(date::serial_from_ymd(2025, 3, 15))),
+    ];
+    let eval = |input: &str| eval_with(input, &ctx).unwrap();
+    let ymd = |year, month, day| Value::Date(date::serial_from_ymd(year, month, day));
+
+    assert_eq!(eval("= YEAR(A1)"), Value::Num(2024.0));
+    assert_eq!(eval("= MONTH(A1)"), Value::Num(1.0));
+    assert_eq!(eval("= DAY(A1 + 0.75)"), Value::Num(31.0));
+
+    // 2024-01-31 is a Wednesday
+    assert_eq!(eval("= WEEKDAY(A1)"), Value::Num(4.0));
+    assert_eq!(eval("= WEEKDAY(A1, 2)"), Value::Num(3.0));
+    assert_eq!(eval("= WEEKDAY(A1, 3)"), Value::Num(2.0));
+    assert_eq!(eval("= WEEKDAY(A1 + 4)"), Value::Num(1.0));
+    assert_eq!(eval("= WEEKDAY(A1 + 4, 2)"), Value::Num(7.0));
+
+    assert_eq!(eval("= EOMONTH(A1, 1)"), ymd(2024, 2, 29));
+    assert_eq!(eval("= EOMONTH(A1, 0)"), ymd(2024, 1, 31));
+    assert_eq!(eval("= EOMONTH(A2, -3)"), ymd(2024, 12, 31));
+
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "Y")"#), Value::Num(1.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "m")"#), Value::Num(13.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "D")"#), Value::Num(409.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "YM")"#), Value::Num(1.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "MD")"#), Value::Num(15.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A2, "YD")"#), Value::Num(43.0));
+    assert_eq!(eval(r#"= DATEDIF(A1, A1, "D")"#), Value::Num(0.0));
+
+    assert!(eval_with(r#"= DATEDIF(A2, A1, "D")"#, &ctx).is_err());
+    assert!(eval_with(r#"= DATEDIF(A1, A2, "W")"#, &ctx).is_err());
+    assert!(eval_with("= WEEKDAY(A1, 4)", &ctx).is_err());
+    assert!(eval_with("= EOMONTH(A1)", &ctx).is_err());
+  }
+}
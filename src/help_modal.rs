@@ -17,8 +17,58 @@ pub fn HelpModal(props: &ModalProps) -> Html {
           <li>{"Select cells with a click."}</li>
           <li>{"Double click, typing with a selected cell, or typing into the big input with a selected cell
           turns a cell into an input."}</li>
+          <li>{"Delete or Backspace clears the selected cells."}</li>
           <li>{"Interpret simple formulas starting with = and containing numeric literals, cell references,
           or the following mathematical operations: + - * / ^."}</li>
+          <li>{"Switch to Decimal Numbers to add, subtract, multiply, divide, and sum exact decimals,
+          so that = 0.1 + 0.2 shows 0.3 instead of 0.30000000000000004."}</li>
+          <li>{"Results too large for regular numbers, such as = 2^100 or = 10^400, are computed exactly
+          with big numbers, and shown in the scientific notation."}</li>
+          <li>{"Compare values with = <> < <= > >=, which produce 1 for true and 0 for false."}</li>
+          <li>{"Use SUM, AVERAGE, MIN, MAX, COUNT, COUNTA, and COUNTBLANK functions with cell references, ranges
+          (such as A1:B5), and other expressions as arguments; empty cells are skipped, and ISBLANK(value)
+          checks if a cell is empty."}</li>
+          <li>{"Count, sum, or average the cells matching a criteria, such as \">5\" or \"apples\",
+          with COUNTIF(range, criteria), SUMIF(range, criteria, [sum_range]),
+          and AVERAGEIF(range, criteria, [average_range])."}</li>
+          <li>{"Use math functions ABS, SQRT, ROUND, ROUNDUP, ROUNDDOWN, TRUNC, MROUND,
+          FLOOR, CEILING, LOG, LN, EXP, SIN, COS, TAN, MOD, GCD, and LCM, and constants PI and E."}</li>
+          <li>{"Convert between units of length, mass, time, temperature, and data sizes with
+          CONVERT(value, \"mi\", \"km\")."}</li>
+          <li>{"Analyze data with MEDIAN, MODE, STDEV, STDEVP, VAR, VARP, and PERCENTILE(range, k)."}</li>
+          <li>{"Plan finances with PMT, FV, PV, NPV, and IRR."}</li>
+          <li>{"Enter dates as 2024-03-05 or 2024-03-05 14:30, create them with TODAY(), NOW(),
+          and DATE(year, month, day), and add or subtract days from them."}</li>
+          <li>{"Take dates apart with YEAR, MONTH, DAY, and WEEKDAY, find the end of a month with
+          EOMONTH(date, months), and measure durations with DATEDIF(start, end, \"Y\" / \"M\" / \"D\")."}</li>
+          <li>{"Generate random numbers with RAND() and RANDBETWEEN(low, high); they change on every edit,
+          or when you press Recalculate."}</li>
+          <li>{"Look values up with VLOOKUP(key, range, column, [is_sorted]), HLOOKUP(key, range, row, [is_sorted]),
+          and XLOOKUP(key, lookup_range, return_range, [if_not_found], [match_mode], [search_mode])."}</li>
+          <li>{"Fill a range with numbers with SEQUENCE(rows, [columns], [start], [step]); the numbers
+          spill into the cells below and to the right, which should be empty."}</li>
+          <li>{"Apply operators to whole ranges, such as = A1:A5 * 2; the results spill like SEQUENCE,
+          and other formulas can reference the spilled cells."}</li>
+          <li>{"Choose between values with IF(condition, then, else), IFS(condition1, value1, ...),
+          and SWITCH(value, case1, result1, ..., [default]), and combine conditions with AND, OR, NOT,
+          and XOR; AND and OR stop at the first argument that decides the result."}</li>
+          <li>{"Work with text using string literals (such as \"apples\") and CONCAT, CONCATENATE, LEN,
+          UPPER, LOWER, TRIM, LEFT, RIGHT, and MID."}</li>
+          <li>{"Search and replace text with FIND(needle, text, [start]) and
+          SUBSTITUTE(text, old, new, [instance]), and take the n-th piece of text with SPLIT(text, delimiter, [n])."}</li>
+          <li>{"Match text against regular expressions with REGEXMATCH(text, pattern),
+          and extract the matching part with REGEXEXTRACT(text, pattern)."}</li>
+          <li>{"Format numbers and dates as text with TEXT(value, pattern), using patterns such as
+          \"#,##0.00\", \"0.0%\", or \"dd/mm/yyyy\"."}</li>
+          <li>{"Cells that cannot be computed show an error code: #REF! for references to deleted cells,
+          #DIV/0! for division by zero, #CYCLE! for circular references, #SPILL! for arrays that cannot spill,
+          #LIMIT! for formulas that are too deep or too slow to compute, and #VALUE! for other errors. Other cells are still computed."}</li>
+          <li>{"Raise or lower the limits behind #LIMIT! by opening the page with
+          ?max_depth=<formula depth>, ?max_cells_touched=<referenced cells>, or ?max_millis=<recomputation time>."}</li>
+          <li>{"Handle missing references and division by zero with IFERROR(value, fallback)
+          and ISERROR(value)."}</li>
+          <li>{"Add formula functions from WebAssembly plugins by opening the page with
+          ?plugin=<manifest url>, where the manifest lists the module and its exported functions."}</li>
           <li>{"Dynamically recompute table on cell change."}</li>
           <li>
             {"Copy & paste the content of the table. Here's "}
@@ -29,7 +79,54 @@ pub fn HelpModal(props: &ModalProps) -> Html {
             </a>
             {" you can try."}
           </li>
+          <li>{"Download how values flow between cells with Export Graph; open the cells.dot file
+          with Graphviz or another DOT viewer."}</li>
           <li>{"Enter can be used to confirm cell input and move to the next cell in the same column."}</li>
+          <li>{"Tab and Shift+Tab confirm cell input and move to the next or the previous column, wrapping to the next or the previous row."}</li>
+          <li>{"F2 starts editing the focused cell, keeping its input."}</li>
+          <li>{"Ctrl+Arrow jumps to the edge of the block of filled cells, or to the next filled cell."}</li>
+          <li>{"Home and End jump to the first and the last used column, Ctrl+Home and Ctrl+End to the first and the last used cell, and PageUp and PageDown move by a screen of rows."}</li>
+          <li>{"The box left of the formula bar shows the focused cell; type a cell there, e.g., M37, and press Enter to jump to it."}</li>
+          <li>{"Select a range of cells by dragging the mouse, with Shift+Click, or with Shift+Arrow; Shift also extends the selection with the other navigation keys."}</li>
+          <li>{"Ctrl+A selects the used range, Ctrl+Space the column, and Shift+Space the row of the focused cell; click a row or a column header to select it."}</li>
+          <li>{"Ctrl+C and Ctrl+X copy and cut the selected cells as tab-separated values, and Ctrl+V pastes them with their formulas at the focused cell; cut cells are cleared on paste."}</li>
+          <li>{"Cells copied from other spreadsheets, or comma-separated values, can be pasted with Ctrl+V as well."}</li>
+          <li>{"Ctrl+Shift+V pastes only the values, only the formulas, or the transposed copied cells."}</li>
+          <li>{"Drag the square at the corner of the selection to fill the next cells: numbers, dates, weekdays, and months continue their series, formulas are copied with their references shifted, and other cells are repeated."}</li>
+          <li>{"New Sheet clears all the cells of the active sheet after a confirmation, without reloading the page."}</li>
+          <li>{"Undo and redo changes with Ctrl+Z and Ctrl+Y, or with the Undo and Redo buttons."}</li>
+          <li>{"Ctrl+F finds the cells by their inputs or values; Enter and Shift+Enter move to the next and the previous one, and Replace changes the text in the inputs, including references in formulas."}</li>
+          <li>{"Bold, Italic, Text, and Fill style the selected cells; the styles are kept by Copy All and Paste All."}</li>
+          <li>{"$, %, 1,000, .0←, and .00→ format the numbers in the selected cells; formulas referencing them still see the exact numbers."}</li>
+          <li>{"Validate allows only a range of numbers, e.g., 1..10, a list of values, e.g., yes, no, or text matching a regular expression, e.g., /^[A-Z]+$/, in the selected cells; typed inputs breaking the rule are reverted, and other invalid values are marked red."}</li>
+          <li>{"Cells with TRUE or FALSE show a checkbox, toggled by clicking it or pressing Space; formulas see them as 1 and 0, e.g., = SUM(A1:A10) counts the checked ones."}</li>
+          <li>{"Cells validated against a list of values show a dropdown instead: click it, or press Enter or F2, to pick a value."}</li>
+          <li>{"Rebind the keyboard shortcuts in More ▾ > Keyboard Shortcuts: press Change next to a command, then the new keys; Reset restores the default keys. The bindings are saved with Copy All."}</li>
+          <li>{"While typing a formula, click a cell to insert its reference, or drag over the cells to insert a range like B2:B10; clicking again replaces the inserted reference until you type something after it."}</li>
+          <li>{"Formulas are colored while typed: functions, cell references, numbers, text, and operators each get their own color; each referenced cell or range is outlined in the table with the color underlining its reference."}</li>
+          <li>{"While typing a formula, e.g., = SU, the matching functions and filled cells are suggested; pick one with the arrows and Enter or Tab, or close the list with Escape. Inside a function call, its arguments are shown, with the optional ones in brackets."}</li>
+          <li>{"Ctrl+K opens the command palette listing all the table commands; type a part of a command's name, e.g., ins row, and press Enter to run it."}</li>
+          <li>{"Right-click a row or a column header to insert or delete a row or a column; formulas referencing the deleted cells show #REF!."}</li>
+          <li>{"Right-click a column header to sort the selected rows, or all the rows, by the column."}</li>
+          <li>{"Press ▾ in a column header to show only the rows where the column matches a filter, e.g., > 10, = done, or a text to search for; Clear Filters shows all the rows again."}</li>
+          <li>{"The table grows when you move or paste past its last row or column."}</li>
+          <li>{"Double-click the right border of a column header to fit the column to its widest value."}</li>
+          <li>{"Freeze the rows and the columns up to the focused cell with the Freeze button to keep them visible while scrolling."}</li>
+          <li>{"Select cells and press Merge to merge them into the top left cell, e.g., for headings; references to the merged cells use its value."}</li>
+          <li>{"A red badge in the top left corner marks a cell whose formula failed to parse or to evaluate; hover over it to see why."}</li>
+          <li>{"The cells of circular references are outlined in red, and their paths, e.g., A01 → B02 → A01, are listed in a banner under the toolbar; click a path to select its first cell."}</li>
+          <li>{"Errors, e.g., a failed paste or an unknown cell typed into the name box, are listed in a red banner under the toolbar until dismissed; other messages pop up in the bottom right corner for a few seconds."}</li>
+          <li>{"The status bar under the table shows the sum, the average, the count, the minimum, and the maximum of the numbers in the selected cells."}</li>
+          <li>{"Turn on Show Formulas to see the inputs of all cells, e.g., their formulas, instead of their values."}</li>
+          <li>{"Turn on Manual Calculation for huge tables: the edits only mark the cells as changed, and Recalculate or F9 computes them."}</li>
+          <li>{"Press To Values to replace the formulas in the selected cells with their current values."}</li>
+          <li>{"Trace Precedents in the Audit menu outlines in blue the cells the focused cell reads from, directly or via other cells, and Trace Dependents outlines in green the cells reading from it; the traces are removed once an input changes."}</li>
+          <li>{"Select cells and press Watch to list their inputs and values in the Watch panel on the right, which stays visible while scrolling; click a watched cell to select it."}</li>
+          <li>{"Click the count of errors in the status bar, or Errors in the Audit menu, to list the cells failing to parse or to evaluate; click one to select it."}</li>
+          <li>{"Add sheets with the + button in the tabs under the table, click a tab to switch to its sheet, double-click it to rename the sheet, and press ✕ on the active tab to delete it after a confirmation; each sheet keeps its own undo history."}</li>
+          <li>{"Split the view horizontally or vertically with the Split menu to scroll two parts of the table independently, e.g., a summary and the data far below it."}</li>
+          <li>{"Turn on Right to Left to mirror the table for Arabic, Hebrew, or Persian: the row numbers go on the right, the columns run from right to left, and the text is aligned the other way. It's on by default if the browser uses such a language."}</li>
+          <li>{"Everything works without a mouse: screen readers announce the focused cell and its value, Tab reaches the sheet tabs and the rows of the Watch panel and the Errors list, which Enter picks (F2 renames a tab), and the command palette runs the remaining commands, e.g., Fill Down, Fill Right, or Go to Sheet."}</li>
         </ul>
         <p>
           {"You can see more of my work at "}
@@ -0,0 +1,109 @@
+use yew::prelude::*;
+
+use crate::cell_id::CellId;
+use crate::parser::{highlight, references, TokenKind};
+use crate::selection::Selection;
+
+/// The colors of the references of the formula being typed, painting the referenced cells.
+const REF_COLORS: [&str; 6] = [
+  "#38bdf8", "#f472b6", "#a3e635", "#fb923c", "#c084fc", "#facc15",
+];
+
+#[derive(PartialEq, Properties)]
+pub struct HighlightedProps {
+  pub input: String,
+  // positions the text over the transparent text of the input
+  pub class: Classes,
+  #[prop_or_default]
+  pub style: String,
+}
+
+/// Whether the input is a formula, whose text is colored by `Highlighted`.
+pub fn is_highlighted(input: &str) -> bool {
+  !highlight(input).is_empty()
+}
+
+/// Returns the cells referenced by the formula with their colors; repeated references
+/// get the same color.
+pub fn painted_refs(input: &str) -> Vec<(Selection, &'static str)> {
+  let mut painted: Vec<(Selection, &'static str)> = vec![];
+  for (_, selection) in references(input) {
+    if !painted.iter().any(|(other, _)| *other == selection) {
+      painted.push((selection, REF_COLORS[painted.len() % REF_COLORS.len()]));
+    }
+  }
+  painted
+}
+
+/// Returns the CSS outlining the painted ranges the cell belongs to, at their edges.
+pub fn paint_css(painted: &[(Selection, &'static str)], cell_id: CellId) -> Option<String> {
+  let shadows = painted
+    .iter()
+    .filter(|(selection, _)| selection.contains(cell_id))
+    .flat_map(|(selection, color)| {
+      let (top_left, bottom_right) = selection.bounds();
+      [
+        (cell_id.row == top_left.row).then(|| format!("inset 0 2px 0 {color}")),
+        (cell_id.row == bottom_right.row).then(|| format!("inset 0 -2px 0 {color}")),
+        (cell_id.col == top_left.col).then(|| format!("inset 2px 0 0 {color}")),
+        (cell_id.col == bottom_right.col).then(|| format!("inset -2px 0 0 {color}")),
+      ]
+    })
+    .flatten()
+    .collect::<Vec<_>>();
+
+  (!shadows.is_empty()).then(|| format!("box-shadow: {};", shadows.join(", ")))
+}
+
+fn color(kind: TokenKind) -> &'static str {
+  match kind {
+    TokenKind::Op => "text-amber-300",
+    TokenKind::Num => "text-emerald-300",
+    TokenKind::Str => "text-orange-300",
+    TokenKind::CellRef => "text-sky-300",
+    TokenKind::Func => "text-fuchsia-300",
+    TokenKind::Punct => "text-neutral-400",
+    TokenKind::Error => "text-red-400",
+    TokenKind::Unknown => "text-white",
+  }
+}
+
+/// The formula being typed with its lexems colored by kind, and its references underlined; it's shown over the input,
+/// which can't color parts of its text, and lets the clicks through.
+#[function_component]
+pub fn Highlighted(props: &HighlightedProps) -> Html {
+  let input = &props.input;
+  let painted = painted_refs(input);
+  let refs = references(input);
+  // the references are underlined with the colors of the painted cells
+  let underline = |start: usize| {
+    let (_, selection) = refs.iter().find(|(range, _)| range.start == start)?;
+    let (_, color) = painted.iter().find(|(other, _)| other == selection)?;
+    Some(format!(
+      "text-decoration: underline 2px {color}; text-underline-offset: 3px;"
+    ))
+  };
+
+  let mut loc = 0;
+  let mut parts = vec![];
+  for (range, kind) in highlight(input) {
+    parts.push(html! { { &input[loc..range.start] } });
+    parts.push(html! {
+      <span class={ color(kind) } style={ underline(range.start) }>{ &input[range.clone()] }</span>
+    });
+    loc = range.end;
+  }
+  parts.push(html! { { &input[loc..] } });
+
+  html! {
+    <div
+      class={classes!(
+        vec!["absolute top-0 left-0 flex items-center pointer-events-none whitespace-pre overflow-hidden font-mono"],
+        props.class.clone()
+      )}
+      style={ props.style.clone() }
+    >
+      <span>{ parts }</span>
+    </div>
+  }
+}
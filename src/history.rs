@@ -0,0 +1,113 @@
+//! Undo and redo of the commands changing the table. Each command, e.g., a paste, is undone
+//! as a whole; typing into a cell is a single command until the input is left.
+use crate::cell_id::CellId;
+use crate::command::Command;
+
+#[derive(Debug, Default)]
+pub struct History {
+  // the applied commands with their inverses
+  undo: Vec<(Command, Command)>,
+  redo: Vec<(Command, Command)>,
+  // the cell being typed into, whose edits are merged into the last command
+  typing: Option<CellId>,
+}
+
+impl History {
+  /// Records an applied command with its inverse, skipping the commands that change nothing.
+  pub fn record(&mut self, command: Command, inverse: Command) {
+    self.typing = None;
+
+    if command != inverse {
+      self.undo.push((command, inverse));
+      self.redo.clear();
+    }
+  }
+
+  /// Records typing the input into the cell, merging it into the last command
+  /// while the same cell is typed into.
+  pub fn record_typing(&mut self, cell_id: CellId, input: String, inverse: Command) {
+    let command = Command::SetInputs(vec![(cell_id, input)]);
+    match self.undo.last_mut() {
+      Some((last, _)) if self.typing == Some(cell_id) => {
+        *last = command;
+        self.redo.clear();
+      }
+      _ => self.record(command, inverse),
+    }
+    self.typing = Some(cell_id);
+  }
+
+  /// Ends merging the typed edits, e.g., when the input is left.
+  pub fn end_typing(&mut self) {
+    self.typing = None;
+  }
+
+  /// Drops the typing into the cell, if it's still typed into, e.g., as the input isn't allowed,
+  /// and returns the command reverting it, which can't be redone.
+  pub fn discard_typing(&mut self, cell_id: CellId) -> Option<Command> {
+    if self.typing != Some(cell_id) {
+      return None;
+    }
+
+    self.typing = None;
+    self.undo.pop().map(|(_, inverse)| inverse)
+  }
+
+  /// Returns the command undoing the last command.
+  pub fn undo(&mut self) -> Option<Command> {
+    self.typing = None;
+    let (command, inverse) = self.undo.pop()?;
+    self.redo.push((command, inverse.clone()));
+    Some(inverse)
+  }
+
+  /// Returns the last undone command to apply it again.
+  pub fn redo(&mut self) -> Option<Command> {
+    self.typing = None;
+    let (command, inverse) = self.redo.pop()?;
+    self.undo.push((command.clone(), inverse));
+    Some(command)
+  }
+
+  pub fn clear(&mut self) {
+    *self = History::default();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn history_test() {
+    let a1 = CellId { col: 'A', row: 1 };
+    let b1 = CellId { col: 'B', row: 1 };
+    let set = |cell_id, input: &str| Command::SetInputs(vec![(cell_id, input.to_string())]);
+
+    let mut history = History::default();
+    history.record_typing(a1, String::from("1"), set(a1, ""));
+    history.record_typing(a1, String::from("12"), set(a1, "1"));
+    history.end_typing();
+    history.record_typing(a1, String::from("123"), set(a1, "12"));
+    history.record(set(b1, "= A1"), set(b1, ""));
+    history.record(set(b1, "= A1"), set(b1, "= A1"));
+
+    assert_eq!(history.undo(), Some(set(b1, "")));
+    assert_eq!(history.undo(), Some(set(a1, "12")));
+    assert_eq!(history.redo(), Some(set(a1, "123")));
+    assert_eq!(history.undo(), Some(set(a1, "12")));
+    assert_eq!(history.undo(), Some(set(a1, "")));
+    assert_eq!(history.undo(), None);
+
+    // a new command discards the undone commands
+    history.record_typing(b1, String::from("2"), set(b1, ""));
+    assert_eq!(history.redo(), None);
+
+    // rejected typing is dropped without being redoable
+    history.record_typing(b1, String::from("3"), set(b1, ""));
+    assert_eq!(history.discard_typing(a1), None);
+    assert_eq!(history.discard_typing(b1), Some(set(b1, "")));
+    assert_eq!(history.redo(), None);
+    assert_eq!(history.discard_typing(b1), None);
+  }
+}
@@ -0,0 +1,354 @@
+//! Keyboard shortcuts: key chords like `Ctrl+Z` bound to the actions of the table.
+//! The user can rebind any action in the Keyboard Shortcuts dialog; the overrides are saved
+//! with the other settings of the table.
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use web_sys::KeyboardEvent;
+
+use crate::navigation::{Direction, Jump};
+
+/// A key pressed with modifiers; Cmd is treated as Ctrl.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Chord {
+  /// The `key` of the keyboard event, e.g., `Enter` or ` `; letters are uppercase.
+  pub key: String,
+  pub ctrl: bool,
+  pub shift: bool,
+  pub alt: bool,
+}
+
+impl Chord {
+  pub fn new(key: &str) -> Self {
+    let key = if key.chars().count() == 1 {
+      key.to_uppercase()
+    } else {
+      key.to_string()
+    };
+
+    Chord {
+      key,
+      ctrl: false,
+      shift: false,
+      alt: false,
+    }
+  }
+
+  pub fn ctrl(self) -> Self {
+    Chord { ctrl: true, ..self }
+  }
+
+  pub fn shift(self) -> Self {
+    Chord {
+      shift: true,
+      ..self
+    }
+  }
+
+  pub fn from_event(ev: &KeyboardEvent) -> Self {
+    Chord {
+      ctrl: ev.ctrl_key() || ev.meta_key(),
+      shift: ev.shift_key(),
+      alt: ev.alt_key(),
+      ..Chord::new(&ev.key())
+    }
+  }
+
+  /// Whether only a modifier is pressed so far, e.g., while recording a new binding.
+  pub fn is_modifier(&self) -> bool {
+    matches!(
+      self.key.as_str(),
+      "Control" | "Shift" | "Alt" | "Meta" | "AltGraph" | "CapsLock"
+    )
+  }
+}
+
+/// Parses chords like `Ctrl+Shift+V`, `Space`, or `Ctrl++`.
+impl TryFrom<&str> for Chord {
+  type Error = String;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    let (modifiers, key) = match value.strip_suffix("++") {
+      Some(modifiers) => (modifiers, "+"),
+      None => value.rsplit_once('+').unwrap_or(("", value)),
+    };
+    let key = match key.trim() {
+      "" => return Err(format!("`{value}` has no key")),
+      "Space" => " ",
+      key => key,
+    };
+
+    let mut chord = Chord::new(key);
+    for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+      match modifier.trim().to_lowercase().as_str() {
+        "ctrl" | "control" | "cmd" => chord.ctrl = true,
+        "shift" => chord.shift = true,
+        "alt" | "option" => chord.alt = true,
+        _ => return Err(format!("`{modifier}` is not a modifier key")),
+      }
+    }
+    Ok(chord)
+  }
+}
+
+impl TryFrom<String> for Chord {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    Chord::try_from(value.as_str())
+  }
+}
+
+impl From<Chord> for String {
+  fn from(chord: Chord) -> Self {
+    chord.to_string()
+  }
+}
+
+impl Display for Chord {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.ctrl {
+      write!(f, "Ctrl+")?;
+    }
+    if self.shift {
+      write!(f, "Shift+")?;
+    }
+    if self.alt {
+      write!(f, "Alt+")?;
+    }
+    match self.key.as_str() {
+      " " => write!(f, "Space"),
+      key => write!(f, "{key}"),
+    }
+  }
+}
+
+/// What a key chord does; Shift extends the selection with the navigation actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+  /// Confirms the input and moves down, or runs the search in the find bar.
+  Commit,
+  NextCell,
+  PrevCell,
+  Edit,
+  Toggle,
+  Clear,
+  SelectRow,
+  SelectColumn,
+  SelectAll,
+  Undo,
+  Redo,
+  PasteSpecial,
+  Find,
+  Palette,
+  Close,
+  Step(Direction),
+  DataEdge(Direction),
+  RowStart,
+  RowEnd,
+  TableStart,
+  TableEnd,
+  PageUp,
+  PageDown,
+  Recalculate,
+}
+
+impl Action {
+  pub fn title(&self) -> String {
+    let title = match self {
+      Action::Commit => "Confirm the input",
+      Action::NextCell => "Next cell",
+      Action::PrevCell => "Previous cell",
+      Action::Edit => "Edit the cell",
+      Action::Toggle => "Toggle the checkbox",
+      Action::Clear => "Clear",
+      Action::SelectRow => "Select the row",
+      Action::SelectColumn => "Select the column",
+      Action::SelectAll => "Select all",
+      Action::Undo => "Undo",
+      Action::Redo => "Redo",
+      Action::PasteSpecial => "Paste special",
+      Action::Find => "Find and replace",
+      Action::Palette => "Command palette",
+      Action::Close => "Close the find bar",
+      Action::Step(direction) => return format!("Extend the selection {}", direction.name()),
+      Action::DataEdge(direction) => return format!("Jump to the data edge {}", direction.name()),
+      Action::RowStart => "Start of the row",
+      Action::RowEnd => "End of the row",
+      Action::TableStart => "Start of the table",
+      Action::TableEnd => "End of the table",
+      Action::PageUp => "Page up",
+      Action::PageDown => "Page down",
+      Action::Recalculate => "Recalculate",
+    };
+    title.to_string()
+  }
+
+  /// Returns the move of the focus for the navigation actions, paging by `page_rows`.
+  pub fn jump(&self, page_rows: usize) -> Option<Jump> {
+    match self {
+      Action::Step(direction) => Some(Jump::Step(*direction)),
+      Action::DataEdge(direction) => Some(Jump::DataEdge(*direction)),
+      Action::RowStart => Some(Jump::RowStart),
+      Action::RowEnd => Some(Jump::RowEnd),
+      Action::TableStart => Some(Jump::TableStart),
+      Action::TableEnd => Some(Jump::TableEnd),
+      Action::PageUp => Some(Jump::PageUp(page_rows)),
+      Action::PageDown => Some(Jump::PageDown(page_rows)),
+      _ => None,
+    }
+  }
+}
+
+/// The default bindings; an action can have several chords.
+pub fn default_bindings() -> Vec<(Chord, Action)> {
+  let mut bindings = vec![
+    (Chord::new("Enter"), Action::Commit),
+    (Chord::new("Tab"), Action::NextCell),
+    (Chord::new("Tab").shift(), Action::PrevCell),
+    (Chord::new("F2"), Action::Edit),
+    (Chord::new(" "), Action::Toggle),
+    (Chord::new("Delete"), Action::Clear),
+    (Chord::new("Backspace"), Action::Clear),
+    (Chord::new(" ").shift(), Action::SelectRow),
+    (Chord::new(" ").ctrl(), Action::SelectColumn),
+    (Chord::new("A").ctrl(), Action::SelectAll),
+    (Chord::new("Z").ctrl(), Action::Undo),
+    (Chord::new("Y").ctrl(), Action::Redo),
+    (Chord::new("Z").ctrl().shift(), Action::Redo),
+    (Chord::new("V").ctrl().shift(), Action::PasteSpecial),
+    (Chord::new("F").ctrl(), Action::Find),
+    (Chord::new("K").ctrl(), Action::Palette),
+    (Chord::new("Escape"), Action::Close),
+  ];
+
+  for direction in [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+  ] {
+    let key = format!("Arrow{direction:?}");
+    bindings.push((Chord::new(&key).shift(), Action::Step(direction)));
+    bindings.push((Chord::new(&key).ctrl(), Action::DataEdge(direction)));
+  }
+
+  bindings.extend([
+    (Chord::new("Home"), Action::RowStart),
+    (Chord::new("End"), Action::RowEnd),
+    (Chord::new("Home").ctrl(), Action::TableStart),
+    (Chord::new("End").ctrl(), Action::TableEnd),
+    (Chord::new("PageUp"), Action::PageUp),
+    (Chord::new("PageDown"), Action::PageDown),
+    (Chord::new("F9"), Action::Recalculate),
+  ]);
+  bindings
+}
+
+/// The bindings of the user replacing the default ones of the same actions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+  pub overrides: Vec<(Action, Chord)>,
+}
+
+impl Keymap {
+  /// Returns all the bindings, the overrides first.
+  pub fn bindings(&self) -> Vec<(Chord, Action)> {
+    let is_overridden = |action: &Action| self.overrides.iter().any(|(other, _)| other == action);
+
+    self
+      .overrides
+      .iter()
+      .map(|(action, chord)| (chord.clone(), *action))
+      .chain(
+        default_bindings()
+          .into_iter()
+          .filter(|(_, action)| !is_overridden(action)),
+      )
+      .collect()
+  }
+
+  /// Returns the action bound to the chord; if none is, and Shift is pressed,
+  /// the action bound to the chord without Shift, e.g., for Shift+Home.
+  pub fn action(&self, chord: &Chord) -> Option<Action> {
+    let bindings = self.bindings();
+    let find = |chord: &Chord| {
+      bindings
+        .iter()
+        .find(|(other, _)| other == chord)
+        .map(|(_, action)| *action)
+    };
+
+    find(chord).or_else(|| {
+      chord.shift.then(|| {
+        find(&Chord {
+          shift: false,
+          ..chord.clone()
+        })
+      })?
+    })
+  }
+
+  /// Returns the chords bound to the action, e.g., to show them in menus.
+  pub fn chords(&self, action: Action) -> Vec<Chord> {
+    self
+      .bindings()
+      .into_iter()
+      .filter(|(_, other)| *other == action)
+      .map(|(chord, _)| chord)
+      .collect()
+  }
+
+  /// Binds the chord to the action instead of its current chords,
+  /// or restores its default chords for `None`.
+  pub fn set(&mut self, action: Action, chord: Option<Chord>) {
+    self.overrides.retain(|(other, _)| *other != action);
+    if let Some(chord) = chord {
+      self.overrides.push((action, chord));
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn keymap_test() {
+    let chord = |text: &str| Chord::try_from(text).unwrap();
+
+    assert_eq!(chord("ctrl+shift+v"), Chord::new("V").ctrl().shift());
+    assert_eq!(chord("Space"), Chord::new(" "));
+    assert_eq!(chord("Ctrl++").key, "+");
+    assert!(Chord::try_from("Hyper+A").is_err());
+    for text in ["Ctrl+Shift+V", "Shift+Space", "F2", "Ctrl+End"] {
+      assert_eq!(chord(text).to_string(), text);
+    }
+
+    let mut keymap = Keymap::default();
+    assert_eq!(keymap.action(&chord("Ctrl+Z")), Some(Action::Undo));
+    assert_eq!(keymap.action(&chord("Ctrl+Shift+Z")), Some(Action::Redo));
+    assert_eq!(keymap.action(&chord("Shift+Tab")), Some(Action::PrevCell));
+    // Shift extends the selection to the start of the row
+    assert_eq!(keymap.action(&chord("Shift+Home")), Some(Action::RowStart));
+    assert_eq!(keymap.action(&chord("Shift+A")), None);
+    assert_eq!(
+      keymap.chords(Action::Redo),
+      vec![chord("Ctrl+Y"), chord("Ctrl+Shift+Z")]
+    );
+
+    keymap.set(Action::Find, Some(chord("Ctrl+H")));
+    assert_eq!(keymap.action(&chord("Ctrl+H")), Some(Action::Find));
+    assert_eq!(keymap.action(&chord("Ctrl+F")), None);
+    // the overrides take precedence over the default bindings
+    keymap.set(Action::Palette, Some(chord("Ctrl+Z")));
+    assert_eq!(keymap.action(&chord("Ctrl+Z")), Some(Action::Palette));
+
+    let serialized = serde_json::to_string(&keymap).unwrap();
+    assert_eq!(serde_json::from_str::<Keymap>(&serialized).unwrap(), keymap);
+
+    keymap.set(Action::Find, None);
+    assert_eq!(keymap.action(&chord("Ctrl+F")), Some(Action::Find));
+  }
+}
@@ -0,0 +1,91 @@
+use std::rc::Rc;
+use yew::prelude::*;
+
+use crate::btn::*;
+use crate::keymap::{default_bindings, Action, Chord, Keymap};
+use crate::modal::*;
+
+#[derive(PartialEq, Properties)]
+pub struct KeymapModalProps {
+  pub keymap: Rc<Keymap>,
+  // binds a new chord to the action, or restores its default chords for `None`
+  pub onchange: Callback<(Action, Option<Chord>)>,
+  pub is_visible: bool,
+  pub onclose: Callback<()>,
+}
+
+/// Lists the keyboard shortcuts; pressing Change and then a key chord rebinds the action.
+#[function_component]
+pub fn KeymapModal(props: &KeymapModalProps) -> Html {
+  // the action waiting for the new chord to be pressed
+  let recording = use_state(|| None::<Action>);
+
+  // the Change button keeps the focus, so its key presses bubble up here
+  let onkeydown = {
+    let recording = recording.clone();
+    let parent_onchange = props.onchange.clone();
+
+    Callback::from(move |ev: KeyboardEvent| {
+      let Some(action) = *recording else {
+        return;
+      };
+      let chord = Chord::from_event(&ev);
+      if chord.is_modifier() {
+        return;
+      }
+
+      ev.prevent_default();
+      recording.set(None);
+      if chord != Chord::new("Escape") {
+        parent_onchange.emit((action, Some(chord)));
+      }
+    })
+  };
+
+  let mut actions = vec![];
+  for (_, action) in default_bindings() {
+    if !actions.contains(&action) {
+      actions.push(action);
+    }
+  }
+
+  let row = |action: Action| {
+    let chords = props
+      .keymap
+      .chords(action)
+      .iter()
+      .map(|chord| chord.to_string())
+      .collect::<Vec<_>>()
+      .join(", ");
+    let is_recording = *recording == Some(action);
+    let onrecord = {
+      let recording = recording.clone();
+      Callback::from(move |_ev: MouseEvent| recording.set(Some(action)))
+    };
+    let onreset = {
+      let parent_onchange = props.onchange.clone();
+      Callback::from(move |_ev: MouseEvent| parent_onchange.emit((action, None)))
+    };
+
+    html! {
+      <tr>
+        <td class="pr-4">{ action.title() }</td>
+        <td class="pr-4 font-mono">
+          { if is_recording { String::from("Press the keys…") } else { chords } }
+        </td>
+        <td class="flex gap-2 py-1">
+          <Btn title="Change" color={ BtnColors::Violet } onclick={onrecord} />
+          <Btn title="Reset" color={ BtnColors::Violet } onclick={onreset} />
+        </td>
+      </tr>
+    }
+  };
+
+  html! {
+    <Modal title="Keyboard Shortcuts" is_visible={props.is_visible} onclose={props.onclose.clone()}>
+      <table class="text-base" {onkeydown}>
+        { actions.into_iter().map(row).collect::<Html>() }
+      </table>
+    </Modal>
+  }
+}
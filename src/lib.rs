@@ -3,12 +3,52 @@ extern crate lazy_static;
 
 mod btn;
 mod cell;
+mod completions;
+mod confirm_modal;
+mod errors_modal;
 mod help_modal;
+mod highlighted;
+mod keymap_modal;
 mod modal;
-mod parser;
+mod palette_modal;
 mod paste_modal;
+mod paste_special_modal;
+mod sheet_tabs;
+mod toasts;
+mod toolbar;
+mod watch_panel;
 
+pub mod autocomplete;
+pub mod bignum;
 pub mod cell_id;
+pub mod clipboard;
+pub mod command;
+pub mod date;
+pub mod editing;
+pub mod error;
 pub mod expr;
+pub mod fill;
+pub mod filter;
+pub mod find;
+pub mod format;
+pub mod func;
+pub mod history;
+pub mod keymap;
+pub mod limits;
+pub mod locale;
+pub mod navigation;
+pub mod notifications;
+pub mod palette;
+pub mod parser;
+pub mod plugin;
+pub mod registry;
+pub mod selection;
+pub mod sheets;
+pub mod stats;
+pub mod style;
 pub mod table;
 pub mod topological;
+pub mod trace;
+pub mod units;
+pub mod validation;
+pub mod value;
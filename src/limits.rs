@@ -0,0 +1,134 @@
+//! Limits on evaluation, so that a pathological table cannot hang the browser tab.
+//! Cells exceeding the limits get `#LIMIT!` errors.
+use crate::cell_id::CellId;
+use crate::error::{ErrorKind, EvalError};
+use crate::expr::Expr;
+use crate::trace;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+  /// Maximum depth of a formula, e.g., `= (A1 + 1) * 2` has depth 3.
+  pub max_depth: usize,
+  /// Maximum number of the cells referenced by all evaluated formulas, including the cells
+  /// in ranges; cells referenced several times are counted several times.
+  pub max_cells_touched: usize,
+  /// Maximum time of recomputing the table in milliseconds.
+  pub max_millis: f64,
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Limits {
+      max_depth: 256,
+      max_cells_touched: 1_000_000,
+      max_millis: 5_000.0,
+    }
+  }
+}
+
+impl Limits {
+  /// Returns the limits with the defaults overridden by the named parameters, e.g.,
+  /// `?max_depth=64&max_millis=1000` in the query string; invalid values are ignored.
+  pub fn from_params(get: impl Fn(&str) -> Option<String>) -> Self {
+    let defaults = Limits::default();
+    Limits {
+      max_depth: parse_param(&get, "max_depth").unwrap_or(defaults.max_depth),
+      max_cells_touched: parse_param(&get, "max_cells_touched")
+        .unwrap_or(defaults.max_cells_touched),
+      max_millis: parse_param(&get, "max_millis").unwrap_or(defaults.max_millis),
+    }
+  }
+
+  /// Returns the cells the expression depends on, or no cells if it references more cells
+  /// than `max_cells_touched`, so that huge ranges are never listed; `Budget::spend` fails
+  /// such expressions anyway.
+  pub fn deps(&self, expr: &Expr) -> Vec<CellId> {
+    if expr.cells_referenced() > self.max_cells_touched {
+      return vec![];
+    }
+    expr.get_deps()
+  }
+}
+
+fn parse_param<T: std::str::FromStr>(
+  get: &impl Fn(&str) -> Option<String>,
+  name: &str,
+) -> Option<T> {
+  get(name)?.trim().parse().ok()
+}
+
+/// Tracks the resources spent on recomputing the table.
+pub(crate) struct Budget<'a> {
+  limits: &'a Limits,
+  start: f64,
+  cells_touched: usize,
+}
+
+impl<'a> Budget<'a> {
+  pub fn new(limits: &'a Limits) -> Self {
+    Budget {
+      limits,
+      start: trace::now(),
+      cells_touched: 0,
+    }
+  }
+
+  /// Checks the limits before evaluating the expression.
+  pub fn spend(&mut self, expr: &Expr) -> Result<(), EvalError> {
+    if trace::now() - self.start > self.limits.max_millis {
+      return Err(EvalError::new(
+        ErrorKind::Limit,
+        format!(
+          "recomputing the table took longer than {} ms",
+          self.limits.max_millis
+        ),
+      ));
+    }
+
+    let depth = expr.depth();
+    if depth > self.limits.max_depth {
+      return Err(EvalError::new(
+        ErrorKind::Limit,
+        format!(
+          "formula depth {depth} exceeds the limit of {}",
+          self.limits.max_depth
+        ),
+      ));
+    }
+
+    let cells_touched = self.cells_touched.saturating_add(expr.cells_referenced());
+    if cells_touched > self.limits.max_cells_touched {
+      return Err(EvalError::new(
+        ErrorKind::Limit,
+        format!(
+          "formulas reference more than {} cells in total",
+          self.limits.max_cells_touched
+        ),
+      ));
+    }
+
+    self.cells_touched = cells_touched;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn from_params_test() {
+    let limits = Limits::from_params(|name| match name {
+      "max_depth" => Some("64".to_string()),
+      "max_millis" => Some("fast".to_string()),
+      _ => None,
+    });
+    assert_eq!(
+      limits,
+      Limits {
+        max_depth: 64,
+        ..Limits::default()
+      }
+    );
+  }
+}
@@ -0,0 +1,28 @@
+//! Settings picked by the language of the browser, e.g., `ar-EG`.
+
+// the languages written from right to left
+const RTL_LANGUAGES: [&str; 10] = ["ar", "ckb", "dv", "fa", "he", "ps", "sd", "ug", "ur", "yi"];
+
+/// Whether the language tag, e.g., `he-IL`, is of a language written from right to left,
+/// which mirrors the table.
+pub fn is_rtl(language: &str) -> bool {
+  let primary = language.split(['-', '_']).next().unwrap_or_default();
+  RTL_LANGUAGES
+    .iter()
+    .any(|rtl| rtl.eq_ignore_ascii_case(primary))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn is_rtl_test() {
+    assert!(is_rtl("ar"));
+    assert!(is_rtl("he-IL"));
+    assert!(is_rtl("FA_ir"));
+    assert!(!is_rtl("en-US"));
+    assert!(!is_rtl("arn"));
+    assert!(!is_rtl(""));
+  }
+}
@@ -0,0 +1,207 @@
+//! Keyboard navigation between the cells of the table, e.g., Ctrl+Arrow jumping
+//! to the edge of a block of filled cells, or Home jumping to the first column.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::cell_id::{CellId, Size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+  Up,
+  Down,
+  Left,
+  Right,
+}
+
+impl Direction {
+  /// Parses the `key` of a keyboard event, e.g., `ArrowUp`.
+  pub fn from_key(key: &str) -> Option<Direction> {
+    match key {
+      "ArrowUp" => Some(Direction::Up),
+      "ArrowDown" => Some(Direction::Down),
+      "ArrowLeft" => Some(Direction::Left),
+      "ArrowRight" => Some(Direction::Right),
+      _ => None,
+    }
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Direction::Up => "up",
+      Direction::Down => "down",
+      Direction::Left => "left",
+      Direction::Right => "right",
+    }
+  }
+
+  /// Returns the neighboring cell in this direction, or `None` in the first row or column,
+  /// or in the column `Z`; the table grows down when the cells below it are used.
+  pub fn step(&self, CellId { col, row }: CellId) -> Option<CellId> {
+    match self {
+      Direction::Up if row > 1 => Some(CellId { col, row: row - 1 }),
+      Direction::Down => Some(CellId { col, row: row + 1 }),
+      Direction::Left if col > 'A' => Some(CellId {
+        col: (col as u8 - 1) as char,
+        row,
+      }),
+      Direction::Right if col < 'Z' => Some(CellId {
+        col: (col as u8 + 1) as char,
+        row,
+      }),
+      _ => None,
+    }
+  }
+}
+
+/// A move of the focus triggered by a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+  /// Shift+Arrow, to the neighboring cell.
+  Step(Direction),
+  /// Ctrl+Arrow, see `data_edge`.
+  DataEdge(Direction),
+  /// Home, to the column `A` of the row.
+  RowStart,
+  /// End, to the last used column of the table in the row.
+  RowEnd,
+  /// Ctrl+Home, to `A1`.
+  TableStart,
+  /// Ctrl+End, to the last used column in the last used row.
+  TableEnd,
+  /// PageUp, by the given number of rows.
+  PageUp(usize),
+  /// PageDown, by the given number of rows.
+  PageDown(usize),
+}
+
+impl Jump {
+  /// Returns the cell the focus moves to from `from`; `inputs` are the non-empty cells.
+  /// Only `Step` and `PageDown` can move past the edge of the table of the `size`.
+  pub fn target<T>(&self, inputs: &HashMap<CellId, T>, from: CellId, size: Size) -> CellId {
+    match self {
+      Jump::Step(direction) => direction.step(from).unwrap_or(from),
+      Jump::DataEdge(direction) => data_edge(inputs, from, *direction, size),
+      Jump::RowStart => CellId { col: 'A', ..from },
+      Jump::RowEnd => CellId {
+        col: last_used(inputs, |cell_id| cell_id.col).unwrap_or('A'),
+        ..from
+      },
+      Jump::TableStart => CellId { col: 'A', row: 1 },
+      Jump::TableEnd => CellId {
+        col: last_used(inputs, |cell_id| cell_id.col).unwrap_or('A'),
+        row: last_used(inputs, |cell_id| cell_id.row).unwrap_or(1),
+      },
+      Jump::PageUp(rows) => CellId {
+        row: from.row.saturating_sub(*rows).max(1),
+        ..from
+      },
+      Jump::PageDown(rows) => CellId {
+        row: from.row + rows,
+        ..from
+      },
+    }
+  }
+}
+
+fn last_used<T, K: Ord>(inputs: &HashMap<CellId, T>, key: impl Fn(&CellId) -> K) -> Option<K> {
+  inputs.keys().map(key).max()
+}
+
+/// Returns the last filled cell of the block `from` belongs to, if the next cell is filled,
+/// otherwise the first filled cell in the `direction`, or the edge of the table if there is none.
+pub fn data_edge<T>(
+  inputs: &HashMap<CellId, T>,
+  from: CellId,
+  direction: Direction,
+  size: Size,
+) -> CellId {
+  let is_filled = |cell_id: &CellId| inputs.contains_key(cell_id);
+  let step = |cell_id| direction.step(cell_id).filter(|next| size.contains(*next));
+
+  let Some(mut cell_id) = step(from) else {
+    return from;
+  };
+
+  if is_filled(&from) && is_filled(&cell_id) {
+    while let Some(next) = step(cell_id).filter(is_filled) {
+      cell_id = next;
+    }
+  } else {
+    while let Some(next) = step(cell_id).filter(|_| !is_filled(&cell_id)) {
+      cell_id = next;
+    }
+  }
+
+  cell_id
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn data_edge_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let inputs = ["A1", "A2", "A3", "A6", "C1"]
+      .into_iter()
+      .map(|id| (cell_id(id), String::from("1")))
+      .collect::<HashMap<_, _>>();
+    let edge =
+      |from: &str, direction| data_edge(&inputs, cell_id(from), direction, Size::default());
+
+    // to the end of the block
+    assert_eq!(edge("A1", Direction::Down), cell_id("A3"));
+    assert_eq!(edge("A2", Direction::Down), cell_id("A3"));
+    // to the next block
+    assert_eq!(edge("A3", Direction::Down), cell_id("A6"));
+    assert_eq!(edge("A4", Direction::Down), cell_id("A6"));
+    assert_eq!(edge("A6", Direction::Up), cell_id("A3"));
+    assert_eq!(edge("A1", Direction::Right), cell_id("C1"));
+    // to the edge of the table
+    assert_eq!(edge("A6", Direction::Down), cell_id("A50"));
+    assert_eq!(edge("C1", Direction::Right), cell_id("J01"));
+    assert_eq!(edge("B5", Direction::Left), cell_id("A05"));
+    assert_eq!(edge("A1", Direction::Up), cell_id("A1"));
+    assert_eq!(edge("A1", Direction::Left), cell_id("A1"));
+    assert_eq!(
+      data_edge(
+        &inputs,
+        cell_id("A6"),
+        Direction::Down,
+        Size { rows: 80, cols: 3 }
+      ),
+      cell_id("A80")
+    );
+  }
+
+  #[test]
+  fn jump_target_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let inputs = ["B7", "D2"]
+      .into_iter()
+      .map(|id| (cell_id(id), String::from("1")))
+      .collect::<HashMap<_, _>>();
+    let target = |jump: Jump, from: &str| jump.target(&inputs, cell_id(from), Size::default());
+
+    assert_eq!(target(Jump::Step(Direction::Up), "C5"), cell_id("C4"));
+    assert_eq!(target(Jump::Step(Direction::Up), "C1"), cell_id("C1"));
+    assert_eq!(target(Jump::RowStart, "C5"), cell_id("A5"));
+    assert_eq!(target(Jump::RowEnd, "A5"), cell_id("D5"));
+    assert_eq!(target(Jump::TableStart, "C5"), cell_id("A1"));
+    assert_eq!(target(Jump::TableEnd, "C5"), cell_id("D7"));
+    assert_eq!(target(Jump::PageUp(10), "C15"), cell_id("C5"));
+    assert_eq!(target(Jump::PageUp(10), "C5"), cell_id("C1"));
+    assert_eq!(target(Jump::PageDown(10), "C15"), cell_id("C25"));
+    assert_eq!(target(Jump::PageDown(10), "C45"), cell_id("C55"));
+    assert_eq!(target(Jump::Step(Direction::Down), "C50"), cell_id("C51"));
+
+    assert_eq!(
+      Jump::RowEnd.target(
+        &HashMap::<CellId, String>::new(),
+        cell_id("C5"),
+        Size::default()
+      ),
+      cell_id("A5")
+    );
+  }
+}
@@ -0,0 +1,114 @@
+//! Messages shown to the user: transient toasts for the information, e.g., a loaded plugin,
+//! and a banner of the errors, e.g., a failed paste, kept until it's dismissed.
+
+/// How long a toast is shown, in milliseconds.
+pub const TOAST_MILLIS: i32 = 4000;
+const MAX_TOASTS: usize = 3;
+const MAX_ERRORS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+  pub id: usize,
+  pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Notifications {
+  toasts: Vec<Toast>,
+  errors: Vec<String>,
+  next_id: usize,
+  // the toasts waiting for their timers to be started
+  unscheduled: Vec<usize>,
+}
+
+impl Notifications {
+  /// Shows a toast, dropping the oldest one if there are too many.
+  pub fn info(&mut self, message: impl Into<String>) {
+    let id = self.next_id;
+    self.next_id += 1;
+    self.toasts.push(Toast {
+      id,
+      message: message.into(),
+    });
+    self.unscheduled.push(id);
+    if self.toasts.len() > MAX_TOASTS {
+      self.toasts.remove(0);
+    }
+  }
+
+  /// Adds the error to the banner, unless it repeats the last one.
+  pub fn error(&mut self, message: impl Into<String>) {
+    let message = message.into();
+    if self.errors.last() != Some(&message) {
+      self.errors.push(message);
+    }
+    if self.errors.len() > MAX_ERRORS {
+      self.errors.remove(0);
+    }
+  }
+
+  pub fn expire(&mut self, id: usize) {
+    self.toasts.retain(|toast| toast.id != id);
+  }
+
+  pub fn dismiss_errors(&mut self) {
+    self.errors.clear();
+  }
+
+  pub fn toasts(&self) -> &[Toast] {
+    &self.toasts
+  }
+
+  /// Returns the errors, the oldest first.
+  pub fn errors(&self) -> &[String] {
+    &self.errors
+  }
+
+  /// Returns the ids of the toasts shown since the last call, to expire them after `TOAST_MILLIS`.
+  pub fn take_unscheduled(&mut self) -> Vec<usize> {
+    std::mem::take(&mut self.unscheduled)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn notifications_test() {
+    let mut notifications = Notifications::default();
+    for i in 0..4 {
+      notifications.info(format!("toast {i}"));
+    }
+    let messages = |notifications: &Notifications| {
+      notifications
+        .toasts()
+        .iter()
+        .map(|toast| toast.message.clone())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(messages(&notifications), ["toast 1", "toast 2", "toast 3"]);
+    assert_eq!(notifications.take_unscheduled(), [0, 1, 2, 3]);
+    assert!(notifications.take_unscheduled().is_empty());
+
+    notifications.expire(2);
+    notifications.expire(0);
+    assert_eq!(messages(&notifications), ["toast 1", "toast 3"]);
+
+    notifications.error("cannot access clipboard");
+    notifications.error("cannot access clipboard");
+    notifications.error("cannot paste");
+    assert_eq!(
+      notifications.errors(),
+      ["cannot access clipboard", "cannot paste"]
+    );
+    for i in 0..MAX_ERRORS {
+      notifications.error(format!("error {i}"));
+    }
+    assert_eq!(notifications.errors().len(), MAX_ERRORS);
+    assert_eq!(notifications.errors()[0], "error 0");
+
+    notifications.dismiss_errors();
+    assert!(notifications.errors().is_empty());
+  }
+}
@@ -0,0 +1,52 @@
+//! Fuzzy search of the commands listed in the Ctrl+K command palette.
+
+/// Returns the score of the text matching the query fuzzily, i.e., containing the characters
+/// of the query in order, case-insensitively, or `None` if it doesn't match. Lower scores
+/// are better: they count the characters skipped before and between the matched ones.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+  let mut chars = text.chars().flat_map(char::to_lowercase).enumerate();
+  let mut score = 0;
+  let mut next = 0;
+  for query_ch in query.chars().flat_map(char::to_lowercase) {
+    if query_ch.is_whitespace() {
+      continue;
+    }
+
+    let (i, _) = chars.find(|(_, ch)| *ch == query_ch)?;
+    score += i - next;
+    next = i + 1;
+  }
+
+  Some(score)
+}
+
+/// Returns the indices of the titles matching the query, the best matches first.
+pub fn search(titles: &[impl AsRef<str>], query: &str) -> Vec<usize> {
+  let mut found = titles
+    .iter()
+    .enumerate()
+    .filter_map(|(i, title)| Some((fuzzy_score(query, title.as_ref())?, i)))
+    .collect::<Vec<_>>();
+  found.sort();
+  found.into_iter().map(|(_, i)| i).collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn fuzzy_search_test() {
+    assert_eq!(fuzzy_score("", "Undo"), Some(0));
+    assert_eq!(fuzzy_score("undo", "Undo"), Some(0));
+    assert_eq!(fuzzy_score("ir", "Insert Row Above"), Some(3));
+    assert_eq!(fuzzy_score("ins row", "Insert Row Above"), Some(4));
+    assert_eq!(fuzzy_score("xyz", "Undo"), None);
+    assert_eq!(fuzzy_score("ou", "Undo"), None);
+
+    let titles = ["Copy All", "Paste All", "Sort Ascending", "Clear Filters"];
+    assert_eq!(search(&titles, "all"), vec![0, 1]);
+    assert_eq!(search(&titles, "ca"), vec![3, 0]);
+    assert_eq!(search(&titles, ""), vec![0, 1, 2, 3]);
+  }
+}
@@ -0,0 +1,126 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::modal::*;
+use crate::palette::search;
+
+#[derive(PartialEq, Properties)]
+pub struct PaletteModalProps {
+  /// The titles of the commands, with their shortcuts.
+  pub commands: Vec<String>,
+  pub is_visible: bool,
+  pub onclose: Callback<()>,
+  /// Runs the command with the index in `commands`.
+  pub onselect: Callback<usize>,
+}
+
+#[function_component]
+pub fn PaletteModal(props: &PaletteModalProps) -> Html {
+  let query = use_state(String::new);
+  // the position of the highlighted command among the found ones
+  let active = use_state(|| 0);
+  let input_ref = use_node_ref();
+
+  {
+    let input_ref = input_ref.clone();
+    let query = query.clone();
+    let active = active.clone();
+    use_effect_with_deps(
+      move |is_visible| {
+        if *is_visible {
+          query.set(String::new());
+          active.set(0);
+          if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+            let _ = input.focus();
+          }
+        }
+      },
+      props.is_visible,
+    );
+  }
+
+  let found = search(&props.commands, &query);
+  let select = {
+    let parent_onclose = props.onclose.clone();
+    let parent_onselect = props.onselect.clone();
+
+    move |index: usize| {
+      parent_onclose.emit(());
+      parent_onselect.emit(index);
+    }
+  };
+
+  let oninput = {
+    let query = query.clone();
+    let active = active.clone();
+
+    Callback::from(move |ev: InputEvent| {
+      let input: HtmlInputElement = ev.target_unchecked_into();
+      query.set(input.value());
+      active.set(0);
+    })
+  };
+
+  let onkeydown = {
+    let found = found.clone();
+    let active = active.clone();
+    let select = select.clone();
+    let parent_onclose = props.onclose.clone();
+
+    Callback::from(move |ev: KeyboardEvent| match ev.key().as_str() {
+      "ArrowDown" => {
+        ev.prevent_default();
+        active.set((*active + 1).min(found.len().saturating_sub(1)));
+      }
+      "ArrowUp" => {
+        ev.prevent_default();
+        active.set(active.saturating_sub(1));
+      }
+      "Enter" => {
+        if let Some(index) = found.get(*active) {
+          select(*index);
+        }
+      }
+      "Escape" => parent_onclose.emit(()),
+      _ => (),
+    })
+  };
+
+  html! {
+    <Modal title="Commands" is_visible={props.is_visible} onclose={props.onclose.clone()}>
+      <input
+        ref={ input_ref }
+        type="text"
+        placeholder="Type to search, e.g., ins row"
+        class="w-full px-2 py-0.5 mb-2 outline-none font-mono bg-indigo-800"
+        value={ (*query).clone() }
+        {oninput}
+        {onkeydown}
+      />
+      <ul class="flex flex-col max-h-[24rem] overflow-y-auto text-base">
+        {
+          found.iter().enumerate().map(|(position, index)| {
+            let index = *index;
+            let select = select.clone();
+
+            html! {
+              <li
+                class={classes!(vec![
+                  "px-2 py-1 cursor-pointer hover:bg-violet-700",
+                  if position == *active { "bg-violet-700" } else { "" },
+                ])}
+                // commands opening popups shouldn't be closed by the click
+                onclick={ Callback::from(move |ev: MouseEvent| {
+                  ev.stop_propagation();
+                  select(index);
+                }) }
+              >
+                { props.commands[index].clone() }
+              </li>
+            }
+          }).collect::<Html>()
+        }
+      </ul>
+    </Modal>
+  }
+}
@@ -1,36 +1,95 @@
 use regex::Regex;
 use std::collections::VecDeque;
+use std::ops::Range;
 
 use crate::cell_id::CellId;
-use crate::expr::{Expr, Op};
+use crate::date;
+use crate::error::ErrorKind;
+use crate::expr::{Expr, NegPrecedence, Op};
+use crate::func::Func;
+use crate::selection::Selection;
 
 pub fn parse(input: &str) -> Result<Expr, String> {
-  if input.trim().starts_with('=') {
-    let tokens = shunting_yard(input.trim().trim_start_matches('='))?;
+  parse_with(input, NegPrecedence::default())
+}
+
+/// Same as `parse`, but allows choosing how unary minus binds relative to `^`.
+pub fn parse_with(input: &str, neg_precedence: NegPrecedence) -> Result<Expr, String> {
+  if input.trim().is_empty() {
+    Ok(Expr::Empty)
+  } else if input.trim().starts_with('=') {
+    let tokens = shunting_yard(input.trim().trim_start_matches('='), neg_precedence)?;
     to_ast(&tokens)
   } else {
     match input.trim().parse::<f64>() {
       Ok(n) => Ok(Expr::Num(n)),
-      Err(_) => Ok(Expr::Str(input.into())),
+      Err(_) => match date::parse(input) {
+        Some(serial) => Ok(Expr::Date(serial)),
+        None if input.trim().eq_ignore_ascii_case("true") => Ok(Expr::Bool(true)),
+        None if input.trim().eq_ignore_ascii_case("false") => Ok(Expr::Bool(false)),
+        None => Ok(Expr::Str(input.into())),
+      },
     }
   }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum Token {
+enum Token<'a> {
   Op(Op),
   Num(f64),
+  // a string literal, including the surrounding quotes
+  Str(&'a str),
   CellRef(CellId),
+  RefError,
+  Range(CellId, CellId),
+  // a function name on the operator stack, waiting for its arguments
+  Func(Func),
+  // a function application with the number of arguments in the output
+  Call(Func, usize),
   LeftParen,
+  // the following tokens are only used for `prev_token` and never reach the output
+  RightParen,
+  Comma,
+}
+
+/// Constants, such as `PI`, can be used without parentheses; other functions cannot.
+/// Replaces the function on the top of the operator stack with its call.
+fn call_constant<'a>(
+  func: Func,
+  ops: &mut Vec<Token<'a>>,
+  output: &mut VecDeque<Token<'a>>,
+) -> Result<Token<'a>, String> {
+  if !func.is_constant() {
+    return Err(format!(
+      "function {} should be followed by `(`",
+      func.name()
+    ));
+  }
+
+  ops.pop();
+  let token = Token::Call(func, 0);
+  output.push_back(token);
+  Ok(token)
 }
 
-fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
+fn shunting_yard(
+  input: &str,
+  neg_precedence: NegPrecedence,
+) -> Result<VecDeque<Token<'_>>, String> {
   let mut output = VecDeque::new();
   let mut ops = Vec::new();
+  // for each currently open parenthesis, stores `Some(commas_count)` if it's a function call's one
+  let mut parens: Vec<Option<usize>> = Vec::new();
 
   // used to differentiate negation & subtraction
   let mut prev_token = None;
   for lexem in lex(input) {
+    if let Some(Token::Func(func)) = prev_token {
+      if lexem != "(" {
+        prev_token = Some(call_constant(func, &mut ops, &mut output)?);
+      }
+    }
+
     if let Ok(num) = lexem.parse::<f64>() {
       let token = Token::Num(num);
       prev_token = Some(token);
@@ -42,11 +101,12 @@ fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
       // convert Sub to Neg if it's:
       // - the very start of the input (such as `-15` or `-B5`)
       // - right after the left parenthesis or binary op token (such as `14 - (- 8)` - the 1st is Sub, the 2nd is Neg)
+      // - right after a comma separating function arguments (such as `MAX(1, -A2)`)
       let is_negation = op == Op::Sub
         && match prev_token {
           None => true,
           Some(Token::Op(op)) if op != Op::Neg => true,
-          Some(Token::LeftParen) => true,
+          Some(Token::LeftParen) | Some(Token::Comma) => true,
           Some(_) => false,
         };
       let op = if is_negation { Op::Neg } else { op };
@@ -61,8 +121,11 @@ fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
           Token::Op(top_stack_op_inner) => {
             // push operators with greater precedence
             // or same precedence, but when the current operator is left-associative, to the output
-            if top_stack_op_inner.precedence() > op.precedence()
-              || (op.is_left_associative() && top_stack_op_inner.precedence() == op.precedence())
+            let top_precedence = top_stack_op_inner.precedence_with(neg_precedence);
+            let precedence = op.precedence_with(neg_precedence);
+
+            if top_precedence > precedence
+              || (op.is_left_associative() && top_precedence == precedence)
             {
               output.push_back(top_stack_op);
             } else {
@@ -71,9 +134,9 @@ fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
             }
           }
           _ => {
-            return Err(
-              format!("impossible token `{top_stack_op:?}` found on the operator stack").into(),
-            )
+            return Err(format!(
+              "impossible token `{top_stack_op:?}` found on the operator stack"
+            ))
           }
         }
       }
@@ -86,30 +149,117 @@ fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
 
     match lexem {
       "(" => {
+        parens.push(match prev_token {
+          Some(Token::Func(_)) => Some(0),
+          _ => None,
+        });
+
         let token = Token::LeftParen;
         prev_token = Some(token);
         ops.push(token);
       }
-      ")" => loop {
-        match ops.pop() {
-          Some(top_stack_op) => match top_stack_op {
-            Token::LeftParen => break,
+      "," => {
+        match parens.last_mut() {
+          Some(Some(commas_count)) => *commas_count += 1,
+          _ => return Err("comma outside of function arguments".into()),
+        }
+
+        // flush the previous argument's operators
+        while let Some(top_stack_op) = ops.pop() {
+          match top_stack_op {
+            Token::LeftParen => {
+              ops.push(top_stack_op);
+              break;
+            }
             token => output.push_back(token),
-          },
+          }
+        }
+
+        if prev_token == Some(Token::LeftParen) || prev_token == Some(Token::Comma) {
+          return Err("empty function argument".into());
+        }
+        prev_token = Some(Token::Comma);
+      }
+      ")" => {
+        loop {
+          match ops.pop() {
+            Some(top_stack_op) => match top_stack_op {
+              Token::LeftParen => break,
+              token => output.push_back(token),
+            },
+            None => return Err("mismatched parenthesis".into()),
+          }
+        }
+
+        match parens.pop() {
+          Some(Some(commas_count)) => {
+            let args_count = match prev_token {
+              Some(Token::LeftParen) => 0,
+              Some(Token::Comma) => return Err("empty function argument".into()),
+              _ => commas_count + 1,
+            };
+
+            match ops.pop() {
+              Some(Token::Func(func)) => output.push_back(Token::Call(func, args_count)),
+              _ => return Err("function call without a function name".into()),
+            }
+          }
+          Some(None) => (),
           None => return Err("mismatched parenthesis".into()),
         }
-      },
-      other => match CellId::try_from(other) {
-        Ok(cell_id) => {
-          let token = Token::CellRef(cell_id);
+
+        prev_token = Some(Token::RightParen);
+      }
+      other => {
+        if let Ok(func) = Func::try_from(other) {
+          let token = Token::Func(func);
+          prev_token = Some(token);
+          ops.push(token);
+          continue;
+        }
+
+        if other == ErrorKind::Ref.code() {
+          let token = Token::RefError;
           prev_token = Some(token);
           output.push_back(token);
+          continue;
         }
-        Err(_) => return Err(format!("unknown lexem `{other}` in `{input}`").into()),
-      },
+
+        if other.starts_with('"') {
+          // complete string literals are matched by the lexer as a whole
+          if other.len() < 2 || !other.ends_with('"') {
+            return Err(format!(
+              "unterminated string literal `{other}` in `{input}`"
+            ));
+          }
+
+          let token = Token::Str(other);
+          prev_token = Some(token);
+          output.push_back(token);
+          continue;
+        }
+
+        let token = match other.split_once(':') {
+          Some((from, to)) => match (CellId::try_from(from.trim()), CellId::try_from(to.trim())) {
+            (Ok(from), Ok(to)) => Token::Range(from, to),
+            _ => return Err(format!("malformed range `{other}` in `{input}`")),
+          },
+          None => match CellId::try_from(other) {
+            Ok(cell_id) => Token::CellRef(cell_id),
+            Err(_) => return Err(format!("unknown lexem `{other}` in `{input}`")),
+          },
+        };
+
+        prev_token = Some(token);
+        output.push_back(token);
+      }
     }
   }
 
+  if let Some(Token::Func(func)) = prev_token {
+    call_constant(func, &mut ops, &mut output)?;
+  }
+
   while let Some(op) = ops.pop() {
     if op == Token::LeftParen {
       return Err("mismatched parenthesis".into());
@@ -122,29 +272,176 @@ fn shunting_yard(input: &str) -> Result<VecDeque<Token>, String> {
 }
 
 lazy_static! {
-  static ref SEP_RE: Regex = Regex::new(r"\s*(?P<op>[*+/()^-])\s*").unwrap();
+  static ref SEP_RE: Regex =
+    Regex::new(r#"\s*(?P<op>"(?:[^"]|"")*"|<=|>=|<>|[*+/()^,<>=-])\s*"#).unwrap();
 }
 
+/// Splits the input into lexems. String literals (such as `"a, ""b"""`) are matched
+/// as a whole, so that they are not split further.
 fn lex(input: &str) -> Vec<&str> {
+  lex_spans(input)
+    .into_iter()
+    .map(|(_, lexem)| lexem)
+    .collect()
+}
+
+/// Same as `lex`, but also returns the offset of each lexem in the input.
+fn lex_spans(input: &str) -> Vec<(usize, &str)> {
   let mut loc = 0;
   let mut res = vec![];
+  let trimmed = |start: usize, end: usize| {
+    let segment = &input[start..end];
+    (
+      start + segment.len() - segment.trim_start().len(),
+      segment.trim(),
+    )
+  };
 
   for sep in SEP_RE.find_iter(input) {
     if sep.start() > loc {
-      res.push(input[loc..sep.start()].trim());
+      res.push(trimmed(loc, sep.start()));
     }
     loc = sep.end();
 
-    res.push(sep.as_str().trim());
+    res.push(trimmed(sep.start(), sep.end()));
   }
 
   if loc < input.len() {
-    res.push(&input[loc..].trim())
+    res.push(trimmed(loc, input.len()));
+  }
+
+  res
+}
+
+/// The kinds of the lexems of a formula, e.g., to color them while the formula is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+  Op,
+  Num,
+  Str,
+  CellRef,
+  Func,
+  /// Parentheses and commas.
+  Punct,
+  Error,
+  /// Lexems the parser doesn't recognize, e.g., a function name being typed.
+  Unknown,
+}
+
+/// Returns the byte ranges of the lexems of a formula with their kinds, including the `=`
+/// starting it; other inputs aren't highlighted.
+pub fn highlight(input: &str) -> Vec<(Range<usize>, TokenKind)> {
+  let Some(eq) = input.find('=').filter(|_| input.trim().starts_with('=')) else {
+    return vec![];
+  };
+  let formula = &input[eq + 1..];
+
+  let kind = |lexem: &str| {
+    if lexem.starts_with('"') {
+      TokenKind::Str
+    } else if matches!(lexem, "(" | ")" | ",") {
+      TokenKind::Punct
+    } else if Op::try_from(lexem).is_ok() {
+      TokenKind::Op
+    } else if lexem.parse::<f64>().is_ok() {
+      TokenKind::Num
+    } else if Func::try_from(lexem).is_ok() {
+      TokenKind::Func
+    } else if lexem
+      .split(':')
+      .all(|cell_id| CellId::try_from(cell_id.trim()).is_ok())
+    {
+      TokenKind::CellRef
+    } else if lexem == ErrorKind::Ref.code() {
+      TokenKind::Error
+    } else {
+      TokenKind::Unknown
+    }
+  };
+
+  let mut spans = vec![(eq..eq + 1, TokenKind::Op)];
+  spans.extend(
+    lex_spans(formula)
+      .into_iter()
+      .filter(|(_, lexem)| !lexem.is_empty())
+      .map(|(start, lexem)| {
+        let start = eq + 1 + start;
+        (start..start + lexem.len(), kind(lexem))
+      }),
+  );
+  spans
+}
+
+/// Returns the byte ranges of the cell references and the ranges in a formula, with the cells
+/// they refer to, e.g., to paint them while the formula is typed.
+pub fn references(input: &str) -> Vec<(Range<usize>, Selection)> {
+  highlight(input)
+    .into_iter()
+    .filter(|(_, kind)| *kind == TokenKind::CellRef)
+    .filter_map(|(range, _)| {
+      let lexem = &input[range.clone()];
+      let (from, to) = lexem.split_once(':').unwrap_or((lexem, lexem));
+      let anchor = CellId::try_from(from.trim()).ok()?;
+      let extent = CellId::try_from(to.trim()).ok()?;
+      Some((range, Selection { anchor, extent }))
+    })
+    .collect()
+}
+
+/// Rewrites the cell references of a formula, e.g., to shift them when the formula is filled
+/// into other cells; references mapped to `None` become `#REF!`. Other inputs are kept as is.
+pub fn map_refs(input: &str, f: impl Fn(CellId) -> Option<CellId>) -> String {
+  let Some(eq) = input.find('=').filter(|_| input.trim().starts_with('=')) else {
+    return input.to_string();
+  };
+
+  let map_lexem = |lexem: &str| {
+    if Func::try_from(lexem).is_ok() {
+      return None;
+    }
+
+    let refs = match lexem.split_once(':') {
+      Some((from, to)) => vec![from.trim(), to.trim()],
+      None => vec![lexem],
+    };
+    let cell_ids = refs
+      .into_iter()
+      .map(CellId::try_from)
+      .collect::<Result<Vec<_>, _>>()
+      .ok()?;
+
+    let mapped = cell_ids
+      .into_iter()
+      .map(|cell_id| f(cell_id).map(|cell_id| cell_id.to_string()))
+      .collect::<Option<Vec<_>>>();
+    Some(mapped.map_or_else(|| String::from("#REF!"), |mapped| mapped.join(":")))
+  };
+
+  let formula = &input[eq + 1..];
+  let mut res = String::from(&input[..=eq]);
+  let mut loc = 0;
+  let push_lexem = |res: &mut String, segment: &str| {
+    let lexem = segment.trim();
+    match map_lexem(lexem) {
+      Some(mapped) if !lexem.is_empty() => res.push_str(&segment.replacen(lexem, &mapped, 1)),
+      _ => res.push_str(segment),
+    }
+  };
+  for sep in SEP_RE.find_iter(formula) {
+    push_lexem(&mut res, &formula[loc..sep.start()]);
+    res.push_str(sep.as_str());
+    loc = sep.end();
   }
+  push_lexem(&mut res, &formula[loc..]);
 
   res
 }
 
+/// Removes the surrounding quotes from a string literal and unescapes `""` inside it.
+fn unquote(literal: &str) -> String {
+  literal[1..literal.len() - 1].replace("\"\"", "\"")
+}
+
 fn to_ast(tokens: &VecDeque<Token>) -> Result<Expr, String> {
   let empty_stack_op_msg = "empty stack when trying to build operator's AST";
   let mut stack = vec![];
@@ -152,7 +449,9 @@ fn to_ast(tokens: &VecDeque<Token>) -> Result<Expr, String> {
   for token in tokens {
     match token {
       Token::Num(num) => stack.push(Expr::Num(*num)),
+      Token::Str(literal) => stack.push(Expr::Str(unquote(literal))),
       Token::CellRef(cell_id) => stack.push(Expr::CellRef(*cell_id)),
+      Token::RefError => stack.push(Expr::RefError),
       Token::Op(Op::Neg) => {
         let arg = stack.pop().ok_or(empty_stack_op_msg)?;
         let op = Expr::Apply {
@@ -170,35 +469,129 @@ fn to_ast(tokens: &VecDeque<Token>) -> Result<Expr, String> {
         };
         stack.push(op);
       }
-      Token::LeftParen => {
-        return Err("encountered left parenthesis in the shunting yard output".into())
+      Token::Range(from, to) => stack.push(Expr::Range {
+        from: *from,
+        to: *to,
+      }),
+      Token::Call(func, args_count) => {
+        if stack.len() < *args_count {
+          return Err(format!(
+            "not enough arguments on the stack for {}",
+            func.name()
+          ));
+        }
+
+        let args = stack.split_off(stack.len() - args_count);
+        stack.push(Expr::Call { func: *func, args });
+      }
+      Token::LeftParen | Token::RightParen | Token::Comma | Token::Func(_) => {
+        return Err(format!("encountered {token:?} in the shunting yard output"))
       }
     }
   }
 
   match stack.pop() {
     Some(expr) => Ok(expr),
-    None => Err(format!("empty stack encountered when building AST for tokens {tokens:?}").into()),
+    None => Err(format!(
+      "empty stack encountered when building AST for tokens {tokens:?}"
+    )),
   }
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
   use super::*;
   use crate::expr::Expr;
   use crate::expr::Op::*;
 
+  #[test]
+  fn highlight_test() {
+    let input = " = SUM(A1:B2, 1.5) * \"a, b\" + fo + #REF!";
+    let highlighted = highlight(input)
+      .into_iter()
+      .map(|(range, kind)| (&input[range], kind))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      highlighted,
+      vec![
+        ("=", TokenKind::Op),
+        ("SUM", TokenKind::Func),
+        ("(", TokenKind::Punct),
+        ("A1:B2", TokenKind::CellRef),
+        (",", TokenKind::Punct),
+        ("1.5", TokenKind::Num),
+        (")", TokenKind::Punct),
+        ("*", TokenKind::Op),
+        ("\"a, b\"", TokenKind::Str),
+        ("+", TokenKind::Op),
+        ("fo", TokenKind::Unknown),
+        ("+", TokenKind::Op),
+        ("#REF!", TokenKind::Error),
+      ]
+    );
+    assert!(highlight("1 + 2").is_empty());
+
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    assert_eq!(
+      references("= A1 + SUM(B2:C3)"),
+      vec![
+        (2..4, Selection::new(cell_id("A1"))),
+        (
+          11..16,
+          Selection {
+            anchor: cell_id("B2"),
+            extent: cell_id("C3")
+          }
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn map_refs_test() {
+    let shift_down = |cell_id: CellId| {
+      Some(CellId {
+        row: cell_id.row + 1,
+        ..cell_id
+      })
+    };
+
+    assert_eq!(
+      map_refs("= A1 + SUM(B2:C3) * LOG10(2)", shift_down),
+      "= A02 + SUM(B03:C04) * LOG10(2)"
+    );
+    assert_eq!(
+      map_refs("=IF(A1, \"A1\", 0)", shift_down),
+      "=IF(A02, \"A1\", 0)"
+    );
+    assert_eq!(map_refs("A1", shift_down), "A1");
+    assert_eq!(
+      map_refs("= A1 + B1:B2", |cell_id| Some(cell_id)
+        .filter(|cell_id| cell_id.col != 'B')),
+      "= A01 + #REF!"
+    );
+    assert_eq!(
+      parse("= #REF! * 2").unwrap(),
+      Expr::Apply {
+        op: Mul,
+        args: vec![Expr::RefError, Expr::Num(2.0)],
+      }
+    );
+  }
+
   #[test]
   fn shunting_yard_test() {
     use Token::*;
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ 3").unwrap(),
+      shunting_yard("12 + 5 ^ 3", NegPrecedence::StrictMath).unwrap(),
       VecDeque::from(vec![Num(12.0), Num(5.0), Num(3.0), Op(Pow), Op(Add)])
     );
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ 3 - 8 / 2 * 3.5 + 6.5").unwrap(),
+      shunting_yard("12 + 5 ^ 3 - 8 / 2 * 3.5 + 6.5", NegPrecedence::StrictMath).unwrap(),
       VecDeque::from(vec![
         Num(12.0),
         Num(5.0),
@@ -217,12 +610,16 @@ mod tests {
     );
 
     assert_eq!(
-      shunting_yard("(12 + 5) ^ 3").unwrap(),
+      shunting_yard("(12 + 5) ^ 3", NegPrecedence::StrictMath).unwrap(),
       VecDeque::from(vec![Num(12.0), Num(5.0), Op(Add), Num(3.0), Op(Pow)])
     );
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ (3 - 8 / 2 * 3.5) + 6.5").unwrap(),
+      shunting_yard(
+        "12 + 5 ^ (3 - 8 / 2 * 3.5) + 6.5",
+        NegPrecedence::StrictMath
+      )
+      .unwrap(),
       VecDeque::from(vec![
         Num(12.0),
         Num(5.0),
@@ -241,21 +638,24 @@ mod tests {
     );
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ (3 - 8 / 2 * 3.5 + 6.5")
+      shunting_yard("12 + 5 ^ (3 - 8 / 2 * 3.5 + 6.5", NegPrecedence::StrictMath)
         .unwrap_err()
         .to_string(),
       "mismatched parenthesis"
     );
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ (3 - 8 / 2 * (3.5) + 6.5")
-        .unwrap_err()
-        .to_string(),
+      shunting_yard(
+        "12 + 5 ^ (3 - 8 / 2 * (3.5) + 6.5",
+        NegPrecedence::StrictMath
+      )
+      .unwrap_err()
+      .to_string(),
       "mismatched parenthesis"
     );
 
     assert_eq!(
-      shunting_yard("12 + 5 ^ 3 - 8 / 2 * 3.5) + 6.5")
+      shunting_yard("12 + 5 ^ 3 - 8 / 2 * 3.5) + 6.5", NegPrecedence::StrictMath)
         .unwrap_err()
         .to_string(),
       "mismatched parenthesis"
@@ -291,7 +691,10 @@ mod tests {
     use Expr::*;
 
     assert_eq!(parse("12"), Ok(Num(12.0)));
+    assert_eq!(parse("2024-01-01"), Ok(Date(45292.0)));
     assert_eq!(parse("yo"), Ok(Str("yo".to_string())));
+    assert_eq!(parse(""), Ok(Empty));
+    assert_eq!(parse("  "), Ok(Empty));
 
     assert_eq!(parse("A12"), Ok(Str("A12".to_string())));
     assert_eq!(parse("= A12"), Ok(CellRef(CellId { col: 'A', row: 12 })));
@@ -496,4 +899,222 @@ mod tests {
       })
     );
   }
+
+  #[test]
+  fn neg_precedence_test() {
+    use Expr::*;
+
+    assert_eq!(
+      shunting_yard("-2 ^ 2", NegPrecedence::StrictMath).unwrap(),
+      VecDeque::from(vec![
+        Token::Num(2.0),
+        Token::Num(2.0),
+        Token::Op(Pow),
+        Token::Op(Neg)
+      ])
+    );
+    assert_eq!(
+      shunting_yard("-2 ^ 2", NegPrecedence::Spreadsheet).unwrap(),
+      VecDeque::from(vec![
+        Token::Num(2.0),
+        Token::Op(Neg),
+        Token::Num(2.0),
+        Token::Op(Pow)
+      ])
+    );
+
+    // negation of the exponent is unaffected by the mode
+    for neg_precedence in [NegPrecedence::StrictMath, NegPrecedence::Spreadsheet] {
+      assert_eq!(
+        parse_with("= 2 ^ -A1", neg_precedence),
+        Ok(Apply {
+          op: Pow,
+          args: vec![
+            Num(2.0),
+            Apply {
+              op: Neg,
+              args: vec![CellRef(CellId { col: 'A', row: 1 })]
+            }
+          ]
+        })
+      );
+    }
+
+    assert_eq!(
+      parse_with("= -A1 ^ 2 * 3", NegPrecedence::StrictMath),
+      Ok(Apply {
+        op: Mul,
+        args: vec![
+          Apply {
+            op: Neg,
+            args: vec![Apply {
+              op: Pow,
+              args: vec![CellRef(CellId { col: 'A', row: 1 }), Num(2.0)]
+            }]
+          },
+          Num(3.0)
+        ]
+      })
+    );
+    assert_eq!(
+      parse_with("= -A1 ^ 2 * 3", NegPrecedence::Spreadsheet),
+      Ok(Apply {
+        op: Mul,
+        args: vec![
+          Apply {
+            op: Pow,
+            args: vec![
+              Apply {
+                op: Neg,
+                args: vec![CellRef(CellId { col: 'A', row: 1 })]
+              },
+              Num(2.0)
+            ]
+          },
+          Num(3.0)
+        ]
+      })
+    );
+  }
+
+  #[test]
+  fn functions_parse_test() {
+    use crate::func::Func::*;
+    use Expr::*;
+
+    let a1 = CellId { col: 'A', row: 1 };
+    let b5 = CellId { col: 'B', row: 5 };
+
+    assert_eq!(
+      parse("= SUM(A1:B5)"),
+      Ok(Call {
+        func: Sum,
+        args: vec![Range { from: a1, to: b5 }]
+      })
+    );
+
+    assert_eq!(
+      parse("= max(A1 : B5, -A1, 2 * (3 - 1)) - COUNT()"),
+      Ok(Apply {
+        op: Sub,
+        args: vec![
+          Call {
+            func: Max,
+            args: vec![
+              Range { from: a1, to: b5 },
+              Apply {
+                op: Neg,
+                args: vec![CellRef(a1)]
+              },
+              Apply {
+                op: Mul,
+                args: vec![
+                  Num(2.0),
+                  Apply {
+                    op: Sub,
+                    args: vec![Num(3.0), Num(1.0)]
+                  }
+                ]
+              }
+            ]
+          },
+          Call {
+            func: Count,
+            args: vec![]
+          }
+        ]
+      })
+    );
+
+    assert_eq!(
+      parse("= AVERAGE(MIN(A1, 2), B5)"),
+      Ok(Call {
+        func: Average,
+        args: vec![
+          Call {
+            func: Min,
+            args: vec![CellRef(a1), Num(2.0)]
+          },
+          CellRef(b5)
+        ]
+      })
+    );
+
+    assert!(parse("= SUM").is_err());
+    assert!(parse("= SUM A1").is_err());
+    assert!(parse("= SUM(A1,)").is_err());
+    assert!(parse("= SUM(, A1)").is_err());
+    assert!(parse("= 1, 2").is_err());
+    assert!(parse("= SUM(A1:B)").is_err());
+    assert!(parse("= FOO(A1)").is_err());
+  }
+
+  #[test]
+  fn comparison_parse_test() {
+    use Expr::*;
+
+    assert_eq!(
+      parse("= A1 + 1 >= -2 * B1"),
+      Ok(Apply {
+        op: GreaterOrEq,
+        args: vec![
+          Apply {
+            op: Add,
+            args: vec![CellRef(CellId { col: 'A', row: 1 }), Num(1.0)]
+          },
+          Apply {
+            op: Mul,
+            args: vec![
+              Apply {
+                op: Neg,
+                args: vec![Num(2.0)]
+              },
+              CellRef(CellId { col: 'B', row: 1 })
+            ]
+          }
+        ]
+      })
+    );
+
+    assert_eq!(
+      parse("=1<>2"),
+      Ok(Apply {
+        op: NotEqual,
+        args: vec![Num(1.0), Num(2.0)]
+      })
+    );
+  }
+
+  #[test]
+  fn string_literal_parse_test() {
+    use crate::func::Func::*;
+    use Expr::*;
+
+    assert_eq!(parse(r#"= "a, (b)""#), Ok(Str("a, (b)".to_string())));
+    assert_eq!(
+      parse(r#"= "say ""hi""" = A1"#),
+      Ok(Apply {
+        op: Equal,
+        args: vec![
+          Str(r#"say "hi""#.to_string()),
+          CellRef(CellId { col: 'A', row: 1 })
+        ]
+      })
+    );
+    assert_eq!(
+      parse(r#"= COUNTIF(A1:A5, ">=5")"#),
+      Ok(Call {
+        func: CountIf,
+        args: vec![
+          Range {
+            from: CellId { col: 'A', row: 1 },
+            to: CellId { col: 'A', row: 5 }
+          },
+          Str(">=5".to_string())
+        ]
+      })
+    );
+
+    assert!(parse(r#"= "unterminated"#).is_err());
+  }
 }
@@ -14,7 +14,7 @@ pub struct PasteModalProps {
 
 #[function_component]
 pub fn PasteModal(props: &PasteModalProps) -> Html {
-  let value = use_state(|| String::new());
+  let value = use_state(String::new);
 
   let oninput = {
     let value = value.clone();
@@ -0,0 +1,41 @@
+use yew::prelude::*;
+
+use crate::btn::*;
+use crate::clipboard::PasteMode;
+use crate::modal::*;
+
+#[derive(PartialEq, Properties)]
+pub struct PasteSpecialModalProps {
+  pub onpaste: Callback<PasteMode>,
+  pub is_visible: bool,
+  pub onclose: Callback<()>,
+}
+
+#[function_component]
+pub fn PasteSpecialModal(props: &PasteSpecialModalProps) -> Html {
+  let btn = |title: &str, mode: PasteMode| {
+    let parent_onpaste = props.onpaste.clone();
+    let parent_onclose = props.onclose.clone();
+
+    html! {
+      <Btn
+        title={ title.to_string() }
+        color={ BtnColors::Violet }
+        onclick={ Callback::from(move |_ev: MouseEvent| {
+          parent_onclose.emit(());
+          parent_onpaste.emit(mode);
+        })}
+      />
+    }
+  };
+
+  html! {
+    <Modal title="Paste Special" is_visible={props.is_visible} onclose={props.onclose.clone()}>
+      <div class="flex gap-4">
+        { btn("Values Only", PasteMode::Values) }
+        { btn("Formulas Only", PasteMode::Formulas) }
+        { btn("Transpose", PasteMode::Transpose) }
+      </div>
+    </Modal>
+  }
+}
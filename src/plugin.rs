@@ -0,0 +1,184 @@
+//! Formula functions loaded at runtime from WebAssembly modules. A plugin is described
+//! by a JSON manifest, e.g.:
+//!
+//! ```json
+//! {
+//!   "module": "stats.wasm",
+//!   "functions": [
+//!     { "name": "ZSCORE", "export": "zscore", "args": 3 },
+//!     { "name": "SIGMOID", "args": 1 }
+//!   ]
+//! }
+//! ```
+//!
+//! The module path is relative to the manifest URL, and `export` defaults to the lower case
+//! function name. Exported functions take and return `f64`s; they are registered
+//! as custom functions in `crate::registry`.
+use js_sys::{Array, Function, Object, Reflect, Uint8Array, WebAssembly};
+use serde::Deserialize;
+use std::error::Error;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+use crate::registry::register_function;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Manifest {
+  pub module: String,
+  pub functions: Vec<PluginFunction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PluginFunction {
+  pub name: String,
+  #[serde(default)]
+  pub export: Option<String>,
+  /// Number of arguments of the exported function.
+  pub args: usize,
+}
+
+impl PluginFunction {
+  pub fn export_name(&self) -> String {
+    self
+      .export
+      .clone()
+      .unwrap_or_else(|| self.name.to_ascii_lowercase())
+  }
+}
+
+/// An exported WebAssembly function. JS values cannot be shared between threads,
+/// but the app runs in a single browser thread.
+struct Export(Function);
+
+unsafe impl Send for Export {}
+unsafe impl Sync for Export {}
+
+pub fn parse_manifest(json: &str) -> Result<Manifest, Box<dyn Error>> {
+  let manifest: Manifest = serde_json::from_str(json)?;
+
+  if manifest.functions.is_empty() {
+    return Err("plugin manifest doesn't list any functions".into());
+  }
+  Ok(manifest)
+}
+
+/// Resolves the module path relative to the manifest URL, unless it's absolute.
+pub fn module_url(manifest_url: &str, module: &str) -> String {
+  if module.contains("://") || module.starts_with('/') {
+    return module.to_string();
+  }
+
+  match manifest_url.rfind('/') {
+    Some(idx) => format!("{}{module}", &manifest_url[..=idx]),
+    None => module.to_string(),
+  }
+}
+
+/// Fetches the manifest and the module, and registers the plugin functions;
+/// returns the names of the registered functions.
+pub async fn load(manifest_url: &str) -> Result<Vec<String>, Box<dyn Error>> {
+  let manifest = fetch(manifest_url).await?;
+  let text = JsFuture::from(manifest.text().map_err(js_error)?)
+    .await
+    .map_err(js_error)?;
+  let manifest = parse_manifest(&text.as_string().unwrap_or_default())?;
+
+  let module = fetch(&module_url(manifest_url, &manifest.module)).await?;
+  let bytes = JsFuture::from(module.array_buffer().map_err(js_error)?)
+    .await
+    .map_err(js_error)?;
+  let bytes = Uint8Array::new(&bytes).to_vec();
+  let instantiated = JsFuture::from(WebAssembly::instantiate_buffer(&bytes, &Object::new()))
+    .await
+    .map_err(js_error)?;
+  let instance: WebAssembly::Instance = Reflect::get(&instantiated, &"instance".into())
+    .map_err(js_error)?
+    .dyn_into()
+    .map_err(js_error)?;
+  let exports = instance.exports();
+
+  let mut names = vec![];
+  for func in &manifest.functions {
+    let export_name = func.export_name();
+    let export: Function = Reflect::get(&exports, &export_name.as_str().into())
+      .map_err(js_error)?
+      .dyn_into()
+      .map_err(|_| format!("plugin module doesn't export function `{export_name}`"))?;
+    let export = Export(export);
+
+    register_function(&func.name, func.args..=func.args, move |args| {
+      let nums = args
+        .iter()
+        .map(|arg| arg.as_num().map(JsValue::from_f64))
+        .collect::<Result<Array, _>>()?;
+
+      export
+        .0
+        .apply(&JsValue::NULL, &nums)
+        .map_err(js_error)?
+        .as_f64()
+        .map(Value::Num)
+        .ok_or_else(|| format!("`{export_name}` didn't return a number").into())
+    })?;
+    names.push(func.name.to_ascii_uppercase());
+  }
+
+  Ok(names)
+}
+
+async fn fetch(url: &str) -> Result<Response, Box<dyn Error>> {
+  let window = web_sys::window().ok_or("plugins can only be loaded in a browser")?;
+  let response: Response = JsFuture::from(window.fetch_with_str(url))
+    .await
+    .map_err(js_error)?
+    .dyn_into()
+    .map_err(js_error)?;
+
+  if !response.ok() {
+    return Err(format!("couldn't fetch {url}: {}", response.status()).into());
+  }
+  Ok(response)
+}
+
+fn js_error(err: JsValue) -> Box<dyn Error> {
+  format!("{err:?}").into()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn manifest_test() {
+    let manifest = parse_manifest(
+      r#"{
+        "module": "stats.wasm",
+        "functions": [
+          { "name": "ZSCORE", "export": "zscore_f64", "args": 3 },
+          { "name": "Sigmoid", "args": 1 }
+        ]
+      }"#,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.module, "stats.wasm");
+    assert_eq!(manifest.functions[0].export_name(), "zscore_f64");
+    assert_eq!(manifest.functions[1].export_name(), "sigmoid");
+    assert_eq!(manifest.functions[1].args, 1);
+
+    assert!(parse_manifest(r#"{ "module": "stats.wasm", "functions": [] }"#).is_err());
+    assert!(parse_manifest(r#"{ "functions": [{ "name": "F", "args": 1 }] }"#).is_err());
+
+    assert_eq!(
+      module_url("https://example.com/plugins/stats.json", "stats.wasm"),
+      "https://example.com/plugins/stats.wasm"
+    );
+    assert_eq!(module_url("stats.json", "stats.wasm"), "stats.wasm");
+    assert_eq!(
+      module_url("https://example.com/plugins/stats.json", "/wasm/stats.wasm"),
+      "/wasm/stats.wasm"
+    );
+  }
+}
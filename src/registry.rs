@@ -0,0 +1,166 @@
+//! Custom formula functions, which embedders of the crate can register in addition to
+//! the built-in ones, e.g.:
+//!
+//! ```
+//! use cells::registry::register_function;
+//! use cells::value::Value;
+//!
+//! register_function("DOUBLE", 1..=1, |args| Ok(Value::Num(args[0].as_num()? * 2.0))).unwrap();
+//! ```
+//!
+//! After that, `= DOUBLE(A1)` can be used in formulas. Custom functions get their arguments
+//! evaluated, with ranges evaluated to arrays.
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, RwLock};
+
+use crate::cell_id::CellId;
+use crate::func::Func;
+use crate::value::Value;
+
+pub type CustomFn = dyn Fn(&[Value]) -> Result<Value, Box<dyn Error>> + Send + Sync;
+
+/// The upper case name of a registered custom function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FuncName(&'static str);
+
+impl FuncName {
+  pub fn as_str(&self) -> &'static str {
+    self.0
+  }
+}
+
+impl Serialize for FuncName {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.0)
+  }
+}
+
+/// Only names of registered functions can be deserialized.
+impl<'de> Deserialize<'de> for FuncName {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    get(&name)
+      .map(|func| func.name)
+      .ok_or_else(|| serde::de::Error::custom(format!("`{name}` is not a registered function")))
+  }
+}
+
+/// A registered custom function.
+pub struct CustomFunction {
+  pub name: FuncName,
+  /// Allowed numbers of arguments.
+  pub arity: RangeInclusive<usize>,
+  pub f: Arc<CustomFn>,
+}
+
+lazy_static! {
+  static ref REGISTRY: RwLock<HashMap<&'static str, Arc<CustomFunction>>> =
+    RwLock::new(HashMap::new());
+}
+
+/// Registers a custom function, or replaces the previously registered function with
+/// the same name. Names are case-insensitive, and cannot clash with built-in functions
+/// or cell ids.
+pub fn register_function(
+  name: &str,
+  arity: RangeInclusive<usize>,
+  f: impl Fn(&[Value]) -> Result<Value, Box<dyn Error>> + Send + Sync + 'static,
+) -> Result<(), String> {
+  let name = name.to_ascii_uppercase();
+
+  if name.is_empty()
+    || !name.starts_with(|c: char| c.is_ascii_alphabetic())
+    || !name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+  {
+    return Err(format!("`{name}` is not a valid function name"));
+  }
+  if CellId::try_from(name.as_str()).is_ok() {
+    return Err(format!("`{name}` is a cell id, not a function name"));
+  }
+  if matches!(Func::try_from(name.as_str()), Ok(func) if !matches!(func, Func::Custom(_))) {
+    return Err(format!("`{name}` is a built-in function"));
+  }
+
+  let mut registry = REGISTRY.write().unwrap_or_else(|err| err.into_inner());
+  // names are leaked, so that `Func` can stay `Copy`; they are only leaked once per name
+  let name = match registry.get_key_value(name.as_str()) {
+    Some((name, _)) => *name,
+    None => Box::leak(name.into_boxed_str()),
+  };
+
+  registry.insert(
+    name,
+    Arc::new(CustomFunction {
+      name: FuncName(name),
+      arity,
+      f: Arc::new(f),
+    }),
+  );
+  Ok(())
+}
+
+/// Returns the registered custom function with the name, ignoring case.
+pub fn get(name: &str) -> Option<Arc<CustomFunction>> {
+  let registry = REGISTRY.read().unwrap_or_else(|err| err.into_inner());
+  registry.get(name.to_ascii_uppercase().as_str()).cloned()
+}
+
+/// Returns the names of all the registered custom functions.
+pub fn names() -> Vec<&'static str> {
+  let registry = REGISTRY.read().unwrap_or_else(|err| err.into_inner());
+  registry.keys().copied().collect()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::expr::EvalContext;
+  use crate::parser::parse;
+
+  #[test]
+  fn register_function_test() {
+    register_function("Hypot", 2..=2, |args| {
+      Ok(Value::Num(args[0].as_num()?.hypot(args[1].as_num()?)))
+    })
+    .unwrap();
+    register_function("COUNT_ITEMS", 0..=usize::MAX, |args| {
+      let count = args
+        .iter()
+        .map(|arg| match arg {
+          Value::Array(rows) => rows.iter().map(Vec::len).sum(),
+          _ => 1,
+        })
+        .sum::<usize>();
+      Ok(Value::Num(count as f64))
+    })
+    .unwrap();
+
+    let ctx = HashMap::from([(CellId::try_from("A1").unwrap(), Value::Num(3.0))]);
+    let eval = |input: &str| {
+      parse(input)?
+        .eval(&EvalContext::new(&ctx))
+        .map_err(|err| err.to_string())
+    };
+
+    assert_eq!(eval("= HYPOT(A1, 4)"), Ok(Value::Num(5.0)));
+    assert_eq!(eval("= hypot(5, 12) + 1"), Ok(Value::Num(14.0)));
+    assert_eq!(eval("= COUNT_ITEMS(A1:B3, 1, \"a\")"), Ok(Value::Num(8.0)));
+    assert_eq!(eval("= COUNT_ITEMS()"), Ok(Value::Num(0.0)));
+    assert!(eval("= HYPOT(1)").is_err());
+    assert!(eval("= HYPOT(\"a\", 1)").is_err());
+
+    // re-registering replaces the function
+    register_function("hypot", 2..=2, |_| Ok(Value::Num(0.0))).unwrap();
+    assert_eq!(eval("= HYPOT(A1, 4)"), Ok(Value::Num(0.0)));
+
+    assert!(register_function("SUM", 1..=1, |_| Ok(Value::Num(0.0))).is_err());
+    assert!(register_function("B12", 1..=1, |_| Ok(Value::Num(0.0))).is_err());
+    assert!(register_function("MY FN", 1..=1, |_| Ok(Value::Num(0.0))).is_err());
+    assert!(parse("= NOT_REGISTERED(1)").is_err());
+  }
+}
@@ -0,0 +1,202 @@
+//! A rectangle of selected cells, spanned by the cell where the selection started
+//! and the cell it was extended to with Shift+Click, Shift+Arrow, or by dragging.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::cell_id::{CellId, Size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+  /// The cell where the selection started, which stays focused.
+  pub anchor: CellId,
+  /// The opposite corner of the selection.
+  pub extent: CellId,
+}
+
+impl Selection {
+  /// Selects a single cell.
+  pub fn new(cell_id: CellId) -> Self {
+    Selection {
+      anchor: cell_id,
+      extent: cell_id,
+    }
+  }
+
+  /// Selects the whole column of the table of the `size`.
+  pub fn col(col: char, size: Size) -> Self {
+    Selection {
+      anchor: CellId { col, row: 1 },
+      extent: CellId {
+        col,
+        row: size.rows,
+      },
+    }
+  }
+
+  /// Selects the whole row of the table of the `size`.
+  pub fn row(row: usize, size: Size) -> Self {
+    Selection {
+      anchor: CellId { col: 'A', row },
+      extent: CellId {
+        col: size.last_col(),
+        row,
+      },
+    }
+  }
+
+  /// Selects the smallest rectangle containing all the non-empty cells,
+  /// or the whole table of the `size` if there are none.
+  pub fn used_range<T>(inputs: &HashMap<CellId, T>, size: Size) -> Self {
+    let cols = inputs.keys().map(|cell_id| cell_id.col);
+    let rows = inputs.keys().map(|cell_id| cell_id.row);
+
+    match (
+      cols.clone().min(),
+      cols.max(),
+      rows.clone().min(),
+      rows.max(),
+    ) {
+      (Some(min_col), Some(max_col), Some(min_row), Some(max_row)) => Selection {
+        anchor: CellId {
+          col: min_col,
+          row: min_row,
+        },
+        extent: CellId {
+          col: max_col,
+          row: max_row,
+        },
+      },
+      _ => Selection {
+        anchor: CellId { col: 'A', row: 1 },
+        extent: CellId {
+          col: size.last_col(),
+          row: size.rows,
+        },
+      },
+    }
+  }
+
+  /// Returns the top left and the bottom right corners.
+  pub fn bounds(&self) -> (CellId, CellId) {
+    let (anchor, extent) = (self.anchor, self.extent);
+    (
+      CellId {
+        col: anchor.col.min(extent.col),
+        row: anchor.row.min(extent.row),
+      },
+      CellId {
+        col: anchor.col.max(extent.col),
+        row: anchor.row.max(extent.row),
+      },
+    )
+  }
+
+  /// Returns the smallest selection containing both selections, with the anchor
+  /// at the same corner as in this selection.
+  pub fn union(&self, other: Selection) -> Selection {
+    let (top_left, bottom_right) = self.bounds();
+    let (other_top_left, other_bottom_right) = other.bounds();
+    let (min_col, max_col) = (
+      top_left.col.min(other_top_left.col),
+      bottom_right.col.max(other_bottom_right.col),
+    );
+    let (min_row, max_row) = (
+      top_left.row.min(other_top_left.row),
+      bottom_right.row.max(other_bottom_right.row),
+    );
+
+    let (anchor_col, extent_col) = if self.anchor.col == top_left.col {
+      (min_col, max_col)
+    } else {
+      (max_col, min_col)
+    };
+    let (anchor_row, extent_row) = if self.anchor.row == top_left.row {
+      (min_row, max_row)
+    } else {
+      (max_row, min_row)
+    };
+
+    Selection {
+      anchor: CellId {
+        col: anchor_col,
+        row: anchor_row,
+      },
+      extent: CellId {
+        col: extent_col,
+        row: extent_row,
+      },
+    }
+  }
+
+  pub fn contains(&self, cell_id: CellId) -> bool {
+    let (top_left, bottom_right) = self.bounds();
+    (top_left.col..=bottom_right.col).contains(&cell_id.col)
+      && (top_left.row..=bottom_right.row).contains(&cell_id.row)
+  }
+
+  /// Returns the selected cells row by row.
+  pub fn cells(&self) -> impl Iterator<Item = CellId> {
+    CellId::range(self.anchor, self.extent)
+  }
+
+  pub fn is_single_cell(&self) -> bool {
+    self.anchor == self.extent
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn selection_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+    let selection = Selection {
+      anchor: cell_id("C3"),
+      extent: cell_id("B1"),
+    };
+
+    assert_eq!(selection.bounds(), (cell_id("B1"), cell_id("C3")));
+    assert!(selection.contains(cell_id("B2")));
+    assert!(selection.contains(cell_id("C3")));
+    assert!(!selection.contains(cell_id("A2")));
+    assert!(!selection.contains(cell_id("C4")));
+    assert_eq!(selection.cells().count(), 6);
+    assert!(!selection.is_single_cell());
+    assert!(Selection::new(cell_id("A1")).is_single_cell());
+
+    let union = selection.union(Selection::new(cell_id("A1")));
+    assert_eq!(union.anchor, cell_id("C3"));
+    assert_eq!(union.bounds(), (cell_id("A1"), cell_id("C3")));
+    let union = selection.union(Selection::new(cell_id("D5")));
+    assert_eq!(union.anchor, cell_id("D5"));
+    assert_eq!(union.bounds(), (cell_id("B1"), cell_id("D5")));
+  }
+
+  #[test]
+  fn whole_selection_test() {
+    let cell_id = |id: &str| CellId::try_from(id).unwrap();
+
+    assert_eq!(
+      Selection::col('C', Size::default()).bounds(),
+      (cell_id("C1"), cell_id("C50"))
+    );
+    assert_eq!(
+      Selection::row(7, Size { rows: 60, cols: 5 }).bounds(),
+      (cell_id("A7"), cell_id("E7"))
+    );
+
+    let inputs = ["B7", "D2", "C4"]
+      .into_iter()
+      .map(|id| (cell_id(id), String::from("1")))
+      .collect::<HashMap<_, _>>();
+    assert_eq!(
+      Selection::used_range(&inputs, Size::default()).bounds(),
+      (cell_id("B2"), cell_id("D7"))
+    );
+    assert_eq!(
+      Selection::used_range(&HashMap::<CellId, String>::new(), Size::default()).bounds(),
+      (cell_id("A1"), cell_id("J50"))
+    );
+  }
+}
@@ -0,0 +1,136 @@
+use yew::prelude::*;
+
+#[derive(PartialEq, Properties)]
+pub struct SheetTabsProps {
+  pub names: Vec<String>,
+  pub active: usize,
+  // the sheet whose name is being edited
+  pub renaming: Option<usize>,
+  pub onselect: Callback<usize>,
+  pub onadd: Callback<()>,
+  pub onrenamestarted: Callback<usize>,
+  // the new name, or the old one if the editing is cancelled
+  pub onrename: Callback<(usize, String)>,
+  pub ondelete: Callback<usize>,
+}
+
+/// The tab strip switching the sheets; double-clicking a tab renames its sheet.
+/// A focused tab is picked with Enter or Space, and renamed with F2.
+#[function_component]
+pub fn SheetTabs(props: &SheetTabsProps) -> Html {
+  let rename_ref = use_node_ref();
+  {
+    let rename_ref = rename_ref.clone();
+    use_effect_with_deps(
+      move |_| {
+        if let Some(input) = rename_ref.cast::<web_sys::HtmlInputElement>() {
+          input.select();
+        }
+      },
+      props.renaming,
+    );
+  }
+
+  let view_tab = |(index, name): (usize, &String)| {
+    let is_active = index == props.active;
+
+    if props.renaming == Some(index) {
+      let old_name = name.clone();
+      let onkeydown = {
+        let parent_onrename = props.onrename.clone();
+        Callback::from(move |ev: KeyboardEvent| {
+          let input: web_sys::HtmlInputElement = ev.target_unchecked_into();
+          match ev.key().as_str() {
+            "Enter" => parent_onrename.emit((index, input.value())),
+            "Escape" => parent_onrename.emit((index, old_name.clone())),
+            _ => (),
+          }
+        })
+      };
+      let onfocusout = {
+        let parent_onrename = props.onrename.clone();
+        Callback::from(move |ev: FocusEvent| {
+          let input: web_sys::HtmlInputElement = ev.target_unchecked_into();
+          parent_onrename.emit((index, input.value()));
+        })
+      };
+
+      return html! {
+        <input
+          ref={ rename_ref.clone() }
+          type="text"
+          class="w-[8rem] px-2 py-0.5 outline-none bg-indigo-700"
+          value={ name.clone() }
+          {onkeydown}
+          {onfocusout}
+        />
+      };
+    }
+
+    let onclick = {
+      let parent_onselect = props.onselect.clone();
+      Callback::from(move |_ev: MouseEvent| parent_onselect.emit(index))
+    };
+    let ondblclick = {
+      let parent_onrenamestarted = props.onrenamestarted.clone();
+      Callback::from(move |_ev: MouseEvent| parent_onrenamestarted.emit(index))
+    };
+    let tab_onkeydown = {
+      let parent_onselect = props.onselect.clone();
+      let parent_onrenamestarted = props.onrenamestarted.clone();
+      Callback::from(move |ev: KeyboardEvent| match ev.key().as_str() {
+        "Enter" | " " => {
+          ev.prevent_default();
+          parent_onselect.emit(index);
+        }
+        "F2" => {
+          ev.prevent_default();
+          parent_onrenamestarted.emit(index);
+        }
+        _ => (),
+      })
+    };
+    let ondelete = {
+      let parent_ondelete = props.ondelete.clone();
+      Callback::from(move |ev: MouseEvent| {
+        ev.stop_propagation();
+        parent_ondelete.emit(index);
+      })
+    };
+
+    html! {
+      <div
+        class={classes!(vec![
+          "flex items-center gap-2 px-3 py-0.5 cursor-pointer select-none",
+          if is_active { "bg-indigo-700 text-white" } else { "hover:bg-indigo-800" },
+        ])}
+        role="tab"
+        tabindex="0"
+        aria-selected={ is_active.to_string() }
+        title="Double-click or press F2 to rename the sheet"
+        {onclick}
+        {ondblclick}
+        onkeydown={ tab_onkeydown }
+      >
+        { name }
+        if is_active && props.names.len() > 1 {
+          <button class="text-neutral-400 hover:text-red-400" title="Delete the sheet" onclick={ondelete}>
+            { "✕" }
+          </button>
+        }
+      </div>
+    }
+  };
+
+  let onadd = {
+    let parent_onadd = props.onadd.clone();
+    Callback::from(move |_ev: MouseEvent| parent_onadd.emit(()))
+  };
+
+  html! {
+    <div role="tablist" aria-label="Sheets" class="w-screen grow-0 flex gap-1 px-4 pt-1 text-base text-neutral-300 bg-indigo-900 border-t-[1px] border-indigo-800">
+      { props.names.iter().enumerate().map(view_tab).collect::<Html>() }
+      <button class="px-3 hover:bg-indigo-800" title="Add a sheet" onclick={onadd}>{ "+" }</button>
+    </div>
+  }
+}
@@ -0,0 +1,71 @@
+//! The sheets of a document, switched with the tabs under the table. `Table` keeps the cells
+//! and the settings of each sheet, and serializes them all with the document.
+use serde::{Deserialize, Serialize};
+
+/// A named sheet with its table, which is either the live state of the sheet,
+/// or the serialized one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sheet<T> {
+  pub name: String,
+  /// The cells and the settings of the sheet; `None` for the active sheet, which is kept
+  /// separately, and for the new sheets, which are empty.
+  #[serde(default)]
+  pub table: Option<T>,
+}
+
+impl<T> Sheet<T> {
+  pub fn new(name: String) -> Self {
+    Sheet { name, table: None }
+  }
+}
+
+/// Returns the first name like `Sheet2` not taken by the sheets.
+pub fn new_name<T>(sheets: &[Sheet<T>]) -> String {
+  (1..)
+    .map(|n| format!("Sheet{n}"))
+    .find(|name| {
+      !sheets
+        .iter()
+        .any(|sheet| sheet.name.eq_ignore_ascii_case(name))
+    })
+    .unwrap()
+}
+
+/// Returns the trimmed name for the sheet at `index`, unless it's empty or taken by another sheet.
+pub fn check_name<T>(sheets: &[Sheet<T>], index: usize, name: &str) -> Result<String, String> {
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(String::from("a sheet must have a name"));
+  }
+  let is_taken = sheets
+    .iter()
+    .enumerate()
+    .any(|(other, sheet)| other != index && sheet.name.eq_ignore_ascii_case(name));
+  if is_taken {
+    return Err(format!("there is already a sheet named `{name}`"));
+  }
+  Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn sheets_test() {
+    let mut sheets = vec![Sheet::<()>::new(String::from("Sheet1"))];
+    assert_eq!(new_name(&sheets), "Sheet2");
+    sheets.push(Sheet::new(String::from("sheet3")));
+    assert_eq!(new_name(&sheets), "Sheet2");
+    sheets.push(Sheet::new(new_name(&sheets)));
+    assert_eq!(new_name(&sheets), "Sheet4");
+
+    assert_eq!(
+      check_name(&sheets, 0, " Totals "),
+      Ok(String::from("Totals"))
+    );
+    assert_eq!(check_name(&sheets, 0, "Sheet1"), Ok(String::from("Sheet1")));
+    assert!(check_name(&sheets, 0, "SHEET2").is_err());
+    assert!(check_name(&sheets, 0, "  ").is_err());
+  }
+}
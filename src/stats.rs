@@ -0,0 +1,81 @@
+//! Statistics of the selected cells, shown in the status bar under the table.
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+  pub sum: f64,
+  pub count: usize,
+  pub min: f64,
+  pub max: f64,
+}
+
+impl Stats {
+  /// Returns the statistics of the numbers among the values, or `None` if there are none.
+  /// Like `SUM` of a range, skips text and empty values, and counts checked checkboxes as `1`.
+  pub fn of<'a>(values: impl IntoIterator<Item = &'a Value>) -> Option<Stats> {
+    let mut stats: Option<Stats> = None;
+
+    for value in values {
+      let num = match value {
+        Value::Num(_) | Value::Date(_) | Value::Bool(_) => value.as_num().ok(),
+        _ => None,
+      };
+      let Some(num) = num else {
+        continue;
+      };
+
+      stats = Some(match stats {
+        Some(stats) => Stats {
+          sum: stats.sum + num,
+          count: stats.count + 1,
+          min: stats.min.min(num),
+          max: stats.max.max(num),
+        },
+        None => Stats {
+          sum: num,
+          count: 1,
+          min: num,
+          max: num,
+        },
+      });
+    }
+
+    stats
+  }
+
+  pub fn average(&self) -> f64 {
+    self.sum / self.count as f64
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn stats_test() {
+    let values = [
+      Value::Num(4.0),
+      Value::Str(String::from("10")),
+      Value::Empty,
+      Value::Num(-2.0),
+      Value::Bool(true),
+    ];
+    let stats = Stats::of(&values).unwrap();
+    assert_eq!(
+      stats,
+      Stats {
+        sum: 3.0,
+        count: 3,
+        min: -2.0,
+        max: 4.0,
+      }
+    );
+    assert_eq!(stats.average(), 1.0);
+
+    assert_eq!(
+      Stats::of(&[Value::Str(String::from("x")), Value::Empty]),
+      None
+    );
+  }
+}
@@ -0,0 +1,88 @@
+//! Text styles and number formats of the cells, set from the toolbar for the selected cells.
+use serde::{Deserialize, Serialize};
+
+use crate::format::NumberFormat;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+  pub bold: bool,
+  pub italic: bool,
+  /// CSS colors of the text and the background, e.g., `#ff0000`.
+  pub color: Option<String>,
+  pub background: Option<String>,
+  pub number_format: NumberFormat,
+}
+
+/// A change of one property of the style, keeping the others.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StyleChange {
+  Bold(bool),
+  Italic(bool),
+  Color(Option<String>),
+  Background(Option<String>),
+  Decimals(Option<usize>),
+  Percent(bool),
+  Currency(bool),
+  Thousands(bool),
+  Clear,
+}
+
+impl Style {
+  pub fn is_default(&self) -> bool {
+    *self == Style::default()
+  }
+
+  pub fn changed(&self, change: &StyleChange) -> Style {
+    let mut style = self.clone();
+    match change {
+      StyleChange::Bold(bold) => style.bold = *bold,
+      StyleChange::Italic(italic) => style.italic = *italic,
+      StyleChange::Color(color) => style.color = color.clone(),
+      StyleChange::Background(background) => style.background = background.clone(),
+      StyleChange::Decimals(decimals) => style.number_format.decimals = *decimals,
+      StyleChange::Percent(percent) => style.number_format.percent = *percent,
+      StyleChange::Currency(currency) => style.number_format.currency = *currency,
+      StyleChange::Thousands(thousands) => style.number_format.thousands = *thousands,
+      StyleChange::Clear => style = Style::default(),
+    }
+    style
+  }
+
+  /// Returns the inline CSS of the text.
+  pub fn to_css(&self) -> String {
+    let mut css = String::new();
+    if self.bold {
+      css.push_str("font-weight: bold;");
+    }
+    if self.italic {
+      css.push_str("font-style: italic;");
+    }
+    if let Some(color) = &self.color {
+      css.push_str(&format!("color: {color};"));
+    }
+    css
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn style_test() {
+    let style = Style::default()
+      .changed(&StyleChange::Bold(true))
+      .changed(&StyleChange::Color(Some(String::from("#ff0000"))));
+    assert_eq!(style.to_css(), "font-weight: bold;color: #ff0000;");
+    assert!(!style.is_default());
+
+    let style = style.changed(&StyleChange::Bold(false));
+    assert_eq!(style.to_css(), "color: #ff0000;");
+    assert!(style.changed(&StyleChange::Clear).is_default());
+
+    // the styles serialized before a property was introduced get its default
+    let style: Style = serde_json::from_str(r#"{"italic":true}"#).unwrap();
+    assert_eq!(style.to_css(), "font-style: italic;");
+  }
+}
@@ -1,64 +1,509 @@
+use js_sys::Array;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::rc::Rc;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::*;
 use web_sys::console::log_1;
 use web_sys::window;
+use web_sys::Blob;
+use web_sys::CanvasRenderingContext2d;
+use web_sys::HtmlAnchorElement;
+use web_sys::HtmlCanvasElement;
 use web_sys::HtmlElement;
 use web_sys::HtmlInputElement;
+use web_sys::Url;
+use web_sys::UrlSearchParams;
 use yew::prelude::*;
 
+use crate::autocomplete::{complete, signature_hint, suggest, Suggestion};
 use crate::btn::*;
 use crate::cell::*;
-use crate::cell_id::CellId;
-use crate::expr::{eval, Expr};
+use crate::cell_id::{CellId, Size};
+use crate::clipboard::{parse_delimited, paste_rows, Clipboard, PasteMode};
+use crate::command::{Command, CommandTarget};
+use crate::completions::{is_completion_key, Completions};
+use crate::confirm_modal::ConfirmModal;
+use crate::editing::EditState;
+use crate::error::EvalError;
+use crate::errors_modal::{CellProblem, ErrorsModal};
+use crate::expr::{eval_cells, CellStore, EvalOptions, Expr, NegPrecedence, NumberMode};
+use crate::fill::{fill, fill_range};
+use crate::filter::Filter;
+use crate::find::{find, replace};
 use crate::help_modal::HelpModal;
-use crate::parser::parse;
+use crate::highlighted::{is_highlighted, paint_css, painted_refs, Highlighted};
+use crate::history::History;
+use crate::keymap::{Action, Chord, Keymap};
+use crate::keymap_modal::KeymapModal;
+use crate::limits::Limits;
+use crate::locale;
+use crate::navigation::Jump;
+use crate::notifications::{Notifications, TOAST_MILLIS};
+use crate::palette_modal::PaletteModal;
+use crate::parser::parse_with;
 use crate::paste_modal::PasteModal;
+use crate::paste_special_modal::PasteSpecialModal;
+use crate::plugin;
+use crate::selection::Selection;
+use crate::sheet_tabs::SheetTabs;
+use crate::sheets::{check_name, new_name, Sheet};
+use crate::stats::Stats;
+use crate::style::{Style, StyleChange};
+use crate::toasts::Toasts;
+use crate::toolbar::{ToolItem, Toolbar};
+use crate::topological::{format_cycle, State};
+use crate::validation::Rule;
+use crate::value::Value;
+use crate::watch_panel::{WatchPanel, Watched};
 
-#[derive(Debug, PartialEq)]
+/// The width of the columns in pixels, unless they were autofitted.
+const DEFAULT_COL_WIDTH: u32 = 256;
+const MIN_COL_WIDTH: u32 = 48;
+/// The width of the row headers in pixels, and the height of the rows in rem,
+/// fixed to offset the frozen panes.
+const ROW_HEADER_WIDTH: u32 = 96;
+const ROW_HEIGHT: f64 = 2.125;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Msg {
   CopyAll,
   PasteAll,
-  PasteAllContent { serialized_table: String },
+  PasteAllContent {
+    serialized_table: String,
+  },
   PasteModalClose,
+  NewSheet,
+  NewSheetModalClose,
+  NewSheetConfirmed,
   Help,
   HelpModalClose,
-  CellFocused { cell_id: CellId },
-  CellLostFocus { cell_id: CellId },
-  CellBecameInput { cell_id: CellId },
-  CellLostInput { cell_id: CellId },
-  CellChanged { cell_id: CellId, new_value: String },
+  ToggleNegPrecedence,
+  ToggleNumberMode,
+  Recalculate,
+  Undo,
+  Redo,
+  ExportGraph,
+  PluginLoaded {
+    url: String,
+    result: Result<Vec<String>, String>,
+  },
+  CellFocused {
+    cell_id: CellId,
+  },
+  CellLostFocus {
+    cell_id: CellId,
+  },
+  CellBecameInput {
+    cell_id: CellId,
+  },
+  CellLostInput {
+    cell_id: CellId,
+  },
+  CellEditStarted {
+    cell_id: CellId,
+  },
+  CellJumped {
+    cell_id: CellId,
+    jump: Jump,
+  },
+  SelectionStarted {
+    cell_id: CellId,
+  },
+  SelectionExtended {
+    cell_id: CellId,
+  },
+  SelectionJumped {
+    cell_id: CellId,
+    jump: Jump,
+  },
+  Selected {
+    selection: Selection,
+  },
+  SelectAll,
+  CopySelection {
+    is_cut: bool,
+  },
+  Paste {
+    text: String,
+  },
+  PasteSpecial,
+  FillStarted,
+  // fills the selection from its first row, or from its first column, without dragging
+  SelectionFilled {
+    down: bool,
+  },
+  MouseUp,
+  PasteSpecialModalClose,
+  PasteSpecialContent {
+    mode: PasteMode,
+  },
+  CellChanged {
+    cell_id: CellId,
+    new_value: String,
+  },
   BigInputFocused,
-  BigInputChanged { new_value: String },
-  BigInputKeyPress { key_code: u32 },
+  BigInputChanged {
+    new_value: String,
+  },
+  BigInputKeyPress {
+    chord: Chord,
+  },
+  CompletionKey {
+    chord: Chord,
+  },
+  CompletionPicked {
+    index: usize,
+  },
+  NameBoxChanged {
+    new_value: String,
+  },
+  NameBoxKeyPress {
+    chord: Chord,
+  },
+  NameBoxLostFocus,
+  HeaderMenuOpened {
+    header: Header,
+    x: i32,
+    y: i32,
+  },
+  PopupsClosed,
+  HeaderMenuCommand {
+    command: Command,
+  },
+  ColAutofit {
+    col: char,
+  },
+  ToggleFrozenPanes,
+  ToggleMerge,
+  FilterEditorOpened {
+    col: char,
+    x: i32,
+    y: i32,
+  },
+  FilterChanged {
+    col: char,
+    text: String,
+  },
+  FiltersCleared,
+  SelectionCleared,
+  FindOpened,
+  FindClosed,
+  FindQueryChanged {
+    query: String,
+  },
+  FindKeyDown {
+    chord: Chord,
+  },
+  FindNext {
+    backwards: bool,
+  },
+  ReplacementChanged {
+    replacement: String,
+  },
+  Replace,
+  ReplaceAll,
+  StyleChanged {
+    change: StyleChange,
+  },
+  ValidationEditorOpened {
+    x: i32,
+    y: i32,
+  },
+  ValidationChanged {
+    text: String,
+  },
+  PaletteOpened,
+  PaletteClosed,
+  KeymapOpened,
+  KeymapModalClose,
+  KeymapChanged {
+    action: Action,
+    chord: Option<Chord>,
+  },
+  PaletteSelected {
+    index: usize,
+  },
+  ToastExpired {
+    id: usize,
+  },
+  Failed {
+    message: String,
+  },
+  ErrorsDismissed,
+  ToggleShowFormulas,
+  ToggleManualCalc,
+  ToggleRtl,
+  ConvertToValues,
+  TracePrecedents,
+  TraceDependents,
+  TracesRemoved,
+  WatchAdded,
+  WatchRemoved {
+    cell_id: CellId,
+  },
+  WatchPanelToggled,
+  ErrorsOpened,
+  ErrorsModalClose,
+  ErrorSelected {
+    cell_id: CellId,
+  },
+  SheetSelected {
+    index: usize,
+  },
+  SheetAdded,
+  SheetRenameStarted {
+    index: usize,
+  },
+  SheetRenamed {
+    index: usize,
+    name: String,
+  },
+  SheetDeleted {
+    index: usize,
+  },
+  SheetDeleteModalClose,
+  SheetDeleteConfirmed {
+    index: usize,
+  },
+  SplitChanged {
+    split: Option<Split>,
+  },
+  PaneFocused {
+    pane: usize,
+  },
+}
+
+/// How the view is split into two panes scrolled independently.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Split {
+  /// The panes are one above the other.
+  Horizontal,
+  /// The panes are side by side.
+  Vertical,
+}
+
+/// A row or a column header, right-clicked to insert or delete it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Header {
+  Row(usize),
+  Col(char),
+}
+
+/// Everything the table keeps about a cell. The cells merged away only have the formula
+/// referencing the top left cell, and the cells filled by spilled arrays only have a value.
+#[derive(Debug, Default)]
+struct CellRecord {
+  // shared with the cells, so that rendering them only bumps the reference counts
+  input: Option<Rc<str>>,
+  expr: Option<Rc<Expr>>,
+  computed: Option<Result<Value, EvalError>>,
+  // why the formula failed to parse; such cells keep their input as a string
+  parse_error: Option<String>,
+}
+
+impl CellRecord {
+  fn is_empty(&self) -> bool {
+    self.input.is_none() && self.expr.is_none() && self.computed.is_none()
+  }
+}
+
+impl CellStore for HashMap<CellId, CellRecord> {
+  fn expr(&self, cell_id: CellId) -> Option<&Expr> {
+    self.get(&cell_id)?.expr.as_deref()
+  }
+
+  fn cached(&self, cell_id: CellId) -> Option<&Result<Value, EvalError>> {
+    self.get(&cell_id)?.computed.as_ref()
+  }
+
+  fn formula_cells(&self) -> Vec<CellId> {
+    self
+      .iter()
+      .filter(|(_, record)| record.expr.is_some())
+      .map(|(cell_id, _)| *cell_id)
+      .collect()
+  }
+}
+
+/// The cells and the settings of a sheet, with the history of their changes; the view of them,
+/// e.g., the selection and the filters, is kept in `Table` and reset on switching sheets.
+#[derive(Default, Debug)]
+struct SheetData {
+  cells: HashMap<CellId, CellRecord>,
+  // dependencies of the formulas, updated on edits instead of rebuilding them on each evaluation
+  graph: State<CellId>,
+  // changes of the inputs, which can be undone
+  history: History,
+  // the rows and columns shown, growing to contain the focused and the filled cells
+  size: Size,
+  // merged cells, spanning from the top left cell the others are merged into
+  merges: Vec<Selection>,
+  // validation rules of the ranges; the rules set later take precedence
+  validations: Vec<(Selection, Rule)>,
+  // styles of the cells which aren't the default
+  styles: HashMap<CellId, Style>,
+  // widths of the columns autofitted by double-clicking the header borders, in pixels
+  col_widths: HashMap<char, u32>,
+  // the cells listed in the watch panel with their values
+  watched: Vec<CellId>,
+  // the circular references found by the last evaluation, see `State::cycles`
+  cycles: Vec<Vec<CellId>>,
+  // cells changed since the last successful evaluation
+  changed: HashSet<CellId>,
+  neg_precedence: NegPrecedence,
+  number_mode: NumberMode,
+  // the edits only mark the cells as changed until the table is recalculated
+  manual_calc: bool,
+}
+
+impl SheetData {
+  /// Serializes the cells and the settings of the sheet.
+  fn to_serializable(&self) -> SerializableTable {
+    SerializableTable {
+      inputs: self
+        .cells
+        .iter()
+        .filter_map(|(cell_id, record)| {
+          Some((cell_id.to_string(), record.input.as_ref()?.to_string()))
+        })
+        .collect(),
+      neg_precedence: self.neg_precedence,
+      number_mode: self.number_mode,
+      manual_calc: self.manual_calc,
+      watched: self.watched.clone(),
+      size: self.size,
+      merges: self.merges.clone(),
+      validations: self.validations.clone(),
+      styles: self
+        .styles
+        .iter()
+        .map(|(cell_id, style)| (cell_id.to_string(), style.clone()))
+        .collect(),
+      ..SerializableTable::default()
+    }
+  }
 }
 
 #[derive(Default, Debug)]
 pub struct Table {
-  big_input_text: String,
-  focused_cell: Option<CellId>,
-  input_cell: Option<CellId>,
-  prev_focused_cell: Option<CellId>,
+  // the text typed into the name box, which shows the focused cell otherwise
+  name_box_text: Option<String>,
+  // the focused cell and the cell typed into
+  edit_state: EditState,
+  // `None` if only the focused cell is selected
+  selection: Option<Selection>,
+  // the reference inserted into the typed formula by clicking or dragging over the cells,
+  // with its offset in the input, and whether it's still dragged
+  inserted_ref: Option<(usize, Selection)>,
+  is_dragging_ref: bool,
+  // the cells copied or cut with Ctrl+C or Ctrl+X
+  clipboard: Option<Clipboard>,
+  // the filled selection and the cell the fill handle is dragged to
+  fill: Option<(Selection, CellId)>,
+  // the cell to paste into, while the Paste Special dialog is open
+  paste_special_target: Option<CellId>,
+  // the right-clicked header with the position of its context menu
+  header_menu: Option<(Header, i32, i32)>,
   paste_modal_visible: bool,
+  new_sheet_modal_visible: bool,
+  // the sheet to delete, while the confirmation dialog is open
+  deleting_sheet: Option<usize>,
+  // the bottom right cell of the frozen rows and columns, which stay visible on scrolling
+  frozen: Option<CellId>,
+  // the filters typed for the columns, see `crate::filter`
+  filters: BTreeMap<char, String>,
+  // the column whose filter is edited, with the position of the editor
+  filter_editor: Option<(char, i32, i32)>,
+  // the range whose rule is edited, with the position of the editor and the typed rule
+  validation_editor: Option<(Selection, i32, i32)>,
+  validation_text: String,
+  // the cell to focus once it's rendered, after the table grew to contain it
+  pending_focus: Option<CellId>,
+  // the text searched for with Ctrl+F, while the find bar is open, and its replacement
+  find_query: Option<String>,
+  replacement: String,
+  palette_visible: bool,
+  // the key bindings, with the overrides of the user
+  keymap: Rc<Keymap>,
+  keymap_modal_visible: bool,
+  // whether the focused cell is typed into with the big input, which shows the completions then
+  big_input_focused: bool,
+  // the highlighted completion of the typed formula, and whether Escape closed the completions
+  completion_index: usize,
+  completions_closed: bool,
+  // focuses the find input once it's rendered
+  focus_find_input: bool,
   help_modal_visible: bool,
-  inputs: HashMap<CellId, String>,
-  exprs: HashMap<CellId, Expr>,
-  computed: HashMap<CellId, Expr>,
+  errors_modal_visible: bool,
+  notifications: Notifications,
+  // the cells show their inputs instead of their values
+  show_formulas: bool,
+  // the grid is mirrored for the languages written from right to left
+  rtl: bool,
+  // the cells the traced cell reads from and the cells reading from it, until the inputs change
+  precedents: HashSet<CellId>,
+  dependents: HashSet<CellId>,
+  watch_panel_open: bool,
+  split: Option<Split>,
+  // the pane of the split view focused last, whose cells get focused
+  active_pane: usize,
+  // the active sheet
+  sheet: SheetData,
+  // the sheets of the document, with `None` in place of the active sheet; empty for
+  // a document of a single sheet, see `Table::sheets_mut`
+  sheets: Vec<Sheet<SheetData>>,
+  active_sheet: usize,
+  renaming_sheet: Option<usize>,
+  // the limits on recomputing the table, set in the query string, see `Limits::from_params`
+  limits: Limits,
 }
 
 impl Component for Table {
   type Message = Msg;
   type Properties = ();
 
-  fn create(_ctx: &Context<Self>) -> Self {
-    Table::default()
+  fn create(ctx: &Context<Self>) -> Self {
+    // plugins are listed in the query string, e.g., `?plugin=plugins/stats.json`
+    let search = window().unwrap().location().search().unwrap_or_default();
+    let mut limits = Limits::default();
+    if let Ok(params) = UrlSearchParams::new_with_str(&search) {
+      limits = Limits::from_params(|name| params.get(name));
+      for url in params
+        .get_all("plugin")
+        .iter()
+        .filter_map(|url| url.as_string())
+      {
+        ctx.link().send_future(async move {
+          let result = plugin::load(&url).await.map_err(|err| err.to_string());
+          Msg::PluginLoaded { url, result }
+        });
+      }
+    }
+
+    Table {
+      rtl: window()
+        .and_then(|window| window.navigator().language())
+        .is_some_and(|language| locale::is_rtl(&language)),
+      limits,
+      ..Table::default()
+    }
   }
 
   fn view(&self, ctx: &Context<Self>) -> Html {
+    let (suggestions, hint) = self.completions();
+    let has_suggestions = !suggestions.is_empty();
+    let keymap = self.keymap.clone();
+    let big_input_text = self.big_input_text();
+    let is_big_input_highlighted = self.big_input_focused && is_highlighted(&big_input_text);
+
     html! {
-      <div class="mx-auto flex flex-col h-full max-h-full w-full max-w-full text-white text-xl grow-0">
+      <div
+        class="mx-auto flex flex-col h-full max-h-full w-full max-w-full text-white text-xl grow-0"
+        onmouseup={ ctx.link().callback(move |_ev: MouseEvent| { Msg::MouseUp }) }
+        onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::PopupsClosed }) }
+      >
         <PasteModal
           is_visible={ self.paste_modal_visible }
           onclose={ ctx.link().callback(move |()| { Msg::PasteModalClose })}
@@ -66,283 +511,722 @@ impl Component for Table {
             Msg::PasteAllContent { serialized_table }
           })}
         />
+        <PasteSpecialModal
+          is_visible={ self.paste_special_target.is_some() }
+          onclose={ ctx.link().callback(move |()| { Msg::PasteSpecialModalClose }) }
+          onpaste={ ctx.link().callback(move |mode| { Msg::PasteSpecialContent { mode } }) }
+        />
+        <ConfirmModal
+          title="New Sheet"
+          message="Clear all the cells of this sheet with their styles? This cannot be undone; use Copy All to keep a copy."
+          confirm="Clear All"
+          is_visible={ self.new_sheet_modal_visible }
+          onclose={ ctx.link().callback(move |()| { Msg::NewSheetModalClose }) }
+          onconfirm={ ctx.link().callback(move |()| { Msg::NewSheetConfirmed }) }
+        />
+        <ConfirmModal
+          title="Delete Sheet"
+          message={
+            self
+              .deleting_sheet
+              .and_then(|index| self.sheets.get(index))
+              .map(|sheet| format!("Delete the sheet {} with all its cells? This cannot be undone.", sheet.name))
+              .unwrap_or_default()
+          }
+          confirm="Delete"
+          is_visible={ self.deleting_sheet.is_some() }
+          onclose={ ctx.link().callback(move |()| { Msg::SheetDeleteModalClose }) }
+          onconfirm={
+            let index = self.deleting_sheet;
+            ctx.link().batch_callback(move |()| index.map(|index| Msg::SheetDeleteConfirmed { index }))
+          }
+        />
         <HelpModal
           is_visible={ self.help_modal_visible }
           onclose={ ctx.link().callback(move |()| { Msg::HelpModalClose }) }
         />
+        <PaletteModal
+          commands={ self.palette_commands().into_iter().map(|(title, _)| title).collect::<Vec<_>>() }
+          is_visible={ self.palette_visible }
+          onclose={ ctx.link().callback(move |()| { Msg::PaletteClosed }) }
+          onselect={ ctx.link().callback(move |index| { Msg::PaletteSelected { index } }) }
+        />
+        <Toasts
+          toasts={ self.notifications.toasts().to_vec() }
+          onclose={ ctx.link().callback(move |id| { Msg::ToastExpired { id } }) }
+        />
+        <ErrorsModal
+          problems={ self.problems() }
+          is_visible={ self.errors_modal_visible }
+          onclose={ ctx.link().callback(move |()| { Msg::ErrorsModalClose }) }
+          onselect={ ctx.link().callback(move |cell_id| { Msg::ErrorSelected { cell_id } }) }
+        />
+        <WatchPanel
+          watched={
+            self.sheet.watched.iter().map(|cell_id| Watched {
+              cell_id: *cell_id,
+              input: self.input(*cell_id),
+              value: self.formatted_value(*cell_id),
+            }).collect::<Vec<_>>()
+          }
+          is_open={ self.watch_panel_open }
+          ontoggle={ ctx.link().callback(move |()| { Msg::WatchPanelToggled }) }
+          onselect={ ctx.link().callback(move |cell_id| { Msg::Selected { selection: Selection::new(cell_id) } }) }
+          onremove={ ctx.link().callback(move |cell_id| { Msg::WatchRemoved { cell_id } }) }
+        />
+        <KeymapModal
+          keymap={ self.keymap.clone() }
+          is_visible={ self.keymap_modal_visible }
+          onclose={ ctx.link().callback(move |()| { Msg::KeymapModalClose }) }
+          onchange={ ctx.link().callback(move |(action, chord)| { Msg::KeymapChanged { action, chord } }) }
+        />
+        { self.view_header_menu(ctx) }
+        { self.view_filter_editor(ctx) }
+        { self.view_validation_editor(ctx) }
 
-        <div class="w-screen grow-0 sticky top-0 left-0 z-50 flex gap-4 px-4 py-4 bg-indigo-900">
-          <input
-            type="text"
-            class={classes!(vec![
-              "grow ml-[3rem] px-2 py-0.5 outline-none font-mono border-[1px] border-indigo-900 bg-indigo-800"
-            ])}
-            value={ self.big_input_text.clone() }
-            onfocusin={ ctx.link().callback(move |_ev: FocusEvent| { Msg::BigInputFocused })}
-            oninput={ ctx.link().callback(move |ev: InputEvent| {
-              let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
-              let new_value = input.value();
-
-              Msg::BigInputChanged { new_value }
-            })}
-            onkeypress={ ctx.link().callback(move |ev: KeyboardEvent| {
-              Msg::BigInputKeyPress { key_code: ev.key_code() }
-            })}
-          />
-
-          <Btn
-            title="Copy All"
-            color={ BtnColors::Purple }
-            onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::CopyAll }) }
-          />
-          <Btn
-            title="Paste All"
-            color={ BtnColors::Violet }
-            onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::PasteAll }) }
-          />
-          <Btn
-            title="Help"
-            color={ BtnColors::Green }
-            onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::Help }) }
-          />
-        </div>
+        <div class="w-screen grow-0 sticky top-0 left-0 z-50 flex flex-col gap-4 px-4 py-4 bg-indigo-900">
+          <div class="flex gap-4">
+            <input
+              type="text"
+              title="Type a cell, e.g., M37, and press Enter to jump to it"
+              class={classes!(vec![
+                "w-[6rem] ml-[3rem] px-2 py-0.5 outline-none font-mono text-center border-[1px] border-indigo-900 bg-indigo-800"
+              ])}
+              value={
+                self.name_box_text.clone().unwrap_or_else(|| {
+                  self.edit_state.current_cell().map(|cell_id| cell_id.to_string()).unwrap_or_default()
+                })
+              }
+              oninput={ ctx.link().callback(move |ev: InputEvent| {
+                let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+                let new_value = input.value();
 
-        <div class="overflow-scroll snap-y snap-mandatory pb-4">
-          <table class="table table-fixed">
-            <thead>
-              <tr class="snap-start">
-                <th class="sticky top-0 left-0 snap-start pl-6 pr-4 z-40 w-full bg-indigo-900">
-                </th>
-                {
-                  // col id headers
-                  ('A'..='Z').map(move |col| {
-                    let header_style =
-                        match self.focused_cell {
-                            Some(CellId{ col: focused_col, .. }) if focused_col == col =>
-                                "text-neutral-300 hover:text-neutral-200",
-                            _ => "text-neutral-400 hover:text-neutral-300",
-                        };
+                Msg::NameBoxChanged { new_value }
+              })}
+              onkeypress={ ctx.link().callback(move |ev: KeyboardEvent| {
+                Msg::NameBoxKeyPress { chord: Chord::from_event(&ev) }
+              })}
+              onfocusout={ ctx.link().callback(move |_ev: FocusEvent| { Msg::NameBoxLostFocus })}
+            />
+            <div class="relative grow flex">
+              <input
+                type="text"
+                class={classes!(vec![
+                  "grow px-2 py-0.5 outline-none font-mono border-[1px] border-indigo-900 bg-indigo-800",
+                  if is_big_input_highlighted { "text-transparent caret-white" } else { "" }
+                ])}
+                value={ big_input_text.clone() }
+                onfocusin={ ctx.link().callback(move |_ev: FocusEvent| { Msg::BigInputFocused })}
+                oninput={ ctx.link().callback(move |ev: InputEvent| {
+                  let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+                  let new_value = input.value();
 
-                    html! {
-                      <th id={ format!("header-col-{col}") }
-                        class={classes!(vec![
-                            "z-30 sticky top-0 snap-start bg-clip-padding bg-indigo-900 text-center",
-                            header_style
-                        ])}>
-                        { col }
-                      </th>
-                    }
-                  }).collect::<Html>()
-                }
-              </tr>
-            </thead>
-            <tbody>
-              {
-                (1..=50).map(move |row| {
-                  html! {
-                    <tr>
-                    {
-                      ('@'..='Z').map(move |col| {
-                        // row id header
-                        if col == '@' {
-                          let header_style =
-                            match self.focused_cell {
-                              Some(CellId{ row: focused_row, .. }) if focused_row == row =>
-                                "text-neutral-300 hover:text-neutral-200",
-                              _ => "text-neutral-400 hover:text-neutral-300",
-                            };
-
-                          html! {
-                            <th id={ format!("header-row-{row}") }
-                              class={
-                              classes!(vec![
-                                  "z-[35] sticky left-0 snap-start pl-6 pr-4 bg-indigo-900 text-right",
-                                  header_style
-                              ])
-                            }>
-                                { row }
-                            </th>
-                          }
-                        } else {
-                          let cell_id = CellId { col, row };
-                          html! {
-                            <Cell
-                              {cell_id}
-                              is_focused={self.focused_cell == Some(cell_id)}
-                              is_input={self.input_cell == Some(cell_id)}
-                              input={self.inputs.get(&cell_id).map(|x| x.clone())}
-                              expr={self.exprs.get(&cell_id).map(|x| x.clone())}
-                              computed={self.computed.get(&cell_id).map(|x| x.clone())}
-                              onfocused={
-                                ctx.link().callback(move |cell_id| {
-                                  Msg::CellFocused { cell_id }
-                                })
-                              }
-                              onfocusout={
-                                ctx.link().callback(move |_ev: FocusEvent| {
-                                  Msg::CellLostFocus { cell_id }
-                                })
-                              }
-                              onbecameinput={
-                                ctx.link().callback(move |cell_id| {
-                                  Msg::CellBecameInput { cell_id }
-                                })
-                              }
-                              onlostinput={
-                                ctx.link().callback(move |cell_id| {
-                                  Msg::CellLostInput { cell_id }
-                                })
-                              }
-                              oninput={
-                                ctx.link().callback(move |ev: InputEvent| {
-                                  let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
-                                  let new_value = input.value();
-
-                                  Msg::CellChanged { cell_id, new_value }
-                                })
-                              }
-                              sendinput={
-                                ctx.link().callback(move |new_value: String| {
-                                  Msg::CellChanged { cell_id, new_value }
-                                })
-                              }
-                            />
-                          }
-                        }
-                      }).collect::<Html>()
-                    }
-                    </tr>
-                  }
-                }).collect::<Html>()
+                  Msg::BigInputChanged { new_value }
+                })}
+                onkeypress={ ctx.link().callback(move |ev: KeyboardEvent| {
+                  Msg::BigInputKeyPress { chord: Chord::from_event(&ev) }
+                })}
+                onkeydown={ ctx.link().batch_callback(move |ev: KeyboardEvent| {
+                  let chord = Chord::from_event(&ev);
+                  (has_suggestions && is_completion_key(&keymap, &chord)).then(|| {
+                    // Enter doesn't confirm the input then
+                    ev.prevent_default();
+                    Msg::CompletionKey { chord }
+                  })
+                })}
+              />
+              if is_big_input_highlighted {
+                <Highlighted
+                  input={ big_input_text }
+                  class={ classes!(vec!["w-full h-full px-2 py-0.5 border-[1px] border-transparent"]) }
+                />
+              }
+              if self.big_input_focused {
+                <Completions
+                  suggestions={ suggestions.clone() }
+                  index={ self.completion_index }
+                  hint={ hint.clone() }
+                  onpick={ ctx.link().callback(move |index| { Msg::CompletionPicked { index } }) }
+                />
               }
-            </tbody>
-          </table>
+            </div>
+
+          </div>
+
+          <Toolbar groups={ self.toolbar_groups(ctx) } visible_groups={6} />
+
+          { self.view_find_bar(ctx) }
+          { self.view_cycles_banner(ctx) }
+          { self.view_errors_banner(ctx) }
+        </div>
+
+        <div class={classes!(vec![
+          "flex min-h-0",
+          if self.split == Some(Split::Vertical) { "flex-row" } else { "flex-col" },
+        ])}>
+          { self.view_grid(ctx, 0) }
+          if self.split.is_some() {
+            { self.view_grid(ctx, 1) }
+          }
+        </div>
+        <SheetTabs
+          names={ self.sheet_names() }
+          active={ self.active_sheet }
+          renaming={ self.renaming_sheet }
+          onselect={ ctx.link().callback(move |index| { Msg::SheetSelected { index } }) }
+          onadd={ ctx.link().callback(move |()| { Msg::SheetAdded }) }
+          onrenamestarted={ ctx.link().callback(move |index| { Msg::SheetRenameStarted { index } }) }
+          onrename={ ctx.link().callback(move |(index, name)| { Msg::SheetRenamed { index, name } }) }
+          ondelete={ ctx.link().callback(move |index| { Msg::SheetDeleted { index } }) }
+        />
+        { self.view_status_bar(ctx) }
+        <div class="sr-only" aria-live="polite" aria-atomic="true">
+          { self.focus_announcement() }
         </div>
       </div>
     }
   }
 
+  fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+    for id in self.notifications.take_unscheduled() {
+      ctx.link().send_future(async move {
+        sleep(TOAST_MILLIS).await;
+        Msg::ToastExpired { id }
+      });
+    }
+    if let Some(cell_id) = self.pending_focus.take() {
+      self.focus_div_cell(cell_id);
+    }
+    if std::mem::take(&mut self.focus_find_input) {
+      if let Some(input) = window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id("find-input"))
+        .and_then(|elem| elem.dyn_into::<HtmlInputElement>().ok())
+      {
+        input.select();
+      }
+    }
+  }
+
   fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
     match msg {
       Msg::BigInputFocused => {
-        match self.input_cell.or(self.prev_focused_cell) {
-          Some(cell_id) => {
-            self.big_input_text = self.inputs.get(&cell_id).cloned().unwrap_or_default();
-            self.focused_cell = Some(cell_id);
-          }
-          None => (),
-        }
+        self.big_input_focused = true;
+        self.edit_state.focus_big_input();
         true
       }
-      Msg::BigInputChanged { new_value } => match self.input_cell.or(self.focused_cell) {
+      Msg::BigInputChanged { new_value } => match self.edit_state.focused_cell() {
         Some(cell_id) => {
-          self.input_cell = Some(cell_id);
-          self.big_input_text = new_value.clone();
-          let expr = parse(&new_value).unwrap_or_else(|_err| Expr::Str(new_value.clone()));
-          self.inputs.insert(cell_id, new_value);
-          self.exprs.insert(cell_id, expr);
-
-          self.reeval();
+          self.edit_state.edit(cell_id, new_value.clone(), true);
+          self.type_input(cell_id, new_value);
           true
         }
         None => true,
       },
-      Msg::BigInputKeyPress { key_code } => {
-        // Enter
-        if key_code == 13 {
-          self.input_cell = None;
-          self.prev_focused_cell = self.focused_cell;
-          self.focused_cell = self
-            .prev_focused_cell
-            .map(|CellId { row, col }| CellId { row: row + 1, col });
-          self.big_input_text = self
-            .focused_cell
-            .and_then(|cell_id| self.inputs.get(&cell_id))
-            .cloned()
-            .unwrap_or_default();
+      Msg::BigInputKeyPress { chord } => {
+        if self.keymap.action(&chord) == Some(Action::Commit) {
+          if let Some(cell_id) = self.edit_state.focused_cell() {
+            self.reject_invalid_typing(cell_id);
+          }
+          self.edit_state = match self.edit_state.focused_cell() {
+            Some(CellId { row, col }) => EditState::Focused(CellId { row: row + 1, col }),
+            None => EditState::default(),
+          };
+        }
+
+        true
+      }
+      Msg::CompletionKey { chord } => {
+        let count = self.completions().0.len();
+        match (chord.key.as_str(), self.keymap.action(&chord)) {
+          (_, _) if count == 0 => false,
+          ("ArrowDown", _) => {
+            self.completion_index = (self.completion_index + 1) % count;
+            true
+          }
+          ("ArrowUp", _) => {
+            self.completion_index = (self.completion_index + count - 1) % count;
+            true
+          }
+          (_, Some(Action::Close)) => {
+            self.completions_closed = true;
+            true
+          }
+          _ => Component::update(
+            self,
+            ctx,
+            Msg::CompletionPicked {
+              index: self.completion_index,
+            },
+          ),
+        }
+      }
+      Msg::CompletionPicked { index } => {
+        let Some(cell_id) = self.edit_state.focused_cell() else {
+          return false;
+        };
+        let Some(suggestion) = self.completions().0.into_iter().nth(index) else {
+          return false;
+        };
+
+        let input = complete(&self.input(cell_id), &suggestion);
+        self
+          .edit_state
+          .edit(cell_id, input.clone(), self.big_input_focused);
+        self.type_input(cell_id, input);
+        true
+      }
+      // clicking cells while editing a formula inserts references instead
+      Msg::SelectionStarted { cell_id } => {
+        if let Some((edit_cell_id, _)) =
+          self.edit_cell_value_if_formula_cell_reference_insertion(cell_id)
+        {
+          self.is_dragging_ref = true;
+          self.insert_ref(edit_cell_id, Selection::new(cell_id));
+          return true;
+        }
+        if self.edit_state.input_cell().is_none() {
+          // the click isn't fired if the mouse is released over another cell
+          self.selection = Some(Selection::new(cell_id));
+          self.edit_state.focus(cell_id);
+        }
+        true
+      }
+      Msg::SelectionExtended { cell_id } => {
+        if let Some((_, fill_to)) = &mut self.fill {
+          *fill_to = cell_id;
+          return true;
+        }
+        if let (true, Some(edit_cell_id), Some((_, inserted))) = (
+          self.is_dragging_ref,
+          self.edit_state.input_cell(),
+          self.inserted_ref,
+        ) {
+          self.insert_ref(
+            edit_cell_id,
+            Selection {
+              anchor: inserted.anchor,
+              extent: cell_id,
+            },
+          );
+          return true;
+        }
+        if self.edit_state.input_cell().is_some() {
+          return false;
         }
 
+        match (&mut self.selection, self.edit_state.focused_cell()) {
+          (Some(selection), _) => selection.extent = cell_id,
+          (None, Some(focused_cell)) => {
+            self.selection = Some(Selection {
+              anchor: focused_cell,
+              extent: cell_id,
+            })
+          }
+          (None, None) => self.selection = Some(Selection::new(cell_id)),
+        }
+        true
+      }
+      Msg::SelectionJumped { cell_id, jump } => {
+        let mut selection = self.selection.unwrap_or(Selection::new(cell_id));
+        selection.extent = jump.target(&self.inputs(), selection.extent, self.sheet.size);
+        self.selection = Some(selection);
+        self.sheet.size = self.sheet.size.grown_to(selection.extent);
+        true
+      }
+      Msg::Selected { selection } => {
+        self.select(selection);
+        true
+      }
+      Msg::SelectAll => {
+        self.select(Selection::used_range(&self.inputs(), self.sheet.size));
+        true
+      }
+      Msg::NameBoxChanged { new_value } => {
+        self.name_box_text = Some(new_value);
+        true
+      }
+      Msg::NameBoxKeyPress { chord } => {
+        if self.keymap.action(&chord) == Some(Action::Commit) {
+          let text = self.name_box_text.take().unwrap_or_default();
+          match CellId::try_from(text.trim().to_uppercase().as_str()) {
+            Ok(cell_id) if cell_id.is_valid() => {
+              self.selection = None;
+              self.edit_state = EditState::Focused(cell_id);
+              // focusing scrolls the cell into view
+              self.show_cell(cell_id);
+            }
+            _ => self
+              .notifications
+              .error(format!("`{text}` is not a cell of the table")),
+          }
+          true
+        } else {
+          false
+        }
+      }
+      Msg::NameBoxLostFocus => {
+        self.name_box_text = None;
         true
       }
       Msg::CellFocused { cell_id } => {
-        let input_value = self.inputs.get(&cell_id);
+        self.big_input_focused = false;
 
         match self.edit_cell_value_if_formula_cell_reference_insertion(cell_id) {
-          Some((edit_cell_id, edit_cell_value)) => {
-            let new_value = format!("{edit_cell_value}{}", cell_id.to_string());
-
-            self.big_input_text = new_value.clone();
-            self.focused_cell = Some(edit_cell_id);
-            self.input_cell = Some(edit_cell_id);
-            ctx.link().send_message_batch(vec![
-              Msg::CellLostFocus { cell_id },
-              Msg::CellChanged {
-                cell_id: edit_cell_id,
-                new_value,
-              },
-            ]);
+          // the reference was inserted on the mouse down
+          Some((edit_cell_id, _)) => {
+            self.edit_state.focus(edit_cell_id);
+            ctx.link().send_message(Msg::CellLostFocus { cell_id });
 
             // force focus back on the original input
             self.focus_input_cell(edit_cell_id);
           }
           None => {
-            if self.input_cell != Some(cell_id) {
-              self.input_cell = None;
+            self.edit_state.focus(cell_id);
+            // clicking a cell keeps the selection started by the mouse down on it
+            if self.selection.map(|selection| selection.anchor) != Some(cell_id) {
+              self.selection = None;
             }
 
-            if self.input_cell.is_none() {
-              self.focus_div_cell(cell_id);
+            if self.edit_state.input_cell().is_none() {
+              self.show_cell(cell_id);
             }
-
-            self.focused_cell = Some(cell_id);
-            self.big_input_text = input_value.cloned().unwrap_or_default();
           }
         }
         true
       }
-      Msg::CellLostFocus { cell_id } => {
-        if self.focused_cell == Some(cell_id) {
-          self.prev_focused_cell = self.focused_cell;
-          self.focused_cell = None;
-          self.big_input_text = String::from("");
+      Msg::CellLostFocus { cell_id } => self.edit_state.blur(cell_id),
+      Msg::CellBecameInput { cell_id } => {
+        self.big_input_focused = false;
+        self.inserted_ref = None;
+        self.edit_state.edit(cell_id, self.input(cell_id), false);
+        self.sheet.history.end_typing();
+        true
+      }
+      Msg::CellLostInput { cell_id } => {
+        self.inserted_ref = None;
+        self.reject_invalid_typing(cell_id);
+        self.edit_state.commit();
+        self.sheet.history.end_typing();
+        true
+      }
+      Msg::Undo => match self.sheet.history.undo() {
+        Some(command) => {
+          self.apply(&command);
           true
-        } else {
-          false
         }
-      }
-      Msg::CellBecameInput { cell_id } => {
-        self.input_cell = Some(cell_id);
+        None => false,
+      },
+      Msg::Redo => match self.sheet.history.redo() {
+        Some(command) => {
+          self.apply(&command);
+          true
+        }
+        None => false,
+      },
+      Msg::CellEditStarted { cell_id } => {
+        self.edit_state.edit(cell_id, self.input(cell_id), false);
+        self.focus_input_cell(cell_id);
         true
       }
-      Msg::CellLostInput { .. } => {
-        self.input_cell = None;
+      Msg::CellJumped { cell_id, jump } => {
+        let target = jump.target(&self.inputs(), cell_id, self.sheet.size);
+        self.selection = None;
+        self.edit_state = EditState::Focused(target);
+        // focusing scrolls the cell into view
+        self.show_cell(target);
         true
       }
       Msg::CellChanged { cell_id, new_value } => {
-        self.big_input_text = new_value.clone();
-        let expr = parse(&new_value).unwrap_or_else(|_err| Expr::Str(new_value.clone()));
-        self.inputs.insert(cell_id, new_value);
-        self.exprs.insert(cell_id, expr.clone());
+        if self.edit_state.input_cell() == Some(cell_id) {
+          self.edit_state.set_draft(new_value.clone());
+        }
+        self.type_input(cell_id, new_value);
+        true
+      }
+      Msg::SelectionCleared => {
+        let Some(selection) = self
+          .selection
+          .or_else(|| self.edit_state.focused_cell().map(Selection::new))
+        else {
+          return false;
+        };
 
-        self.reeval();
+        let cleared = selection
+          .cells()
+          .filter(|cell_id| self.is_filled(*cell_id))
+          .map(|cell_id| (cell_id, String::new()))
+          .collect::<Vec<_>>();
+        if cleared.is_empty() {
+          return false;
+        }
+
+        self.execute(Command::SetInputs(cleared));
         true
       }
       Msg::CopyAll => {
-        let serialized_cells = self.cells_to_str();
-
-        spawn_local(async move {
-          match web_sys::window().unwrap().navigator().clipboard() {
-            Some(clipboard) => {
-              match JsFuture::from(clipboard.write_text(&serialized_cells)).await {
-                Ok(_) => (),
-                Err(err) => log_1(&JsValue::from(format!(
-                  "couldn't copy cells to clipboard due to {err:?}"
-                ))),
-              }
+        write_to_clipboard(self.cells_to_str(), Self::onerror(ctx));
+        true
+      }
+      Msg::CopySelection { is_cut } => {
+        let Some(source) = self
+          .selection
+          .or_else(|| self.edit_state.focused_cell().map(Selection::new))
+        else {
+          return false;
+        };
+
+        let clipboard = Clipboard::new(
+          source,
+          is_cut,
+          |cell_id| self.input(cell_id),
+          |cell_id| self.displayed_value(cell_id),
+        );
+        write_to_clipboard(clipboard.text.clone(), Self::onerror(ctx));
+        self.clipboard = Some(clipboard);
+        false
+      }
+      Msg::Paste { text } => {
+        if let Some(to) = self.paste_target() {
+          self.paste(&text, to);
+        }
+        true
+      }
+      Msg::FillStarted => {
+        self.fill = self
+          .selection
+          .or_else(|| self.edit_state.focused_cell().map(Selection::new))
+          .map(|source| (source, source.extent));
+        false
+      }
+      Msg::SelectionFilled { down } => {
+        let Some(selection) = self.selection else {
+          return false;
+        };
+        let (top_left, bottom_right) = selection.bounds();
+        let source = Selection {
+          anchor: top_left,
+          extent: if down {
+            CellId {
+              col: bottom_right.col,
+              row: top_left.row,
+            }
+          } else {
+            CellId {
+              col: top_left.col,
+              row: bottom_right.row,
             }
-            None => log_1(&JsValue::from("cannot access clipboard")),
+          },
+        };
+        if fill_range(source, bottom_right).is_none() {
+          return false;
+        }
+        self.execute(Command::SetInputs(fill(
+          &self.inputs(),
+          source,
+          bottom_right,
+        )));
+        true
+      }
+      // the focus goes back to the formula after dragging over the referenced cells
+      Msg::MouseUp if self.is_dragging_ref => {
+        self.is_dragging_ref = false;
+        if let Some(cell_id) = self.edit_state.input_cell() {
+          self.focus_input_cell(cell_id);
+        }
+        true
+      }
+      Msg::MouseUp => match self.fill.take() {
+        Some((source, to)) => {
+          self.execute(Command::SetInputs(fill(&self.inputs(), source, to)));
+
+          // the filled cells stay selected with the source
+          if let Some((_, filled)) = fill_range(source, to) {
+            self.select(source.union(filled));
           }
-        });
+          true
+        }
+        None => false,
+      },
+      // the focus is lost when the dialog opens
+      Msg::StyleChanged { change } => {
+        let cells = match (self.selection, self.edit_state.current_cell()) {
+          (Some(selection), _) => selection.cells().collect(),
+          (None, Some(cell_id)) => vec![cell_id],
+          (None, None) => return false,
+        };
+        let styles = cells
+          .into_iter()
+          .map(|cell_id| (cell_id, self.style(cell_id).changed(&change)))
+          .collect();
+        self.execute(Command::SetStyles(styles));
+        true
+      }
+      Msg::ValidationEditorOpened { x, y } => {
+        let Some(selection) = self
+          .selection
+          .or_else(|| self.edit_state.current_cell().map(Selection::new))
+        else {
+          return false;
+        };
+
+        self.validation_text = self
+          .sheet
+          .validations
+          .iter()
+          .rfind(|(validated, _)| *validated == selection)
+          .map(|(_, rule)| rule.to_string())
+          .unwrap_or_default();
+        self.validation_editor = Some((selection, x, y));
+        true
+      }
+      Msg::ValidationChanged { text } => {
+        if let (Some((selection, _, _)), Ok(rule)) = (self.validation_editor, Rule::parse(&text)) {
+          self
+            .sheet
+            .validations
+            .retain(|(validated, _)| *validated != selection);
+          self
+            .sheet
+            .validations
+            .extend(rule.map(|rule| (selection, rule)));
+        }
+        self.validation_text = text;
+        true
+      }
+      Msg::KeymapOpened => {
+        self.keymap_modal_visible = true;
+        true
+      }
+      Msg::KeymapModalClose => {
+        self.keymap_modal_visible = false;
+        true
+      }
+      Msg::KeymapChanged { action, chord } => {
+        Rc::make_mut(&mut self.keymap).set(action, chord);
+        true
+      }
+      Msg::PaletteOpened => {
+        self.palette_visible = true;
+        true
+      }
+      Msg::PaletteClosed => {
+        self.palette_visible = false;
+        if let Some(cell_id) = self.edit_state.current_cell() {
+          self.focus_div_cell(cell_id);
+        }
+        true
+      }
+      Msg::PaletteSelected { index } => match self.palette_commands().into_iter().nth(index) {
+        Some((_, msg)) => Component::update(self, ctx, msg),
+        None => false,
+      },
+      Msg::ToastExpired { id } => {
+        self.notifications.expire(id);
+        true
+      }
+      Msg::Failed { message } => {
+        self.notifications.error(message);
+        true
+      }
+      Msg::ToggleShowFormulas => {
+        self.show_formulas = !self.show_formulas;
+        true
+      }
+      Msg::ToggleRtl => {
+        self.rtl = !self.rtl;
+        true
+      }
+      Msg::ErrorsDismissed => {
+        self.notifications.dismiss_errors();
+        true
+      }
+      Msg::FindOpened => {
+        self.find_query.get_or_insert_with(String::new);
+        self.focus_find_input = true;
+        true
+      }
+      Msg::FindClosed => {
+        self.find_query = None;
+        if let Some(cell_id) = self.edit_state.current_cell() {
+          self.focus_div_cell(cell_id);
+        }
+        true
+      }
+      Msg::FindQueryChanged { query } => {
+        self.find_query = Some(query);
+        true
+      }
+      Msg::FindKeyDown { chord } => match self.keymap.action(&chord) {
+        Some(Action::Commit) => Component::update(
+          self,
+          ctx,
+          Msg::FindNext {
+            backwards: chord.shift,
+          },
+        ),
+        Some(Action::Close) => Component::update(self, ctx, Msg::FindClosed),
+        _ => false,
+      },
+      Msg::FindNext { backwards } => {
+        let found = self.found_cells();
+        let current = self.edit_state.current_cell();
+        let next = if backwards {
+          let mut before = found.iter().rev();
+          before
+            .find(|cell_id| current.map_or(true, |current| **cell_id < current))
+            .or(found.last())
+        } else {
+          let mut after = found.iter();
+          after
+            .find(|cell_id| current.map_or(true, |current| **cell_id > current))
+            .or(found.first())
+        };
+
+        match next.copied() {
+          // the focus stays in the find bar
+          Some(cell_id) => {
+            self.selection = None;
+            self.edit_state = EditState::Focused(cell_id);
+            self.scroll_to_cell(cell_id);
+            true
+          }
+          None => false,
+        }
+      }
+      Msg::ReplacementChanged { replacement } => {
+        self.replacement = replacement;
+        false
+      }
+      Msg::Replace => {
+        let current = self.edit_state.current_cell();
+        match current.filter(|cell_id| self.found_cells().contains(cell_id)) {
+          Some(cell_id) => {
+            self.execute(Command::SetInputs(self.replaced_inputs(&[cell_id])));
+            Component::update(self, ctx, Msg::FindNext { backwards: false });
+            true
+          }
+          None => Component::update(self, ctx, Msg::FindNext { backwards: false }),
+        }
+      }
+      Msg::ReplaceAll => {
+        let found = self.found_cells();
+        self.execute(Command::SetInputs(self.replaced_inputs(&found)));
+        true
+      }
+      Msg::PasteSpecial => {
+        self.paste_special_target = self.paste_target().filter(|_| self.clipboard.is_some());
+        true
+      }
+      Msg::PasteSpecialModalClose => {
+        self.paste_special_target = None;
+        true
+      }
+      Msg::PasteSpecialContent { mode } => {
+        if let (Some(to), Some(clipboard)) = (self.paste_special_target.take(), &self.clipboard) {
+          let inputs = clipboard.paste(to, mode);
+          self.paste_inputs(inputs);
+        }
         true
       }
       Msg::PasteAll => {
@@ -353,6 +1237,18 @@ impl Component for Table {
         self.paste_modal_visible = false;
         true
       }
+      Msg::NewSheet => {
+        self.new_sheet_modal_visible = true;
+        true
+      }
+      Msg::NewSheetModalClose => {
+        self.new_sheet_modal_visible = false;
+        true
+      }
+      Msg::NewSheetConfirmed => {
+        self.clear_sheet();
+        true
+      }
       Msg::PasteAllContent { serialized_table } => {
         self.cells_from_str(&serialized_table);
         true
@@ -361,78 +1257,1939 @@ impl Component for Table {
         self.help_modal_visible = true;
         true
       }
-      Msg::HelpModalClose => {
-        self.help_modal_visible = false;
+      Msg::HeaderMenuOpened { header, x, y } => {
+        self.header_menu = Some((header, x, y));
         true
       }
-    }
-  }
-}
-
-impl Table {
-  fn reeval(&mut self) {
-    match eval(&self.exprs) {
-      Ok(computed) => self.computed = computed,
-      Err(err) => log_1(&JsValue::from_str(&format!(
-        "Failed when trying to recompute: {err}."
-      ))),
-    };
-  }
-
-  fn cells_to_str(&self) -> String {
-    let t = SerializableTable {
-      inputs: self
-        .inputs
-        .iter()
-        .map(|(cell_id, input)| (cell_id.to_string(), input.clone()))
-        .collect(),
-    };
-    serde_json::to_string(&t).unwrap()
-  }
-
-  fn cells_from_str(&mut self, encoded: &str) {
-    match parse_from_input(encoded) {
-      Ok((inputs, exprs)) => {
-        self.inputs = inputs;
-        self.exprs = exprs;
-        self.reeval();
+      Msg::PopupsClosed => {
+        self.header_menu.take().is_some()
+          | self.filter_editor.take().is_some()
+          | self.validation_editor.take().is_some()
       }
-      Err(err) => log_1(&JsValue::from(err.to_string())),
-    }
-  }
-
-  fn edit_cell_value_if_formula_cell_reference_insertion(
-    &self,
-    clicked_on_cell: CellId,
-  ) -> Option<(CellId, String)> {
-    match self.input_cell {
-      Some(another_cell_id) if another_cell_id != clicked_on_cell => {
-        let another_cell_value = self
-          .inputs
-          .get(&another_cell_id)
-          .cloned()
-          .unwrap_or_else(|| String::new());
-
-        if another_cell_value.trim_start().starts_with('=') {
-          Some((another_cell_id, another_cell_value))
+      Msg::FilterEditorOpened { col, x, y } => {
+        self.header_menu = None;
+        self.filter_editor = Some((col, x, y));
+        true
+      }
+      Msg::FilterChanged { col, text } => {
+        if text.trim().is_empty() {
+          self.filters.remove(&col);
         } else {
-          None
+          self.filters.insert(col, text);
+        }
+        true
+      }
+      Msg::FiltersCleared => {
+        self.filters.clear();
+        self.filter_editor = None;
+        true
+      }
+      Msg::HeaderMenuCommand { command } => {
+        self.header_menu = None;
+        match command.check(self) {
+          Ok(()) => self.execute(command),
+          Err(message) => self.notifications.error(message),
+        }
+        true
+      }
+      Msg::ColAutofit { col } => {
+        let texts = (1..=self.sheet.size.rows)
+          .map(|row| CellId { col, row })
+          .flat_map(|cell_id| [self.input(cell_id), self.formatted_value(cell_id)])
+          .filter(|text| !text.is_empty())
+          .collect::<Vec<_>>();
+
+        match measure_text_width(&texts, &CellId { col, row: 1 }.to_string()) {
+          // the padding and the borders of the cell
+          Some(width) => self
+            .sheet
+            .col_widths
+            .insert(col, (width.ceil() as u32 + 18).max(MIN_COL_WIDTH)),
+          None => self.sheet.col_widths.remove(&col),
+        };
+        true
+      }
+      Msg::ToggleFrozenPanes => {
+        self.frozen = match self.frozen {
+          Some(_) => None,
+          None => self.edit_state.current_cell(),
+        };
+        true
+      }
+      Msg::ToggleMerge => {
+        let Some(cell_id) = self.edit_state.current_cell() else {
+          return false;
+        };
+
+        if let Some(merged) = self.merge_at(cell_id) {
+          self.execute(Command::Unmerge(merged, vec![]));
+        } else if let Some(selection) = self
+          .selection
+          .filter(|selection| !selection.is_single_cell())
+        {
+          if self
+            .sheet
+            .merges
+            .iter()
+            .any(|merged| merged.cells().any(|cell_id| selection.contains(cell_id)))
+          {
+            self
+              .notifications
+              .error("cannot merge cells overlapping merged cells");
+            return false;
+          }
+
+          let (top_left, bottom_right) = selection.bounds();
+          self.execute(Command::Merge(Selection {
+            anchor: top_left,
+            extent: bottom_right,
+          }));
+        }
+        true
+      }
+      Msg::HelpModalClose => {
+        self.help_modal_visible = false;
+        true
+      }
+      Msg::ToggleNegPrecedence => {
+        self.execute(Command::SetNegPrecedence(match self.sheet.neg_precedence {
+          NegPrecedence::StrictMath => NegPrecedence::Spreadsheet,
+          NegPrecedence::Spreadsheet => NegPrecedence::StrictMath,
+        }));
+        true
+      }
+      Msg::ToggleNumberMode => {
+        self.execute(Command::SetNumberMode(match self.sheet.number_mode {
+          NumberMode::Float => NumberMode::Decimal,
+          NumberMode::Decimal => NumberMode::Float,
+        }));
+        true
+      }
+      // only the volatile cells, the cells changed in the manual mode, and their dependents can change
+      Msg::Recalculate => {
+        self.sheet.changed.extend(self.sheet.graph.all_volatile());
+        self.eval_changed();
+        true
+      }
+      Msg::ConvertToValues => {
+        let Some(selection) = self
+          .selection
+          .or_else(|| self.edit_state.focused_cell().map(Selection::new))
+        else {
+          return false;
+        };
+
+        let inputs = self.formula_values(selection);
+        self.execute(Command::SetInputs(inputs));
+        true
+      }
+      Msg::TracePrecedents | Msg::TraceDependents => {
+        let Some(cell_id) = self.edit_state.current_cell() else {
+          return false;
+        };
+
+        if msg == Msg::TracePrecedents {
+          // the empty cells referenced by the formulas, e.g., in ranges, are left out
+          self.precedents = self
+            .sheet
+            .graph
+            .all_precedents(&cell_id)
+            .into_iter()
+            .filter(|precedent| self.has_expr(*precedent))
+            .collect();
+        } else {
+          self.dependents = self.sheet.graph.all_dependents(&cell_id);
+        }
+        true
+      }
+      Msg::WatchAdded => {
+        let Some(selection) = self
+          .selection
+          .or_else(|| self.edit_state.focused_cell().map(Selection::new))
+        else {
+          return false;
+        };
+
+        // the empty cells of a range, e.g., of a column, are skipped
+        let watched = selection
+          .cells()
+          .filter(|cell_id| selection.is_single_cell() || self.is_filled(*cell_id))
+          .collect::<Vec<_>>();
+        for cell_id in watched {
+          if !self.sheet.watched.contains(&cell_id) {
+            self.sheet.watched.push(cell_id);
+          }
+        }
+        self.watch_panel_open = true;
+        true
+      }
+      Msg::WatchRemoved { cell_id } => {
+        self.sheet.watched.retain(|watched| *watched != cell_id);
+        true
+      }
+      Msg::ErrorsOpened => {
+        self.errors_modal_visible = true;
+        true
+      }
+      Msg::ErrorsModalClose => {
+        self.errors_modal_visible = false;
+        true
+      }
+      Msg::ErrorSelected { cell_id } => {
+        self.errors_modal_visible = false;
+        self.select(Selection::new(cell_id));
+        true
+      }
+      Msg::SheetSelected { index } => {
+        self.switch_sheet(index);
+        true
+      }
+      Msg::SheetAdded => {
+        let sheets = self.sheets_mut();
+        sheets.push(Sheet::new(new_name(sheets)));
+        self.switch_sheet(self.sheets.len() - 1);
+        true
+      }
+      Msg::SheetRenameStarted { index } => {
+        self.renaming_sheet = Some(index);
+        true
+      }
+      Msg::SheetRenamed { index, name } => {
+        // both Enter and the following focus loss rename the sheet
+        if self.renaming_sheet != Some(index) {
+          return false;
+        }
+        self.renaming_sheet = None;
+        match check_name(self.sheets_mut(), index, &name) {
+          Ok(name) => self.sheets_mut()[index].name = name,
+          Err(message) => self.notifications.error(message),
+        }
+        true
+      }
+      Msg::SheetDeleted { index } => {
+        if self.sheets.len() < 2 || index >= self.sheets.len() {
+          return false;
+        }
+        self.deleting_sheet = Some(index);
+        true
+      }
+      Msg::SheetDeleteModalClose => {
+        self.deleting_sheet = None;
+        true
+      }
+      Msg::SheetDeleteConfirmed { index } => {
+        if self.sheets.len() < 2 || index >= self.sheets.len() {
+          return false;
+        }
+
+        if index == self.active_sheet {
+          self.switch_sheet(if index == 0 { 1 } else { index - 1 });
+        }
+        self.sheets.remove(index);
+        if index < self.active_sheet {
+          self.active_sheet -= 1;
+        }
+        true
+      }
+      Msg::SplitChanged { split } => {
+        self.split = split;
+        self.active_pane = 0;
+        true
+      }
+      Msg::PaneFocused { pane } => {
+        self.active_pane = pane;
+        false
+      }
+      Msg::WatchPanelToggled => {
+        self.watch_panel_open = !self.watch_panel_open;
+        true
+      }
+      Msg::TracesRemoved => {
+        self.precedents.clear();
+        self.dependents.clear();
+        true
+      }
+      Msg::ToggleManualCalc => {
+        self.sheet.manual_calc = !self.sheet.manual_calc;
+        self.reeval();
+        true
+      }
+      Msg::ExportGraph => {
+        // references to empty cells are left out
+        let dot = self
+          .sheet
+          .graph
+          .to_dot_with(|cell_id| self.has_expr(*cell_id));
+        if let Err(err) = download("cells.dot", &dot) {
+          self
+            .notifications
+            .error(format!("couldn't export the graph due to {err:?}"));
+          return true;
+        }
+        false
+      }
+      Msg::PluginLoaded { url, result } => match result {
+        Ok(names) => {
+          self
+            .notifications
+            .info(format!("loaded {} from the plugin {url}", names.join(", ")));
+          // formulas with the plugin functions couldn't be parsed before
+          self.reparse();
+          true
+        }
+        Err(err) => {
+          self
+            .notifications
+            .error(format!("couldn't load the plugin {url}: {err}"));
+          true
+        }
+      },
+    }
+  }
+}
+
+impl Table {
+  /// Reports the errors of the asynchronous tasks, e.g., writing to the clipboard.
+  fn onerror(ctx: &Context<Self>) -> Callback<String> {
+    ctx.link().callback(move |message| Msg::Failed { message })
+  }
+
+  /// Recomputes the changed and volatile cells, and the cells depending on them,
+  /// unless the table is recalculated manually.
+  fn reeval(&mut self) {
+    if !self.sheet.manual_calc {
+      self.eval_changed();
+    }
+  }
+
+  fn eval_changed(&mut self) {
+    let options = EvalOptions {
+      graph: Some(&mut self.sheet.graph),
+      limits: self.limits.clone(),
+      number_mode: self.sheet.number_mode,
+      ..EvalOptions::default()
+    };
+    let updates = eval_cells(&self.sheet.cells, &self.sheet.changed, options);
+    self.sheet.changed.clear();
+    self.sheet.cycles = self.sheet.graph.cycles();
+
+    for (cell_id, value) in updates {
+      match value {
+        Some(value) => self.sheet.cells.entry(cell_id).or_default().computed = Some(value),
+        // the cells no longer filled by spilled arrays are left empty
+        None => {
+          if let Some(record) = self.sheet.cells.get_mut(&cell_id) {
+            record.computed = None;
+            if record.is_empty() {
+              self.sheet.cells.remove(&cell_id);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Recomputes all cells, also in the manual mode, e.g., after loading a table.
+  fn recalculate(&mut self) {
+    self.sheet.changed.extend(self.formula_cells());
+    self.eval_changed();
+  }
+
+  /// Returns the input of the cell, unless it's empty.
+  fn input_of(&self, cell_id: CellId) -> Option<&Rc<str>> {
+    self.sheet.cells.get(&cell_id)?.input.as_ref()
+  }
+
+  /// Returns the value of the cell, or the error of computing it, once it's evaluated.
+  fn computed(&self, cell_id: CellId) -> Option<&Result<Value, EvalError>> {
+    self.sheet.cells.get(&cell_id)?.computed.as_ref()
+  }
+
+  fn is_filled(&self, cell_id: CellId) -> bool {
+    self.input_of(cell_id).is_some()
+  }
+
+  /// Unlike `is_filled`, also true for the cells merged away, which reference the top left cell.
+  fn has_expr(&self, cell_id: CellId) -> bool {
+    self
+      .sheet
+      .cells
+      .get(&cell_id)
+      .is_some_and(|record| record.expr.is_some())
+  }
+
+  /// Returns the inputs of the filled cells, e.g., to navigate between them.
+  fn inputs(&self) -> HashMap<CellId, &str> {
+    self
+      .sheet
+      .cells
+      .iter()
+      .filter_map(|(cell_id, record)| Some((*cell_id, record.input.as_deref()?)))
+      .collect()
+  }
+
+  /// Returns the formulas of the cells, including the cells merged away.
+  fn exprs(&self) -> HashMap<CellId, &Expr> {
+    self
+      .sheet
+      .cells
+      .iter()
+      .filter_map(|(cell_id, record)| Some((*cell_id, record.expr.as_deref()?)))
+      .collect()
+  }
+
+  fn formula_cells(&self) -> Vec<CellId> {
+    self.sheet.cells.formula_cells()
+  }
+
+  /// Selects the range, focusing its anchor.
+  fn select(&mut self, selection: Selection) {
+    self.selection = Some(selection);
+    self.edit_state = EditState::Focused(selection.anchor);
+    self.focus_div_cell(selection.anchor);
+  }
+
+  /// Whether the cell is selected, or is about to be filled with the fill handle.
+  fn is_selected(&self, cell_id: CellId) -> bool {
+    let is_filled = self.fill.is_some_and(|(source, to)| {
+      fill_range(source, to).is_some_and(|(_, filled)| filled.contains(cell_id))
+    });
+    is_filled
+      || self
+        .selection
+        .is_some_and(|selection| selection.contains(cell_id))
+  }
+
+  /// Returns the bottom right cell of the selection, which shows the fill handle.
+  fn fill_handle_cell(&self) -> Option<CellId> {
+    self
+      .selection
+      .map(|selection| selection.bounds().1)
+      .or(self.edit_state.focused_cell())
+  }
+
+  /// Returns the top left cell of the selection, where the cells are pasted.
+  fn paste_target(&self) -> Option<CellId> {
+    self
+      .selection
+      .map(|selection| selection.bounds().0)
+      .or(self.edit_state.focused_cell())
+  }
+
+  /// Pastes the cells copied from the table with their formulas, if the text was copied
+  /// from it, or the values copied from other spreadsheets otherwise.
+  fn paste(&mut self, text: &str, to: CellId) {
+    let inputs = match &self.clipboard {
+      Some(clipboard) if clipboard.matches(text) => clipboard.paste(to, PasteMode::Formulas),
+      _ => paste_rows(&parse_delimited(text), to),
+    };
+    self.paste_inputs(inputs);
+  }
+
+  fn paste_inputs(&mut self, inputs: Vec<(CellId, String)>) {
+    // cut cells can be pasted only once
+    if self
+      .clipboard
+      .as_ref()
+      .is_some_and(|clipboard| clipboard.is_cut)
+    {
+      self.clipboard = None;
+    }
+
+    self.execute(Command::SetInputs(inputs));
+  }
+
+  /// Returns the command sorting the selected rows by the column, or all the rows
+  /// up to the last used one, unless several rows are selected.
+  fn sort_rows(&self, col: char, descending: bool) -> Command {
+    let rows = match self.selection {
+      Some(selection) if selection.anchor.row != selection.extent.row => {
+        let (top_left, bottom_right) = selection.bounds();
+        (top_left.row, bottom_right.row)
+      }
+      _ => (
+        1,
+        self
+          .inputs()
+          .keys()
+          .map(|cell_id| cell_id.row)
+          .max()
+          .unwrap_or(1),
+      ),
+    };
+
+    Command::SortRows {
+      col,
+      rows,
+      descending,
+    }
+  }
+
+  /// Returns the groups of the toolbar buttons; the settings go to the overflow menu.
+  fn toolbar_groups(&self, ctx: &Context<Self>) -> Vec<Vec<ToolItem>> {
+    let link = ctx.link();
+    let focused_cell = self.edit_state.current_cell();
+    let style = focused_cell
+      .map(|cell_id| self.style(cell_id))
+      .unwrap_or_default();
+    let decimals = focused_cell.map_or(0, |cell_id| self.shown_decimals(cell_id));
+    let msg = |msg: Msg| link.callback(move |_ev: MouseEvent| msg.clone());
+    let style_change = |change: StyleChange| msg(Msg::StyleChanged { change });
+
+    let mut data = vec![
+      ToolItem::toggle(
+        &match (self.frozen, focused_cell) {
+          (Some(_), _) => String::from("Unfreeze"),
+          (None, Some(cell_id)) => format!("Freeze up to {cell_id}"),
+          (None, None) => String::from("Freeze Panes"),
+        },
+        self.frozen.is_some(),
+        msg(Msg::ToggleFrozenPanes),
+      ),
+      ToolItem::toggle(
+        "Merge",
+        focused_cell
+          .and_then(|cell_id| self.merge_at(cell_id))
+          .is_some(),
+        msg(Msg::ToggleMerge),
+      ),
+      ToolItem::button("To Values", msg(Msg::ConvertToValues)),
+      ToolItem::button("Watch", msg(Msg::WatchAdded)),
+      ToolItem::button(
+        "Validate",
+        link.callback(move |ev: MouseEvent| {
+          ev.stop_propagation();
+          Msg::ValidationEditorOpened {
+            x: ev.client_x(),
+            y: ev.client_y(),
+          }
+        }),
+      ),
+    ];
+    if !self.filters.is_empty() {
+      data.push(ToolItem::button(
+        &format!("Clear Filters ({})", self.filters.len()),
+        msg(Msg::FiltersCleared),
+      ));
+    }
+
+    let color_picker =
+      |title: &str, tooltip: &str, value: String, change: fn(String) -> StyleChange| {
+        ToolItem::Custom(html! {
+          <label class="flex items-center gap-1 text-base" title={ tooltip.to_string() }>
+            { title }
+            <input
+              type="color"
+              class="w-8 h-8 bg-transparent cursor-pointer"
+              {value}
+              onchange={ link.callback(move |ev: Event| {
+                let input: HtmlInputElement = ev.target_unchecked_into();
+                Msg::StyleChanged { change: change(input.value()) }
+              }) }
+            />
+          </label>
+        })
+      };
+
+    vec![
+      vec![
+        ToolItem::button("Undo", msg(Msg::Undo)),
+        ToolItem::button("Redo", msg(Msg::Redo)),
+      ],
+      vec![ToolItem::Dropdown {
+        title: String::from("Sheet"),
+        items: vec![
+          (String::from("Copy All"), msg(Msg::CopyAll)),
+          (String::from("Paste All"), msg(Msg::PasteAll)),
+          (String::from("New Sheet"), msg(Msg::NewSheet)),
+          (String::from("Export Graph"), msg(Msg::ExportGraph)),
+        ],
+      }],
+      vec![ToolItem::Dropdown {
+        title: String::from("Split"),
+        items: vec![
+          (
+            String::from("Split Horizontally"),
+            msg(Msg::SplitChanged {
+              split: Some(Split::Horizontal),
+            }),
+          ),
+          (
+            String::from("Split Vertically"),
+            msg(Msg::SplitChanged {
+              split: Some(Split::Vertical),
+            }),
+          ),
+          (
+            String::from("Remove Split"),
+            msg(Msg::SplitChanged { split: None }),
+          ),
+        ],
+      }],
+      vec![ToolItem::Dropdown {
+        title: String::from("Audit"),
+        items: vec![
+          (String::from("Trace Precedents"), msg(Msg::TracePrecedents)),
+          (String::from("Trace Dependents"), msg(Msg::TraceDependents)),
+          (String::from("Remove Traces"), msg(Msg::TracesRemoved)),
+          (String::from("Errors"), msg(Msg::ErrorsOpened)),
+        ],
+      }],
+      vec![
+        ToolItem::toggle(
+          "Bold",
+          style.bold,
+          style_change(StyleChange::Bold(!style.bold)),
+        ),
+        ToolItem::toggle(
+          "Italic",
+          style.italic,
+          style_change(StyleChange::Italic(!style.italic)),
+        ),
+        color_picker(
+          "Text",
+          "The text color of the selected cells",
+          style
+            .color
+            .clone()
+            .unwrap_or_else(|| String::from("#ffffff")),
+          |color| StyleChange::Color(Some(color)),
+        ),
+        color_picker(
+          "Fill",
+          "The background color of the selected cells",
+          style
+            .background
+            .clone()
+            .unwrap_or_else(|| String::from("#3730a3")),
+          |background| StyleChange::Background(Some(background)),
+        ),
+        ToolItem::button("Clear Style", style_change(StyleChange::Clear)),
+      ],
+      vec![
+        ToolItem::toggle(
+          "$",
+          style.number_format.currency,
+          style_change(StyleChange::Currency(!style.number_format.currency)),
+        ),
+        ToolItem::toggle(
+          "%",
+          style.number_format.percent,
+          style_change(StyleChange::Percent(!style.number_format.percent)),
+        ),
+        ToolItem::toggle(
+          "1,000",
+          style.number_format.thousands,
+          style_change(StyleChange::Thousands(!style.number_format.thousands)),
+        ),
+        ToolItem::button(
+          ".0←",
+          style_change(StyleChange::Decimals(Some(decimals.saturating_sub(1)))),
+        ),
+        ToolItem::button(
+          ".00→",
+          style_change(StyleChange::Decimals(Some(decimals + 1))),
+        ),
+      ],
+      data,
+      vec![ToolItem::Custom(html! {
+        <Btn title="Help" color={ BtnColors::Green } onclick={ msg(Msg::Help) } />
+      })],
+      vec![
+        ToolItem::toggle(
+          "-2^2 = 4",
+          self.sheet.neg_precedence == NegPrecedence::Spreadsheet,
+          msg(Msg::ToggleNegPrecedence),
+        ),
+        ToolItem::toggle(
+          "Decimal Numbers",
+          self.sheet.number_mode == NumberMode::Decimal,
+          msg(Msg::ToggleNumberMode),
+        ),
+        ToolItem::toggle(
+          "Show Formulas",
+          self.show_formulas,
+          msg(Msg::ToggleShowFormulas),
+        ),
+        ToolItem::toggle(
+          "Manual Calculation",
+          self.sheet.manual_calc,
+          msg(Msg::ToggleManualCalc),
+        ),
+        ToolItem::toggle("Right to Left", self.rtl, msg(Msg::ToggleRtl)),
+        ToolItem::button(
+          &self.with_shortcut("Recalculate", Action::Recalculate),
+          msg(Msg::Recalculate),
+        ),
+        ToolItem::button("Keyboard Shortcuts", msg(Msg::KeymapOpened)),
+      ],
+    ]
+  }
+
+  /// Returns the completions of the formula typed into the input cell, and the hint
+  /// of the arguments of the function being typed.
+  fn completions(&self) -> (Vec<Suggestion>, Option<String>) {
+    match self.edit_state.input_cell() {
+      Some(cell_id) if !self.completions_closed => {
+        let input = self.input(cell_id);
+        (suggest(&input, &self.inputs()), signature_hint(&input))
+      }
+      _ => (vec![], None),
+    }
+  }
+
+  /// Returns the message of an action bound to a key, which isn't handled by the cell itself.
+  fn action_msg(action: Action) -> Option<Msg> {
+    match action {
+      Action::Undo => Some(Msg::Undo),
+      Action::Redo => Some(Msg::Redo),
+      Action::SelectAll => Some(Msg::SelectAll),
+      Action::PasteSpecial => Some(Msg::PasteSpecial),
+      Action::Find => Some(Msg::FindOpened),
+      Action::Palette => Some(Msg::PaletteOpened),
+      Action::Clear => Some(Msg::SelectionCleared),
+      Action::Recalculate => Some(Msg::Recalculate),
+      _ => None,
+    }
+  }
+
+  /// Appends the first chord bound to the action to the title, e.g., `Undo (Ctrl+Z)`.
+  fn with_shortcut(&self, title: &str, action: Action) -> String {
+    match self.keymap.chords(action).first() {
+      Some(chord) => format!("{title} ({chord})"),
+      None => title.to_string(),
+    }
+  }
+
+  /// Returns the titles and the messages of the commands listed in the command palette,
+  /// including the ones for the focused cell and its row and column.
+  fn palette_commands(&self) -> Vec<(String, Msg)> {
+    let shortcut_commands = [
+      ("Undo", Action::Undo),
+      ("Redo", Action::Redo),
+      ("Select All", Action::SelectAll),
+      ("Paste Special", Action::PasteSpecial),
+      ("Find and Replace", Action::Find),
+      ("Clear", Action::Clear),
+      ("Recalculate", Action::Recalculate),
+    ];
+    let mut commands = shortcut_commands
+      .into_iter()
+      .filter_map(|(title, action)| {
+        Table::action_msg(action).map(|msg| (self.with_shortcut(title, action), msg))
+      })
+      .collect::<Vec<_>>();
+    commands.extend(
+      [
+        ("Copy (Ctrl+C)", Msg::CopySelection { is_cut: false }),
+        ("Cut (Ctrl+X)", Msg::CopySelection { is_cut: true }),
+        ("Copy All", Msg::CopyAll),
+        ("Paste All", Msg::PasteAll),
+        ("New Sheet", Msg::NewSheet),
+        ("Fill Down", Msg::SelectionFilled { down: true }),
+        ("Fill Right", Msg::SelectionFilled { down: false }),
+        ("Add Sheet", Msg::SheetAdded),
+        (
+          "Rename Sheet",
+          Msg::SheetRenameStarted {
+            index: self.active_sheet,
+          },
+        ),
+        (
+          "Bold",
+          Msg::StyleChanged {
+            change: StyleChange::Bold(true),
+          },
+        ),
+        (
+          "Unbold",
+          Msg::StyleChanged {
+            change: StyleChange::Bold(false),
+          },
+        ),
+        (
+          "Italic",
+          Msg::StyleChanged {
+            change: StyleChange::Italic(true),
+          },
+        ),
+        (
+          "Unitalic",
+          Msg::StyleChanged {
+            change: StyleChange::Italic(false),
+          },
+        ),
+        (
+          "Currency Format",
+          Msg::StyleChanged {
+            change: StyleChange::Currency(true),
+          },
+        ),
+        (
+          "Percent Format",
+          Msg::StyleChanged {
+            change: StyleChange::Percent(true),
+          },
+        ),
+        (
+          "Thousands Separators",
+          Msg::StyleChanged {
+            change: StyleChange::Thousands(true),
+          },
+        ),
+        (
+          "General Number Format",
+          Msg::StyleChanged {
+            change: StyleChange::Decimals(None),
+          },
+        ),
+        (
+          "Clear Style",
+          Msg::StyleChanged {
+            change: StyleChange::Clear,
+          },
+        ),
+        (
+          "Validate Selected Cells",
+          Msg::ValidationEditorOpened {
+            x: ROW_HEADER_WIDTH as i32,
+            y: 160,
+          },
+        ),
+        ("Convert Formulas to Values", Msg::ConvertToValues),
+        ("Toggle Negation Precedence", Msg::ToggleNegPrecedence),
+        ("Toggle Decimal Numbers", Msg::ToggleNumberMode),
+        ("Toggle Show Formulas", Msg::ToggleShowFormulas),
+        ("Toggle Manual Calculation", Msg::ToggleManualCalc),
+        ("Toggle Right to Left", Msg::ToggleRtl),
+        ("Export Graph", Msg::ExportGraph),
+        ("Trace Precedents", Msg::TracePrecedents),
+        ("Trace Dependents", Msg::TraceDependents),
+        ("Remove Traces", Msg::TracesRemoved),
+        ("Watch Selected Cells", Msg::WatchAdded),
+        ("Toggle Watch Panel", Msg::WatchPanelToggled),
+        ("List Errors", Msg::ErrorsOpened),
+        (
+          "Split Horizontally",
+          Msg::SplitChanged {
+            split: Some(Split::Horizontal),
+          },
+        ),
+        (
+          "Split Vertically",
+          Msg::SplitChanged {
+            split: Some(Split::Vertical),
+          },
+        ),
+        ("Remove Split", Msg::SplitChanged { split: None }),
+        ("Keyboard Shortcuts", Msg::KeymapOpened),
+        ("Help", Msg::Help),
+      ]
+      .into_iter()
+      .map(|(title, msg)| (title.to_string(), msg)),
+    );
+
+    if !self.filters.is_empty() {
+      commands.push((String::from("Clear Filters"), Msg::FiltersCleared));
+    }
+    if self.sheets.len() > 1 {
+      commands.push((
+        String::from("Delete Sheet"),
+        Msg::SheetDeleted {
+          index: self.active_sheet,
+        },
+      ));
+      commands.extend(
+        self
+          .sheets
+          .iter()
+          .enumerate()
+          .filter(|(index, _)| *index != self.active_sheet)
+          .map(|(index, sheet)| {
+            (
+              format!("Go to Sheet {}", sheet.name),
+              Msg::SheetSelected { index },
+            )
+          }),
+      );
+    }
+
+    let Some(cell_id) = self.edit_state.current_cell() else {
+      return commands;
+    };
+    let header_command = |title: String, command| (title, Msg::HeaderMenuCommand { command });
+    commands.extend([
+      (
+        match self.frozen {
+          Some(_) => String::from("Unfreeze Panes"),
+          None => format!("Freeze Panes up to {cell_id}"),
+        },
+        Msg::ToggleFrozenPanes,
+      ),
+      (
+        match self.merge_at(cell_id) {
+          Some(_) => String::from("Unmerge Cells"),
+          None => String::from("Merge Selected Cells"),
+        },
+        Msg::ToggleMerge,
+      ),
+      (
+        format!("Fit Column {} to Contents", cell_id.col),
+        Msg::ColAutofit { col: cell_id.col },
+      ),
+      header_command(
+        format!("Sort Ascending by Column {}", cell_id.col),
+        self.sort_rows(cell_id.col, false),
+      ),
+      header_command(
+        format!("Sort Descending by Column {}", cell_id.col),
+        self.sort_rows(cell_id.col, true),
+      ),
+      header_command(
+        format!("Insert Row Above {}", cell_id.row),
+        Command::InsertRow(cell_id.row),
+      ),
+      header_command(
+        format!("Delete Row {}", cell_id.row),
+        Command::DeleteRow(cell_id.row),
+      ),
+      header_command(
+        format!("Insert Column Left of {}", cell_id.col),
+        Command::InsertCol(cell_id.col),
+      ),
+      header_command(
+        format!("Delete Column {}", cell_id.col),
+        Command::DeleteCol(cell_id.col),
+      ),
+    ]);
+
+    commands
+  }
+
+  fn rule_at(&self, cell_id: CellId) -> Option<&Rule> {
+    self
+      .sheet
+      .validations
+      .iter()
+      .rfind(|(validated, _)| validated.contains(cell_id))
+      .map(|(_, rule)| rule)
+  }
+
+  /// Returns the validation rule broken by the value of the cell, if any.
+  fn invalid_rule(&self, cell_id: CellId) -> Option<&Rule> {
+    let rule = self.rule_at(cell_id)?;
+    match self.computed(cell_id) {
+      Some(Ok(value)) if !rule.check(value) => Some(rule),
+      _ => None,
+    }
+  }
+
+  /// Reverts the input typed into the cell if it breaks the validation rule of the cell.
+  fn reject_invalid_typing(&mut self, cell_id: CellId) {
+    let Some(rule) = self.invalid_rule(cell_id) else {
+      return;
+    };
+
+    let message = format!("{cell_id} {}", rule.describe());
+    if let Some(inverse) = self.sheet.history.discard_typing(cell_id) {
+      self
+        .notifications
+        .error(format!("rejected the input: {message}"));
+      self.apply(&inverse);
+    }
+  }
+
+  /// Returns the cells matching the Ctrl+F search, row by row.
+  fn found_cells(&self) -> Vec<CellId> {
+    let Some(query) = &self.find_query else {
+      return vec![];
+    };
+
+    // spilled arrays have values, but no inputs
+    find(
+      self
+        .sheet
+        .cells
+        .keys()
+        .map(|&cell_id| (cell_id, self.input(cell_id), self.formatted_value(cell_id))),
+      query,
+    )
+  }
+
+  /// Returns the inputs of the cells with the searched text replaced, skipping the unchanged ones.
+  fn replaced_inputs(&self, cells: &[CellId]) -> Vec<(CellId, String)> {
+    let query = self.find_query.clone().unwrap_or_default();
+    cells
+      .iter()
+      .map(|cell_id| {
+        let input = self.input(*cell_id);
+        (*cell_id, replace(&input, &query, &self.replacement), input)
+      })
+      .filter(|(_, replaced, input)| replaced != input)
+      .map(|(cell_id, replaced, _)| (cell_id, replaced))
+      .collect()
+  }
+
+  /// Renders the find bar, if it's open.
+  fn view_find_bar(&self, ctx: &Context<Self>) -> Html {
+    let Some(query) = &self.find_query else {
+      return html! {};
+    };
+
+    let found = self.found_cells();
+    let current = self.edit_state.current_cell();
+    let status =
+      match current.and_then(|current| found.iter().position(|cell_id| *cell_id == current)) {
+        Some(index) => format!("{} of {}", index + 1, found.len()),
+        None => format!("{} found", found.len()),
+      };
+    let onkeydown = ctx
+      .link()
+      .callback(move |ev: KeyboardEvent| Msg::FindKeyDown {
+        chord: Chord::from_event(&ev),
+      });
+
+    html! {
+      <div class="flex gap-4 items-center ml-[3rem]">
+        <input
+          id="find-input"
+          type="text"
+          placeholder="Find"
+          class="w-[16rem] px-2 py-0.5 outline-none font-mono border-[1px] border-indigo-900 bg-indigo-800"
+          value={ query.clone() }
+          oninput={ ctx.link().callback(move |ev: InputEvent| {
+            let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            Msg::FindQueryChanged { query: input.value() }
+          })}
+          onkeydown={ onkeydown.clone() }
+        />
+        <span class="w-[8rem] text-base text-neutral-300">{ status }</span>
+        <Btn
+          title="Previous"
+          color={ BtnColors::Violet }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::FindNext { backwards: true } }) }
+        />
+        <Btn
+          title="Next"
+          color={ BtnColors::Violet }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::FindNext { backwards: false } }) }
+        />
+        <input
+          type="text"
+          placeholder="Replace with"
+          class="w-[16rem] px-2 py-0.5 outline-none font-mono border-[1px] border-indigo-900 bg-indigo-800"
+          value={ self.replacement.clone() }
+          oninput={ ctx.link().callback(move |ev: InputEvent| {
+            let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            Msg::ReplacementChanged { replacement: input.value() }
+          })}
+          {onkeydown}
+        />
+        <Btn
+          title="Replace"
+          color={ BtnColors::Violet }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::Replace }) }
+        />
+        <Btn
+          title="Replace All"
+          color={ BtnColors::Violet }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::ReplaceAll }) }
+        />
+        <Btn
+          title="Close"
+          color={ BtnColors::Purple }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::FindClosed }) }
+        />
+      </div>
+    }
+  }
+
+  /// Shows the statistics of the numbers in the selection, skipping the rows hidden by the filters.
+  fn view_status_bar(&self, ctx: &Context<Self>) -> Html {
+    let hidden_rows = self.hidden_rows();
+    let stats = self.selection.and_then(|selection| {
+      Stats::of(
+        selection
+          .cells()
+          .filter(|cell_id| !hidden_rows.contains(&cell_id.row))
+          .filter_map(|cell_id| self.computed(cell_id)?.as_ref().ok()),
+      )
+    });
+    let num = |num: f64| Value::Num(num).to_string();
+    let error_count = self
+      .sheet
+      .cells
+      .values()
+      .filter(|record| record.parse_error.is_some() || matches!(record.computed, Some(Err(_))))
+      .count();
+
+    html! {
+      <div class="w-screen grow-0 flex justify-end gap-6 px-4 py-1 min-h-[2rem] font-mono text-base text-neutral-300 bg-indigo-900">
+        <div class="mr-auto flex gap-6">
+          if self.sheet.manual_calc && !self.sheet.changed.is_empty() {
+            <span class="text-amber-300" title="Some cells changed since the last recalculation">
+              { format!("Calculate ({} changed)", self.sheet.changed.len()) }
+            </span>
+          }
+          if error_count > 0 {
+            <button
+              class="text-red-300 hover:text-red-200"
+              title="List the cells with errors"
+              onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::ErrorsOpened }) }
+            >
+              { format!("{error_count} {}", if error_count == 1 { "error" } else { "errors" }) }
+            </button>
+          }
+        </div>
+        if let Some(stats) = stats {
+          <span>{ format!("Sum: {}", num(stats.sum)) }</span>
+          <span>{ format!("Average: {}", num(stats.average())) }</span>
+          <span>{ format!("Count: {}", stats.count) }</span>
+          <span>{ format!("Min: {}", num(stats.min)) }</span>
+          <span>{ format!("Max: {}", num(stats.max)) }</span>
+        }
+      </div>
+    }
+  }
+
+  /// Returns the cells failing to parse or to evaluate, row by row.
+  fn problems(&self) -> Vec<CellProblem> {
+    let mut problems = self
+      .sheet
+      .cells
+      .iter()
+      .filter_map(|(cell_id, record)| {
+        let message = match (&record.parse_error, &record.computed) {
+          (Some(message), _) => message.clone(),
+          (None, Some(Err(err))) => err.message.clone(),
+          _ => return None,
+        };
+        Some(CellProblem {
+          cell_id: *cell_id,
+          input: self.input(*cell_id),
+          message,
+        })
+      })
+      .collect::<Vec<_>>();
+    problems.sort_by_key(|problem| problem.cell_id);
+    problems
+  }
+
+  /// Lists the errors reported since they were last dismissed, the latest first.
+  fn view_errors_banner(&self, ctx: &Context<Self>) -> Html {
+    let errors = self.notifications.errors();
+    if errors.is_empty() {
+      return html! {};
+    }
+
+    html! {
+      <div class="flex gap-4 items-start ml-[3rem] px-2 py-0.5 text-base bg-red-900 border-[1px] border-red-700">
+        <ul class="grow">
+          { errors.iter().rev().map(|error| html! { <li>{ error }</li> }).collect::<Html>() }
+        </ul>
+        <Btn
+          title="Dismiss"
+          color={ BtnColors::Purple }
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| { Msg::ErrorsDismissed }) }
+        />
+      </div>
+    }
+  }
+
+  /// Describes the circular references; clicking one selects its first cell.
+  fn view_cycles_banner(&self, ctx: &Context<Self>) -> Html {
+    if self.sheet.cycles.is_empty() {
+      return html! {};
+    }
+
+    let view_cycle = |cycle: &Vec<CellId>| {
+      let first = cycle[0];
+      html! {
+        <button
+          class="font-mono hover:underline"
+          onclick={ ctx.link().callback(move |_ev: MouseEvent| {
+            Msg::Selected { selection: Selection::new(first) }
+          })}
+        >
+          { format_cycle(cycle) }
+        </button>
+      }
+    };
+
+    html! {
+      <div class="flex flex-wrap gap-4 items-center ml-[3rem] px-2 py-0.5 text-base bg-red-900 border-[1px] border-red-700">
+        <span>{ "Circular references:" }</span>
+        { self.sheet.cycles.iter().map(view_cycle).collect::<Html>() }
+      </div>
+    }
+  }
+
+  /// Returns the rows hidden by the filters, among the rows after the first used one,
+  /// which is taken as the header, up to the last used one.
+  fn hidden_rows(&self) -> HashSet<usize> {
+    let filters = self
+      .filters
+      .iter()
+      .filter_map(|(col, text)| Some((*col, Filter::parse(text)?)))
+      .collect::<Vec<_>>();
+    let inputs = self.inputs();
+    let rows = inputs.keys().map(|cell_id| cell_id.row);
+    let (Some(first), Some(last)) = (rows.clone().min(), rows.max()) else {
+      return HashSet::new();
+    };
+
+    (first + 1..=last)
+      .filter(|row| {
+        !filters.iter().all(|(col, filter)| {
+          let cell_id = CellId {
+            col: *col,
+            row: *row,
+          };
+          match self.computed(cell_id) {
+            Some(Ok(value)) => filter.matches(value),
+            Some(Err(err)) => filter.matches(&Value::Str(err.kind.code().to_string())),
+            None => filter.matches(&Value::Empty),
+          }
+        })
+      })
+      .collect()
+  }
+
+  /// Renders the cells in a pane of the split view, or in the only one.
+  fn view_grid(&self, ctx: &Context<Self>, pane: usize) -> Html {
+    let size = self.sheet.size;
+    let hidden_rows = self.hidden_rows();
+    let found = self.found_cells().into_iter().collect::<HashSet<_>>();
+    let found = &found;
+    let (suggestions, hint) = self.completions();
+    let (suggestions, hint) = (&suggestions, &hint);
+    // the cells referenced by the formula being typed are painted with the colors of the references
+    let painted = self
+      .edit_state
+      .input_cell()
+      .map(|cell_id| painted_refs(&self.input(cell_id)))
+      .unwrap_or_default();
+    let painted = &painted;
+
+    html! {
+    <div
+      class={classes!(vec![
+        "overflow-scroll snap-y snap-mandatory pb-4",
+        if self.split.is_some() { "flex-1 min-h-0 min-w-0" } else { "" },
+        if pane > 0 { "border-t-2 border-l-2 border-indigo-700" } else { "" },
+      ])}
+      dir={ if self.rtl { "rtl" } else { "ltr" } }
+      onfocusin={ ctx.link().callback(move |_ev: FocusEvent| { Msg::PaneFocused { pane } }) }
+    >
+      <table
+        class="table table-fixed"
+        role="grid"
+        aria-label={ self.sheet_names().get(self.active_sheet).cloned() }
+        aria-multiselectable="true"
+        aria-rowcount={ (self.sheet.size.rows + 1).to_string() }
+        aria-colcount={ (self.sheet.size.cols + 1).to_string() }
+      >
+        <thead>
+          <tr class="snap-start" role="row" aria-rowindex="1">
+            <th
+              class="sticky top-0 [inset-inline-start:0] snap-start [padding-inline:1.5rem_1rem] z-40 h-[2.125rem] bg-indigo-900"
+              style={ format!("width: {ROW_HEADER_WIDTH}px; min-width: {ROW_HEADER_WIDTH}px;") }
+            >
+            </th>
+            {
+              // col id headers
+              self.sheet.size.cols().map(move |col| {
+                let header_style =
+                    match self.edit_state.focused_cell() {
+                        Some(CellId{ col: focused_col, .. }) if focused_col == col =>
+                            "text-neutral-300 hover:text-neutral-200",
+                        _ => "text-neutral-400 hover:text-neutral-300",
+                    };
+
+                html! {
+                  <th id={ element_id(pane, &format!("header-col-{col}")) }
+                    role="columnheader"
+                    onclick={ ctx.link().callback(move |_ev: MouseEvent| {
+                      Msg::Selected { selection: Selection::col(col, size) }
+                    })}
+                    oncontextmenu={ ctx.link().callback(move |ev: MouseEvent| {
+                      ev.prevent_default();
+                      Msg::HeaderMenuOpened { header: Header::Col(col), x: ev.client_x(), y: ev.client_y() }
+                    })}
+                    class={classes!(vec![
+                        "sticky top-0 snap-start h-[2.125rem] bg-clip-padding bg-indigo-900 text-center",
+                        if self.frozen_left(col).is_some() { "z-[36]" } else { "z-30" },
+                        header_style
+                    ])}
+                    style={ self.frozen_left(col).map(|left| format!("inset-inline-start: {left}px;")) }>
+                    { col }
+                    <button
+                      title="Filter the rows by the column"
+                      class={classes!(vec![
+                        "ml-1 text-sm",
+                        if self.filters.contains_key(&col) { "text-amber-300" } else { "text-neutral-500 hover:text-neutral-300" },
+                      ])}
+                      onclick={ ctx.link().callback(move |ev: MouseEvent| {
+                        ev.stop_propagation();
+                        Msg::FilterEditorOpened { col, x: ev.client_x(), y: ev.client_y() }
+                      })}
+                    >
+                      { "▾" }
+                    </button>
+                    <div
+                      title="Double-click to fit the column to its contents"
+                      class="absolute top-0 -right-1 w-2 h-full cursor-col-resize"
+                      onclick={ |ev: MouseEvent| ev.stop_propagation() }
+                      ondblclick={ ctx.link().callback(move |_ev: MouseEvent| {
+                        Msg::ColAutofit { col }
+                      })}
+                    />
+                  </th>
+                }
+              }).collect::<Html>()
+            }
+          </tr>
+        </thead>
+        <tbody>
+          {
+            (1..=self.sheet.size.rows).filter(move |row| !hidden_rows.contains(row)).map(move |row| {
+              html! {
+                // the filtered out rows are skipped, so each row tells its index
+                <tr role="row" aria-rowindex={ (row + 1).to_string() }>
+                {
+                  std::iter::once('@').chain(self.sheet.size.cols()).map(move |col| {
+                    // row id header
+                    if col == '@' {
+                      let header_style =
+                        match self.edit_state.focused_cell() {
+                          Some(CellId{ row: focused_row, .. }) if focused_row == row =>
+                            "text-neutral-300 hover:text-neutral-200",
+                          _ => "text-neutral-400 hover:text-neutral-300",
+                        };
+
+                      html! {
+                        <th id={ element_id(pane, &format!("header-row-{row}")) }
+                          role="rowheader"
+                          onclick={ ctx.link().callback(move |_ev: MouseEvent| {
+                            Msg::Selected { selection: Selection::row(row, size) }
+                          })}
+                          oncontextmenu={ ctx.link().callback(move |ev: MouseEvent| {
+                            ev.prevent_default();
+                            Msg::HeaderMenuOpened { header: Header::Row(row), x: ev.client_x(), y: ev.client_y() }
+                          })}
+                          class={
+                          classes!(vec![
+                              "sticky [inset-inline-start:0] snap-start [padding-inline:1.5rem_1rem] bg-indigo-900 [text-align:end]",
+                              if self.frozen_top(row).is_some() { "z-[36]" } else { "z-[35]" },
+                              header_style
+                          ])
+                        }
+                        style={ self.frozen_top(row).map(|top| format!("top: {top}rem;")) }>
+                            { row }
+                        </th>
+                      }
+                    } else {
+                      let cell_id = CellId { col, row };
+                      // the cells merged away are covered by the top left cell spanning them
+                      let merged = self.merge_at(cell_id);
+                      if merged.is_some_and(|merged| merged.anchor != cell_id) {
+                        return html! {};
+                      }
+                      let extent = merged.map_or(cell_id, |merged| merged.extent);
+                      let is_typed_in = self.edit_state.is_typed_in(cell_id);
+                      let record = self.sheet.cells.get(&cell_id);
+
+                      html! {
+                        <Cell
+                          {pane}
+                          {cell_id}
+                          {size}
+                          colspan={(extent.col as u8 - col as u8) as usize + 1}
+                          rowspan={extent.row - row + 1}
+                          width={(col..=extent.col).map(|col| self.col_width(col)).sum::<u32>()}
+                          frozen_top={self.frozen_top(row)}
+                          frozen_left={self.frozen_left(col)}
+                          is_focused={self.edit_state.focused_cell() == Some(cell_id)}
+                          is_input={self.edit_state.input_cell() == Some(cell_id)}
+                          is_selected={self.is_selected(cell_id)}
+                          is_found={found.contains(&cell_id)}
+                          show_formulas={self.show_formulas}
+                          is_precedent={self.precedents.contains(&cell_id)}
+                          is_dependent={self.dependents.contains(&cell_id)}
+                          in_cycle={self.sheet.cycles.iter().any(|cycle| cycle.contains(&cell_id))}
+                          style={self.style(cell_id)}
+                          options={
+                            match self.rule_at(cell_id) {
+                              Some(Rule::List(allowed)) => Some(allowed.clone()),
+                              _ => None,
+                            }
+                          }
+                          invalid={self.invalid_rule(cell_id).map(|rule| format!("{cell_id} {}", rule.describe()))}
+                          painted={paint_css(painted, cell_id)}
+                          parse_error={record.and_then(|record| record.parse_error.clone())}
+                          has_fill_handle={self.fill_handle_cell() == Some(cell_id)}
+                          input={record.and_then(|record| record.input.clone())}
+                          expr={record.and_then(|record| record.expr.clone())}
+                          computed={record.and_then(|record| record.computed.clone())}
+                          onfocused={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::CellFocused { cell_id }
+                            })
+                          }
+                          onfocusout={
+                            ctx.link().callback(move |_ev: FocusEvent| {
+                              Msg::CellLostFocus { cell_id }
+                            })
+                          }
+                          onbecameinput={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::CellBecameInput { cell_id }
+                            })
+                          }
+                          onlostinput={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::CellLostInput { cell_id }
+                            })
+                          }
+                          onedit={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::CellEditStarted { cell_id }
+                            })
+                          }
+                          onjump={
+                            ctx.link().callback(move |jump| {
+                              Msg::CellJumped { cell_id, jump }
+                            })
+                          }
+                          onselectionstarted={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::SelectionStarted { cell_id }
+                            })
+                          }
+                          onselectionextended={
+                            ctx.link().callback(move |cell_id| {
+                              Msg::SelectionExtended { cell_id }
+                            })
+                          }
+                          onselectionjumped={
+                            ctx.link().callback(move |jump| {
+                              Msg::SelectionJumped { cell_id, jump }
+                            })
+                          }
+                          onselected={
+                            ctx.link().callback(move |selection| {
+                              Msg::Selected { selection }
+                            })
+                          }
+                          oncopy={ ctx.link().callback(move |()| { Msg::CopySelection { is_cut: false } }) }
+                          oncut={ ctx.link().callback(move |()| { Msg::CopySelection { is_cut: true } }) }
+                          onpaste={ ctx.link().callback(move |text| { Msg::Paste { text } }) }
+                          onfillstarted={ ctx.link().callback(move |()| { Msg::FillStarted }) }
+                          keymap={ self.keymap.clone() }
+                          onaction={ ctx.link().batch_callback(Table::action_msg) }
+                          suggestions={ if is_typed_in { suggestions.clone() } else { vec![] } }
+                          suggestion_index={ if is_typed_in { self.completion_index } else { 0 } }
+                          hint={ if is_typed_in { hint.clone() } else { None } }
+                          oncompletionkey={ ctx.link().callback(move |chord| { Msg::CompletionKey { chord } }) }
+                          oncompletionpicked={ ctx.link().callback(move |index| { Msg::CompletionPicked { index } }) }
+                          oninput={
+                            ctx.link().callback(move |ev: InputEvent| {
+                              let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+                              let new_value = input.value();
+
+                              Msg::CellChanged { cell_id, new_value }
+                            })
+                          }
+                          sendinput={
+                            ctx.link().callback(move |new_value: String| {
+                              Msg::CellChanged { cell_id, new_value }
+                            })
+                          }
+                        />
+                      }
+                    }
+                  }).collect::<Html>()
+                }
+                </tr>
+              }
+            }).collect::<Html>()
+          }
+        </tbody>
+      </table>
+    </div>
+    }
+  }
+
+  /// Renders the editor of the filter of a column, if it's open.
+  fn view_filter_editor(&self, ctx: &Context<Self>) -> Html {
+    let Some((col, x, y)) = self.filter_editor else {
+      return html! {};
+    };
+
+    html! {
+      <div
+        class="fixed z-[60] flex flex-col gap-1 p-2 text-base bg-indigo-800 border-[1px] border-indigo-900 shadow-lg"
+        style={ format!("left: {x}px; top: {y}px;") }
+        onclick={ |ev: MouseEvent| ev.stop_propagation() }
+      >
+        <label for="filter-editor">{ format!("Show the rows where {col} is") }</label>
+        <input
+          id="filter-editor"
+          type="text"
+          placeholder="> 10, = done, or text"
+          class="px-2 py-0.5 outline-none font-mono bg-indigo-900"
+          value={ self.filters.get(&col).cloned().unwrap_or_default() }
+          oninput={ ctx.link().callback(move |ev: InputEvent| {
+            let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            Msg::FilterChanged { col, text: input.value() }
+          })}
+        />
+      </div>
+    }
+  }
+
+  fn view_validation_editor(&self, ctx: &Context<Self>) -> Html {
+    let Some((selection, x, y)) = self.validation_editor else {
+      return html! {};
+    };
+
+    let (top_left, bottom_right) = selection.bounds();
+    let range = if selection.is_single_cell() {
+      top_left.to_string()
+    } else {
+      format!("{top_left}:{bottom_right}")
+    };
+
+    html! {
+      <div
+        class="fixed z-[60] flex flex-col gap-1 p-2 text-base bg-indigo-800 border-[1px] border-indigo-900 shadow-lg"
+        style={ format!("left: {x}px; top: {y}px;") }
+        onclick={ |ev: MouseEvent| ev.stop_propagation() }
+      >
+        <label for="validation-editor">{ format!("Allow in {range}") }</label>
+        <input
+          id="validation-editor"
+          type="text"
+          placeholder="1..10, yes, no, or /^[A-Z]+$/"
+          class="px-2 py-0.5 outline-none font-mono bg-indigo-900"
+          value={ self.validation_text.clone() }
+          oninput={ ctx.link().callback(move |ev: InputEvent| {
+            let input: HtmlInputElement = ev.target().unwrap().dyn_into().unwrap();
+            Msg::ValidationChanged { text: input.value() }
+          })}
+        />
+        if let Err(err) = Rule::parse(&self.validation_text) {
+          <span class="text-red-300">{ err.to_string() }</span>
+        }
+      </div>
+    }
+  }
+
+  /// Renders the context menu of the right-clicked header, if any.
+  fn view_header_menu(&self, ctx: &Context<Self>) -> Html {
+    let Some((header, x, y)) = self.header_menu else {
+      return html! {};
+    };
+
+    let entries = match header {
+      Header::Row(row) => vec![
+        ("Insert Row Above", Command::InsertRow(row)),
+        ("Insert Row Below", Command::InsertRow(row + 1)),
+        ("Delete Row", Command::DeleteRow(row)),
+      ],
+      Header::Col(col) => vec![
+        ("Sort Ascending", self.sort_rows(col, false)),
+        ("Sort Descending", self.sort_rows(col, true)),
+        ("Insert Column Left", Command::InsertCol(col)),
+        (
+          "Insert Column Right",
+          Command::InsertCol((col as u8 + 1) as char),
+        ),
+        ("Delete Column", Command::DeleteCol(col)),
+      ],
+    };
+
+    html! {
+      <div
+        class="fixed z-[60] flex flex-col py-1 text-base bg-indigo-800 border-[1px] border-indigo-900 shadow-lg"
+        style={ format!("left: {x}px; top: {y}px;") }
+      >
+        {
+          entries.into_iter().map(|(title, command)| html! {
+            <button
+              class="px-4 py-1 text-left hover:bg-indigo-700"
+              onclick={ ctx.link().callback(move |ev: MouseEvent| {
+                ev.stop_propagation();
+                Msg::HeaderMenuCommand { command: command.clone() }
+              })}
+            >
+              { title }
+            </button>
+          }).collect::<Html>()
+        }
+      </div>
+    }
+  }
+
+  /// Applies the command, so that it can be undone.
+  fn execute(&mut self, command: Command) {
+    let inverse = command.invert(self);
+    self.apply(&command);
+    self.sheet.history.record(command, inverse);
+  }
+
+  /// Sets the input typed into the cell; typing into the same cell is undone at once.
+  fn type_input(&mut self, cell_id: CellId, input: String) {
+    self.completion_index = 0;
+    self.completions_closed = false;
+    let inverse = Command::SetInputs(vec![(cell_id, self.input(cell_id))]);
+    self.set_input(cell_id, input.clone());
+    self.reeval();
+    self.sheet.history.record_typing(cell_id, input, inverse);
+  }
+
+  /// Applies the command and recomputes the changed cells, without recording it.
+  fn apply(&mut self, command: &Command) {
+    command.apply(self);
+    self.reeval();
+    if let Some(cell_id) = self.edit_state.input_cell() {
+      self.edit_state.set_draft(self.input(cell_id));
+    }
+  }
+
+  /// Returns the text of the big input: the typed text, or the input of the focused cell.
+  fn big_input_text(&self) -> String {
+    match &self.edit_state {
+      EditState::Editing { draft, .. } => draft.clone(),
+      EditState::Focused(cell_id) => self.input(*cell_id),
+      EditState::Idle { .. } => String::new(),
+    }
+  }
+
+  /// Returns the text shown in the cell, see `Cell`.
+  fn displayed_value(&self, cell_id: CellId) -> String {
+    match self.computed(cell_id) {
+      Some(Ok(value)) => value.to_string(),
+      Some(Err(err)) => err.kind.code().to_string(),
+      None => self.input(cell_id),
+    }
+  }
+
+  /// Returns the values of the formulas in the selection, including the cells filled
+  /// by spilled arrays, as the inputs replacing them; the formulas failing to evaluate are kept.
+  fn formula_values(&self, selection: Selection) -> Vec<(CellId, String)> {
+    selection
+      .cells()
+      .filter(|cell_id| match self.input_of(*cell_id) {
+        Some(input) => input.trim_start().starts_with('='),
+        None => true,
+      })
+      .filter_map(|cell_id| match self.computed(cell_id)? {
+        Ok(Value::Empty) | Err(_) => None,
+        Ok(value) => Some((cell_id, value.to_string())),
+      })
+      .collect()
+  }
+
+  /// Returns the displayed value with the number format of the cell; copied cells keep
+  /// the exact values instead.
+  fn formatted_value(&self, cell_id: CellId) -> String {
+    match self.computed(cell_id) {
+      Some(Ok(value)) => self.style(cell_id).number_format.format(value),
+      _ => self.displayed_value(cell_id),
+    }
+  }
+
+  /// Returns what the screen readers announce when a cell gets the focus, e.g., `B2 42`.
+  fn focus_announcement(&self) -> String {
+    let Some(cell_id) = self.edit_state.focused_cell() else {
+      return String::new();
+    };
+    let value = if self.show_formulas {
+      self.input(cell_id)
+    } else {
+      self.formatted_value(cell_id)
+    };
+    if value.is_empty() {
+      format!("{cell_id} blank")
+    } else {
+      format!("{cell_id} {value}")
+    }
+  }
+
+  /// Returns the decimal places shown in the cell, to add or remove one.
+  fn shown_decimals(&self, cell_id: CellId) -> usize {
+    match self.style(cell_id).number_format.decimals {
+      Some(decimals) => decimals,
+      None => self
+        .formatted_value(cell_id)
+        .trim_end_matches('%')
+        .split_once('.')
+        .map_or(0, |(_, frac)| frac.len()),
+    }
+  }
+
+  /// Re-parses all inputs, e.g., after the parsing settings were changed.
+  fn reparse(&mut self) {
+    for record in self.sheet.cells.values_mut() {
+      let Some(input) = &record.input else {
+        continue;
+      };
+      record.parse_error = None;
+      let expr = parse_with(input, self.sheet.neg_precedence).unwrap_or_else(|err| {
+        record.parse_error = Some(err);
+        Expr::Str(input.to_string())
+      });
+      record.expr = Some(Rc::new(expr));
+    }
+    self.sheet.graph = State::from_references(&self.exprs(), &self.limits);
+
+    self.recalculate();
+  }
+
+  /// Serializes the document with all its sheets.
+  fn cells_to_str(&self) -> String {
+    let sheets = self
+      .sheets
+      .iter()
+      .map(|sheet| Sheet {
+        name: sheet.name.clone(),
+        table: sheet
+          .table
+          .as_ref()
+          .map(|table| Box::new(table.to_serializable())),
+      })
+      .collect();
+    let t = SerializableTable {
+      keymap: (*self.keymap).clone(),
+      sheets,
+      active_sheet: self.active_sheet,
+      ..self.sheet.to_serializable()
+    };
+    serde_json::to_string(&t).unwrap()
+  }
+
+  /// Replaces the document with the deserialized one, loading all its sheets.
+  fn cells_from_str(&mut self, encoded: &str) {
+    // all the sheets are parsed before loading any, so that a failing one changes nothing
+    let parsed = parse_from_input(encoded).and_then(|mut parsed| {
+      let sheets = std::mem::take(&mut parsed.sheets)
+        .into_iter()
+        .map(|sheet| {
+          let table = sheet.table.map(|table| parse_table(*table)).transpose()?;
+          Ok(Sheet {
+            name: sheet.name,
+            table,
+          })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+      Ok((parsed, sheets))
+    });
+
+    match parsed {
+      Ok((mut parsed, sheets)) => {
+        let active_sheet = parsed.active_sheet.min(sheets.len().saturating_sub(1));
+        let mut loaded = Vec::with_capacity(sheets.len());
+        for (index, sheet) in sheets.into_iter().enumerate() {
+          let table = match sheet.table {
+            _ if index == active_sheet => None,
+            Some(table) => {
+              self.load_sheet(table);
+              Some(std::mem::take(&mut self.sheet))
+            }
+            None => Some(SheetData::default()),
+          };
+          loaded.push(Sheet {
+            name: sheet.name,
+            table,
+          });
+        }
+
+        self.keymap = Rc::new(std::mem::take(&mut parsed.keymap));
+        self.load_sheet(parsed);
+        self.sheets = loaded;
+        self.active_sheet = active_sheet;
+        self.renaming_sheet = None;
+      }
+      Err(err) => self.notifications.error(err.to_string()),
+    }
+  }
+
+  /// Replaces the cells and the settings of the active sheet.
+  fn load_sheet(&mut self, parsed: ParsedTable) {
+    let ParsedTable {
+      inputs,
+      mut exprs,
+      mut parse_errors,
+      neg_precedence,
+      number_mode,
+      manual_calc,
+      watched,
+      size,
+      merges,
+      validations,
+      styles,
+      ..
+    } = parsed;
+    self.sheet = SheetData {
+      graph: State::from_references(&exprs, &self.limits),
+      cells: inputs
+        .into_iter()
+        .map(|(cell_id, input)| {
+          let record = CellRecord {
+            input: Some(Rc::from(input)),
+            expr: exprs.remove(&cell_id).map(Rc::new),
+            computed: None,
+            parse_error: parse_errors.remove(&cell_id),
+          };
+          (cell_id, record)
+        })
+        .collect(),
+      size,
+      neg_precedence,
+      number_mode,
+      manual_calc,
+      watched,
+      styles,
+      validations,
+      ..SheetData::default()
+    };
+    for merged in merges {
+      self.set_merged(merged, true);
+    }
+    self.recalculate();
+  }
+
+  /// Returns the names of the sheets, or the default name of the only sheet.
+  fn sheet_names(&self) -> Vec<String> {
+    if self.sheets.is_empty() {
+      vec![new_name(&self.sheets)]
+    } else {
+      self.sheets.iter().map(|sheet| sheet.name.clone()).collect()
+    }
+  }
+
+  /// Returns the sheets, adding the only sheet of a new document.
+  fn sheets_mut(&mut self) -> &mut Vec<Sheet<SheetData>> {
+    if self.sheets.is_empty() {
+      self.sheets.push(Sheet::new(new_name(&self.sheets)));
+      self.active_sheet = 0;
+    }
+    &mut self.sheets
+  }
+
+  /// Clears the cells of the active sheet with their styles, merges, validation rules, and
+  /// undo history, and the view of them. The settings of the sheet, the other sheets,
+  /// and the preferences of the user stay.
+  fn clear_sheet(&mut self) {
+    let SheetData {
+      neg_precedence,
+      number_mode,
+      manual_calc,
+      watched,
+      ..
+    } = std::mem::take(&mut self.sheet);
+    self.sheet = SheetData {
+      neg_precedence,
+      number_mode,
+      manual_calc,
+      watched,
+      ..SheetData::default()
+    };
+    self.reset_view();
+  }
+
+  /// Keeps the cells, the settings, and the undo history of the active sheet, and switches
+  /// to the sheet at `index`, resetting the view.
+  fn switch_sheet(&mut self, index: usize) {
+    if index == self.active_sheet || index >= self.sheets.len() {
+      return;
+    }
+
+    let sheet = self.sheets[index].table.take().unwrap_or_default();
+    self.sheets[self.active_sheet].table = Some(std::mem::replace(&mut self.sheet, sheet));
+    self.active_sheet = index;
+    self.reset_view();
+  }
+
+  /// Resets the view of the active sheet, e.g., the selection, the filters, and the traced
+  /// cells, after its cells were replaced.
+  fn reset_view(&mut self) {
+    self.edit_state = EditState::default();
+    self.name_box_text = None;
+    self.selection = None;
+    self.inserted_ref = None;
+    self.is_dragging_ref = false;
+    self.clipboard = None;
+    self.fill = None;
+    self.paste_special_target = None;
+    self.header_menu = None;
+    self.frozen = None;
+    self.filters.clear();
+    self.filter_editor = None;
+    self.validation_editor = None;
+    self.pending_focus = None;
+    self.find_query = None;
+    self.split = None;
+    self.active_pane = 0;
+    self.precedents.clear();
+    self.dependents.clear();
+  }
+
+  fn edit_cell_value_if_formula_cell_reference_insertion(
+    &self,
+    clicked_on_cell: CellId,
+  ) -> Option<(CellId, String)> {
+    match self.edit_state.input_cell() {
+      Some(another_cell_id) if another_cell_id != clicked_on_cell => {
+        let another_cell_value = self.input(another_cell_id);
+
+        if another_cell_value.trim_start().starts_with('=') {
+          Some((another_cell_id, another_cell_value))
+        } else {
+          None
         }
       }
       _ => None,
     }
   }
 
+  /// Inserts the reference to the range into the formula typed into the cell, replacing
+  /// the reference inserted before, unless something was typed after it.
+  fn insert_ref(&mut self, edit_cell_id: CellId, selection: Selection) {
+    let reference = |selection: Selection| match selection.bounds() {
+      (top_left, bottom_right) if top_left == bottom_right => top_left.to_string(),
+      (top_left, bottom_right) => format!("{top_left}:{bottom_right}"),
+    };
+
+    let input = self.input(edit_cell_id);
+    let start = match self.inserted_ref {
+      Some((start, inserted)) if input.get(start..) == Some(reference(inserted).as_str()) => start,
+      _ => input.len(),
+    };
+    let new_value = format!("{}{}", &input[..start], reference(selection));
+
+    self.inserted_ref = Some((start, selection));
+    self
+      .edit_state
+      .edit(edit_cell_id, new_value.clone(), self.big_input_focused);
+    self.type_input(edit_cell_id, new_value);
+  }
+
+  /// Focuses the input of the cell, placing the caret at the end of its text.
   fn focus_input_cell(&self, cell_id: CellId) {
     window().and_then(|window| {
       window.document().and_then(|document| {
-        match document.get_element_by_id(&cell_id.to_string()) {
+        match document.get_element_by_id(&element_id(self.active_pane, &cell_id.to_string())) {
           Some(elem) => {
             match elem.dyn_into::<HtmlInputElement>() {
-              Ok(input) => match input.focus() {
-                Ok(_) => (),
-                Err(err) => log_1(&err),
-              },
+              Ok(input) => {
+                // selection offsets are in UTF-16 code units
+                let end = input.value().encode_utf16().count() as u32;
+                if let Err(err) = input.focus().and(input.set_selection_range(end, end)) {
+                  log_1(&err);
+                }
+              }
               Err(err) => log_1(&err),
             }
             Some(())
@@ -443,10 +3200,64 @@ impl Table {
     });
   }
 
+  fn col_width(&self, col: char) -> u32 {
+    self
+      .sheet
+      .col_widths
+      .get(&col)
+      .copied()
+      .unwrap_or(DEFAULT_COL_WIDTH)
+  }
+
+  /// Returns the merged cells containing the cell, if any.
+  fn merge_at(&self, cell_id: CellId) -> Option<Selection> {
+    self
+      .sheet
+      .merges
+      .iter()
+      .copied()
+      .find(|merged| merged.contains(cell_id))
+  }
+
+  /// Returns the offset of the row from the top in rem, if it's frozen.
+  fn frozen_top(&self, row: usize) -> Option<f64> {
+    let frozen = self.frozen?;
+    (row <= frozen.row).then_some(ROW_HEIGHT * row as f64)
+  }
+
+  /// Returns the offset of the column from the left in pixels, if it's frozen.
+  fn frozen_left(&self, col: char) -> Option<u32> {
+    let frozen = self.frozen?;
+    (col <= frozen.col)
+      .then(|| ROW_HEADER_WIDTH + ('A'..col).map(|col| self.col_width(col)).sum::<u32>())
+  }
+
+  /// Focuses the cell, once it's rendered if the table grows to contain it.
+  fn show_cell(&mut self, cell_id: CellId) {
+    if self.sheet.size.contains(cell_id) {
+      self.focus_div_cell(cell_id);
+    } else {
+      self.sheet.size = self.sheet.size.grown_to(cell_id);
+      self.pending_focus = Some(cell_id);
+    }
+  }
+
+  /// Scrolls the cell into view without focusing it, e.g., to keep typing into the find bar.
+  fn scroll_to_cell(&self, cell_id: CellId) {
+    if let Some(elem) = window()
+      .and_then(|window| window.document())
+      .and_then(|document| {
+        document.get_element_by_id(&element_id(self.active_pane, &format!("div_{cell_id}")))
+      })
+    {
+      elem.scroll_into_view();
+    }
+  }
+
   fn focus_div_cell(&self, cell_id: CellId) {
     window().and_then(|window| {
       window.document().and_then(|document| {
-        match document.get_element_by_id(&format!("div_{}", &cell_id.to_string())) {
+        match document.get_element_by_id(&element_id(self.active_pane, &format!("div_{cell_id}"))) {
           Some(elem) => {
             match elem.dyn_into::<HtmlElement>() {
               Ok(div) => {
@@ -468,46 +3279,308 @@ impl Table {
   }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// All changes of the inputs and the settings go through `Command`s.
+impl CommandTarget for Table {
+  fn cells(&self) -> Vec<CellId> {
+    self.inputs().into_keys().collect()
+  }
+
+  fn input(&self, cell_id: CellId) -> String {
+    self
+      .input_of(cell_id)
+      .map_or_else(String::new, |input| input.to_string())
+  }
+
+  fn value(&self, cell_id: CellId) -> Option<Value> {
+    match self.computed(cell_id) {
+      Some(Ok(value)) => Some(value.clone()),
+      Some(Err(_)) => None,
+      None => Some(Value::Empty),
+    }
+  }
+
+  /// Parses the new input of the cell; cleared cells keep only their last computed value.
+  fn set_input(&mut self, cell_id: CellId, input: String) {
+    // the cells merged away keep referencing the top left cell, and their inputs are parsed
+    // after splitting them, e.g., the inputs moved into them
+    if self
+      .merge_at(cell_id)
+      .is_some_and(|merged| merged.bounds().0 != cell_id)
+    {
+      let record = self.sheet.cells.entry(cell_id).or_default();
+      record.input = (!input.is_empty()).then(|| Rc::from(input));
+      return;
+    }
+
+    self.precedents.clear();
+    self.dependents.clear();
+    let mut parse_error = None;
+    let expr = parse_with(&input, self.sheet.neg_precedence).unwrap_or_else(|err| {
+      parse_error = Some(err);
+      Expr::Str(input.clone())
+    });
+    self
+      .sheet
+      .graph
+      .set_dependencies(cell_id, self.limits.deps(&expr));
+    self.sheet.graph.set_volatile(cell_id, expr.is_volatile());
+    self.sheet.graph.set_array(cell_id, expr.may_return_array());
+    let record = self.sheet.cells.entry(cell_id).or_default();
+    record.parse_error = parse_error;
+    if expr == Expr::Empty {
+      record.input = None;
+      record.expr = None;
+      if record.is_empty() {
+        self.sheet.cells.remove(&cell_id);
+      }
+    } else {
+      self.sheet.size = self.sheet.size.grown_to(cell_id);
+      record.input = Some(Rc::from(input));
+      record.expr = Some(Rc::new(expr));
+    }
+    self.sheet.changed.insert(cell_id);
+  }
+
+  fn neg_precedence(&self) -> NegPrecedence {
+    self.sheet.neg_precedence
+  }
+
+  fn set_neg_precedence(&mut self, neg_precedence: NegPrecedence) {
+    self.sheet.neg_precedence = neg_precedence;
+    self.reparse();
+  }
+
+  fn number_mode(&self) -> NumberMode {
+    self.sheet.number_mode
+  }
+
+  fn set_number_mode(&mut self, number_mode: NumberMode) {
+    self.sheet.number_mode = number_mode;
+    self.sheet.changed.extend(self.formula_cells());
+  }
+
+  fn merges(&self) -> Vec<Selection> {
+    self.sheet.merges.clone()
+  }
+
+  /// References to the cells merged away resolve to the top left cell.
+  fn set_merged(&mut self, selection: Selection, is_merged: bool) {
+    self.sheet.merges.retain(|merged| *merged != selection);
+    let (top_left, _) = selection.bounds();
+    for cell_id in selection.cells().filter(|cell_id| *cell_id != top_left) {
+      if is_merged {
+        self.sheet.graph.set_dependencies(cell_id, [top_left]);
+        self.sheet.cells.entry(cell_id).or_default().expr = Some(Rc::new(Expr::CellRef(top_left)));
+        self.sheet.changed.insert(cell_id);
+      } else {
+        self.set_input(cell_id, self.input(cell_id));
+      }
+    }
+
+    if is_merged {
+      self.sheet.merges.push(selection);
+    }
+  }
+
+  fn style(&self, cell_id: CellId) -> Style {
+    self.sheet.styles.get(&cell_id).cloned().unwrap_or_default()
+  }
+
+  fn set_style(&mut self, cell_id: CellId, style: Style) {
+    if style.is_default() {
+      self.sheet.styles.remove(&cell_id);
+    } else {
+      self.sheet.styles.insert(cell_id, style);
+    }
+  }
+
+  fn styled_cells(&self) -> Vec<CellId> {
+    self.sheet.styles.keys().copied().collect()
+  }
+
+  fn validations(&self) -> Vec<(Selection, Rule)> {
+    self.sheet.validations.clone()
+  }
+
+  fn set_validations(&mut self, validations: Vec<(Selection, Rule)>) {
+    self.sheet.validations = validations;
+  }
+}
+
+fn write_to_clipboard(text: String, onerror: Callback<String>) {
+  spawn_local(async move {
+    match web_sys::window().unwrap().navigator().clipboard() {
+      Some(clipboard) => match JsFuture::from(clipboard.write_text(&text)).await {
+        Ok(_) => (),
+        Err(err) => onerror.emit(format!("couldn't copy cells to clipboard due to {err:?}")),
+      },
+      None => onerror.emit(String::from("cannot access clipboard")),
+    }
+  });
+}
+
+/// Resolves after the timeout, e.g., to expire a toast.
+async fn sleep(millis: i32) {
+  let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+    if let Some(window) = window() {
+      let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis);
+    }
+  });
+  let _ = JsFuture::from(promise).await;
+}
+
+/// Downloads the text as a file with the given name.
+fn download(file_name: &str, text: &str) -> Result<(), JsValue> {
+  let document = window()
+    .and_then(|window| window.document())
+    .ok_or("cannot access the document")?;
+  let blob = Blob::new_with_str_sequence(&Array::of1(&JsValue::from_str(text)))?;
+  let url = Url::create_object_url_with_blob(&blob)?;
+
+  let link: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+  link.set_href(&url);
+  link.set_download(file_name);
+  link.click();
+  Url::revoke_object_url(&url)
+}
+
+/// Returns the width of the widest text in pixels, rendered with the font of the element,
+/// or `None` if there are no texts.
+fn measure_text_width(texts: &[String], element_id: &str) -> Option<f64> {
+  let window = window()?;
+  let document = window.document()?;
+  let style = window
+    .get_computed_style(&document.get_element_by_id(element_id)?)
+    .ok()??;
+  let font = format!(
+    "{} {}",
+    style.get_property_value("font-size").ok()?,
+    style.get_property_value("font-family").ok()?
+  );
+
+  let canvas: HtmlCanvasElement = document.create_element("canvas").ok()?.dyn_into().ok()?;
+  let context: CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+  context.set_font(&font);
+  texts
+    .iter()
+    .filter_map(|text| context.measure_text(text).ok())
+    .map(|metrics| metrics.width())
+    .reduce(f64::max)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SerializableTable {
   // serde-json doesn't allow using non-string keys in hashmaps
   pub inputs: HashMap<String, String>,
+  // tables serialized before this setting was introduced use the default
+  #[serde(default)]
+  pub neg_precedence: NegPrecedence,
+  #[serde(default)]
+  pub number_mode: NumberMode,
+  #[serde(default)]
+  pub manual_calc: bool,
+  #[serde(default)]
+  pub watched: Vec<CellId>,
+  #[serde(default)]
+  pub size: Size,
+  #[serde(default)]
+  pub merges: Vec<Selection>,
+  #[serde(default)]
+  pub validations: Vec<(Selection, Rule)>,
+  #[serde(default)]
+  pub styles: HashMap<String, Style>,
+  #[serde(default)]
+  pub keymap: Keymap,
+  /// The sheets of the document, with `None` in place of the table of this sheet.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub sheets: Vec<Sheet<Box<SerializableTable>>>,
+  #[serde(default)]
+  pub active_sheet: usize,
 }
 
-pub fn parse_from_input(
-  encoded: &str,
-) -> Result<(HashMap<CellId, String>, HashMap<CellId, Expr>), Box<dyn Error>> {
+/// Inputs, parsed expressions, and settings of a deserialized table.
+#[derive(Debug)]
+pub struct ParsedTable {
+  pub inputs: HashMap<CellId, String>,
+  pub exprs: HashMap<CellId, Expr>,
+  /// The formulas failing to parse, kept as text, e.g., the ones using a plugin not loaded yet.
+  pub parse_errors: HashMap<CellId, String>,
+  pub neg_precedence: NegPrecedence,
+  pub number_mode: NumberMode,
+  pub manual_calc: bool,
+  pub watched: Vec<CellId>,
+  /// The serialized size, grown to contain all the inputs.
+  pub size: Size,
+  pub merges: Vec<Selection>,
+  pub validations: Vec<(Selection, Rule)>,
+  pub styles: HashMap<CellId, Style>,
+  pub keymap: Keymap,
+  pub sheets: Vec<Sheet<Box<SerializableTable>>>,
+  pub active_sheet: usize,
+}
+
+pub fn parse_from_input(encoded: &str) -> Result<ParsedTable, Box<dyn Error>> {
   match serde_json::from_str::<SerializableTable>(encoded) {
-    Ok(serializable_table) => {
-      let inputs = serializable_table
-        .inputs
-        .into_iter()
-        .map(|(cell_id, input)| CellId::try_from(cell_id.as_ref()).map(|cell_id| (cell_id, input)))
-        .collect::<Result<HashMap<_, _>, _>>();
-
-      match inputs {
-        Ok(inputs) => {
-          let mut exprs = HashMap::new();
-          for (cell_id, input) in &inputs {
-            match parse(&input) {
-              Ok(expr) => {
-                exprs.insert(*cell_id, expr);
-              }
-              Err(err) => {
-                return Err(
-                  format!("cannot parse `{cell_id}` with `{input}` due to: {err:?}").into(),
-                )
-              }
-            }
-          }
+    Ok(serializable_table) => parse_table(serializable_table),
+    Err(err) => Err(format!("failed when trying to deserialized table: {err:?}").into()),
+  }
+}
 
-          Ok((inputs, exprs))
-        }
-        Err(err) => {
-          Err(format!("cannot deserialize table from pasted input due to: {err:?}").into())
-        }
+/// Parses the inputs of a deserialized table; the formulas failing to parse are kept as text.
+pub fn parse_table(serializable_table: SerializableTable) -> Result<ParsedTable, Box<dyn Error>> {
+  let neg_precedence = serializable_table.neg_precedence;
+  let number_mode = serializable_table.number_mode;
+  let manual_calc = serializable_table.manual_calc;
+  let watched = serializable_table.watched;
+  let size = serializable_table.size;
+  let merges = serializable_table.merges;
+  let validations = serializable_table.validations;
+  let keymap = serializable_table.keymap;
+  let sheets = serializable_table.sheets;
+  let active_sheet = serializable_table.active_sheet;
+  let styles = serializable_table
+    .styles
+    .into_iter()
+    .map(|(cell_id, style)| CellId::try_from(cell_id.as_ref()).map(|cell_id| (cell_id, style)))
+    .collect::<Result<HashMap<_, _>, _>>()
+    .map_err(|err| format!("cannot deserialize the styles due to: {err:?}"))?;
+  let inputs = serializable_table
+    .inputs
+    .into_iter()
+    .map(|(cell_id, input)| CellId::try_from(cell_id.as_ref()).map(|cell_id| (cell_id, input)))
+    .collect::<Result<HashMap<_, _>, _>>();
+
+  match inputs {
+    Ok(inputs) => {
+      let mut exprs = HashMap::new();
+      let mut parse_errors = HashMap::new();
+      for (cell_id, input) in &inputs {
+        let expr = parse_with(input, neg_precedence).unwrap_or_else(|err| {
+          parse_errors.insert(*cell_id, err);
+          Expr::Str(input.clone())
+        });
+        exprs.insert(*cell_id, expr);
       }
+
+      Ok(ParsedTable {
+        size: inputs
+          .keys()
+          .fold(size, |size, cell_id| size.grown_to(*cell_id)),
+        inputs,
+        exprs,
+        parse_errors,
+        neg_precedence,
+        number_mode,
+        manual_calc,
+        watched,
+        merges,
+        validations,
+        styles,
+        keymap,
+        sheets,
+        active_sheet,
+      })
     }
-    Err(err) => Err(format!("failed when trying to deserialized table: {err:?}").into()),
+    Err(err) => Err(format!("cannot deserialize table from pasted input due to: {err:?}").into()),
   }
 }
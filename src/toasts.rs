@@ -0,0 +1,38 @@
+use yew::prelude::*;
+
+use crate::notifications::Toast;
+
+#[derive(PartialEq, Properties)]
+pub struct ToastsProps {
+  pub toasts: Vec<Toast>,
+  // closes the toast before it expires
+  pub onclose: Callback<usize>,
+}
+
+/// The transient messages in the bottom right corner.
+#[function_component]
+pub fn Toasts(props: &ToastsProps) -> Html {
+  let view_toast = |toast: &Toast| {
+    let id = toast.id;
+    let parent_onclose = props.onclose.clone();
+
+    html! {
+      <div class="flex gap-4 items-center px-4 py-2 text-base bg-indigo-700 border-[1px] border-indigo-900 shadow-lg">
+        <span>{ toast.message.clone() }</span>
+        <button
+          class="text-neutral-300 hover:text-white"
+          title="Close"
+          onclick={ Callback::from(move |_ev: MouseEvent| parent_onclose.emit(id)) }
+        >
+          { "✕" }
+        </button>
+      </div>
+    }
+  };
+
+  html! {
+    <div class="fixed bottom-4 right-4 z-[70] flex flex-col gap-2">
+      { props.toasts.iter().map(view_toast).collect::<Html>() }
+    </div>
+  }
+}
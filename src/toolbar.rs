@@ -0,0 +1,166 @@
+use yew::prelude::*;
+
+use crate::btn::*;
+
+/// An item of the toolbar.
+#[derive(Clone, PartialEq)]
+pub enum ToolItem {
+  Button {
+    title: String,
+    onclick: Callback<MouseEvent>,
+  },
+  /// A button showing whether a setting is on, e.g., bold text.
+  Toggle {
+    title: String,
+    is_pressed: bool,
+    onclick: Callback<MouseEvent>,
+  },
+  /// A button opening a menu of commands.
+  Dropdown {
+    title: String,
+    items: Vec<(String, Callback<MouseEvent>)>,
+  },
+  /// Any other control, e.g., a color picker.
+  Custom(Html),
+}
+
+impl ToolItem {
+  pub fn button(title: &str, onclick: Callback<MouseEvent>) -> Self {
+    ToolItem::Button {
+      title: title.to_string(),
+      onclick,
+    }
+  }
+
+  pub fn toggle(title: &str, is_pressed: bool, onclick: Callback<MouseEvent>) -> Self {
+    ToolItem::Toggle {
+      title: title.to_string(),
+      is_pressed,
+      onclick,
+    }
+  }
+}
+
+#[derive(PartialEq, Properties)]
+pub struct ToolbarProps {
+  /// Groups of related items, separated by gaps.
+  pub groups: Vec<Vec<ToolItem>>,
+  /// The number of groups shown; the other ones are listed in the overflow menu.
+  #[prop_or(usize::MAX)]
+  pub visible_groups: usize,
+}
+
+#[function_component]
+pub fn Toolbar(props: &ToolbarProps) -> Html {
+  // the index of the open dropdown among all items, or `None` if all are closed
+  let open_dropdown = use_state(|| None::<usize>);
+
+  let menu_item = |title: String, onclick: Callback<MouseEvent>| {
+    let open_dropdown = open_dropdown.clone();
+
+    html! {
+      <button
+        class="px-4 py-1 text-left whitespace-nowrap hover:bg-indigo-700"
+        onclick={ Callback::from(move |ev: MouseEvent| {
+          open_dropdown.set(None);
+          onclick.emit(ev);
+        })}
+      >
+        { title }
+      </button>
+    }
+  };
+
+  let dropdown = |index: usize, title: String, items: Html| {
+    let is_open = *open_dropdown == Some(index);
+    let toggle = {
+      let open_dropdown = open_dropdown.clone();
+      Callback::from(move |_ev: MouseEvent| {
+        open_dropdown.set(if is_open { None } else { Some(index) });
+      })
+    };
+
+    html! {
+      <div class="relative">
+        <Btn title={ format!("{title} ▾") } color={ BtnColors::Violet } onclick={toggle} />
+        if is_open {
+          <div class="absolute top-full right-0 mt-1 z-[60] flex flex-col py-1 text-base bg-indigo-800 border-[1px] border-indigo-900 shadow-lg">
+            { items }
+          </div>
+        }
+      </div>
+    }
+  };
+
+  let view_item = |index: usize, item: &ToolItem| match item.clone() {
+    ToolItem::Button { title, onclick } => html! {
+      <Btn {title} color={ BtnColors::Violet } {onclick} />
+    },
+    ToolItem::Toggle {
+      title,
+      is_pressed,
+      onclick,
+    } => html! {
+      <Btn
+        {title}
+        color={ if is_pressed { BtnColors::Purple } else { BtnColors::Violet } }
+        {onclick}
+      />
+    },
+    ToolItem::Dropdown { title, items } => dropdown(
+      index,
+      title,
+      items
+        .into_iter()
+        .map(|(title, onclick)| menu_item(title, onclick))
+        .collect(),
+    ),
+    ToolItem::Custom(html) => html,
+  };
+
+  let visible = props.groups.iter().take(props.visible_groups);
+  let overflow = props
+    .groups
+    .iter()
+    .skip(props.visible_groups)
+    .flatten()
+    .map(|item| match item.clone() {
+      ToolItem::Button { title, onclick } => menu_item(title, onclick),
+      ToolItem::Toggle {
+        title,
+        is_pressed,
+        onclick,
+      } => menu_item(
+        format!("{} {title}", if is_pressed { "✓" } else { "\u{2003}" }),
+        onclick,
+      ),
+      ToolItem::Dropdown { items, .. } => items
+        .into_iter()
+        .map(|(title, onclick)| menu_item(title, onclick))
+        .collect(),
+      ToolItem::Custom(html) => html! { <div class="px-4 py-1">{ html }</div> },
+    })
+    .collect::<Vec<_>>();
+
+  // items are numbered across the groups, to keep only one dropdown open
+  let mut index = 0;
+  html! {
+    <div class="flex gap-6">
+      {
+        visible.map(|group| html! {
+          <div class="flex gap-2">
+            {
+              group.iter().map(|item| {
+                index += 1;
+                view_item(index, item)
+              }).collect::<Html>()
+            }
+          </div>
+        }).collect::<Html>()
+      }
+      if !overflow.is_empty() {
+        { dropdown(0, String::from("More"), overflow.into_iter().collect()) }
+      }
+    </div>
+  }
+}
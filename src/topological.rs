@@ -1,11 +1,105 @@
-use crate::{cell_id::CellId, expr::Expr};
+use crate::{cell_id::CellId, expr::Expr, limits::Limits};
 use std::{
-  collections::{HashMap, HashSet},
+  borrow::Borrow,
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap, HashSet},
+  error::Error,
   hash::Hash,
 };
 
 /// Performs topological sorting for a `T` that can be converted to `State<Id>`
 /// (`From<T>` is implemented for `State<Id>`).
+pub fn topological_sort<T, Id>(deps: T) -> Result<Vec<Id>, Box<dyn std::error::Error>>
+where
+  Id: Ord + std::hash::Hash + Clone + std::fmt::Display,
+  State<Id>: From<T>,
+{
+  let (res, state) = kahn(State::from(deps));
+
+  if !state.is_resolved() {
+    return Err(cycles_error(&strongly_connected_cycles(&state.depends_on)));
+  }
+
+  Ok(res)
+}
+
+/// Like `topological_sort`, but groups the ids into levels: the ids of a level depend only
+/// on the ids of the previous levels, so they can be evaluated in any order or in parallel.
+/// The ids of each level are sorted.
+pub fn topological_levels<T, Id>(deps: T) -> Result<Vec<Vec<Id>>, Box<dyn std::error::Error>>
+where
+  Id: Ord + std::hash::Hash + Clone + std::fmt::Display,
+  State<Id>: From<T>,
+{
+  let mut levels = vec![];
+  let mut state = State::from(deps);
+  state.init_no_deps();
+
+  while !state.no_deps.is_empty() {
+    let level = std::mem::take(&mut state.no_deps)
+      .into_sorted_vec()
+      .into_iter()
+      .rev()
+      .map(|Reverse(id)| id)
+      .collect::<Vec<_>>();
+
+    for id in &level {
+      if let Some(dependents) = state.get_dependents(id) {
+        for dependent in dependents.clone() {
+          state.resolve(&dependent, id);
+        }
+      }
+    }
+    levels.push(level);
+  }
+
+  if !state.is_resolved() {
+    return Err(cycles_error(&strongly_connected_cycles(&state.depends_on)));
+  }
+  Ok(levels)
+}
+
+/// Like `topological_sort`, but doesn't fail on cycles: returns the sorted ids,
+/// and the ids that cannot be sorted, i.e., the ids in cycles and the ids depending on them.
+pub fn topological_sort_partial<T, Id>(deps: T) -> (Vec<Id>, Vec<Id>)
+where
+  Id: Ord + std::hash::Hash + Clone,
+  State<Id>: From<T>,
+{
+  let (res, state) = kahn(State::from(deps));
+  (res, state.unresolved())
+}
+
+/// Returns the cycles in the dependencies, each as a list of ids in which an id depends on
+/// the next one, and the last one depends on the first one.
+pub fn find_cycles<T, Id>(deps: T) -> Vec<Vec<Id>>
+where
+  Id: Ord + std::hash::Hash + Clone,
+  State<Id>: From<T>,
+{
+  let (_, state) = kahn(State::from(deps));
+  strongly_connected_cycles(&state.depends_on)
+}
+
+/// Formats the cycle as, e.g., `A1 → B2 → A1`.
+pub fn format_cycle<Id: std::fmt::Display>(cycle: &[Id]) -> String {
+  cycle
+    .iter()
+    .chain(cycle.first())
+    .map(ToString::to_string)
+    .collect::<Vec<_>>()
+    .join(" → ")
+}
+
+fn cycles_error<Id: std::fmt::Display>(cycles: &[Vec<Id>]) -> Box<dyn Error> {
+  let cycles = cycles
+    .iter()
+    .map(|cycle| format_cycle(cycle))
+    .collect::<Vec<_>>();
+  format!("cycles detected: {}", cycles.join(", ")).into()
+}
+
+/// Sorts the ids with Kahn's algorithm, returning the state with the unresolved ids.
 ///
 /// ## Implementation Notes
 ///
@@ -13,35 +107,150 @@ use std::{
 /// the following line, but we prefer significantly better readability over
 /// slightly better performance (this avoids one clone):
 /// `state.resolve_for_dependants_of(&cell_id);`
-pub fn topological_sort<T, Id>(deps: T) -> Result<Vec<Id>, Box<dyn std::error::Error>>
+fn kahn<Id>(mut state: State<Id>) -> (Vec<Id>, State<Id>)
 where
-  Id: Eq + std::hash::Hash + Copy + std::fmt::Debug,
-  State<Id>: From<T>,
+  Id: Ord + std::hash::Hash + Clone,
 {
   let mut res = vec![];
-  let mut state = State::from(deps);
-
-  while let Some(cell_id) = state.no_deps.pop() {
-    res.push(cell_id);
+  state.init_no_deps();
 
+  while let Some(Reverse(cell_id)) = state.no_deps.pop() {
     if let Some(dependents) = state.get_dependents(&cell_id) {
       for dependent in dependents.clone() {
         state.resolve(&dependent, &cell_id);
       }
     }
+
+    res.push(cell_id);
   }
 
-  if !state.is_resolved() {
-    return Err(
-      format!(
-        "cycle or non-computable cell reference detected in cells: {:?}",
-        state.unresolved().collect::<Vec<_>>()
-      )
-      .into(),
-    );
+  (res, state)
+}
+
+/// Finds strongly connected components of the graph with Tarjan's algorithm,
+/// and returns a shortest cycle through the smallest id of each component;
+/// the cycles are sorted by their first ids.
+///
+/// The depth-first search uses an explicit stack, since long chains of references
+/// would overflow the call stack.
+fn strongly_connected_cycles<Id>(graph: &Graph<Id>) -> Vec<Vec<Id>>
+where
+  Id: Ord + std::hash::Hash + Clone,
+{
+  let mut indices = HashMap::new();
+  let mut low_links = HashMap::new();
+  let mut stack = vec![];
+  let mut on_stack = HashSet::new();
+  let mut components = vec![];
+
+  let mut roots = graph.keys().cloned().collect::<Vec<_>>();
+  roots.sort_unstable();
+
+  for root in roots {
+    if indices.contains_key(&root) {
+      continue;
+    }
+
+    // frames of the depth-first search: an id, its neighbors, and the next neighbor to visit
+    let mut frames = vec![(root.clone(), sorted_neighbors(graph, &root), 0)];
+    let idx = indices.len();
+    indices.insert(root.clone(), idx);
+    low_links.insert(root.clone(), idx);
+    on_stack.insert(root.clone());
+    stack.push(root);
+
+    while let Some((id, next_ids, next_idx)) = frames.last_mut() {
+      let id = id.clone();
+
+      if let Some(next) = next_ids.get(*next_idx).cloned() {
+        *next_idx += 1;
+
+        if !indices.contains_key(&next) {
+          let idx = indices.len();
+          indices.insert(next.clone(), idx);
+          low_links.insert(next.clone(), idx);
+          on_stack.insert(next.clone());
+          stack.push(next.clone());
+          let next_ids = sorted_neighbors(graph, &next);
+          frames.push((next, next_ids, 0));
+        } else if on_stack.contains(&next) {
+          let low_link = low_links[&id].min(indices[&next]);
+          low_links.insert(id, low_link);
+        }
+        continue;
+      }
+
+      frames.pop();
+      if let Some((parent, _, _)) = frames.last() {
+        let low_link = low_links[parent].min(low_links[&id]);
+        low_links.insert(parent.clone(), low_link);
+      }
+
+      if low_links[&id] == indices[&id] {
+        let mut component = HashSet::new();
+        while let Some(member) = stack.pop() {
+          on_stack.remove(&member);
+          let is_root = member == id;
+          component.insert(member);
+          if is_root {
+            break;
+          }
+        }
+        let start = component.iter().min().cloned().unwrap_or(id);
+        components.push((start, component));
+      }
+    }
   }
 
-  Ok(res)
+  components.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+  components
+    .into_iter()
+    .filter_map(|(start, component)| shortest_cycle(graph, start, &component))
+    .collect()
+}
+
+fn sorted_neighbors<Id>(graph: &Graph<Id>, id: &Id) -> Vec<Id>
+where
+  Id: Ord + std::hash::Hash + Clone,
+{
+  let mut neighbors = graph
+    .get(id)
+    .into_iter()
+    .flatten()
+    .cloned()
+    .collect::<Vec<_>>();
+  neighbors.sort_unstable();
+  neighbors
+}
+
+/// Finds a shortest cycle through `start` with a breadth-first search within the component;
+/// components of a single id are cycles only if the id depends on itself.
+fn shortest_cycle<Id>(graph: &Graph<Id>, start: Id, component: &HashSet<Id>) -> Option<Vec<Id>>
+where
+  Id: Ord + std::hash::Hash + Clone,
+{
+  let mut parents = HashMap::new();
+  let mut queue = std::collections::VecDeque::from([start.clone()]);
+
+  while let Some(id) = queue.pop_front() {
+    for next in sorted_neighbors(graph, &id) {
+      if next == start {
+        let mut cycle = vec![id];
+        while let Some(parent) = parents.get(cycle.last()?) {
+          cycle.push(Id::clone(parent));
+        }
+        cycle.reverse();
+        return Some(cycle);
+      }
+
+      if component.contains(&next) && !parents.contains_key(&next) {
+        parents.insert(next.clone(), id.clone());
+        queue.push_back(next);
+      }
+    }
+  }
+
+  None
 }
 
 /// A directed graph is represented as a hash map mapping a vertex `a`
@@ -58,38 +267,154 @@ type Graph<T> = HashMap<T, HashSet<T>>;
 #[inline]
 pub fn add_edge<T>(graph: &mut Graph<T>, from: T, to: T)
 where
-  T: Eq + Hash + Copy,
+  T: Eq + Hash,
 {
-  graph
-    .entry(from)
-    .and_modify(|pointees| {
-      pointees.insert(to);
-    })
-    .or_insert_with(|| {
-      let mut s = HashSet::new();
-      s.insert(to);
-      s
-    });
+  graph.entry(from).or_default().insert(to);
 }
 
 /// Preprocessed state for Kahn's topological sorting algorithm.
 ///
 /// Allows (expected) O(1) dependencies & dependents retrieval for any `node_id: T`
-/// and stores `no_deps` vector.
+/// and stores `no_deps` heap. The smallest id without dependencies goes first,
+/// so the order doesn't depend on the hash map iteration order.
+///
+/// Besides the `From` implementations for tables, a state can be built with `add_node`
+/// and `add_edge`, e.g., to sort ids of other types:
+///
+/// ```
+/// use cells::topological::{topological_sort, State};
+///
+/// let mut state = State::default();
+/// state.add_edge("total".to_string(), "price".to_string());
+/// state.add_node("notes".to_string());
+/// assert_eq!(topological_sort(state).unwrap(), vec!["notes", "price", "total"]);
+/// ```
+#[derive(Debug, Clone)]
 pub struct State<T> {
+  // all ids, including the ids without dependencies
+  nodes: HashSet<T>,
   // maps a cell_id to a set of cell_ids it depends on
   depends_on: Graph<T>,
   // maps a cell_id to a set of cell_ids depending on it
   dependents: Graph<T>,
-  no_deps: Vec<T>,
+  // ids that must be recomputed on every evaluation, such as cells calling `RAND()`
+  volatile: HashSet<T>,
+  // ids that may spill arrays into the neighboring ids, which are recomputed on every
+  // evaluation too, since whether they fit depends on the neighbors
+  arrays: HashSet<T>,
+  // maps the ids filled by spilled arrays to the ids containing the arrays; the filled ids
+  // depend on the arrays, so that the ids referencing them are sorted after the arrays
+  spilled: HashMap<T, T>,
+  no_deps: BinaryHeap<Reverse<T>>,
+  // the order kept between edits, see `sorted`; `None` until the first sort,
+  // and after the changes that may have resolved or created cycles
+  order: Option<Order<T>>,
 }
 
 impl<T> Default for State<T> {
   fn default() -> Self {
     Self {
+      nodes: HashSet::new(),
       depends_on: HashMap::new(),
       dependents: HashMap::new(),
-      no_deps: vec![],
+      volatile: HashSet::new(),
+      arrays: HashSet::new(),
+      spilled: HashMap::new(),
+      no_deps: BinaryHeap::new(),
+      order: None,
+    }
+  }
+}
+
+/// A topological order maintained incrementally with the Pearce-Kelly algorithm, so that
+/// adding a dependency only reorders the ids between the positions of the dependency and
+/// the dependent, and removing one keeps the order valid.
+#[derive(Debug, Clone)]
+struct Order<T> {
+  // the sorted ids, dependencies first
+  ids: Vec<T>,
+  // maps an id to its index in `ids`
+  positions: HashMap<T, usize>,
+  // ids in cycles and the ids depending on them, which are missing from `ids`
+  unresolved: HashSet<T>,
+}
+
+impl<T> Order<T>
+where
+  T: Clone + Eq + Hash,
+{
+  /// Adds the id without dependencies to the end of the order.
+  fn add_id(&mut self, id: T) {
+    self.positions.insert(id.clone(), self.ids.len());
+    self.ids.push(id);
+  }
+
+  /// Reorders the ids for a new edge; returns false, if the order must be sorted again,
+  /// since the edge may create a cycle, or depends on the unresolved ids.
+  fn add_edge(&mut self, dependent: &T, dependency: &T, graph: (&Graph<T>, &Graph<T>)) -> bool {
+    let (depends_on, dependents) = graph;
+    if self.unresolved.contains(dependent) {
+      return true;
+    }
+    let (Some(&lower), Some(&upper)) = (
+      self.positions.get(dependent),
+      self.positions.get(dependency),
+    ) else {
+      return false;
+    };
+
+    if lower < upper {
+      // ids depending on `dependent`, which are not after `dependency`
+      let forward = self.reachable(dependent, dependents, |pos| pos <= upper);
+      if forward.contains(dependency) {
+        return false;
+      }
+      // ids `dependency` depends on, which are not before `dependent`
+      let backward = self.reachable(dependency, depends_on, |pos| pos >= lower);
+      self.reorder(backward, forward);
+    }
+    lower != upper
+  }
+
+  /// Returns the ids reachable from `start` via the `graph` edges, visiting only the ids
+  /// with the positions satisfying `is_affected`; the unresolved ids are skipped, since
+  /// a path to them cannot lead back to the resolved ids.
+  fn reachable(&self, start: &T, graph: &Graph<T>, is_affected: impl Fn(usize) -> bool) -> Vec<T> {
+    let mut visited = HashSet::from([start.clone()]);
+    let mut stack = vec![start.clone()];
+    let mut res = vec![];
+
+    while let Some(id) = stack.pop() {
+      for next in graph.get(&id).into_iter().flatten() {
+        if self
+          .positions
+          .get(next)
+          .is_some_and(|&pos| is_affected(pos))
+          && visited.insert(next.clone())
+        {
+          stack.push(next.clone());
+        }
+      }
+      res.push(id);
+    }
+    res
+  }
+
+  /// Moves the `backward` ids before the `forward` ids, reusing their positions.
+  fn reorder(&mut self, mut backward: Vec<T>, mut forward: Vec<T>) {
+    backward.sort_by_key(|id| self.positions[id]);
+    forward.sort_by_key(|id| self.positions[id]);
+
+    let mut positions = backward
+      .iter()
+      .chain(forward.iter())
+      .map(|id| self.positions[id])
+      .collect::<Vec<_>>();
+    positions.sort_unstable();
+
+    for (id, pos) in backward.into_iter().chain(forward).zip(positions) {
+      self.positions.insert(id.clone(), pos);
+      self.ids[pos] = id;
     }
   }
 }
@@ -98,26 +423,211 @@ impl<T> State<T>
 where
   T: Eq + std::hash::Hash,
 {
-  pub fn get_dependents(self: &Self, dependency: &T) -> Option<&HashSet<T>> {
+  pub fn get_dependents(&self, dependency: &T) -> Option<&HashSet<T>> {
     // it's possible to replace the return type with HashSet<T>, but then we'll need to allocate
     self.dependents.get(dependency)
   }
 
-  pub fn is_resolved(self: &Self) -> bool {
+  pub fn is_resolved(&self) -> bool {
     self.depends_on.is_empty()
   }
 }
 
+/// Dependency queries for formula auditing; sorting resolves the dependencies,
+/// so they should be asked before sorting.
+impl<T> State<T>
+where
+  T: Clone + Eq + std::hash::Hash,
+{
+  /// Returns the ids `id` depends on, directly or via other ids.
+  pub fn all_precedents(&self, id: &T) -> HashSet<T> {
+    reachable(&self.depends_on, id)
+  }
+
+  /// Returns the ids depending on `id`, directly or via other ids.
+  pub fn all_dependents(&self, id: &T) -> HashSet<T> {
+    reachable(&self.dependents, id)
+  }
+
+  pub fn set_volatile(&mut self, id: T, is_volatile: bool) {
+    if is_volatile {
+      self.volatile.insert(id);
+    } else {
+      self.volatile.remove(&id);
+    }
+  }
+
+  pub fn is_volatile(&self, id: &T) -> bool {
+    self.volatile.contains(id)
+  }
+
+  pub fn volatile(&self) -> &HashSet<T> {
+    &self.volatile
+  }
+
+  pub fn set_array(&mut self, id: T, may_return_array: bool) {
+    if may_return_array {
+      self.arrays.insert(id);
+    } else {
+      self.arrays.remove(&id);
+    }
+  }
+
+  pub fn arrays(&self) -> &HashSet<T> {
+    &self.arrays
+  }
+
+  /// Maps the ids filled by spilled arrays to the ids containing the arrays, see `set_spilled`.
+  pub fn spilled(&self) -> &HashMap<T, T> {
+    &self.spilled
+  }
+
+  /// Returns the volatile ids and the ids depending on them, i.e., the ids
+  /// that must be recomputed even if nothing changed.
+  pub fn all_volatile(&self) -> HashSet<T> {
+    let mut all = self.volatile.clone();
+    for id in &self.volatile {
+      all.extend(self.all_dependents(id));
+    }
+    all
+  }
+}
+
+impl<T> State<T>
+where
+  T: Ord + std::hash::Hash + std::fmt::Display,
+{
+  /// Serializes the dependencies to the Graphviz DOT format; the edges go from
+  /// the dependencies to their dependents, i.e., in the direction values flow.
+  pub fn to_dot(&self) -> String {
+    self.to_dot_with(|_| true)
+  }
+
+  /// Same as `to_dot`, but only with the ids matching `is_shown` and the edges between them.
+  pub fn to_dot_with(&self, is_shown: impl Fn(&T) -> bool) -> String {
+    let mut nodes = self
+      .nodes
+      .iter()
+      .filter(|id| is_shown(id))
+      .collect::<Vec<_>>();
+    nodes.sort_unstable();
+    let mut edges = self
+      .dependents
+      .iter()
+      .flat_map(|(dependency, dependents)| {
+        dependents
+          .iter()
+          .map(move |dependent| (dependency, dependent))
+      })
+      .filter(|(dependency, dependent)| is_shown(dependency) && is_shown(dependent))
+      .collect::<Vec<_>>();
+    edges.sort_unstable();
+
+    let mut dot = String::from("digraph cells {\n");
+    for node in nodes {
+      dot.push_str(&format!("  \"{node}\";\n"));
+    }
+    for (dependency, dependent) in edges {
+      dot.push_str(&format!("  \"{dependency}\" -> \"{dependent}\";\n"));
+    }
+    dot.push_str("}\n");
+    dot
+  }
+}
+
+/// Returns the vertices reachable from `start`, excluding `start` unless it's in a cycle.
+fn reachable<T>(graph: &Graph<T>, start: &T) -> HashSet<T>
+where
+  T: Clone + Eq + std::hash::Hash,
+{
+  let mut visited = HashSet::new();
+  let mut stack = vec![start];
+
+  while let Some(id) = stack.pop() {
+    for next in graph.get(id).into_iter().flatten() {
+      if visited.insert(next.clone()) {
+        stack.push(next);
+      }
+    }
+  }
+  visited
+}
+
 impl<T> State<T>
 where
-  T: Copy + Eq + std::hash::Hash,
+  T: Clone + Ord + std::hash::Hash,
 {
-  pub fn resolve(self: &mut Self, dependent: &T, dependency: &T) {
+  /// Adds an id, which may have no dependencies.
+  pub fn add_node(&mut self, id: T) {
+    if self.nodes.insert(id.clone()) {
+      if let Some(order) = &mut self.order {
+        order.add_id(id);
+      }
+    }
+  }
+
+  /// Adds a dependency of `dependent` on `dependency`, adding the ids if needed.
+  pub fn add_edge(&mut self, dependent: T, dependency: T) {
+    self.add_node(dependent.clone());
+    self.add_node(dependency.clone());
+    if let Some(order) = &mut self.order {
+      if !order.add_edge(
+        &dependent,
+        &dependency,
+        (&self.depends_on, &self.dependents),
+      ) {
+        self.order = None;
+      }
+    }
+    add_edge(&mut self.depends_on, dependent.clone(), dependency.clone());
+    add_edge(&mut self.dependents, dependency, dependent);
+  }
+
+  /// Replaces the dependencies of the id, e.g., after the formula of a cell was edited;
+  /// the id filled by a spilled array keeps depending on the array.
+  pub fn set_dependencies(&mut self, id: T, dependencies: impl IntoIterator<Item = T>) {
+    // removing the dependencies keeps the order valid, unless they were in a cycle
+    if self
+      .order
+      .as_ref()
+      .is_some_and(|order| order.unresolved.contains(&id))
+    {
+      self.order = None;
+    }
+    for dependency in self.depends_on.remove(&id).into_iter().flatten() {
+      if let Some(dependents) = self.dependents.get_mut(&dependency) {
+        dependents.remove(&id);
+      }
+    }
+
+    self.add_node(id.clone());
+    let spilled_from = self.spilled.get(&id).cloned();
+    for dependency in dependencies.into_iter().chain(spilled_from) {
+      self.add_edge(id.clone(), dependency);
+    }
+  }
+
+  /// Marks the id as filled by the array spilled from `source`, or no longer filled,
+  /// replacing its dependencies with its own `dependencies`, like `set_dependencies`.
+  pub fn set_spilled(
+    &mut self,
+    id: T,
+    source: Option<T>,
+    dependencies: impl IntoIterator<Item = T>,
+  ) {
+    match source {
+      Some(source) => self.spilled.insert(id.clone(), source),
+      None => self.spilled.remove(&id),
+    };
+    self.set_dependencies(id, dependencies);
+  }
+
+  pub fn resolve(&mut self, dependent: &T, dependency: &T) {
     if let Some(dependencies) = self.depends_on.get_mut(dependent) {
-      dependencies.remove(&dependency);
+      dependencies.remove(dependency);
 
       if dependencies.is_empty() {
-        self.no_deps.push(*dependent);
+        self.no_deps.push(Reverse(dependent.clone()));
 
         // to be able to report unresolved
         self.depends_on.remove(dependent);
@@ -125,25 +635,156 @@ where
     }
   }
 
-  pub fn unresolved(self: &Self) -> impl Iterator<Item = &T> {
-    self.depends_on.keys()
+  /// Returns the unresolved ids, sorted.
+  pub fn unresolved(&self) -> Vec<T> {
+    let mut unresolved = self.depends_on.keys().cloned().collect::<Vec<_>>();
+    unresolved.sort_unstable();
+    unresolved
+  }
+
+  /// Like `topological_sort_partial`, but doesn't consume the state: the order is kept
+  /// between calls, and updated on each change of the dependencies instead of sorting
+  /// all ids again.
+  pub fn sorted(&mut self) -> (&[T], Vec<T>) {
+    let order = self.order();
+    let mut unresolved = order.unresolved.iter().cloned().collect::<Vec<_>>();
+    unresolved.sort_unstable();
+    (&order.ids, unresolved)
+  }
+
+  /// Like `sorted`, but only returns the given ids.
+  pub fn sort(&mut self, ids: impl IntoIterator<Item = T>) -> (Vec<T>, Vec<T>) {
+    let order = self.order();
+    let (mut unresolved, mut sorted): (Vec<_>, Vec<_>) = ids
+      .into_iter()
+      .partition(|id| order.unresolved.contains(id));
+    sorted.sort_by_key(|id| order.positions.get(id));
+    unresolved.sort_unstable();
+    (sorted, unresolved)
+  }
+
+  /// Returns the cycles among the unresolved ids, see `find_cycles`.
+  pub fn cycles(&mut self) -> Vec<Vec<T>> {
+    let unresolved = self.order().unresolved.clone();
+    if unresolved.is_empty() {
+      return vec![];
+    }
+
+    let graph = unresolved
+      .iter()
+      .map(|id| {
+        let dependencies = self.depends_on[id]
+          .iter()
+          .filter(|dependency| unresolved.contains(dependency))
+          .cloned()
+          .collect();
+        (id.clone(), dependencies)
+      })
+      .collect();
+    strongly_connected_cycles(&graph)
+  }
+
+  /// Returns the ids and the ids depending on them, directly or via other ids.
+  pub fn with_dependents(&self, ids: impl IntoIterator<Item = T>) -> HashSet<T> {
+    let mut visited = HashSet::new();
+    let mut stack = vec![];
+    for id in ids {
+      if visited.insert(id.clone()) {
+        stack.push(id);
+      }
+    }
+
+    while let Some(id) = stack.pop() {
+      for next in self.dependents.get(&id).into_iter().flatten() {
+        if visited.insert(next.clone()) {
+          stack.push(next.clone());
+        }
+      }
+    }
+    visited
+  }
+
+  /// Sorts the ids with Kahn's algorithm, unless the order is kept from the previous sort.
+  fn order(&mut self) -> &Order<T> {
+    self.order.get_or_insert_with(|| {
+      let state = State {
+        nodes: self.nodes.clone(),
+        depends_on: self.depends_on.clone(),
+        dependents: self.dependents.clone(),
+        ..State::default()
+      };
+      let (ids, state) = kahn(state);
+      let positions = ids
+        .iter()
+        .enumerate()
+        .map(|(idx, id)| (id.clone(), idx))
+        .collect();
+      Order {
+        ids,
+        positions,
+        unresolved: state.depends_on.into_keys().collect(),
+      }
+    })
+  }
+
+  /// Starts sorting with the ids without dependencies.
+  fn init_no_deps(&mut self) {
+    self.no_deps = self
+      .nodes
+      .iter()
+      .filter(|id| !self.depends_on.contains_key(id))
+      .cloned()
+      .map(Reverse)
+      .collect();
   }
 }
 
-impl From<&HashMap<CellId, Expr>> for State<CellId> {
-  fn from(exprs: &HashMap<CellId, Expr>) -> State<CellId> {
+impl State<CellId> {
+  /// Unlike `State::from(exprs)`, keeps the references to empty cells, so that the state
+  /// can be kept between edits, and updated with `set_dependencies` when a cell changes.
+  /// The cells exceeding the limits have no dependencies, see `Limits::deps`.
+  pub fn from_references<E: Borrow<Expr>>(
+    exprs: &HashMap<CellId, E>,
+    limits: &Limits,
+  ) -> State<CellId> {
+    let mut graphs = State::default();
+    for (&cell_id, expr) in exprs.iter() {
+      let expr = expr.borrow();
+      graphs.set_dependencies(cell_id, limits.deps(expr));
+      graphs.set_volatile(cell_id, expr.is_volatile());
+      graphs.set_array(cell_id, expr.may_return_array());
+    }
+    graphs
+  }
+}
+
+impl<E: Borrow<Expr>> From<&HashMap<CellId, E>> for State<CellId> {
+  fn from(exprs: &HashMap<CellId, E>) -> State<CellId> {
+    State::from((exprs, &HashMap::new()))
+  }
+}
+
+/// Cells filled by spilled arrays are mapped to the cells containing the arrays,
+/// so that the cells referencing spilled cells are evaluated after the arrays.
+impl<E: Borrow<Expr>> From<(&HashMap<CellId, E>, &HashMap<CellId, CellId>)> for State<CellId> {
+  fn from((exprs, spilled): (&HashMap<CellId, E>, &HashMap<CellId, CellId>)) -> State<CellId> {
     let mut graphs = State::default();
 
     for (&cell_id, expr) in exprs.iter() {
-      let dependencies = expr.get_deps();
+      let expr = expr.borrow();
+      graphs.add_node(cell_id);
+      graphs.set_volatile(cell_id, expr.is_volatile());
 
-      if dependencies.is_empty() {
-        graphs.no_deps.push(cell_id);
-      } else {
-        for dependency_cell_id in dependencies {
-          add_edge(&mut graphs.depends_on, cell_id, dependency_cell_id);
-          add_edge(&mut graphs.dependents, dependency_cell_id, cell_id);
-        }
+      // references to empty cells don't need to be resolved; e.g., ranges commonly include them
+      let dependencies = Limits::default()
+        .deps(expr)
+        .into_iter()
+        .map(|dependency| spilled.get(&dependency).copied().unwrap_or(dependency))
+        .filter(|dependency| exprs.contains_key(dependency))
+        .collect::<HashSet<_>>();
+
+      for dependency_cell_id in dependencies {
+        graphs.add_edge(cell_id, dependency_cell_id);
       }
     }
 
@@ -167,8 +808,309 @@ mod test {
     exprs.insert(CellId { col: 'B', row: 1 }, Num(15.0));
     exprs.insert(CellId { col: 'C', row: 1 }, Num(3.0));
 
+    // ties are broken by the cell order
     let ordering = topological_sort(&exprs).unwrap();
-    assert_eq!(ordering.len(), 3);
-    assert_eq!(*ordering.last().unwrap(), CellId { col: 'A', row: 1 });
+    assert_eq!(
+      ordering,
+      vec![
+        CellId { col: 'B', row: 1 },
+        CellId { col: 'C', row: 1 },
+        CellId { col: 'A', row: 1 }
+      ]
+    );
+
+    exprs.insert(CellId { col: 'B', row: 1 }, parse("= A1 + 1").unwrap());
+    exprs.insert(CellId { col: 'D', row: 1 }, parse("= B1 * 2").unwrap());
+    assert!(topological_sort(&exprs).is_err());
+
+    assert_eq!(
+      topological_sort(&exprs).unwrap_err().to_string(),
+      "cycles detected: A01 → B01 → A01"
+    );
+
+    let (ordering, unresolved) = topological_sort_partial(&exprs);
+    assert_eq!(ordering, vec![CellId { col: 'C', row: 1 }]);
+    assert_eq!(
+      unresolved,
+      vec![
+        CellId { col: 'A', row: 1 },
+        CellId { col: 'B', row: 1 },
+        CellId { col: 'D', row: 1 }
+      ]
+    );
+  }
+
+  #[test]
+  fn topological_levels_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), parse("= B1 + C1").unwrap());
+    exprs.insert(cell("B1"), parse("= C2 * 2").unwrap());
+    exprs.insert(cell("C1"), Num(3.0));
+    exprs.insert(cell("C2"), Num(4.0));
+    exprs.insert(cell("D1"), parse("= C2 + A1").unwrap());
+
+    let levels = topological_levels(&exprs).unwrap();
+    assert_eq!(
+      levels,
+      vec![
+        vec![cell("C1"), cell("C2")],
+        vec![cell("B1")],
+        vec![cell("A1")],
+        vec![cell("D1")]
+      ]
+    );
+
+    exprs.insert(cell("C2"), parse("= D1").unwrap());
+    assert!(topological_levels(&exprs).is_err());
+  }
+
+  #[test]
+  fn state_builder_test() {
+    let a1 = CellId { col: 'A', row: 1 };
+    let mut state = State::default();
+    state.add_edge(("summary".to_string(), a1), ("data".to_string(), a1));
+    state.add_edge(("data".to_string(), a1), ("input".to_string(), a1));
+    state.add_node(("notes".to_string(), a1));
+
+    let (ordering, unresolved) = topological_sort_partial(state);
+    let sheets = ordering
+      .iter()
+      .map(|(sheet, _)| sheet.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(sheets, vec!["input", "data", "notes", "summary"]);
+    assert!(unresolved.is_empty());
+  }
+
+  #[test]
+  fn precedents_and_dependents_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let cells = |ids: &[&str]| ids.iter().map(|s| cell(s)).collect::<HashSet<_>>();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), Num(2.0));
+    exprs.insert(cell("A2"), parse("= A1 * 2").unwrap());
+    exprs.insert(cell("A3"), parse("= SUM(A1:A2)").unwrap());
+    exprs.insert(cell("B1"), parse("= A3 + C1").unwrap());
+    exprs.insert(cell("C1"), Num(1.0));
+
+    let state = State::from(&exprs);
+    assert_eq!(
+      state.all_precedents(&cell("B1")),
+      cells(&["A1", "A2", "A3", "C1"])
+    );
+    assert_eq!(state.all_precedents(&cell("A2")), cells(&["A1"]));
+    assert!(state.all_precedents(&cell("A1")).is_empty());
+    assert_eq!(
+      state.all_dependents(&cell("A1")),
+      cells(&["A2", "A3", "B1"])
+    );
+    assert_eq!(state.all_dependents(&cell("C1")), cells(&["B1"]));
+    assert!(state.all_dependents(&cell("B1")).is_empty());
+  }
+
+  #[test]
+  fn set_dependencies_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), parse("= B1 + 1").unwrap());
+    exprs.insert(cell("C1"), Num(2.0));
+
+    // B1 is empty, but A1 must still go after it once it's filled
+    let mut state = State::from_references(&exprs, &Limits::default());
+    exprs.insert(cell("B1"), parse("= C1 * 2").unwrap());
+    state.set_dependencies(cell("B1"), exprs[&cell("B1")].get_deps());
+    assert_eq!(
+      topological_sort(state.clone()).unwrap(),
+      vec![cell("C1"), cell("B1"), cell("A1")]
+    );
+
+    exprs.insert(cell("C1"), parse("= A1").unwrap());
+    state.set_dependencies(cell("C1"), exprs[&cell("C1")].get_deps());
+    assert!(topological_sort(state.clone()).is_err());
+
+    state.set_dependencies(cell("C1"), vec![]);
+    assert_eq!(state.all_dependents(&cell("A1")), HashSet::new());
+    assert!(topological_sort(state).is_ok());
+  }
+
+  #[test]
+  fn volatile_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let cells = |ids: &[&str]| ids.iter().map(|s| cell(s)).collect::<HashSet<_>>();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), parse("= RAND()").unwrap());
+    exprs.insert(cell("A2"), parse("= A1 * 2").unwrap());
+    exprs.insert(cell("A3"), parse("= A2 + B1").unwrap());
+    exprs.insert(cell("B1"), Num(1.0));
+    exprs.insert(cell("B2"), parse("= B1 + 1").unwrap());
+
+    let mut state = State::from_references(&exprs, &Limits::default());
+    assert!(state.is_volatile(&cell("A1")));
+    assert!(!state.is_volatile(&cell("A2")));
+    assert_eq!(state.all_volatile(), cells(&["A1", "A2", "A3"]));
+
+    state.set_volatile(cell("B1"), true);
+    state.set_volatile(cell("A1"), false);
+    assert_eq!(state.all_volatile(), cells(&["B1", "A3", "B2"]));
+  }
+
+  #[test]
+  fn to_dot_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), Num(2.0));
+    exprs.insert(cell("B1"), parse("= A1 * A2").unwrap());
+    exprs.insert(cell("A2"), parse("= A1 + 1").unwrap());
+
+    assert_eq!(
+      State::from(&exprs).to_dot(),
+      "digraph cells {
+  \"A01\";
+  \"B01\";
+  \"A02\";
+  \"A01\" -> \"B01\";
+  \"A01\" -> \"A02\";
+  \"A02\" -> \"B01\";
+}
+"
+    );
+
+    // the kept graph has the empty cells too
+    exprs.insert(cell("B2"), parse("= SUM(A1:A3)").unwrap());
+    assert_eq!(
+      State::from_references(&exprs, &Limits::default())
+        .to_dot_with(|cell_id| exprs.contains_key(cell_id)),
+      State::from(&exprs).to_dot()
+    );
+  }
+
+  #[test]
+  fn sorted_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let cells = |ids: &[&str]| ids.iter().map(|s| cell(s)).collect::<Vec<_>>();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), Num(1.0));
+    exprs.insert(cell("A2"), parse("= A1 + 1").unwrap());
+    exprs.insert(cell("A3"), parse("= A2 + 1").unwrap());
+    exprs.insert(cell("B1"), parse("= A3 + 1").unwrap());
+
+    let mut state = State::from_references(&exprs, &Limits::default());
+    let (sorted, unresolved) = state.sorted();
+    assert_eq!(sorted, cells(&["A1", "A2", "A3", "B1"]));
+    assert!(unresolved.is_empty());
+
+    // the order is updated in place, only the ids between the dependency and the dependent move
+    state.set_dependencies(cell("A3"), vec![cell("A1"), cell("B2")]);
+    assert!(state.order.is_some());
+    let (sorted, _) = state.sorted();
+    assert_eq!(sorted, cells(&["A1", "A2", "B2", "A3", "B1"]));
+
+    state.set_dependencies(cell("A1"), vec![cell("B1")]);
+    assert!(state.order.is_none());
+    let (sorted, unresolved) = state.sorted();
+    assert_eq!(sorted, cells(&["B2"]));
+    assert_eq!(unresolved, cells(&["A1", "B1", "A2", "A3"]));
+
+    state.set_dependencies(cell("A1"), vec![]);
+    let (sorted, unresolved) = state.sorted();
+    assert_eq!(sorted, cells(&["A1", "A2", "B2", "A3", "B1"]));
+    assert!(unresolved.is_empty());
+    assert_eq!(
+      state.sort(cells(&["B1", "A2", "B2"])),
+      (cells(&["A2", "B2", "B1"]), vec![])
+    );
+  }
+
+  #[test]
+  fn find_cycles_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), parse("= B1 + A2").unwrap());
+    exprs.insert(cell("B1"), parse("= C1 * 2").unwrap());
+    exprs.insert(cell("C1"), parse("= A1 - 1").unwrap());
+    exprs.insert(cell("A2"), parse("= 5").unwrap());
+    exprs.insert(cell("D1"), parse("= D1").unwrap());
+    exprs.insert(cell("E1"), parse("= C1 + D1").unwrap());
+
+    let cycles = find_cycles(&exprs)
+      .iter()
+      .map(|cycle| format_cycle(cycle))
+      .collect::<Vec<_>>();
+    assert_eq!(cycles, vec!["A01 → B01 → C01 → A01", "D01 → D01"]);
+
+    exprs.insert(cell("C1"), parse("= A2 - 1").unwrap());
+    exprs.insert(cell("D1"), parse("= 1").unwrap());
+    assert!(find_cycles(&exprs).is_empty());
+  }
+
+  #[test]
+  fn order_test() {
+    let cell = |s: &str| CellId::try_from(s).unwrap();
+    let cells = |ids: &[&str]| ids.iter().map(|s| cell(s)).collect::<Vec<_>>();
+    let is_valid = |state: &mut State<CellId>, edges: &[(&str, &str)]| {
+      let (sorted, unresolved) = state.sorted();
+      let position = |id: &str| sorted.iter().position(|cell_id| *cell_id == cell(id));
+      unresolved.is_empty()
+        && edges
+          .iter()
+          .all(|(dependent, dependency)| position(dependent) > position(dependency))
+    };
+
+    let mut exprs = HashMap::new();
+    exprs.insert(cell("A1"), parse("= B1 + C1").unwrap());
+    exprs.insert(cell("B1"), Num(15.0));
+    exprs.insert(cell("C1"), Num(3.0));
+    exprs.insert(cell("D1"), Num(1.0));
+
+    let mut state = State::from_references(&exprs, &Limits::default());
+    assert!(is_valid(&mut state, &[("A1", "B1"), ("A1", "C1")]));
+
+    // E1 is new, so it goes to the end of the order first, and then moves before C1 and D1
+    state.add_edge(cell("B1"), cell("D1"));
+    state.add_edge(cell("D1"), cell("E1"));
+    state.add_edge(cell("C1"), cell("E1"));
+    assert!(state.order.is_some());
+    let edges = [
+      ("A1", "B1"),
+      ("A1", "C1"),
+      ("B1", "D1"),
+      ("D1", "E1"),
+      ("C1", "E1"),
+    ];
+    assert!(is_valid(&mut state, &edges));
+    assert_eq!(state.sorted().0.len(), 5);
+
+    // the ids of a cycle are left unresolved
+    state.add_edge(cell("E1"), cell("A1"));
+    assert!(state.order.is_none());
+    assert_eq!(state.sorted().1, cells(&["A1", "B1", "C1", "D1", "E1"]));
+    let cycles = state
+      .cycles()
+      .iter()
+      .map(|cycle| format_cycle(cycle))
+      .collect::<Vec<_>>();
+    assert_eq!(cycles, vec!["A01 → C01 → E01 → A01"]);
+
+    // the edge creating the cycle can be kept after removing other edges of the cycle
+    state.set_dependencies(cell("A1"), []);
+    assert!(is_valid(
+      &mut state,
+      &[("B1", "D1"), ("D1", "E1"), ("C1", "E1"), ("E1", "A1")]
+    ));
+    assert!(state.cycles().is_empty());
+
+    // the cells filled by spilled arrays keep depending on them
+    state.set_spilled(cell("F1"), Some(cell("E1")), []);
+    state.set_dependencies(cell("F1"), [cell("A1")]);
+    assert_eq!(
+      state.all_precedents(&cell("F1")),
+      HashSet::from([cell("E1"), cell("A1")])
+    );
+    state.set_spilled(cell("F1"), None, []);
+    assert!(state.all_precedents(&cell("F1")).is_empty());
+    assert_eq!(
+      state.with_dependents([cell("D1")]),
+      HashSet::from([cell("D1"), cell("B1")])
+    );
   }
 }
@@ -0,0 +1,70 @@
+//! Evaluation statistics, which help to find slow formulas in large tables,
+//! see `crate::expr::EvalOptions::trace`.
+use std::collections::HashMap;
+
+use crate::cell_id::CellId;
+
+/// Evaluation statistics of a cell in the last evaluation pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellTrace {
+  /// Evaluation time in milliseconds, including spilling arrays.
+  pub millis: f64,
+  /// Number of the cells referenced by the formula, including the cells in ranges.
+  pub deps_read: usize,
+  /// Whether the cached value was reused instead of evaluating the formula.
+  pub is_cached: bool,
+  /// Whether the value differs from the cached value.
+  pub is_changed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalTrace {
+  pub cells: HashMap<CellId, CellTrace>,
+  /// Number of evaluation passes, which re-evaluate cells when arrays spill into other cells.
+  pub passes: usize,
+  /// Total evaluation time in milliseconds.
+  pub millis: f64,
+}
+
+impl EvalTrace {
+  /// Returns the `n` cells that took the longest to evaluate, the slowest first.
+  pub fn slowest(&self, n: usize) -> Vec<(CellId, &CellTrace)> {
+    let mut cells = self
+      .cells
+      .iter()
+      .map(|(cell_id, trace)| (*cell_id, trace))
+      .collect::<Vec<_>>();
+    cells.sort_by(|(_, a), (_, b)| b.millis.total_cmp(&a.millis));
+    cells.truncate(n);
+    cells
+  }
+
+  /// Returns the cells whose values changed.
+  pub fn changed(&self) -> impl Iterator<Item = CellId> + '_ {
+    self
+      .cells
+      .iter()
+      .filter(|(_, trace)| trace.is_changed)
+      .map(|(cell_id, _)| *cell_id)
+  }
+}
+
+/// Returns the time in milliseconds since the page was loaded.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+  web_sys::window()
+    .and_then(|window| window.performance())
+    .map_or_else(js_sys::Date::now, |performance| performance.now())
+}
+
+/// Returns the time in milliseconds since the first call; `Instant` is not available
+/// in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+  use std::time::Instant;
+
+  lazy_static! {
+    static ref START: Instant = Instant::now();
+  }
+  START.elapsed().as_secs_f64() * 1000.0
+}
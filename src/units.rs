@@ -0,0 +1,180 @@
+//! Units of measurement for the `CONVERT` function, named like in other spreadsheets,
+//! e.g., `"mi"` for miles or `"lbm"` for pounds. Units marked as prefixable can be used
+//! with metric prefixes, such as `"km"` or `"mg"`, and data units with binary prefixes,
+//! such as `"kibyte"`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantity {
+  Length,
+  Mass,
+  Time,
+  Temperature,
+  Data,
+}
+
+/// A unit converts to the base unit of its quantity as `value * scale + offset`;
+/// the base units are meters, grams, seconds, kelvins, and bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Unit {
+  quantity: Quantity,
+  scale: f64,
+  offset: f64,
+}
+
+// (name, quantity, scale, offset, is_prefixable)
+const UNITS: [(&str, Quantity, f64, f64, bool); 28] = [
+  ("m", Quantity::Length, 1.0, 0.0, true),
+  ("in", Quantity::Length, 0.0254, 0.0, false),
+  ("ft", Quantity::Length, 0.3048, 0.0, false),
+  ("yd", Quantity::Length, 0.9144, 0.0, false),
+  ("mi", Quantity::Length, 1609.344, 0.0, false),
+  ("Nmi", Quantity::Length, 1852.0, 0.0, false),
+  ("ang", Quantity::Length, 1e-10, 0.0, true),
+  ("g", Quantity::Mass, 1.0, 0.0, true),
+  ("lbm", Quantity::Mass, 453.59237, 0.0, false),
+  ("ozm", Quantity::Mass, 28.349523125, 0.0, false),
+  ("stone", Quantity::Mass, 6350.29318, 0.0, false),
+  ("ton", Quantity::Mass, 907184.74, 0.0, false),
+  ("u", Quantity::Mass, 1.66053906660e-24, 0.0, true),
+  ("s", Quantity::Time, 1.0, 0.0, true),
+  ("sec", Quantity::Time, 1.0, 0.0, true),
+  ("mn", Quantity::Time, 60.0, 0.0, false),
+  ("min", Quantity::Time, 60.0, 0.0, false),
+  ("hr", Quantity::Time, 3600.0, 0.0, false),
+  ("day", Quantity::Time, 86400.0, 0.0, false),
+  ("yr", Quantity::Time, 31557600.0, 0.0, false),
+  ("K", Quantity::Temperature, 1.0, 0.0, true),
+  ("kel", Quantity::Temperature, 1.0, 0.0, true),
+  ("C", Quantity::Temperature, 1.0, 273.15, false),
+  ("cel", Quantity::Temperature, 1.0, 273.15, false),
+  (
+    "F",
+    Quantity::Temperature,
+    5.0 / 9.0,
+    273.15 - 32.0 * 5.0 / 9.0,
+    false,
+  ),
+  (
+    "fah",
+    Quantity::Temperature,
+    5.0 / 9.0,
+    273.15 - 32.0 * 5.0 / 9.0,
+    false,
+  ),
+  ("bit", Quantity::Data, 1.0, 0.0, true),
+  ("byte", Quantity::Data, 8.0, 0.0, true),
+];
+
+const METRIC_PREFIXES: [(&str, f64); 17] = [
+  ("Y", 1e24),
+  ("Z", 1e21),
+  ("E", 1e18),
+  ("P", 1e15),
+  ("T", 1e12),
+  ("G", 1e9),
+  ("M", 1e6),
+  ("k", 1e3),
+  ("h", 1e2),
+  ("da", 1e1),
+  ("d", 1e-1),
+  ("c", 1e-2),
+  ("m", 1e-3),
+  ("u", 1e-6),
+  ("n", 1e-9),
+  ("p", 1e-12),
+  ("f", 1e-15),
+];
+
+const BINARY_PREFIXES: [(&str, f64); 6] = [
+  ("ki", 1024.0),
+  ("Mi", 1048576.0),
+  ("Gi", 1073741824.0),
+  ("Ti", 1099511627776.0),
+  ("Pi", 1125899906842624.0),
+  ("Ei", 1152921504606846976.0),
+];
+
+/// Converts the value between the units of the same quantity; unit names are case-sensitive.
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64, String> {
+  let (from_unit, to_unit) = (parse(from)?, parse(to)?);
+
+  if from_unit.quantity != to_unit.quantity {
+    return Err(format!(
+      "cannot convert {:?} in `{from}` to {:?} in `{to}`",
+      from_unit.quantity, to_unit.quantity
+    ));
+  }
+
+  let base = value * from_unit.scale + from_unit.offset;
+  Ok((base - to_unit.offset) / to_unit.scale)
+}
+
+fn parse(name: &str) -> Result<Unit, String> {
+  let unit = |name: &str, needs_prefixable: bool| {
+    UNITS
+      .iter()
+      .find(|unit| unit.0 == name && (unit.4 || !needs_prefixable))
+      .map(|&(_, quantity, scale, offset, _)| Unit {
+        quantity,
+        scale,
+        offset,
+      })
+  };
+
+  if let Some(unit) = unit(name, false) {
+    return Ok(unit);
+  }
+
+  let prefixed = |(prefix, multiplier): &(&str, f64)| {
+    let unit = unit(name.strip_prefix(prefix)?, true)?;
+    Some(Unit {
+      scale: unit.scale * multiplier,
+      ..unit
+    })
+  };
+  METRIC_PREFIXES
+    .iter()
+    .find_map(prefixed)
+    .or_else(|| {
+      BINARY_PREFIXES
+        .iter()
+        .find_map(prefixed)
+        .filter(|unit| unit.quantity == Quantity::Data)
+    })
+    .ok_or_else(|| format!("`{name}` is not a known unit"))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn assert_converts(value: f64, from: &str, to: &str, expected: f64) {
+    let res = convert(value, from, to).unwrap();
+    assert!(
+      (res - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+      "{value} {from} is {res} {to}, expected {expected}"
+    );
+  }
+
+  #[test]
+  fn convert_test() {
+    assert_converts(1.0, "mi", "km", 1.609344);
+    assert_converts(12.0, "in", "ft", 1.0);
+    assert_converts(5.0, "cm", "mm", 50.0);
+    assert_converts(1.0, "lbm", "kg", 0.45359237);
+    assert_converts(2.5, "hr", "mn", 150.0);
+    assert_converts(1.0, "day", "sec", 86400.0);
+    assert_converts(100.0, "C", "F", 212.0);
+    assert_converts(-40.0, "fah", "cel", -40.0);
+    assert_converts(0.0, "K", "C", -273.15);
+    assert_converts(1.0, "kibyte", "byte", 1024.0);
+    assert_converts(1.0, "Mbyte", "kbit", 8000.0);
+    assert_converts(3.0, "m", "m", 3.0);
+
+    assert!(convert(1.0, "m", "g").is_err());
+    assert!(convert(1.0, "parsec", "m").is_err());
+    assert!(convert(1.0, "kin", "m").is_err());
+    assert!(convert(1.0, "kim", "m").is_err());
+    assert!(convert(1.0, "M", "m").is_err());
+  }
+}
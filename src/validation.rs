@@ -0,0 +1,140 @@
+//! Validation rules of the cells, typed into the validation editor for the selected cells,
+//! e.g., `1..10`, `yes, no`, or `/^[A-Z]{3}$/`. Typed inputs breaking the rules are rejected,
+//! and other invalid values, e.g., computed by formulas, are marked.
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::func::regex;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Rule {
+  /// A number from the minimum to the maximum, inclusive; either bound can be omitted.
+  Range(Option<f64>, Option<f64>),
+  /// One of the values, case-insensitively.
+  List(Vec<String>),
+  /// Text matching the regular expression.
+  Regex(String),
+}
+
+impl Rule {
+  /// Parses a range of numbers like `1..10` or `0..`, a regular expression in slashes,
+  /// or comma-separated allowed values; returns `None` for an empty text.
+  pub fn parse(text: &str) -> Result<Option<Rule>, Box<dyn Error>> {
+    let text = text.trim();
+    if text.is_empty() {
+      return Ok(None);
+    }
+
+    if let Some(pattern) = text
+      .strip_prefix('/')
+      .and_then(|text| text.strip_suffix('/'))
+    {
+      regex(pattern)?;
+      return Ok(Some(Rule::Regex(pattern.to_string())));
+    }
+
+    if let Some((min, max)) = text.split_once("..") {
+      let bound = |bound: &str| match bound.trim() {
+        "" => Ok(None),
+        bound => bound
+          .parse::<f64>()
+          .map(Some)
+          .map_err(|_| format!("`{bound}` is not a number")),
+      };
+      return Ok(Some(Rule::Range(bound(min)?, bound(max)?)));
+    }
+
+    Ok(Some(Rule::List(
+      text
+        .split(',')
+        .map(|allowed| allowed.trim().to_string())
+        .filter(|allowed| !allowed.is_empty())
+        .collect(),
+    )))
+  }
+
+  /// Whether the value is allowed; empty cells are always allowed.
+  pub fn check(&self, value: &Value) -> bool {
+    match (self, value) {
+      (_, Value::Empty) => true,
+      (Rule::Range(min, max), Value::Num(_) | Value::Big(_) | Value::Date(_)) => value
+        .as_num()
+        .is_ok_and(|num| min.map_or(true, |min| num >= min) && max.map_or(true, |max| num <= max)),
+      (Rule::Range(..), _) => false,
+      (Rule::List(allowed), value) => {
+        let value = value.to_string();
+        allowed
+          .iter()
+          .any(|allowed| allowed.eq_ignore_ascii_case(&value))
+      }
+      (Rule::Regex(pattern), value) => {
+        regex(pattern).is_ok_and(|re| re.is_match(&value.to_string()))
+      }
+    }
+  }
+
+  /// Explains the rule, e.g., in the tooltip of invalid cells.
+  pub fn describe(&self) -> String {
+    match self {
+      Rule::Range(Some(min), Some(max)) => format!("must be a number from {min} to {max}"),
+      Rule::Range(Some(min), None) => format!("must be a number not less than {min}"),
+      Rule::Range(None, Some(max)) => format!("must be a number not greater than {max}"),
+      Rule::Range(None, None) => String::from("must be a number"),
+      Rule::List(allowed) => format!("must be one of: {}", allowed.join(", ")),
+      Rule::Regex(pattern) => format!("must match /{pattern}/"),
+    }
+  }
+}
+
+/// Shows the rule as it's typed into the validation editor.
+impl Display for Rule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let bound = |bound: &Option<f64>| bound.map(|bound| bound.to_string()).unwrap_or_default();
+    match self {
+      Rule::Range(min, max) => write!(f, "{}..{}", bound(min), bound(max)),
+      Rule::List(allowed) => write!(f, "{}", allowed.join(", ")),
+      Rule::Regex(pattern) => write!(f, "/{pattern}/"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn rule_test() {
+    let check = |rule: &str, value: Value| Rule::parse(rule).unwrap().unwrap().check(&value);
+
+    assert_eq!(Rule::parse(" ").unwrap(), None);
+    assert_eq!(
+      Rule::parse("1..10").unwrap(),
+      Some(Rule::Range(Some(1.0), Some(10.0)))
+    );
+    assert_eq!(
+      Rule::parse("0..").unwrap(),
+      Some(Rule::Range(Some(0.0), None))
+    );
+    assert!(Rule::parse("a..10").is_err());
+    assert!(Rule::parse("/(/").is_err());
+
+    assert!(check("1..10", Value::Num(10.0)));
+    assert!(!check("1..10", Value::Num(10.5)));
+    assert!(!check("1..10", Value::Str(String::from("5"))));
+    assert!(check("1..10", Value::Empty));
+    assert!(check("yes, no", Value::Str(String::from("Yes"))));
+    assert!(!check("yes, no", Value::Str(String::from("maybe"))));
+    assert!(check("/^[A-Z]{3}$/", Value::Str(String::from("USD"))));
+    assert!(!check("/^[A-Z]{3}$/", Value::Str(String::from("usd"))));
+
+    for text in ["1..10", "..0", "yes, no", "/^a/"] {
+      assert_eq!(Rule::parse(text).unwrap().unwrap().to_string(), text);
+    }
+    assert_eq!(
+      Rule::parse("1..").unwrap().unwrap().describe(),
+      "must be a number not less than 1"
+    );
+  }
+}
@@ -0,0 +1,123 @@
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::bignum;
+use crate::date;
+use crate::expr::{from_bool, Expr};
+
+/// A computed value of a cell or an expression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+  Num(f64),
+  Str(String),
+  /// A date represented as a serial number, see `crate::date`.
+  /// Behaves like a number, but is displayed as a date.
+  Date(f64),
+  /// A boolean typed as `TRUE` or `FALSE`, shown as a checkbox.
+  /// Behaves like `1` or `0`, as booleans computed by formulas do.
+  Bool(bool),
+  /// A rectangular array of values, stored row by row, such as the result of `SEQUENCE`.
+  /// Spills into the neighboring cells, see `crate::expr::eval`.
+  Array(Vec<Vec<Value>>),
+  /// A number outside of the range or the precision of `f64`, see `crate::bignum`.
+  Big(BigDecimal),
+  /// A cleared cell. Aggregate functions skip it, while operators treat it as zero
+  /// or as empty text, like other spreadsheets do.
+  Empty,
+}
+
+impl Value {
+  pub fn as_num(&self) -> Result<f64, Box<dyn Error>> {
+    match self {
+      Value::Num(num) | Value::Date(num) => Ok(*num),
+      Value::Bool(value) => Ok(from_bool(*value)),
+      Value::Big(big) => Ok(bignum::to_f64(big)),
+      Value::Empty => Ok(0.0),
+      Value::Str(s) => Err(format!("expected a number, got text `{s}`").into()),
+      Value::Array(_) => Err(format!("expected a number, got an array {self}").into()),
+    }
+  }
+
+  pub fn is_text(&self) -> bool {
+    matches!(self, Value::Str(_))
+  }
+
+  /// Empty text is considered blank too, like in other spreadsheets.
+  pub fn is_blank(&self) -> bool {
+    match self {
+      Value::Empty => true,
+      Value::Str(s) => s.is_empty(),
+      _ => false,
+    }
+  }
+
+  /// Compares values like other spreadsheets do: numbers are compared numerically,
+  /// text is compared case-insensitively, and numbers are always less than text.
+  /// Empty values are equal to both zero and empty text.
+  pub fn compare(&self, other: &Value) -> Option<Ordering> {
+    match (self, other) {
+      (Value::Empty, Value::Str(_)) => Value::Str(String::new()).compare(other),
+      (Value::Str(_), Value::Empty) => self.compare(&Value::Str(String::new())),
+      (Value::Str(a), Value::Str(b)) => Some(a.to_lowercase().cmp(&b.to_lowercase())),
+      (_, Value::Str(_)) => Some(Ordering::Less),
+      (Value::Str(_), _) => Some(Ordering::Greater),
+      (Value::Big(_), _) | (_, Value::Big(_)) => {
+        match (bignum::to_big(self), bignum::to_big(other)) {
+          (Some(a), Some(b)) => Some(a.cmp(&b)),
+          _ => self.as_num().ok()?.partial_cmp(&other.as_num().ok()?),
+        }
+      }
+      _ => self.as_num().ok()?.partial_cmp(&other.as_num().ok()?),
+    }
+  }
+}
+
+impl Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Num(num) => write!(f, "{num}"),
+      Value::Str(s) => write!(f, "{s}"),
+      Value::Date(serial) => write!(f, "{}", date::format(*serial)),
+      Value::Bool(true) => write!(f, "TRUE"),
+      Value::Bool(false) => write!(f, "FALSE"),
+      Value::Big(big) => write!(f, "{}", bignum::format(big)),
+      Value::Empty => Ok(()),
+      // uses the array constant notation of other spreadsheets, e.g., `{1, 2; 3, 4}`
+      Value::Array(rows) => {
+        let rows = rows
+          .iter()
+          .map(|row| {
+            row
+              .iter()
+              .map(|value| value.to_string())
+              .collect::<Vec<_>>()
+              .join(", ")
+          })
+          .collect::<Vec<_>>();
+        write!(f, "{{{}}}", rows.join("; "))
+      }
+    }
+  }
+}
+
+impl From<Value> for Expr {
+  fn from(value: Value) -> Self {
+    match value {
+      Value::Num(num) => Expr::Num(num),
+      Value::Str(s) => Expr::Str(s),
+      Value::Date(serial) => Expr::Date(serial),
+      Value::Bool(value) => Expr::Bool(value),
+      Value::Big(big) => Expr::Num(bignum::to_f64(&big)),
+      Value::Empty => Expr::Empty,
+      // arrays are spilled, so only the top left value stays in the cell
+      Value::Array(rows) => rows
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_iter().next())
+        .map_or_else(Expr::default, Expr::from),
+    }
+  }
+}
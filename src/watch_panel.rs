@@ -0,0 +1,106 @@
+use yew::prelude::*;
+
+use crate::cell_id::CellId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watched {
+  pub cell_id: CellId,
+  pub input: String,
+  // the displayed value, with the number format of the cell
+  pub value: String,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct WatchPanelProps {
+  pub watched: Vec<Watched>,
+  pub is_open: bool,
+  pub ontoggle: Callback<()>,
+  // selects the watched cell
+  pub onselect: Callback<CellId>,
+  pub onremove: Callback<CellId>,
+}
+
+/// The collapsible panel on the right listing the watched cells with their inputs and values.
+#[function_component]
+pub fn WatchPanel(props: &WatchPanelProps) -> Html {
+  let ontoggle = {
+    let parent_ontoggle = props.ontoggle.clone();
+    Callback::from(move |ev: MouseEvent| {
+      ev.stop_propagation();
+      parent_ontoggle.emit(());
+    })
+  };
+
+  if !props.is_open {
+    return html! {
+      <button
+        class="fixed top-1/3 right-0 z-[45] px-2 py-4 text-base bg-indigo-800 hover:bg-indigo-700 border-[1px] border-indigo-900 [writing-mode:vertical-rl]"
+        title="Show the watched cells"
+        onclick={ontoggle}
+      >
+        { format!("Watch ({})", props.watched.len()) }
+      </button>
+    };
+  }
+
+  let view_watched = |watched: &Watched| {
+    let cell_id = watched.cell_id;
+    let parent_onselect = props.onselect.clone();
+    // the row is picked with the keyboard too, but not through its buttons
+    let onkeydown = {
+      let parent_onselect = parent_onselect.clone();
+      Callback::from(move |ev: KeyboardEvent| {
+        if matches!(ev.key().as_str(), "Enter" | " ") && ev.target() == ev.current_target() {
+          ev.prevent_default();
+          parent_onselect.emit(cell_id);
+        }
+      })
+    };
+    let parent_onremove = props.onremove.clone();
+
+    html! {
+      <tr
+        class="cursor-pointer hover:bg-indigo-700 focus:outline-none focus:bg-indigo-700"
+        tabindex="0"
+        onclick={ Callback::from(move |_ev: MouseEvent| parent_onselect.emit(cell_id)) }
+        {onkeydown}
+      >
+        <td class="pr-2">{ cell_id.to_string() }</td>
+        <td class="pr-2 max-w-[10rem] truncate text-neutral-300" title={ watched.input.clone() }>
+          { watched.input.clone() }
+        </td>
+        <td class="pr-2 max-w-[8rem] truncate text-right">{ watched.value.clone() }</td>
+        <td>
+          <button
+            class="text-neutral-400 hover:text-white"
+            title="Stop watching the cell"
+            onclick={ Callback::from(move |ev: MouseEvent| {
+              ev.stop_propagation();
+              parent_onremove.emit(cell_id);
+            })}
+          >
+            { "✕" }
+          </button>
+        </td>
+      </tr>
+    }
+  };
+
+  html! {
+    <div class="fixed top-1/3 right-0 z-[45] flex flex-col gap-2 p-2 max-h-[50vh] overflow-y-auto font-mono text-base bg-indigo-800 border-[1px] border-indigo-900 shadow-lg">
+      <div class="flex justify-between gap-4">
+        <span class="font-sans">{ "Watch" }</span>
+        <button class="text-neutral-300 hover:text-white" title="Hide the watched cells" onclick={ontoggle}>
+          { "▸" }
+        </button>
+      </div>
+      if props.watched.is_empty() {
+        <span class="font-sans text-neutral-300">{ "Select cells and press Watch to list them here." }</span>
+      } else {
+        <table>
+          { props.watched.iter().map(view_watched).collect::<Html>() }
+        </table>
+      }
+    </div>
+  }
+}